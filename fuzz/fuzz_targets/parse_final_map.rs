@@ -0,0 +1,16 @@
+//! 译文文件解析（`qa::parse_final_map`）：任意输入不得 panic，成功时每个键都来自输入对象
+//!
+//! 运行：`cargo +nightly fuzz run parse_final_map`
+#![no_main]
+
+use juzhen_r_fanyi::model::qa::parse_final_map;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else { return };
+    if let Ok(map) = parse_final_map(text) {
+        let value: serde_json::Value = serde_json::from_str(text).expect("解析成功的输入必须是合法JSON");
+        let object = value.as_object().expect("解析成功的输入必须是对象");
+        assert!(map.keys().all(|seq| object.contains_key(seq)));
+    }
+});
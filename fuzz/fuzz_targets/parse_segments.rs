@@ -0,0 +1,15 @@
+//! 具体路径分段解析（`path::parse_segments`）：任意输入不得 panic，解析出的段可直接用于定位
+//!
+//! 运行：`cargo +nightly fuzz run parse_segments`
+#![no_main]
+
+use juzhen_r_fanyi::model::path::{parse_segments, resolve};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(path) = std::str::from_utf8(data) else { return };
+    if let Some(segments) = parse_segments(path) {
+        let dom = serde_json::json!({"a": [1, {"b": "c"}], "": {"'": null}});
+        let _ = resolve(&dom, &segments);
+    }
+});
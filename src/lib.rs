@@ -1,12 +1,88 @@
 //! JSON翻译工具库
-//! 
+//!
 //! 提供JSON文件加载、影子树构建、节点提取和回写功能
 //! 遵循MVVM架构模式，支持大文件高性能处理
+//!
+//! 常用类型可通过 [`prelude`] 一次性导入。以下示例覆盖三个主要工作流。
+//!
+//! # 加载与浏览
+//!
+//! ```
+//! use juzhen_r_fanyi::prelude::*;
+//!
+//! let text = r#"{"menu": {"open": "Open", "close": "Close"}}"#;
+//! let (dom, tree) = build_shadow_tree_from_reader(text.as_bytes(), ShadowTreeOptions::default())?;
+//! assert_eq!(tree.iter().filter(|n| n.kind == NodeKind::String).count(), 2);
+//!
+//! let mut state = AppState::default();
+//! state.replace_dom(dom)?;
+//! assert_eq!(state.extract_leaf_text("$.menu.open")?, "Open");
+//! let matches = state.search("close", SearchMode::ExactKey)?;
+//! assert_eq!(matches[0].node.path, "$.menu.close");
+//! # Ok::<(), AppError>(())
+//! ```
+//!
+//! # 提取待翻译文本
+//!
+//! ```
+//! use juzhen_r_fanyi::prelude::*;
+//!
+//! let mut state = AppState::default();
+//! state.replace_dom(serde_json::json!({"menu": {"title": "Open", "icon": "open.png"}}))?;
+//!
+//! // 中间产物2：带序号与源路径的条目列表
+//! let stage2 = state.build_intermediate_stage2_multi(&["title".to_string()], Stage2Options::default(), |_, _| {})?;
+//! // 最终产物：`{"序号": "原文"}`，交给译者翻译
+//! let final_text = build_final_product(&stage2, FinalOptions::default())?;
+//! assert_eq!(serde_json::from_str::<serde_json::Value>(&final_text)?, serde_json::json!({"0": "Open"}));
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! # 回写译文
+//!
+//! ```
+//! use juzhen_r_fanyi::prelude::*;
+//!
+//! let mut state = AppState::default();
+//! state.replace_dom(serde_json::json!({"menu": {"title": "Open"}}))?;
+//! let stage2 = state.build_intermediate_stage2_multi(&["title".to_string()], Stage2Options::default(), |_, _| {})?;
+//!
+//! let translated = serde_json::json!({"0": "打开"});
+//! let mut dom = state.dom.clone().unwrap();
+//! let plan = WritebackPlan::build(&translated, &serde_json::from_str(&stage2)?, &dom, WritebackOptions::default())?;
+//! assert!(plan.fingerprint_warning.is_none(), "文档未变化，指纹一致");
+//! assert_eq!(plan.apply(&mut dom, |_, _| {}), 1);
+//! assert_eq!(dom["menu"]["title"], "打开");
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
 
 pub mod model;
 pub mod utils;
 pub mod vm;
 
 // 重新导出主要类型
-pub use model::data_core::{AppState, AppError};
-pub use model::shadow_tree::{JsonTreeNode, NodeKind, build_shadow_tree};
+pub use model::data_core::{AppState, AppError, JsonPathKind, Lang, MatchCountOptions, MatchCounts, NodeInfo, OversizedValues, Stage2Options, Stage2Sort, StateKind, TreeSnapshot, TreeSnapshotSummary};
+pub use model::shadow_tree::{JsonTreeNode, NodeKind, build_shadow_tree, build_shadow_tree_limited};
+/// 从任意 `Read` 源（文件、解压流、网络流）加载 JSON 并构建影子树，与 `AppState::load_file` 共用加载流程
+pub use model::shadow_tree::{build_shadow_tree_from_reader, ShadowTreeOptions};
+pub use model::json_schema::{CompiledSchema, SchemaViolation};
+/// 库模式下的值监听（`AppState::watch_value`）
+pub use model::watch::{WatchCallback, WatcherId};
+pub use model::search::{MatchKind, SearchMatch, SearchMode};
+pub use model::path::PathSegment;
+pub use model::project::{Project, ProjectConfig, ProjectEvent};
+pub use model::diff::{ChangeSummary, DiffEntry, DiffKind};
+pub use model::workflow::{
+    build_final_product, build_final_product_with_progress, FinalOptions, FinalShape, PlannedUpdate, WritebackNote, WritebackOptions,
+    WritebackPlan,
+};
+/// 批量导出映射解析（`JSONPath => 输出文件`）
+pub use vm::export;
+
+/// 常用类型一次性导入：`use juzhen_r_fanyi::prelude::*;`
+pub mod prelude {
+    pub use crate::{
+        build_final_product, build_shadow_tree_from_reader, AppError, AppState, FinalOptions, JsonTreeNode, MatchKind, NodeKind,
+        SearchMatch, SearchMode, ShadowTreeOptions, Stage2Options, Stage2Sort, WritebackOptions, WritebackPlan,
+    };
+}
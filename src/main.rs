@@ -1,7 +1,8 @@
 //! 程序入口：初始化日志、加载 Slint UI，并准备后续 VM 绑定
 
-use std::{cell::RefCell, rc::Rc, path::PathBuf};
+use std::{cell::RefCell, rc::{Rc, Weak}, path::PathBuf};
 use tracing_subscriber::fmt::SubscriberBuilder;
+use utils::logging::LogLevelHandle;
 use slint::{ComponentHandle, ModelRc, VecModel, Model};
 use serde_json::Value;
 
@@ -11,8 +12,14 @@ mod model;
 mod utils;
 mod vm;
 
-use model::{data_core::AppState, shadow_tree::JsonTreeNode};
+use model::{data_core::{AppError, AppState, MatchCountOptions, OversizedValues, Stage2Options, Stage2Sort, StateKind, SubtreeMergeMode}, search::SearchMode, shadow_tree::JsonTreeNode};
+use model::diff::DIFF_RENDER_LIMIT;
+use model::json_schema::{CompiledSchema, SchemaViolation};
+use model::project::{Project, ProjectConfig, ProjectEvent, PROJECT_FILE_SUFFIX};
 use vm::bridge::*;
+use model::workflow::{build_final_product_with_progress, FinalOptions, FinalShape, WritebackOptions, WritebackPlan};
+use vm::progress::{ProgressTiming, ThrottledProgress};
+use vm::reload::ReloadChoice;
 use std::time::Instant;
 
 // TreeNodeData转换实现
@@ -28,6 +35,7 @@ impl From<&JsonTreeNode> for TreeNodeData {
             depth: node.depth as i32,
             expanded: node.expanded,
             visible: true, // 在Rust端已过滤，这里总是true
+            size: if node.size_bytes > 0 { format_byte_size(node.size_bytes).into() } else { Default::default() },
         }
     }
 }
@@ -95,10 +103,186 @@ impl ViewModelBridge {
         // === 复制按钮回调 ===
         {
             let app_state = app_state.clone();
+            let preview_full_text = self.preview_full_text.clone();
             let app_window_weak = app_window.as_weak();
             app_window.on_copy_pressed(move || {
                 if let Some(app_window) = app_window_weak.upgrade() {
-                    Self::handle_copy_pressed(&app_window, &app_state);
+                    Self::handle_copy_pressed(&app_window, &app_state, &preview_full_text);
+                }
+            });
+        }
+
+        // === 批量导出回调 ===
+        {
+            let app_state = app_state.clone();
+            let app_window_weak = app_window.as_weak();
+            app_window.on_batch_export(move |spec| {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_batch_export(&app_window, &app_state, &spec);
+                }
+            });
+        }
+        {
+            let app_state = app_state.clone();
+            let app_window_weak = app_window.as_weak();
+            app_window.on_rename_key(move |old, new, scope| {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_rename_key(&app_window, &app_state, &old, &new, &scope);
+                }
+            });
+        }
+        {
+            let app_state = app_state.clone();
+            let app_window_weak = app_window.as_weak();
+            app_window.on_delete_node(move |path| {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_delete_node(&app_window, &app_state, &path);
+                }
+            });
+        }
+        {
+            let app_state = app_state.clone();
+            let app_window_weak = app_window.as_weak();
+            app_window.on_duplicate_node(move |path| {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_duplicate_node(&app_window, &app_state, &path);
+                }
+            });
+        }
+        {
+            let app_state = app_state.clone();
+            let app_window_weak = app_window.as_weak();
+            app_window.on_export_node_to_file(move |path| {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_export_node_to_file(&app_window, &app_state, &path);
+                }
+            });
+        }
+        {
+            let app_state = app_state.clone();
+            let app_window_weak = app_window.as_weak();
+            app_window.on_insert_child(move |parent_path, key, value_json| {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_insert_child(&app_window, &app_state, &parent_path, &key, &value_json);
+                }
+            });
+        }
+        {
+            let app_window_weak = app_window.as_weak();
+            app_window.on_merge_subtree_from_file(move |path| {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_merge_subtree_from_file(&app_window, &path);
+                }
+            });
+        }
+        {
+            let app_state = app_state.clone();
+            let app_window_weak = app_window.as_weak();
+            app_window.on_merge_subtree_confirmed(move |path, file, merge_keys| {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_merge_subtree_confirmed(&app_window, &app_state, &path, &file, merge_keys);
+                }
+            });
+        }
+
+        // === 提取全部匹配回调 ===
+        {
+            let app_state = app_state.clone();
+            let app_window_weak = app_window.as_weak();
+            app_window.on_extract_all_pressed(move || {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_extract_all_pressed(&app_window, &app_state);
+                }
+            });
+        }
+        {
+            let app_state = app_state.clone();
+            let app_window_weak = app_window.as_weak();
+            app_window.on_preview_stage2_pressed(move || {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_preview_stage2_pressed(&app_window, &app_state);
+                }
+            });
+        }
+        {
+            let app_state = app_state.clone();
+            let app_window_weak = app_window.as_weak();
+            app_window.on_save_search_results_pressed(move || {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_save_search_results_pressed(&app_window, &app_state);
+                }
+            });
+        }
+        {
+            let app_state = app_state.clone();
+            let app_window_weak = app_window.as_weak();
+            app_window.on_value_histogram_pressed(move || {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_value_histogram_pressed(&app_window, &app_state);
+                }
+            });
+        }
+        {
+            let app_state = app_state.clone();
+            let app_window_weak = app_window.as_weak();
+            app_window.on_schema_report_pressed(move || {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_schema_report_pressed(&app_window, &app_state);
+                }
+            });
+        }
+        {
+            let app_state = app_state.clone();
+            let app_window_weak = app_window.as_weak();
+            app_window.on_effort_estimate_pressed(move || {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_effort_estimate_pressed(&app_window, &app_state);
+                }
+            });
+        }
+        {
+            let app_state = app_state.clone();
+            let app_window_weak = app_window.as_weak();
+            app_window.on_largest_subtrees_pressed(move || {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_largest_subtrees_pressed(&app_window, &app_state);
+                }
+            });
+        }
+        {
+            let app_state = app_state.clone();
+            let app_window_weak = app_window.as_weak();
+            app_window.on_reveal_node(move |path| {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_reveal_node(&app_window, &app_state, &path);
+                }
+            });
+        }
+        {
+            let app_state = app_state.clone();
+            let app_window_weak = app_window.as_weak();
+            app_window.on_load_schema_pressed(move || {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_load_schema_pressed(&app_window, &app_state);
+                }
+            });
+        }
+        {
+            let app_state = app_state.clone();
+            let app_window_weak = app_window.as_weak();
+            app_window.on_schema_save_anyway(move || {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_schema_save_anyway(&app_window, &app_state);
+                }
+            });
+        }
+        {
+            let app_window_weak = app_window.as_weak();
+            app_window.on_schema_cancel_save(move || {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    PENDING_SCHEMA_SAVE.with(|pending| pending.borrow_mut().take());
+                    app_window.set_schema_confirm_visible(false);
+                    app_window.set_status_message("Schema 校验未通过，已取消保存".into());
                 }
             });
         }
@@ -140,6 +324,17 @@ impl ViewModelBridge {
             });
         }
 
+        {
+            let app_state = app_state.clone();
+            let app_window_weak = app_window.as_weak();
+            let preview_full_text = self.preview_full_text.clone();
+            app_window.on_preview_filter_match(move |filter| {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_preview_filter_match(&app_window, &app_state, &preview_full_text, &filter);
+                }
+            });
+        }
+
         // === 复制全部回调（后台聚合） ===
         {
             let app_state = app_state.clone();
@@ -175,6 +370,77 @@ impl ViewModelBridge {
                 }
             });
         }
+        {
+            let app_state = app_state.clone();
+            let app_window_weak = app_window.as_weak();
+            app_window.on_copy_for_llm_pressed(move || {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_copy_for_llm_pressed(&app_window, &app_state);
+                }
+            });
+        }
+        {
+            let app_state = app_state.clone();
+            let app_window_weak = app_window.as_weak();
+            app_window.on_show_changes_pressed(move || {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_show_changes_pressed(&app_window, &app_state);
+                }
+            });
+        }
+        {
+            let app_state = app_state.clone();
+            let app_window_weak = app_window.as_weak();
+            app_window.on_session_changes_pressed(move || {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_session_changes_pressed(&app_window, &app_state);
+                }
+            });
+        }
+        {
+            let app_state = app_state.clone();
+            let app_window_weak = app_window.as_weak();
+            app_window.on_read_only_toggled(move || {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_read_only_toggled(&app_window, &app_state);
+                }
+            });
+        }
+        {
+            let app_state = app_state.clone();
+            let app_window_weak = app_window.as_weak();
+            app_window.on_indent_size_changed(move |spaces| {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_indent_size_changed(&app_window, &app_state, spaces);
+                }
+            });
+        }
+        {
+            let app_window_weak = app_window.as_weak();
+            let preview_full_text = self.preview_full_text.clone();
+            app_window.on_qa_check_pressed(move || {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_qa_check_pressed(&app_window, &preview_full_text);
+                }
+            });
+        }
+        {
+            let app_window_weak = app_window.as_weak();
+            app_window.on_export_qa_csv(move || {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_export_qa_csv(&app_window);
+                }
+            });
+        }
+        {
+            let app_window_weak = app_window.as_weak();
+            let preview_full_text = self.preview_full_text.clone();
+            app_window.on_export_stage2_xlsx(move || {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_export_stage2_xlsx(&app_window, &preview_full_text);
+                }
+            });
+        }
 
         // === 分页回调 ===
         {
@@ -196,6 +462,28 @@ impl ViewModelBridge {
             });
         }
 
+        // === 翻译项目回调 ===
+        {
+            let app_state = app_state.clone();
+            let app_window_weak = app_window.as_weak();
+            let preview_full_text = self.preview_full_text.clone();
+            app_window.on_save_project_pressed(move || {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_save_project(&app_window, &app_state, &preview_full_text);
+                }
+            });
+        }
+        {
+            let app_state = app_state.clone();
+            let app_window_weak = app_window.as_weak();
+            let preview_full_text = self.preview_full_text.clone();
+            app_window.on_open_project_pressed(move || {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_open_project(&app_window, &app_state, &preview_full_text);
+                }
+            });
+        }
+
         // === 回写功能回调 ===
         {
             let app_state = app_state.clone();
@@ -208,6 +496,16 @@ impl ViewModelBridge {
                 }
             });
         }
+        {
+            let app_state = app_state.clone();
+            let app_window_weak = app_window.as_weak();
+            let preview_full_text = self.preview_full_text.clone();
+            app_window.on_fan_out_pressed(move || {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_fan_out_pressed(&app_window, &app_state, &preview_full_text);
+                }
+            });
+        }
 
 
         // === 清空回写日志回调 ===
@@ -245,9 +543,37 @@ impl ViewModelBridge {
         {
             let app_state = app_state.clone();
             let app_window_weak = app_window.as_weak();
-            app_window.on_reload_file_after_writeback(move |file_path| {
+            app_window.on_reload_file_after_writeback(move |path_id| {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_reload_file_after_writeback(&app_window, &app_state, path_id, ReloadChoice::Now);
+                }
+            });
+        }
+        {
+            let app_state = app_state.clone();
+            let app_window_weak = app_window.as_weak();
+            app_window.on_postpone_reload(move |path_id| {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_reload_file_after_writeback(&app_window, &app_state, path_id, ReloadChoice::Later);
+                }
+            });
+        }
+
+        {
+            let app_state = app_state.clone();
+            let app_window_weak = app_window.as_weak();
+            app_window.on_load_full_tree_pressed(move || {
                 if let Some(app_window) = app_window_weak.upgrade() {
-                    Self::handle_reload_file_after_writeback(&app_window, &app_state, &file_path.to_string());
+                    Self::handle_load_full_tree(&app_window, &app_state);
+                }
+            });
+        }
+
+        {
+            let app_window_weak = app_window.as_weak();
+            app_window.on_cancel_operation(move || {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_cancel_operation(&app_window);
                 }
             });
         }
@@ -280,6 +606,15 @@ impl ViewModelBridge {
                 }
             });
         }
+        {
+            let app_state = app_state.clone();
+            let app_window_weak = app_window.as_weak();
+            app_window.on_compute_sizes_pressed(move || {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_compute_sizes_pressed(&app_window, &app_state);
+                }
+            });
+        }
         {
             let app_state = app_state.clone();
             let app_window_weak = app_window.as_weak();
@@ -312,6 +647,17 @@ impl ViewModelBridge {
             });
         }
 
+        // === 搜索策略切换回调 ===
+        {
+            let app_state = app_state.clone();
+            let app_window_weak = app_window.as_weak();
+            app_window.on_search_mode_changed(move |index| {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_search_mode_changed(&app_window, &app_state, index);
+                }
+            });
+        }
+
         // === 应用搜索过滤回调 ===
         {
             let app_state = app_state.clone();
@@ -333,6 +679,60 @@ impl ViewModelBridge {
                 }
             });
         }
+
+        // === 崩溃诊断事件记录 ===
+        app_window.on_status_event(|message| {
+            utils::crash::record_status_event(&message);
+        });
+        app_window.on_performance_event(|info| {
+            utils::crash::record_perf_event(&info);
+        });
+
+        // === 自检（隐藏入口） ===
+        {
+            let app_window_weak = app_window.as_weak();
+            app_window.on_run_selftest(move || {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    Self::handle_run_selftest(&app_window);
+                }
+            });
+        }
+    }
+
+    /// 运行自检：报告显示在预览区，JSON 写入产物目录以便附在问题反馈中
+    fn handle_run_selftest(app_window: &AppWindow) {
+        let report = vm::selftest::run();
+        app_window.set_preview_text(report.render().into());
+
+        let path = utils::fs::artifact_path(utils::fs::ArtifactKind::SelftestReport, None);
+        let saved = std::fs::create_dir_all(utils::fs::artifacts_dir(None)).and_then(|_| std::fs::write(&path, report.to_json()));
+        let summary = match &report.failed_stage {
+            Some(stage) => format!("{}自检失败于「{}」", STATUS_ERROR_PREFIX, stage),
+            None => "自检通过".to_string(),
+        };
+        let message = match saved {
+            Ok(()) => format!("{}，报告: {}", summary, path.display()),
+            Err(e) => format!("{}（报告写入失败: {}）", summary, e),
+        };
+        tracing::info!("{}", message);
+        app_window.set_status_message(message.into());
+    }
+
+    /// 绑定日志级别切换回调
+    fn setup_log_level(&self, app_window: &AppWindow, handle: LogLevelHandle) {
+        let app_window_weak = app_window.as_weak();
+        app_window.on_set_log_level(move |level| {
+            let Some(app_window) = app_window_weak.upgrade() else { return };
+            match handle.set_level(&level) {
+                Ok(()) => {
+                    app_window.set_status_message(format!("日志级别已切换为: {}", level).into());
+                    tracing::info!("日志级别已切换为: {}", level);
+                }
+                Err(e) => {
+                    app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into());
+                }
+            }
+        });
     }
 
     /// 初始化UI状态
@@ -343,6 +743,7 @@ impl ViewModelBridge {
 
         app_window.set_selected_json_path("".into());
         app_window.set_writeback_log("".into());
+        app_window.set_xlsx_enabled(cfg!(feature = "xlsx"));
 
         // 设置空的树模型
         let empty_model = ModelRc::new(VecModel::<TreeNodeData>::default());
@@ -382,14 +783,30 @@ impl ViewModelBridge {
                 return;
             }
         };
+        // 直接打开的文件不属于之前打开的项目
+        CURRENT_PROJECT.with(|cell| cell.borrow_mut().take());
+        Self::load_file_at(app_window, app_state, &file_path);
+    }
 
+    /// 加载指定文件并刷新结构树，返回是否成功
+    fn load_file_at(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>, file_path: &std::path::Path) -> bool {
         app_window.set_status_message(STATUS_LOADING.into());
         app_window.set_performance_info("".into());
 
         // 开始性能监控
         let start_time = Instant::now();
 
-        let load_result = app_state.borrow_mut().load_file(&file_path);
+        app_state.borrow_mut().reject_duplicate_keys = app_window.get_reject_duplicate_keys();
+        app_state.borrow_mut().shadow_tree_options.track_offsets = app_window.get_track_byte_offsets();
+        app_state.borrow_mut().shadow_tree_options.max_nodes = Some(TREE_MAX_NODES);
+        let mut load_result = app_state.borrow_mut().load_file(file_path);
+        // 解析失败且含注释（VSCode 设置等 JSONC 文件）时去除注释后重试
+        if matches!(load_result, Err(AppError::Parse(_)))
+            && std::fs::read_to_string(file_path).is_ok_and(|text| utils::json_comments::detect_json_comments(&text))
+        {
+            tracing::info!("文件含注释，去除注释后重新加载: {}", file_path.display());
+            load_result = app_state.borrow_mut().load_file_with_comments(file_path);
+        }
         match load_result {
             Ok(()) => {
                 let load_duration = start_time.elapsed();
@@ -405,13 +822,13 @@ impl ViewModelBridge {
                     let state = app_state.borrow();
                     let path_str = state.source_path
                         .as_ref()
-                        .map(|p| p.to_string_lossy().to_string())
+                        .map(|p| utils::fs::display_path(p))
                         .unwrap_or_default();
 
-                    // 转换树模型数据 - 只包含可见的节点
-                    let tree_data: Vec<TreeNodeData> = state.tree_flat
+                    // 转换树模型数据 - 只包含可见的节点，超大文档截断以保证首次渲染流畅
+                    let tree_data: Vec<TreeNodeData> = state
+                        .pack_for_display(TREE_DISPLAY_LIMIT, TREE_AUTO_COLLAPSE_DEPTH)
                         .iter()
-                        .filter(|node| node.visible)
                         .map(TreeNodeData::from)
                         .collect();
 
@@ -422,6 +839,7 @@ impl ViewModelBridge {
                 app_window.set_current_path(path_str.into());
                 let model = ModelRc::new(VecModel::from(tree_data));
                 app_window.set_tree_model(model);
+                app_window.set_tree_truncated(app_state.borrow().truncated);
 
                 // 初始化树控制状态
                 app_window.set_tree_flatten_mode(false);
@@ -436,118 +854,675 @@ impl ViewModelBridge {
                 );
                 app_window.set_performance_info(perf_info.into());
 
-                app_window.set_status_message(STATUS_LOADED.into());
+                if app_state.borrow().truncated {
+                    app_window.set_status_message(format!("{}：节点数超过 {}，结构树已截断", STATUS_LOADED, TREE_MAX_NODES).into());
+                } else {
+                    app_window.set_status_message(STATUS_LOADED.into());
+                }
                 tracing::info!("文件加载成功: {} 个节点，耗时: {:.2}ms",
                     node_count, load_duration.as_millis());
 
-                // 自动检测英文字段
-                Self::handle_detect_english_fields(app_window, app_state);
+                // 自动检测英文字段；只有检测实际选出了字段，重新加载时才重跑检测
+                let detected = Self::handle_detect_english_fields(app_window, app_state);
+                app_state.borrow_mut().english_fields_auto_detected = detected;
+                app_window.set_disk_changed(false);
+
+                // 重复键等加载警告
+                let warnings = app_state.borrow().load_warnings.clone();
+                if !warnings.is_empty() {
+                    tracing::warn!("文件加载警告: {:?}", warnings);
+                    app_window.invoke_show_message_dialog(
+                        "加载警告".into(),
+                        format!("源文件存在 {} 处重复键，翻译与回写可能遗漏被覆盖的值：\n\n{}", warnings.len(), warnings.join("\n")).into()
+                    );
+                }
+                true
             }
             Err(e) => {
                 let error_msg = format!("{}{}", STATUS_ERROR_PREFIX, e);
                 app_window.set_status_message(error_msg.into());
                 tracing::error!("文件加载失败: {}", e);
+                false
+            }
+        }
+    }
+
+    /// 处理节点选择操作
+    fn handle_node_selected(
+        app_window: &AppWindow,
+        app_state: &Rc<RefCell<AppState>>,
+        json_path: &str
+    ) {
+        // 截断标记不对应任何节点
+        if json_path.is_empty() {
+            return;
+        }
+
+        // 检查是否在搜索状态，如果是则不覆盖搜索结果
+
+
+        let search_text = app_window.get_search_filter().to_string();
+        if !search_text.trim().is_empty() {
+            tracing::info!("搜索状态下跳过节点选择: {}", json_path);
+            return;
+        }
+
+        app_window.set_selected_json_path(json_path.into());
+
+        // 先校验语法，给出带位置的错误而不是查询库的原始报错
+        if let Err(e) = AppState::validate_path_format(json_path) {
+            app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into());
+            app_window.set_preview_text(e.to_string().into());
+            tracing::error!("节点路径无效: {}", e);
+            return;
+        }
+
+        // 开始性能监控
+        let start_time = Instant::now();
+
+        match app_state.borrow().extract_subtree_preview(json_path, utils::jsonfmt::DEFAULT_MAX_INLINE_WIDTH) {
+            Ok(pretty_json) => {
+                let extract_duration = start_time.elapsed();
+                app_window.set_preview_text(pretty_json.into());
+
+                // 更新性能信息（保留加载信息，添加提取信息）
+                let current_perf = app_window.get_performance_info().to_string();
+                let extract_info = format!("提取: {:.1}ms", extract_duration.as_millis());
+                let new_perf = if current_perf.is_empty() {
+                    extract_info
+                } else {
+                    format!("{} | {}", current_perf, extract_info)
+                };
+                app_window.set_performance_info(new_perf.into());
+
+                // 状态栏：由与 get_node_info 相同的节点信息生成（不克隆子树，访问只计一次）
+                match app_state.borrow().node_status_line(json_path) {
+                    Ok(status) => app_window.set_status_message(status.into()),
+                    Err(e) => tracing::warn!("读取节点信息失败 {}: {}", json_path, e),
+                }
+
+                tracing::info!("节点选择成功: {}，耗时: {:.1}ms", json_path, extract_duration.as_millis());
+            }
+            Err(e) => {
+                let error_msg = format!("{}{}", STATUS_ERROR_PREFIX, e);
+                app_window.set_status_message(error_msg.into());
+                tracing::error!("节点选择失败: {}", e);
+            }
+        }
+    }
+
+    /// 处理复制按钮操作（搜索结果复制完整聚合，通配符路径复制全部匹配，节点复制完整 JSON，否则复制预览区文本）
+    fn handle_copy_pressed(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>, preview_full_text: &Rc<RefCell<String>>) {
+        let selected_path = app_window.get_selected_json_path().to_string();
+        let preview_text = app_window.get_preview_text().to_string();
+        let preview_paginated = app_window.get_preview_total_pages() > 1;
+        let format = vm::copy::CopyFormat::ALL
+            .get(app_window.get_copy_format() as usize)
+            .copied()
+            .unwrap_or_default();
+
+        let content = vm::copy::resolve_copy_content(
+            &app_state.borrow(),
+            &selected_path,
+            &preview_text,
+            &preview_full_text.borrow(),
+            preview_paginated,
+            format,
+        );
+
+        if content.text.trim().is_empty() {
+            app_window.set_status_message("错误: 没有可复制的内容".into());
+            return;
+        }
+
+        let text = content.text.clone();
+        Self::copy_text_to_clipboard(app_window, &text, move |app_window, result| match result {
+            Ok(()) => {
+                app_window.set_status_message(vm::copy::copy_status_message(&content).into());
+                tracing::info!("内容已复制到剪贴板，来源: {:?}，长度: {} 字符", content.source, content.text.len());
+            }
+            Err(e) => {
+                let error_msg = format!("{}{}", STATUS_ERROR_PREFIX, e);
+                app_window.set_status_message(error_msg.into());
+                tracing::error!("复制失败: {}", e);
+            }
+        });
+    }
+
+    /// 复制文本到剪贴板：失败时用 `slint::Timer` 安排重试而不阻塞事件循环，结束后以结果调用 `on_done`
+    fn copy_text_to_clipboard(
+        app_window: &AppWindow,
+        text: &str,
+        on_done: impl FnOnce(&AppWindow, Result<(), utils::clipboard::ClipboardError>) + 'static,
+    ) {
+        let app_window_weak = app_window.as_weak();
+        utils::clipboard::copy_to_clipboard_scheduled(
+            text,
+            |delay, retry| slint::Timer::single_shot(delay, retry),
+            move |result| {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    on_done(&app_window, result);
+                }
+            },
+        );
+    }
+
+    /// 处理批量导出：解析映射并逐项导出，汇总失败项
+    fn handle_batch_export(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>, spec: &str) {
+        let base_dir = app_state
+            .borrow()
+            .original_file_path
+            .as_ref()
+            .and_then(|p| p.parent().map(|d| d.to_path_buf()));
+        let exports = match vm::export::parse_export_spec(spec, base_dir.as_deref()) {
+            Ok(exports) => exports,
+            Err(e) => {
+                app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into());
+                return;
+            }
+        };
+
+        let result = app_state.borrow().bulk_export_subtrees(&exports);
+        match result {
+            Ok(failures) if failures.is_empty() => {
+                app_window.set_batch_export_visible(false);
+                app_window.set_status_message(format!("批量导出完成: {} 个文件", exports.len()).into());
+                tracing::info!("批量导出完成: {} 个文件", exports.len());
+            }
+            Ok(failures) => {
+                let details: Vec<String> = failures
+                    .iter()
+                    .map(|(path, e)| format!("{}: {}", path.display(), e))
+                    .collect();
+                app_window.set_status_message(
+                    format!("批量导出部分完成: 成功 {} 个，失败 {} 个", exports.len() - failures.len(), failures.len()).into()
+                );
+                app_window.invoke_show_message_dialog("批量导出失败项".into(), details.join("\n").into());
+            }
+            Err(e) => {
+                app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into());
+                tracing::error!("批量导出失败: {}", e);
+            }
+        }
+    }
+
+    /// 重命名对象键（作用域为空时作用于整个文档），成功后刷新结构树
+    fn handle_rename_key(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>, old: &str, new: &str, scope: &str) {
+        let scope = scope.trim();
+        let scope = (!scope.is_empty()).then_some(scope);
+        let result = app_state.borrow_mut().rename_key(old.trim(), new.trim(), scope);
+        match result {
+            Ok(0) => app_window.set_status_message(format!("未找到键: {}", old.trim()).into()),
+            Ok(count) => {
+                app_window.set_rename_key_visible(false);
+                Self::rebuild_tree_model(app_window, app_state);
+                app_window.set_status_message(format!("已将 {} 处 {} 重命名为 {}", count, old.trim(), new.trim()).into());
+                tracing::info!("重命名键: {} -> {}（{} 处，作用域 {}）", old.trim(), new.trim(), count, scope.unwrap_or("$"));
+            }
+            Err(e) => {
+                app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into());
+                app_window.invoke_show_message_dialog("重命名失败".into(), e.to_string().into());
+                tracing::error!("重命名键失败: {}", e);
+            }
+        }
+    }
+
+    /// 删除结构树中的节点（可撤销），成功后刷新结构树
+    fn handle_delete_node(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>, path: &str) {
+        let result = app_state.borrow_mut().delete_node(path);
+        match result {
+            Ok(_) => {
+                Self::rebuild_tree_model(app_window, app_state);
+                if app_window.get_selected_json_path().as_str() == path {
+                    app_window.set_selected_json_path("".into());
+                    app_window.set_preview_text("".into());
+                }
+                app_window.set_status_message(format!("已删除节点: {}", path).into());
+                tracing::info!("删除节点: {}", path);
+            }
+            Err(e) => {
+                app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into());
+                tracing::error!("删除节点失败: {}", e);
+            }
+        }
+    }
+
+    /// 复制节点到同级位置（对象字段自动命名），成功后刷新结构树并选中副本
+    fn handle_duplicate_node(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>, path: &str) {
+        let result = app_state.borrow_mut().duplicate_node(path, None);
+        match result {
+            Ok(new_path) => {
+                Self::rebuild_tree_model(app_window, app_state);
+                Self::handle_node_selected(app_window, app_state, &new_path);
+                app_window.set_status_message(format!("已复制节点: {} -> {}", path, new_path).into());
+                tracing::info!("复制节点: {} -> {}", path, new_path);
+            }
+            Err(e) => {
+                app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into());
+                tracing::error!("复制节点失败: {}", e);
+            }
+        }
+    }
+
+    /// 将节点子树另存为单独的JSON文件，保存对话框默认文件名为 `<键名>.json`
+    fn handle_export_node_to_file(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>, path: &str) {
+        let name = app_state.borrow().tree_flat.iter().find(|n| n.path == path).map(|n| n.name.clone()).unwrap_or_default();
+        let Some(output) = rfd::FileDialog::new()
+            .add_filter("JSON文件", &["json"])
+            .set_title("导出此节点为文件")
+            .set_file_name(subtree_file_name(&name))
+            .save_file()
+        else {
+            return;
+        };
+        match app_state.borrow().save_subtree(&output, path, app_state.borrow().save_options()) {
+            Ok(_) => {
+                app_window.set_status_message(format!("已导出 {} 到: {}", path, output.display()).into());
+                tracing::info!("导出节点: {} -> {}", path, output.display());
+            }
+            Err(e) => {
+                app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into());
+                tracing::error!("导出节点失败: {}", e);
+            }
+        }
+    }
+
+    /// 选择要并入节点的外部JSON文件，再由对话框选择合并方式
+    fn handle_merge_subtree_from_file(app_window: &AppWindow, path: &str) {
+        let Some(file) = rfd::FileDialog::new()
+            .add_filter("JSON文件", &["json"])
+            .set_title("选择要合并的JSON文件")
+            .pick_file()
+        else {
+            return;
+        };
+        app_window.set_merge_subtree_path(path.into());
+        app_window.set_merge_subtree_file(file.display().to_string().into());
+        app_window.set_merge_subtree_visible(true);
+    }
+
+    /// 按选择的方式将外部文件并入节点，成功后刷新结构树
+    fn handle_merge_subtree_confirmed(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>, path: &str, file: &str, merge_keys: bool) {
+        app_window.set_merge_subtree_visible(false);
+        let mode = if merge_keys { SubtreeMergeMode::MergeKeys } else { SubtreeMergeMode::Replace };
+        let result = app_state.borrow_mut().replace_subtree_from_file(path, std::path::Path::new(file), mode);
+        match result {
+            Ok(report) => {
+                Self::rebuild_tree_model(app_window, app_state);
+                app_window.set_status_message(format!("已合并到 {}: {}", path, report.summary()).into());
+                for conflict in &report.type_conflicts {
+                    tracing::warn!("合并时类型冲突，已整体覆盖: {}", conflict);
+                }
+                tracing::info!("合并子树 {} <- {}: {}", path, file, report.summary());
+            }
+            Err(e) => {
+                app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into());
+                tracing::error!("合并子树失败: {}", e);
+            }
+        }
+    }
+
+    /// 在对象/数组节点下插入子节点，成功后刷新结构树
+    fn handle_insert_child(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>, parent_path: &str, key: &str, value_json: &str) {
+        let result = app_state.borrow_mut().insert_child(parent_path, key.trim(), value_json);
+        match result {
+            Ok(new_path) => {
+                app_window.set_insert_child_visible(false);
+                app_window.set_insert_child_key("".into());
+                app_window.set_insert_child_value("".into());
+                Self::rebuild_tree_model(app_window, app_state);
+                app_window.set_status_message(format!("已插入节点: {}", new_path).into());
+                tracing::info!("插入节点: {}", new_path);
+            }
+            Err(e) => {
+                app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into());
+                tracing::error!("插入节点失败: {}", e);
+            }
+        }
+    }
+
+    /// 界面上的中间产物2选项（叶子节点、排序、锚点字段）
+    fn stage2_options(app: &AppWindow) -> Stage2Options {
+        Stage2Options {
+            leaf_nodes_only: app.get_leaf_nodes_only(),
+            sort: Stage2Sort::ALL.get(app.get_stage2_sort() as usize).copied().unwrap_or_default(),
+            max_value_chars: Some(STAGE2_MAX_VALUE_CHARS),
+            oversized: OversizedValues::Exclude,
+            include_context: true,
+            anchor_field: Some(app.get_anchor_field().trim().to_string()).filter(|field| !field.is_empty()),
+        }
+    }
+
+    /// 预览提取：只构建前 [`STAGE2_PREVIEW_LIMIT`] 条中间产物2条目，显示在预览区（不替换已生成的中间产物）
+    fn handle_preview_stage2_pressed(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>) {
+        let filter = app_window.get_search_filter().to_string();
+        if filter.trim().is_empty() {
+            app_window.set_status_message("错误: 过滤条件为空".into());
+            return;
+        }
+        let state = app_state.borrow();
+        match state.preview_stage2(&filter, Self::stage2_options(app_window), STAGE2_PREVIEW_LIMIT) {
+            Ok(preview) => {
+                app_window.set_selected_json_path("[预览提取]".into());
+                app_window.set_preview_text(preview.into());
+                app_window.set_status_message(format!("已预览中间产物2（最多 {} 条）", STAGE2_PREVIEW_LIMIT).into());
+            }
+            Err(e) => app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into()),
+        }
+    }
+
+    /// 处理提取全部操作：按搜索框中的（通配符）JSONPath 提取所有匹配并复制为 JSON 数组
+    fn handle_extract_all_pressed(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>) {
+        let json_path = app_window.get_search_filter().trim().to_string();
+        if !json_path.starts_with('$') {
+            app_window.set_status_message(format!("{}请在搜索框输入JSONPath（如 $.items[*].name）", STATUS_ERROR_PREFIX).into());
+            return;
+        }
+
+        let result = app_state.borrow().extract_all_matching(&json_path);
+        let hits = match result {
+            Ok(hits) => hits,
+            Err(e) => {
+                app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into());
+                tracing::error!("提取全部失败: {}", e);
+                return;
+            }
+        };
+
+        let pretty = match serde_json::to_string_pretty(&hits) {
+            Ok(pretty) => pretty,
+            Err(e) => {
+                app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into());
+                return;
+            }
+        };
+
+        app_window.set_selected_json_path(json_path.clone().into());
+        app_window.set_preview_text(pretty.clone().into());
+        let hit_count = hits.len();
+        Self::copy_text_to_clipboard(app_window, &pretty, move |app_window, result| match result {
+            Ok(()) => {
+                app_window.set_status_message(format!("已提取 {} 个匹配并复制到剪贴板", hit_count).into());
+                tracing::info!("提取全部: {} -> {} 个匹配", json_path, hit_count);
+            }
+            Err(e) => {
+                app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into());
+                tracing::error!("复制失败: {}", e);
+            }
+        });
+    }
+
+    /// 将当前搜索条件的匹配结果保存为 JSON 文件，便于离线查看
+    fn handle_save_search_results_pressed(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>) {
+        let filter = app_window.get_search_filter().trim().to_string();
+        if filter.is_empty() {
+            app_window.set_status_message(format!("{}请先输入搜索条件", STATUS_ERROR_PREFIX).into());
+            return;
+        }
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("JSON文件", &["json"])
+            .set_title("保存搜索结果")
+            .set_file_name("search_results.json")
+            .save_file()
+        else {
+            return;
+        };
+
+        let result = app_state.borrow().save_search_results(&filter, &path);
+        match result {
+            Ok(count) => {
+                app_window.set_status_message(format!("已保存 {} 个搜索结果: {}", count, path.display()).into());
+                tracing::info!("保存搜索结果: {} -> {} ({} 个匹配)", filter, path.display(), count);
+            }
+            Err(e) => {
+                app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into());
+                tracing::error!("保存搜索结果失败: {}", e);
+            }
+        }
+    }
+
+    /// 统计选中节点（未选中时为整个文档）下最常见的字符串值，在预览区列出可点击的条目
+    fn handle_value_histogram_pressed(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>) {
+        const HISTOGRAM_TOP_N: usize = 20;
+        let selected_path = app_window.get_selected_json_path().to_string();
+        let scope = selected_path.starts_with('$').then_some(selected_path.as_str());
+
+        let result = app_state.borrow().value_histogram(scope, HISTOGRAM_TOP_N);
+        match result {
+            Ok(entries) => {
+                let items: Vec<HistogramEntry> = entries
+                    .iter()
+                    .map(|(value, count)| HistogramEntry { value: value.clone().into(), count: *count as i32 })
+                    .collect();
+                app_window.set_histogram_entries(ModelRc::new(VecModel::from(items)));
+                app_window.set_status_message(format!("值频率: {} 下前 {} 个高频值", scope.unwrap_or("$"), entries.len()).into());
+            }
+            Err(e) => {
+                app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into());
+                tracing::error!("值频率统计失败: {}", e);
+            }
+        }
+    }
+
+    /// 列出体积最大的子树（排除已列出节点的祖先），在预览区显示可点击定位的条目
+    fn handle_largest_subtrees_pressed(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>) {
+        const LARGEST_TOP_N: usize = 20;
+        let entries = app_state.borrow().largest_subtrees(LARGEST_TOP_N);
+        if entries.is_empty() {
+            app_window.set_status_message(format!("{}请先加载JSON文件", STATUS_ERROR_PREFIX).into());
+            return;
+        }
+        let items: Vec<HeavySubtreeEntry> = entries
+            .iter()
+            .map(|(path, size)| HeavySubtreeEntry { path: path.clone().into(), size: format_byte_size(*size).into() })
+            .collect();
+        app_window.set_heavy_subtrees(ModelRc::new(VecModel::from(items)));
+        app_window.set_status_message(format!("体积最大的 {} 个子树，最大 {}", entries.len(), format_byte_size(entries[0].1)).into());
+    }
+
+    /// 展开节点的所有祖先并选中该节点
+    fn handle_reveal_node(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>, path: &str) {
+        let result = app_state.borrow_mut().reveal_node(path);
+        match result {
+            Ok(()) => {
+                Self::rebuild_tree_model(app_window, app_state);
+                app_window.set_selected_json_path(path.into());
+                Self::handle_node_selected(app_window, app_state, path);
+            }
+            Err(e) => app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into()),
+        }
+    }
+
+    /// 分析选中数组节点的元素结构，报告显示在预览区
+    fn handle_schema_report_pressed(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>) {
+        let selected_path = app_window.get_selected_json_path().to_string();
+        if !selected_path.starts_with('$') {
+            app_window.set_status_message(format!("{}请先在结构树中选择数组节点", STATUS_ERROR_PREFIX).into());
+            return;
+        }
+
+        let result = app_state.borrow().array_schema_report(&selected_path);
+        match result {
+            Ok(report) => {
+                app_window.set_preview_text(report.render().into());
+                let summary = if report.is_homogeneous() {
+                    "结构一致".to_string()
+                } else {
+                    format!("{} 个键结构不一致", report.keys.iter().filter(|k| k.heterogeneous).count())
+                };
+                app_window.set_status_message(format!("结构分析: {} 共 {} 个元素，{}", selected_path, report.elements, summary).into());
+            }
+            Err(e) => {
+                app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into());
+                tracing::error!("结构分析失败: {}", e);
             }
         }
     }
 
-    /// 处理节点选择操作
-    fn handle_node_selected(
-        app_window: &AppWindow,
-        app_state: &Rc<RefCell<AppState>>,
-        json_path: &str
-    ) {
-        // 检查是否在搜索状态，如果是则不覆盖搜索结果
+    /// 估算翻译工作量，可选一个参考语言文件计算相同比例，报告显示在预览区
+    fn handle_effort_estimate_pressed(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>) {
+        // 参考文件可选：取消选择时只统计当前文件
+        let reference = rfd::FileDialog::new()
+            .add_filter("JSON文件", &["json"])
+            .set_title("选择参考语言文件（可取消）")
+            .pick_file()
+            .map(|path| {
+                let mut reference = AppState::default();
+                reference.load_file(&path).map(|_| reference)
+            })
+            .transpose();
+        let reference = match reference {
+            Ok(reference) => reference,
+            Err(e) => {
+                app_window.set_status_message(format!("{}参考文件加载失败: {}", STATUS_ERROR_PREFIX, e).into());
+                return;
+            }
+        };
 
+        let estimate = app_state.borrow().estimate_translation_effort(reference.as_ref());
+        app_window.set_preview_text(estimate.render().into());
+        app_window.set_status_message(
+            format!("工作量估算: {} 词，{} 个去重字符串", estimate.total_words, estimate.unique_strings).into(),
+        );
+    }
 
-        let search_text = app_window.get_search_filter().to_string();
-        if !search_text.trim().is_empty() {
-            tracing::info!("搜索状态下跳过节点选择: {}", json_path);
+    /// 选择 JSON Schema，此后另存为与回写保存前自动校验；取消选择则清除 Schema
+    fn handle_load_schema_pressed(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("JSON Schema", &["json"])
+            .set_title("选择 JSON Schema（取消则不再校验）")
+            .pick_file()
+        else {
+            app_state.borrow_mut().clear_schema();
+            app_window.set_schema_file_name("".into());
+            app_window.set_status_message("已清除 JSON Schema".into());
             return;
-        }
-
-        app_window.set_selected_json_path(json_path.into());
+        };
 
-        // 开始性能监控
-        let start_time = Instant::now();
+        let mut state = app_state.borrow_mut();
+        if let Err(e) = state.set_schema(&path) {
+            app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into());
+            return;
+        }
+        let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let violations = state.validate_dom();
+        app_window.set_schema_file_name(name.clone().into());
+        app_window.set_status_message(format!("已加载 Schema: {}，当前文档 {} 处违规", name, violations.len()).into());
+    }
 
-        match app_state.borrow().extract_subtree_pretty(json_path) {
-            Ok(pretty_json) => {
-                let extract_duration = start_time.elapsed();
-                app_window.set_preview_text(pretty_json.into());
+    /// 弹出 Schema 违规确认对话框，确认后按 `pending` 保存
+    fn confirm_schema_save(app_window: &AppWindow, violations: &[SchemaViolation], pending: PendingSchemaSave) {
+        const MAX_LISTED: usize = 20;
+        let mut text: Vec<String> = violations.iter().take(MAX_LISTED).map(|v| v.to_string()).collect();
+        if violations.len() > MAX_LISTED {
+            text.push(format!("……另有 {} 处", violations.len() - MAX_LISTED));
+        }
+        text.push(String::new());
+        text.push("是否仍然保存？".to_string());
+        PENDING_SCHEMA_SAVE.with(|slot| *slot.borrow_mut() = Some(pending));
+        app_window.set_schema_confirm_text(text.join("\n").into());
+        app_window.set_schema_confirm_visible(true);
+    }
 
-                // 更新性能信息（保留加载信息，添加提取信息）
-                let current_perf = app_window.get_performance_info().to_string();
-                let extract_info = format!("提取: {:.1}ms", extract_duration.as_millis());
-                let new_perf = if current_perf.is_empty() {
-                    extract_info
-                } else {
-                    format!("{} | {}", current_perf, extract_info)
-                };
-                app_window.set_performance_info(new_perf.into());
+    /// 确认忽略 Schema 违规后执行待定的保存
+    fn handle_schema_save_anyway(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>) {
+        app_window.set_schema_confirm_visible(false);
+        let Some(pending) = PENDING_SCHEMA_SAVE.with(|slot| slot.borrow_mut().take()) else {
+            return;
+        };
+        match pending {
+            PendingSchemaSave::SaveAs(path) => match app_state.borrow().save_to_file_ignoring_schema(&path) {
+                Ok(()) => app_window.set_status_message(format!("已忽略 Schema 违规保存到: {}", path.display()).into()),
+                Err(e) => app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into()),
+            },
+            PendingSchemaSave::Writeback { path, json, keep_backups } => {
+                let save_options = app_state.borrow().save_options();
+                let result = Self::write_writeback_output(&path, &json, keep_backups, &save_options, &|message| {
+                    Self::append_writeback_log(app_window, &message)
+                });
+                match result {
+                    Ok(()) => Self::show_reload_prompt(app_window, &path),
+                    Err(e) => {
+                        Self::append_writeback_log(app_window, &format!("❌ 保存失败: {}", e));
+                        app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into());
+                    }
+                }
+            }
+        }
+    }
 
-                tracing::info!("节点选择成功: {}，耗时: {:.1}ms", json_path, extract_duration.as_millis());
+    /// 尝试开始长耗时操作；已有任务进行中时提示并返回 None
+    fn begin_busy_operation(app_window: &AppWindow, operation: &str) -> Option<u64> {
+        match OPERATION_GUARD.with(|guard| guard.borrow_mut().try_begin(operation)) {
+            Ok(token) => {
+                PROGRESS_AUTO_HIDE.with(|auto_hide| auto_hide.borrow_mut().invalidate());
+                app_window.set_is_busy(true);
+                app_window.set_busy_operation(operation.into());
+                Some(token)
             }
-            Err(e) => {
-                let error_msg = format!("{}{}", STATUS_ERROR_PREFIX, e);
-                app_window.set_status_message(error_msg.into());
-                tracing::error!("节点选择失败: {}", e);
+            Err(msg) => {
+                tracing::warn!("{}", msg);
+                app_window.set_status_message(msg.into());
+                None
             }
         }
     }
 
-    /// 处理复制按钮操作（优先复制选中节点的完整 JSON；否则复制预览区文本）
-    fn handle_copy_pressed(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>) {
-        let selected_path = app_window.get_selected_json_path().to_string();
-        let preview_text = app_window.get_preview_text().to_string();
+    /// 约一帧（[`FRAME_YIELD_MS`]）后由事件循环的定时器继续执行 `resume`，配合 [`yield_to_event_loop`] 使用
+    fn resume_after_frame(resume: Box<dyn FnOnce()>) {
+        slint::Timer::single_shot(std::time::Duration::from_millis(FRAME_YIELD_MS), resume);
+    }
 
-        // 优先尝试基于选中路径提取完整 JSON（保持原始深度）
-        let content_to_copy = if !selected_path.is_empty() && selected_path.starts_with("$") && !selected_path.starts_with("搜索结果") {
-            match app_state.borrow().extract_subtree_pretty(&selected_path) {
-                Ok(pretty) => Some(pretty),
-                Err(e) => {
-                    tracing::warn!("基于路径提取失败，将回退使用预览文本: {}", e);
-                    None
+    /// 当前长耗时操作的取消标志（在 [`Self::begin_busy_operation`] 成功后获取）
+    fn busy_cancel_flag() -> CancelFlag {
+        OPERATION_GUARD.with(|guard| guard.borrow().cancel_flag())
+    }
+
+    /// 更新进度条，并附带已用时间与预计剩余时间
+    fn update_progress(app_window: &AppWindow, value: f32, text: &str, timing: ProgressTiming) {
+        let (elapsed, eta) = format_progress_timing(&timing);
+        app_window.invoke_update_progress(value, text.into(), elapsed.into(), eta.into());
+    }
+
+    /// 进度条显示完成状态，停留片刻后自动隐藏（期间开始新任务则不隐藏）
+    fn complete_progress_and_auto_hide(app_window: &AppWindow, timing: ProgressTiming) {
+        Self::update_progress(app_window, 1.0, "完成", timing);
+        let generation = PROGRESS_AUTO_HIDE.with(|auto_hide| auto_hide.borrow_mut().schedule());
+        let app_window_weak = app_window.as_weak();
+        slint::Timer::single_shot(std::time::Duration::from_millis(PROGRESS_COMPLETE_HOLD_MS), move || {
+            if PROGRESS_AUTO_HIDE.with(|auto_hide| auto_hide.borrow().should_hide(generation)) {
+                if let Some(app_window) = app_window_weak.upgrade() {
+                    app_window.invoke_hide_progress();
                 }
             }
-        } else {
-            None
-        };
-
-        let final_text = content_to_copy.unwrap_or(preview_text);
+        });
+    }
 
-        if final_text.trim().is_empty() {
-            app_window.set_status_message("错误: 没有可复制的内容".into());
-            return;
+    /// 结束长耗时操作（操作已被取消时忽略）
+    fn finish_busy_operation(app_window: &AppWindow, token: u64) {
+        if OPERATION_GUARD.with(|guard| guard.borrow_mut().finish(token)) {
+            app_window.set_is_busy(false);
+            app_window.set_busy_operation("".into());
         }
+    }
 
-        match utils::clipboard::copy_to_clipboard(&final_text) {
-            Ok(()) => {
-                app_window.set_status_message(STATUS_COPIED.into());
-                tracing::info!("内容已复制到剪贴板，长度: {} 字符", final_text.len());
-            }
-            Err(e) => {
-                let error_msg = format!("{}{}", STATUS_ERROR_PREFIX, e);
-                app_window.set_status_message(error_msg.into());
-                tracing::error!("复制失败: {}", e);
-            }
+    /// 取消当前长耗时操作：清除守卫并隐藏进度条
+    fn handle_cancel_operation(app_window: &AppWindow) {
+        let cancelled = OPERATION_GUARD.with(|guard| guard.borrow_mut().cancel());
+        app_window.set_is_busy(false);
+        app_window.set_busy_operation("".into());
+        app_window.invoke_hide_progress();
+        if let Some(operation) = cancelled {
+            tracing::info!("用户取消操作: {}", operation);
+            app_window.set_status_message(format!("{}: {}", STATUS_CANCELLED, operation).into());
         }
     }
 
     /// 添加日志到回写日志区域（异步版本，避免阻塞UI线程）
     fn append_writeback_log(app_window: &AppWindow, message: &str) {
         let current_log = app_window.get_writeback_log().to_string();
-        let timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_millis() % 86400000; // 取一天内的毫秒数
-        let seconds = timestamp / 1000;
-        let millis = timestamp % 1000;
-        let hours = seconds / 3600;
-        let minutes = (seconds % 3600) / 60;
-        let secs = seconds % 60;
-        let time_str = format!("{:02}:{:02}:{:02}.{:03}", hours, minutes, secs, millis);
-        let new_entry = format!("[{}] {}", time_str, message);
+        // 本地时间 + 单调序号 + 操作耗时
+        let new_entry = vm::writeback_log::WritebackLogEntry::now(message).format();
         let updated_log = if current_log.is_empty() {
             new_entry
         } else {
@@ -566,6 +1541,75 @@ impl ViewModelBridge {
 
 
 
+    /// 多语言分发：选择以语言代码命名的译文目录（`zh-CN.json` …），每种语言回写一份 `{源文件名}.{语言}.json` 到产物目录
+    fn handle_fan_out_pressed(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>, preview_full_text: &Rc<RefCell<String>>) {
+        let stage2_text = preview_full_text.borrow().clone();
+        if stage2_text.trim().is_empty() {
+            app_window.set_status_message("错误: 中间产物为空，请先生成中间产物".into());
+            return;
+        }
+        let Some(dir) = rfd::FileDialog::new().set_title("选择译文目录（文件按语言代码命名）").pick_folder() else {
+            app_window.set_status_message("用户取消了目录选择".into());
+            return;
+        };
+
+        let (dom, source) = {
+            let state = app_state.borrow();
+            (state.dom.clone(), state.original_file_path.clone())
+        };
+        let Some(dom) = dom else {
+            app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, AppError::not_loaded()).into());
+            return;
+        };
+        let out_dir = utils::fs::artifacts_dir(source.as_deref());
+        let opts = model::batch::FanOutOptions {
+            file_stem: source.as_deref().and_then(|p| p.file_stem()).map_or_else(|| "app".to_string(), |s| s.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+
+        // 每种语言都要克隆并写出整份文档，在后台线程执行
+        let Some(token) = Self::begin_busy_operation(app_window, "多语言分发") else {
+            return;
+        };
+        let cancel = Self::busy_cancel_flag();
+        app_window.invoke_show_progress("正在多语言分发...".into());
+        let app_window_weak = app_window.as_weak();
+        std::thread::spawn(move || {
+            let result = serde_json::from_str::<Value>(&stage2_text)
+                .map_err(AppError::from)
+                .and_then(|stage2| {
+                    std::fs::create_dir_all(&out_dir).map_err(|e| AppError::io(e, &out_dir))?;
+                    model::batch::fan_out_dir_with_cancel(&dom, &stage2, &dir, &out_dir, &opts, &|| cancel.is_cancelled())
+                });
+            let _ = slint::invoke_from_event_loop(move || {
+                let Some(app_window) = app_window_weak.upgrade() else {
+                    return;
+                };
+                if cancel.is_cancelled() {
+                    Self::append_writeback_log(&app_window, "⏹️ 多语言分发已取消，未处理的语言不再写出");
+                    return;
+                }
+                app_window.invoke_hide_progress();
+                match result {
+                    Ok(results) => {
+                        for locale in &results {
+                            Self::append_writeback_log(&app_window, &format!("🌐 {}", locale.describe()));
+                        }
+                        let failed = results.iter().filter(|r| !r.is_ok()).count();
+                        app_window.set_status_message(
+                            format!("多语言分发完成: 成功 {} 种，失败 {} 种，输出目录: {}", results.len() - failed, failed, out_dir.display()).into(),
+                        );
+                    }
+                    Err(e) => {
+                        app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into());
+                        tracing::error!("多语言分发失败: {}", e);
+                    }
+                }
+                Self::finish_busy_operation(&app_window, token);
+            });
+        });
+    }
+
     /// 一键获得最终产物：自动执行生成中间产物2 + 转换为最终产物
     fn handle_one_click_final_product(
         app_window: &AppWindow,
@@ -599,6 +1643,11 @@ impl ViewModelBridge {
             return;
         }
 
+        let Some(token) = Self::begin_busy_operation(app_window, "一键获得最终产物") else {
+            return;
+        };
+        let cancel = Self::busy_cancel_flag();
+
         // 显示进度条
         app_window.invoke_show_progress("正在一键生成最终产物...".into());
 
@@ -610,32 +1659,44 @@ impl ViewModelBridge {
         let filter_clone = effective_filter.clone();
 
         slint::spawn_local(async move {
+            // 让出一轮，进度条先渲染再开始耗时工作
+            yield_to_event_loop(Self::resume_after_frame).await;
             tracing::info!("一键获得最终产物：开始执行");
+            // 整个操作共用一个节流器，已用/剩余时间按总体进度估算
+            let mut throttle = ThrottledProgress::default();
 
             // 第一阶段：生成中间产物2
-            app_weak.upgrade().map(|app| app.invoke_update_progress(0.1, "正在生成中间产物...".into()));
+            if let Some(app) = app_weak.upgrade() {
+                ViewModelBridge::update_progress(&app, 0.1, "正在生成中间产物...", throttle.timing(0.1));
+            }
 
-            let app_weak_progress = app_weak.clone();
-            let progress_callback = move |progress: f32, message: &str| {
+            let progress_callback = |progress: f32, message: &str| {
                 // 将进度映射到0.1-0.5范围（第一阶段占50%）
                 let mapped_progress = 0.1 + progress * 0.4;
-                if let Some(app) = app_weak_progress.upgrade() {
-                    app.invoke_update_progress(mapped_progress, format!("阶段1: {}", message).into());
-                }
+                throttle.report(mapped_progress, message, |progress, message, timing| {
+                    if let Some(app) = app_weak.upgrade() {
+                        ViewModelBridge::update_progress(&app, progress, &format!("阶段1: {}", message), timing);
+                    }
+                });
             };
 
-            let leaf_nodes_only = if let Some(app) = app_weak.upgrade() {
-                app.get_leaf_nodes_only()
-            } else {
-                false
-            };
+            let stage2_options = app_weak.upgrade().map(|app| Self::stage2_options(&app)).unwrap_or_default();
+            tracing::info!("中间产物排序方式: {}", stage2_options.sort.label());
 
-            match app_state_clone.borrow().build_intermediate_stage2_with_leaf_filter(&filter_clone, leaf_nodes_only, progress_callback) {
+            match app_state_clone.borrow().build_intermediate_stage2_multi(&parse_filter_list(&filter_clone), stage2_options, progress_callback) {
+                Ok(_) if cancel.is_cancelled() => tracing::info!("一键获得最终产物：已取消，丢弃中间产物"),
                 Ok(stage2_json) => {
                     tracing::info!("一键获得最终产物：中间产物2生成成功");
+                    // 补发阶段1被限流丢弃的最后一条进度
+                    throttle.flush(|progress, message, timing| {
+                        if let Some(app) = app_weak.upgrade() {
+                            ViewModelBridge::update_progress(&app, progress, &format!("阶段1: {}", message), timing);
+                        }
+                    });
 
                     // 保存中间产物到preview_full_text
                     *preview_full_text_clone.borrow_mut() = stage2_json.clone();
+                    Self::record_project_extraction(&filter_clone, &stage2_json);
 
                     if let Some(app) = app_weak.upgrade() {
                         // 显示中间产物2在预览区域
@@ -645,63 +1706,36 @@ impl ViewModelBridge {
                         app.set_preview_total_pages(total_pages);
                         app.set_selected_json_path("中间产物第二阶段".into());
 
-                        app.invoke_update_progress(0.5, "正在转换为最终产物...".into());
+                        ViewModelBridge::update_progress(&app, 0.5, "正在转换为最终产物...", throttle.timing(0.5));
 
                         // 第二阶段：转换为最终产物
-                        match serde_json::from_str::<Value>(&stage2_json) {
-                            Ok(v) => {
-                                app.invoke_update_progress(0.6, "正在处理数据项...".into());
-
-                                // 使用BTreeMap自动排序
-                                let mut out = std::collections::BTreeMap::new();
-
-                                if let Some(items) = v.get("items").and_then(|x| x.as_array()) {
-                                    let total_items = items.len();
-                                    for (index, item) in items.iter().enumerate() {
-                                        // 将进度映射到0.6-0.8范围
-                                        let progress = 0.6 + (index as f32 / total_items as f32) * 0.2;
-                                        if index % 100 == 0 || index == total_items - 1 {
-                                            app.invoke_update_progress(progress, format!("阶段2: 处理项目 {}/{}", index + 1, total_items).into());
-                                        }
-
-                                        let seq = item.get("seq").and_then(|s| s.as_u64()).unwrap_or(0);
-                                        let name_val = item.get("name").and_then(|n| n.as_str()).unwrap_or("");
-                                        out.insert(seq.to_string(), serde_json::Value::String(name_val.to_string()));
-                                    }
-                                }
-
-                                app.invoke_update_progress(0.8, "正在构建最终JSON...".into());
-
-                                // 构建最终JSON
-                                let final_json = serde_json::Value::Object(out.into_iter().collect());
-                                match serde_json::to_string_pretty(&final_json) {
-                                    Ok(s) => {
-                                        app.invoke_update_progress(0.9, "正在格式化输出...".into());
-
-                                        // 保存完整文本
-                                        *final_full_text_clone.borrow_mut() = s.clone();
-
-                                        // 计算分页并显示第一页
-                                        let (page_text, total_pages) = ViewModelBridge::paginate_text(&s, 1, 300);
-                                        app.set_final_product_text(page_text.into());
-                                        app.set_final_current_page(1);
-                                        app.set_final_total_pages(total_pages);
-
-                                        app.invoke_update_progress(1.0, "完成".into());
-                                        app.set_status_message("一键获得最终产物完成！".into());
-
-                                        // 隐藏进度条
-                                        app.invoke_hide_progress();
+                        let options = FinalOptions { shape: Self::final_shape(&app), ..Default::default() };
+                        let result = build_final_product_with_progress(&stage2_json, options, |fraction, message| {
+                            // 将进度映射到0.6-0.9范围
+                            let progress = 0.6 + fraction * 0.3;
+                            if throttle.should_forward(progress) || fraction >= 1.0 {
+                                ViewModelBridge::update_progress(&app, progress, &format!("阶段2: {}", message), throttle.timing(progress));
+                            }
+                        });
+                        match result {
+                            Ok(_) if cancel.is_cancelled() => tracing::info!("一键获得最终产物：已取消，丢弃最终产物"),
+                            Ok(s) => {
+                                // 保存完整文本
+                                *final_full_text_clone.borrow_mut() = s.clone();
+
+                                // 计算分页并显示第一页
+                                let (page_text, total_pages) = ViewModelBridge::paginate_text(&s, 1, 300);
+                                app.set_final_product_text(page_text.into());
+                                app.set_final_current_page(1);
+                                app.set_final_total_pages(total_pages);
+
+                                ViewModelBridge::update_progress(&app, 1.0, "完成", throttle.timing(1.0));
+                                app.set_status_message("一键获得最终产物完成！".into());
+
+                                // 隐藏进度条
+                                app.invoke_hide_progress();
 
-                                        tracing::info!("一键获得最终产物：执行成功");
-                                    }
-                                    Err(e) => {
-                                        app.invoke_hide_progress();
-                                        let msg = format!("{}最终产物格式化失败: {}", STATUS_ERROR_PREFIX, e);
-                                        app.set_status_message(msg.into());
-                                        tracing::error!("一键获得最终产物：最终产物格式化失败: {}", e);
-                                    }
-                                }
+                                tracing::info!("一键获得最终产物：执行成功");
                             }
                             Err(e) => {
                                 app.invoke_hide_progress();
@@ -721,6 +1755,10 @@ impl ViewModelBridge {
                     tracing::error!("一键获得最终产物：生成中间产物失败: {}", e);
                 }
             }
+
+            if let Some(app) = app_weak.upgrade() {
+                ViewModelBridge::finish_busy_operation(&app, token);
+            }
         }).unwrap();
     }
 
@@ -729,8 +1767,15 @@ impl ViewModelBridge {
         app_window: &AppWindow,
         app_state: &Rc<RefCell<AppState>>
     ) {
-        // 目前使用硬编码路径进行测试（后续可添加文件对话框）
-        let save_path = std::path::Path::new("output.json");
+        // 输出到产物目录（`<原文件名>.output.json`），不污染源文件所在目录
+        let save_path = utils::fs::artifact_path(utils::fs::ArtifactKind::Output, app_state.borrow().original_file_path.as_deref());
+        if let Some(dir) = save_path.parent() {
+            if let Err(e) = std::fs::create_dir_all(dir) {
+                app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, AppError::io(e, dir)).into());
+                return;
+            }
+        }
+        let save_path = save_path.as_path();
 
         // 开始性能监控
         let start_time = Instant::now();
@@ -756,6 +1801,10 @@ impl ViewModelBridge {
 
                 tracing::info!("文件保存成功: {}，耗时: {:.1}ms", save_path.display(), save_duration.as_millis());
             }
+            Err(AppError::SchemaViolations(violations)) => {
+                tracing::warn!("保存前 Schema 校验未通过: {} 处违规", violations.len());
+                Self::confirm_schema_save(app_window, &violations, PendingSchemaSave::SaveAs(save_path.to_path_buf()));
+            }
             Err(e) => {
                 let error_msg = format!("{}{}", STATUS_ERROR_PREFIX, e);
                 app_window.set_status_message(error_msg.into());
@@ -764,16 +1813,26 @@ impl ViewModelBridge {
         }
     }
 
-    /// 处理搜索过滤改变
+    /// 处理搜索过滤改变：按 AppState 中记录的搜索策略统一搜索
     fn handle_search_changed(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>, filter: &str) {
         let start_time = Instant::now();
+        Self::schedule_match_count(app_window, app_state);
 
-        // 应用搜索过滤
-        app_state.borrow_mut().apply_search_filter(filter);
+        // 应用搜索过滤（策略在切换后持续生效）
+        let mode = app_state.borrow().search_mode;
+        let result = app_state.borrow_mut().search(filter, mode);
 
         // 使用新的重建函数，支持扁平化和字符过滤
         Self::rebuild_tree_model(app_window, app_state);
 
+        let matches = match result {
+            Ok(matches) => matches,
+            Err(e) => {
+                app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into());
+                return;
+            }
+        };
+
         // 搜索模式：仅构建“匹配列表”模型，不在预览区一次性渲染聚合内容
         if filter.trim().is_empty() {
             app_window.set_preview_text("".into());
@@ -781,16 +1840,7 @@ impl ViewModelBridge {
             let empty: Vec<SearchItemData> = Vec::new();
             app_window.set_search_results(ModelRc::new(VecModel::from(empty)));
         } else {
-            let filter_lower = filter.to_lowercase();
-            let items: Vec<SearchItemData> = {
-                let state = app_state.borrow();
-                state
-                    .tree_flat
-                    .iter()
-                    .filter(|n| n.name.to_lowercase().contains(&filter_lower) || n.path.to_lowercase().contains(&filter_lower))
-                    .map(SearchItemData::from)
-                    .collect()
-            };
+            let items: Vec<SearchItemData> = matches.iter().map(|m| SearchItemData::from(&m.node)).collect();
             app_window.set_search_results(ModelRc::new(VecModel::from(items)));
 
             // 仅设置提示，不强制渲染详情；详情通过点击列表项加载
@@ -804,13 +1854,87 @@ impl ViewModelBridge {
         if filter.trim().is_empty() {
             app_window.set_status_message("已清除搜索过滤".into());
         } else {
-            let visible_count = app_state.borrow().tree_flat.iter().filter(|n| n.visible).count();
-            app_window.set_status_message(format!("搜索过滤: {} (显示 {} 个节点)", filter, visible_count).into());
+            let interpretation = match mode {
+                SearchMode::Substring => describe_filter_list(&parse_filter_list(filter)),
+                other => format!("{}「{}」", other.label(), filter.trim()),
+            };
+            app_window.set_status_message(format!("搜索过滤: {} (显示 {} 个节点)", interpretation, matches.len()).into());
         }
 
         tracing::info!("搜索过滤应用: {}，耗时: {:.1}ms", filter, filter_duration.as_millis());
     }
 
+    /// 输入停顿 [`MATCH_COUNT_DEBOUNCE_MS`] 后按当前过滤条件统计命中数，显示在搜索框旁
+    fn schedule_match_count(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>) {
+        let app_window_weak = app_window.as_weak();
+        let app_state = app_state.clone();
+        MATCH_COUNT_TIMER.with(|timer| {
+            timer.start(slint::TimerMode::SingleShot, std::time::Duration::from_millis(MATCH_COUNT_DEBOUNCE_MS), move || {
+                let Some(app_window) = app_window_weak.upgrade() else {
+                    return;
+                };
+                let filter = app_window.get_search_filter().to_string();
+                if filter.trim().is_empty() {
+                    app_window.set_match_count_text("".into());
+                    return;
+                }
+                let opts = MatchCountOptions { leaf_nodes_only: app_window.get_leaf_nodes_only(), ..MatchCountOptions::default() };
+                let counts = app_state.borrow().count_matches(&filter, &opts);
+                app_window.set_match_count_text(counts.describe().into());
+            });
+        });
+    }
+
+    /// 尚未生成中间产物2时，输入停顿 [`MATCH_COUNT_DEBOUNCE_MS`] 后在预览区显示中间产物1（过滤条件命中的节点）
+    fn handle_preview_filter_match(
+        app_window: &AppWindow,
+        app_state: &Rc<RefCell<AppState>>,
+        preview_full_text: &Rc<RefCell<String>>,
+        filter: &str,
+    ) {
+        // 已生成中间产物2时保留其预览，避免与后续转换/回写使用的内容不一致
+        if filter.trim().is_empty() || !preview_full_text.borrow().is_empty() {
+            STAGE1_PREVIEW_TIMER.with(|timer| timer.stop());
+            return;
+        }
+        let app_window_weak = app_window.as_weak();
+        let app_state = app_state.clone();
+        let preview_full_text = preview_full_text.clone();
+        STAGE1_PREVIEW_TIMER.with(|timer| {
+            timer.start(slint::TimerMode::SingleShot, std::time::Duration::from_millis(MATCH_COUNT_DEBOUNCE_MS), move || {
+                let Some(app_window) = app_window_weak.upgrade() else {
+                    return;
+                };
+                // 定时器触发时重新读取过滤条件，期间可能已生成中间产物2
+                let filter = app_window.get_search_filter().to_string();
+                if filter.trim().is_empty() || !preview_full_text.borrow().is_empty() {
+                    return;
+                }
+                match app_state.borrow().build_intermediate_stage1(&filter, app_window.get_leaf_nodes_only()) {
+                    Ok(stage1) => {
+                        let (page_text, total_pages) = Self::paginate_text(&stage1, 0, 300);
+                        let page_text = if total_pages > 1 {
+                            format!("{}\n… 仅显示前 300 行（共 {} 页），生成中间产物2查看完整结果", page_text, total_pages)
+                        } else {
+                            page_text
+                        };
+                        app_window.set_preview_text(page_text.into());
+                    }
+                    Err(e) => tracing::warn!("中间产物1生成失败: {}", e),
+                }
+            });
+        });
+    }
+
+    /// 处理搜索策略切换：记录策略并按当前过滤条件重新搜索
+    fn handle_search_mode_changed(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>, index: i32) {
+        let mode = SearchMode::ALL.get(index as usize).copied().unwrap_or_default();
+        app_state.borrow_mut().search_mode = mode;
+        tracing::info!("搜索策略切换为: {}", mode.label());
+        let filter = app_window.get_search_filter().to_string();
+        Self::handle_search_changed(app_window, app_state, &filter);
+    }
+
     /// 处理节点展开/折叠切换
     fn handle_toggle_node_expanded(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>, node_path: &str) {
         let start_time = Instant::now();
@@ -863,18 +1987,14 @@ impl ViewModelBridge {
             app_window.set_status_message("错误: 过滤条件为空".into());
             return;
         }
-        // 优化：预显示进度条并添加小延迟确保UI更新完成
-        let start_time = std::time::Instant::now();
+        let Some(token) = Self::begin_busy_operation(app_window, "生成中间产物") else {
+            return;
+        };
+        let cancel = Self::busy_cancel_flag();
+        // 先设置进度条属性；耗时工作在异步任务中让出一轮后再执行，保证进度条先渲染
         tracing::info!("开始显示进度条");
-
         app_window.invoke_show_progress("正在生成中间产物第二阶段...".into());
 
-        // 添加小延迟确保进度条显示完成
-        std::thread::sleep(std::time::Duration::from_millis(16)); // 约1帧时间
-
-        let progress_show_time = start_time.elapsed().as_millis();
-        tracing::info!("进度条显示调用已执行，耗时: {}ms", progress_show_time);
-
         // 使用 spawn_local 让长时间操作异步执行，保持UI响应
         let app_weak = app_window.as_weak();
         let app_state_clone = app_state.clone();
@@ -882,31 +2002,32 @@ impl ViewModelBridge {
         let filter_clone = filter.clone();
 
         slint::spawn_local(async move {
+            yield_to_event_loop(Self::resume_after_frame).await;
             let build_start = std::time::Instant::now();
             tracing::info!("异步任务开始：调用 build_intermediate_stage2");
 
             // 优化：创建简化的进度回调，减少UI更新频率
-            let app_weak_progress = app_weak.clone();
-            let progress_callback = move |progress: f32, message: &str| {
-                let callback_start = std::time::Instant::now();
-
-                tracing::info!("进度回调被调用: {}% - {}", (progress * 100.0) as i32, message);
-                if let Some(app) = app_weak_progress.upgrade() {
-                    app.invoke_update_progress(progress, message.into());
-                    let callback_time = callback_start.elapsed().as_millis();
-                    tracing::info!("进度更新函数调用完成，耗时: {}ms", callback_time);
-                } else {
-                    tracing::warn!("无法获取app实例进行进度更新");
-                }
-            };
+            let mut throttle = ThrottledProgress::default();
+            let progress_callback = |progress: f32, message: &str| {
+                throttle.report(progress, message, |progress, message, timing| {
+                    let callback_start = std::time::Instant::now();
 
-            let leaf_nodes_only = if let Some(app) = app_weak.upgrade() {
-                app.get_leaf_nodes_only()
-            } else {
-                false
+                    tracing::info!("进度回调被调用: {}% - {}", (progress * 100.0) as i32, message);
+                    if let Some(app) = app_weak.upgrade() {
+                        ViewModelBridge::update_progress(&app, progress, message, timing);
+                        let callback_time = callback_start.elapsed().as_millis();
+                        tracing::info!("进度更新函数调用完成，耗时: {}ms", callback_time);
+                    } else {
+                        tracing::warn!("无法获取app实例进行进度更新");
+                    }
+                });
             };
 
-            match app_state_clone.borrow().build_intermediate_stage2_with_leaf_filter(&filter_clone, leaf_nodes_only, progress_callback) {
+            let stage2_options = app_weak.upgrade().map(|app| Self::stage2_options(&app)).unwrap_or_default();
+            tracing::info!("中间产物排序方式: {}", stage2_options.sort.label());
+
+            match app_state_clone.borrow().build_intermediate_stage2_multi(&parse_filter_list(&filter_clone), stage2_options, progress_callback) {
+                Ok(_) if cancel.is_cancelled() => tracing::info!("生成中间产物已取消，丢弃结果"),
                 Ok(stage2_json) => {
                     let build_time = build_start.elapsed().as_millis();
                     tracing::info!("build_intermediate_stage2 执行成功，总耗时: {}ms，开始处理结果", build_time);
@@ -916,6 +2037,7 @@ impl ViewModelBridge {
                         let save_start = std::time::Instant::now();
                         tracing::info!("开始保存完整文本");
                         *preview_full_text_clone.borrow_mut() = stage2_json.clone();
+                        Self::record_project_extraction(&filter_clone, &stage2_json);
                         let save_time = save_start.elapsed().as_millis();
                         tracing::info!("保存完整文本完成，耗时: {}ms", save_time);
 
@@ -939,8 +2061,9 @@ impl ViewModelBridge {
                         let ui_time = ui_start.elapsed().as_millis();
                         tracing::info!("UI更新完成，耗时: {}ms", ui_time);
 
-                        // 暂时不隐藏进度条，让用户看到完成状态
-                        // app.invoke_hide_progress();
+                        // 补发被限流丢弃的最后一条进度，再短暂显示完成状态后自动隐藏
+                        throttle.flush(|progress, message, timing| ViewModelBridge::update_progress(&app, progress, message, timing));
+                        ViewModelBridge::complete_progress_and_auto_hide(&app, throttle.timing(1.0));
                     }
                 }
                 Err(e) => {
@@ -952,93 +2075,267 @@ impl ViewModelBridge {
                     tracing::error!("生成中间产物 第二阶段 失败: {}", e);
                 }
             }
+
+            if let Some(app) = app_weak.upgrade() {
+                ViewModelBridge::finish_busy_operation(&app, token);
+            }
         }).unwrap();
     }
 
-    /// 将中间产物2转换为最终产物 {seq: name_value}
-    fn handle_transform_pressed(app_window: &AppWindow, _app_state: &Rc<RefCell<AppState>>, preview_full_text: &Rc<RefCell<String>>, final_full_text: &Rc<RefCell<String>>) {
+    /// 将中间产物2转换为最终产物 {seq: name_value}
+    fn handle_transform_pressed(app_window: &AppWindow, _app_state: &Rc<RefCell<AppState>>, preview_full_text: &Rc<RefCell<String>>, final_full_text: &Rc<RefCell<String>>) {
+        let stage2_text = preview_full_text.borrow().clone();
+        if stage2_text.trim().is_empty() {
+            app_window.set_status_message("错误: 中间产物为空，无法转换".into());
+            return;
+        }
+        // 同步执行，期间不会有新任务开始，只需避免与进行中的任务冲突
+        let Some(token) = Self::begin_busy_operation(app_window, "转换最终产物") else {
+            return;
+        };
+        Self::transform_stage2(app_window, &stage2_text, final_full_text);
+        Self::finish_busy_operation(app_window, token);
+    }
+
+    /// 界面选择的最终产物结构
+    fn final_shape(app_window: &AppWindow) -> FinalShape {
+        FinalShape::ALL.get(app_window.get_final_shape() as usize).copied().unwrap_or_default()
+    }
+
+    /// 解析中间产物2并生成分页显示的最终产物
+    fn transform_stage2(app_window: &AppWindow, stage2_text: &str, final_full_text: &Rc<RefCell<String>>) {
+
+        // 显示进度条
+        app_window.invoke_show_progress("正在生成最终产物...".into());
+        let mut throttle = ThrottledProgress::default();
+        Self::update_progress(app_window, 0.1, "正在解析中间产物...", throttle.timing(0.1));
+        let options = FinalOptions { shape: Self::final_shape(app_window), ..Default::default() };
+        let result = build_final_product_with_progress(stage2_text, options, |fraction, message| {
+            // 将进度映射到0.3-0.9范围
+            let progress = 0.3 + fraction * 0.6;
+            if throttle.should_forward(progress) || fraction >= 1.0 {
+                Self::update_progress(app_window, progress, message, throttle.timing(progress));
+            }
+        });
+        match result {
+            Ok(s) => {
+                // 保存完整文本
+                *final_full_text.borrow_mut() = s.clone();
+
+                // 计算分页并显示第一页
+                let (page_text, total_pages) = Self::paginate_text(&s, 1, 300);
+                app_window.set_final_product_text(page_text.into());
+                app_window.set_final_current_page(1);
+                app_window.set_final_total_pages(total_pages);
+
+                Self::update_progress(app_window, 1.0, "完成", throttle.timing(1.0));
+                app_window.set_status_message("已构建最终产物".into());
+
+                // 隐藏进度条
+                app_window.invoke_hide_progress();
+            }
+            Err(e) => {
+                app_window.invoke_hide_progress();
+                let msg = format!("{}{}", STATUS_ERROR_PREFIX, e);
+                app_window.set_status_message(msg.into());
+            }
+        }
+    }
+
+    /// 复制最终产物到剪贴板
+    fn handle_copy_final_pressed(app_window: &AppWindow, _app_state: &Rc<RefCell<AppState>>, final_full_text: &Rc<RefCell<String>>) {
+        let text = final_full_text.borrow().clone();
+        if text.trim().is_empty() {
+            app_window.set_status_message("错误: 最终产物为空".into());
+            return;
+        }
+        Self::copy_text_to_clipboard(app_window, &text, |app_window, result| match result {
+            Ok(()) => app_window.set_status_message(STATUS_COPIED.into()),
+            Err(e) => {
+                let msg = format!("{}{}", STATUS_ERROR_PREFIX, e);
+                app_window.set_status_message(msg.into());
+            }
+        });
+    }
+
+    /// 复制字符串叶子的键值对（按搜索框过滤）到剪贴板，供粘贴到 LLM 提示词中翻译
+    fn handle_copy_for_llm_pressed(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>) {
+        let filter = app_window.get_search_filter().to_string();
+        let filter = Some(filter.trim()).filter(|f| !f.is_empty());
+        let result = app_state.borrow().export_key_value_pairs_for_llm(filter, LLM_MAX_PAIRS);
+        match result {
+            Ok(text) => Self::copy_text_to_clipboard(app_window, &text, |app_window, result| match result {
+                Ok(()) => app_window.set_status_message(STATUS_COPIED.into()),
+                Err(e) => app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into()),
+            }),
+            Err(e) => app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into()),
+        }
+    }
+
+    /// 与磁盘上的原文件对比，差异列表显示在差异面板中，点击条目定位节点
+    fn handle_show_changes_pressed(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>) {
+        let state = app_state.borrow();
+        let result = state
+            .original_file_path
+            .as_deref()
+            .ok_or_else(|| AppError::State(StateKind::Invalid, "原始文件路径未设置".into()))
+            .and_then(|path| state.diff_against_file(path));
+        match result {
+            Ok(diff) => {
+                let items: Vec<ChangeLineEntry> = diff
+                    .entries
+                    .iter()
+                    .take(DIFF_RENDER_LIMIT)
+                    .map(|entry| ChangeLineEntry { path: entry.path.clone().into(), line: entry.render().into() })
+                    .collect();
+                app_window.set_file_diff_title(format!("{}（点击定位）", diff.summary).into());
+                app_window.set_file_diff(ModelRc::new(VecModel::from(items)));
+                app_window.set_status_message(diff.summary.clone().into());
+            }
+            Err(e) => {
+                app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into());
+                tracing::error!("对比磁盘文件失败: {}", e);
+            }
+        }
+    }
+
+    /// 本次会话的改动日志（相对加载时的文档），显示在改动面板中，点击条目定位节点
+    fn handle_session_changes_pressed(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>) {
+        match app_state.borrow().summarize_changes_since_load() {
+            Ok(summary) => {
+                let items: Vec<ChangeLineEntry> = summary
+                    .added
+                    .iter()
+                    .chain(&summary.removed)
+                    .chain(&summary.changed)
+                    .take(DIFF_RENDER_LIMIT)
+                    .map(|entry| ChangeLineEntry { path: entry.path.clone().into(), line: entry.render().into() })
+                    .collect();
+                app_window.set_session_changes_title(
+                    format!("本次会话的改动：新增 {}，删除 {}，修改 {}（点击定位）", summary.added.len(), summary.removed.len(), summary.changed.len()).into(),
+                );
+                app_window.set_session_changes(ModelRc::new(VecModel::from(items)));
+                app_window.set_status_message(format!("本次会话共 {} 处改动", summary.total).into());
+            }
+            Err(e) => app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into()),
+        }
+    }
+
+    /// 同步界面上的只读开关到 AppState
+    fn handle_read_only_toggled(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>) {
+        let read_only = app_window.get_read_only();
+        app_state.borrow_mut().read_only = read_only;
+        let message = if read_only { "只读模式：编辑与写回原文件已禁用，仍可提取、搜索、另存为与导出" } else { "已退出只读模式" };
+        tracing::info!("{}", message);
+        app_window.set_status_message(message.into());
+    }
+
+    /// 设置JSON输出缩进（保存、子树提取与中间产物）
+    fn handle_indent_size_changed(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>, spaces: i32) {
+        let result = u8::try_from(spaces)
+            .map_err(|_| AppError::State(StateKind::Invalid, format!("缩进只支持2或4个空格: {}", spaces)))
+            .and_then(|spaces| app_state.borrow_mut().set_indent_size(spaces));
+        match result {
+            Ok(()) => app_window.set_status_message(format!("JSON缩进: {} 个空格", spaces).into()),
+            Err(e) => app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into()),
+        }
+    }
+
+    /// 质量检查：选择已翻译的最终产物，对照中间产物2检查译文冲突，报告显示在预览区
+    fn handle_qa_check_pressed(app_window: &AppWindow, preview_full_text: &Rc<RefCell<String>>) {
         let stage2_text = preview_full_text.borrow().clone();
         if stage2_text.trim().is_empty() {
-            app_window.set_status_message("错误: 中间产物为空，无法转换".into());
+            app_window.set_status_message("错误: 中间产物为空，请先生成中间产物".into());
             return;
         }
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("JSON文件", &["json"])
+            .set_title("选择已翻译的最终产物")
+            .pick_file()
+        else {
+            app_window.set_status_message("用户取消了文件选择".into());
+            return;
+        };
 
-        // 显示进度条
-        app_window.invoke_show_progress("正在生成最终产物...".into());
-        app_window.invoke_update_progress(0.1, "正在解析中间产物...".into());
-        match serde_json::from_str::<Value>(&stage2_text) {
-            Ok(v) => {
-                app_window.invoke_update_progress(0.3, "正在处理数据项...".into());
-
-                // 使用BTreeMap自动排序，避免额外的排序步骤
-                let mut out = std::collections::BTreeMap::new();
-
-                if let Some(items) = v.get("items").and_then(|x| x.as_array()) {
-                    let total_items = items.len();
-                    for (index, item) in items.iter().enumerate() {
-                        // 更新进度
-                        let progress = 0.3 + (index as f32 / total_items as f32) * 0.4;
-                        if index % 100 == 0 || index == total_items - 1 {
-                            app_window.invoke_update_progress(progress, format!("处理项目 {}/{}", index + 1, total_items).into());
-                        }
-
-                        let seq = item.get("seq").and_then(|s| s.as_u64()).unwrap_or(0);
-                        let name_val = item.get("name").and_then(|n| n.as_str()).unwrap_or("");
-                        // 直接插入BTreeMap，自动按key排序
-                        out.insert(seq.to_string(), serde_json::Value::String(name_val.to_string()));
-                    }
-                }
-
-                app_window.invoke_update_progress(0.8, "正在构建最终JSON...".into());
-
-                // 直接从BTreeMap构建JSON对象，无需额外排序
-                let final_json = serde_json::Value::Object(out.into_iter().collect());
-                match serde_json::to_string_pretty(&final_json) {
-                    Ok(s) => {
-                        app_window.invoke_update_progress(0.9, "正在格式化输出...".into());
-
-                        // 保存完整文本
-                        *final_full_text.borrow_mut() = s.clone();
-
-                        // 计算分页并显示第一页
-                        let (page_text, total_pages) = Self::paginate_text(&s, 1, 300);
-                        app_window.set_final_product_text(page_text.into());
-                        app_window.set_final_current_page(1);
-                        app_window.set_final_total_pages(total_pages);
+        let result = serde_json::from_str::<Value>(&stage2_text).map_err(model::data_core::AppError::from).and_then(|stage2| {
+            let text = std::fs::read_to_string(&path).map_err(|e| model::data_core::AppError::io(e, &path))?;
+            let final_map = model::qa::parse_translation_file(&text, &stage2)?;
+            Ok(model::qa::analyze_final(&stage2, &final_map))
+        });
+        match result {
+            Ok(report) => {
+                app_window.set_selected_json_path("[质量检查报告]".into());
+                app_window.set_preview_text(report.render().into());
+                app_window.set_status_message(
+                    format!(
+                        "质量检查完成: 译文不一致 {} 组，译文相同 {} 组",
+                        report.inconsistent_translations.len(),
+                        report.shared_translations.len()
+                    )
+                    .into(),
+                );
+                LAST_QA_REPORT.with(|last| *last.borrow_mut() = Some(report));
+                app_window.set_has_qa_report(true);
+            }
+            Err(e) => {
+                app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into());
+                tracing::error!("质量检查失败: {}", e);
+            }
+        }
+    }
 
-                        app_window.invoke_update_progress(1.0, "完成".into());
-                        app_window.set_status_message("已构建最终产物".into());
+    /// 将最近一次质量检查报告导出为 CSV
+    fn handle_export_qa_csv(app_window: &AppWindow) {
+        let Some(report) = LAST_QA_REPORT.with(|last| last.borrow().clone()) else {
+            app_window.set_status_message("错误: 请先运行质量检查".into());
+            return;
+        };
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV文件", &["csv"])
+            .set_title("导出质量检查报告")
+            .set_file_name("qa_report.csv")
+            .save_file()
+        else {
+            return;
+        };
 
-                        // 隐藏进度条
-                        app_window.invoke_hide_progress();
-                    }
-                    Err(e) => {
-                        app_window.invoke_hide_progress();
-                        let msg = format!("{}{}", STATUS_ERROR_PREFIX, e);
-                        app_window.set_status_message(msg.into());
-                    }
-                }
-            }
+        let result = std::fs::File::create(&path)
+            .map_err(|e| model::data_core::AppError::io(e, &path))
+            .and_then(|file| {
+                let mut writer = std::io::BufWriter::new(file);
+                report.write_csv(&mut writer)?;
+                std::io::Write::flush(&mut writer).map_err(|e| model::data_core::AppError::io(e, &path))
+            });
+        match result {
+            Ok(()) => app_window.set_status_message(format!("质量检查报告已导出: {}", path.display()).into()),
             Err(e) => {
-                app_window.invoke_hide_progress();
-                let msg = format!("{}{}", STATUS_ERROR_PREFIX, e);
-                app_window.set_status_message(msg.into());
+                app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into());
+                tracing::error!("导出质量检查报告失败: {}", e);
             }
         }
     }
 
-    /// 复制最终产物到剪贴板
-    fn handle_copy_final_pressed(app_window: &AppWindow, _app_state: &Rc<RefCell<AppState>>, final_full_text: &Rc<RefCell<String>>) {
-        let text = final_full_text.borrow().clone();
-        if text.trim().is_empty() {
-            app_window.set_status_message("错误: 最终产物为空".into());
+    /// 将中间产物2导出为 Excel 工作簿，供译者在 target 列填写译文
+    fn handle_export_stage2_xlsx(app_window: &AppWindow, preview_full_text: &Rc<RefCell<String>>) {
+        let stage2_text = preview_full_text.borrow().clone();
+        if stage2_text.trim().is_empty() {
+            app_window.set_status_message("错误: 中间产物为空，无法导出".into());
             return;
         }
-        match utils::clipboard::copy_to_clipboard(&text) {
-            Ok(()) => app_window.set_status_message(STATUS_COPIED.into()),
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Excel工作簿", &["xlsx"])
+            .set_title("导出中间产物为Excel")
+            .set_file_name("stage2.xlsx")
+            .save_file()
+        else {
+            return;
+        };
+
+        match model::export::stage2_to_xlsx(&stage2_text, &path) {
+            Ok(count) => app_window.set_status_message(format!("已导出 {} 个条目: {}", count, path.display()).into()),
             Err(e) => {
-                let msg = format!("{}{}", STATUS_ERROR_PREFIX, e);
-                app_window.set_status_message(msg.into());
+                app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into());
+                tracing::error!("导出Excel失败: {}", e);
             }
         }
     }
@@ -1080,17 +2377,48 @@ impl ViewModelBridge {
 
     /// 处理上传回写文件（真正的非阻塞版本）
     fn handle_upload_writeback_file(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>, preview_full_text: &Rc<RefCell<String>>, final_full_text: &Rc<RefCell<String>>) {
+        // 回写在后台线程直接写源文件，只读模式下在此拦截
+        if app_state.borrow().read_only {
+            app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, AppError::State(StateKind::ReadOnly, "只读模式".into())).into());
+            return;
+        }
+        let Some(token) = Self::begin_busy_operation(app_window, "回写") else {
+            return;
+        };
+        let cancel = Self::busy_cancel_flag();
+        vm::writeback_log::begin_operation();
+
+        // 自动检测源文件的占位符风格并写入回写日志
+        let style = app_state.borrow().detect_interpolation_style(None);
+        Self::append_writeback_log(app_window, &format!("🔎 检测到占位符风格: {}", style.label()));
+
+        // 报告源文件中可能在回写后损坏的字符串（无效转义、U+0000）
+        let encoding_findings = app_state.borrow().check_utf8_validity();
+        for (path, description) in &encoding_findings {
+            Self::append_writeback_log(app_window, &format!("⚠️ 字符串编码问题 {}: {}", path, description));
+        }
+
         Self::append_writeback_log(app_window, "📂 开始选择回写文件...");
 
-        // 打开文件选择对话框
-        let file_dialog = rfd::FileDialog::new()
-            .add_filter("JSON文件", &["json"])
-            .set_title("选择回写JSON文件");
+        // 打开文件选择对话框（未启用 xlsx 特性时不提供 Excel 工作簿）
+        let mut file_dialog = rfd::FileDialog::new().add_filter("JSON文件", &["json"]);
+        if cfg!(feature = "xlsx") {
+            file_dialog = file_dialog.add_filter("Excel工作簿", &["xlsx"]);
+        }
+        let file_dialog = file_dialog.set_title("选择回写文件");
 
         if let Some(path) = file_dialog.pick_file() {
             Self::append_writeback_log(app_window, &format!("📁 已选择文件: {}", path.display()));
 
-            match std::fs::read_to_string(&path) {
+            // Excel 工作簿读取 target 列并转为回写映射；空白译文已跳过，条目数可少于最终产物
+            let is_xlsx = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("xlsx"));
+            let read_result = if is_xlsx {
+                model::export::from_xlsx(&path).map(|map| Value::Object(map).to_string())
+            } else {
+                std::fs::read_to_string(&path).map_err(|e| AppError::io(e, &path))
+            };
+
+            match read_result {
                 Ok(content) => {
                     Self::append_writeback_log(app_window, &format!("📖 文件读取成功，大小: {} 字节", content.len()));
 
@@ -1098,7 +2426,9 @@ impl ViewModelBridge {
                     let final_product_text = final_full_text.borrow().clone();
                     Self::append_writeback_log(app_window, &format!("🔍 最终产物文本长度: {} 字符", final_product_text.len()));
 
-                    if final_product_text.trim().is_empty() {
+                    if is_xlsx {
+                        Self::append_writeback_log(app_window, "ℹ️ Excel 工作簿按 seq 列回写，跳过格式验证");
+                    } else if final_product_text.trim().is_empty() {
                         Self::append_writeback_log(app_window, "⚠️ 最终产物为空，跳过格式验证");
                     } else if let Err(validation_error) = Self::validate_json_format(&content, &final_product_text) {
                         Self::append_writeback_log(app_window, &format!("⚠️ 格式验证失败: {}", validation_error));
@@ -1106,6 +2436,8 @@ impl ViewModelBridge {
                             "格式不一致警告".into(),
                             format!("请上传与最终产物格式一致的JSON文件\n\n错误详情: {}", validation_error).into()
                         );
+                        vm::writeback_log::end_operation();
+                        Self::finish_busy_operation(app_window, token);
                         return;
                     } else {
                         Self::append_writeback_log(app_window, "✅ 格式验证通过");
@@ -1119,18 +2451,64 @@ impl ViewModelBridge {
                     let intermediate_stage2 = preview_full_text.borrow().clone();
                     let original_file_path = app_state.borrow().original_file_path.clone();
 
+                    // 源文件在提取后改动（键被重命名或删除）时，提前列出已失效的条目
+                    let mut orphaned: Vec<String> = Vec::new();
+                    for json in [&content, &intermediate_stage2] {
+                        for path in app_state.borrow().find_orphaned_paths(json).unwrap_or_default() {
+                            if !orphaned.contains(&path) {
+                                orphaned.push(path);
+                            }
+                        }
+                    }
+                    if !orphaned.is_empty() {
+                        Self::append_writeback_log(app_window, &format!("⚠️ {} 个条目的源路径在当前文件中已不存在，将被跳过:", orphaned.len()));
+                        for path in &orphaned {
+                            Self::append_writeback_log(app_window, &format!("   • {}", path));
+                        }
+                    }
+
                     // 提取原始JSON数据用于更新
                     let original_json = app_state.borrow().dom.clone();
+                    let normalize_text = app_window.get_normalize_writeback_text();
+                    let force_on_mismatch = app_window.get_force_writeback_on_mismatch();
+                    let keep_backups = app_window.get_keep_writeback_backups();
+                    let save_options = app_state.borrow().save_options();
+                    let schema_source = app_state.borrow().schema_source().cloned();
+                    let writeback_file = path.clone();
 
                     std::thread::spawn(move || {
                         // 在后台线程中处理回写
-                        match Self::process_writeback_in_background(&content, &intermediate_stage2, original_json, original_file_path, &app_window_weak) {
-                            Ok((modified_count, updated_json)) => {
+                        let result = Self::process_writeback_in_background(&content, &intermediate_stage2, original_json, original_file_path.clone(), normalize_text, force_on_mismatch, keep_backups, save_options, schema_source, &cancel, &app_window_weak);
+                        if cancel.is_cancelled() {
+                            // 取消后不再记录项目事件、更新状态或弹出保存确认，结果直接丢弃
+                            let _ = slint::invoke_from_event_loop(move || {
+                                if let Some(app_window) = app_window_weak.upgrade() {
+                                    Self::append_writeback_log(&app_window, "⏹️ 回写已取消，结果已丢弃");
+                                    vm::writeback_log::end_operation();
+                                }
+                            });
+                            return;
+                        }
+                        match result {
+                            Ok((modified_count, updated_json, violations)) => {
                                 // 使用invoke_from_event_loop安全地更新UI
                                 let _ = slint::invoke_from_event_loop(move || {
                                     if let Some(app_window) = app_window_weak.upgrade() {
                                         Self::append_writeback_log(&app_window, &format!("🎉 回写完成！共修改了 {} 个字段", modified_count));
-                                        app_window.set_status_message(format!("回写成功，修改了 {} 个字段", modified_count).into());
+                                        Self::update_project(|project| project.record_event(ProjectEvent::Writeback { file: writeback_file, applied: modified_count }));
+                                        vm::writeback_log::end_operation();
+                                        Self::finish_busy_operation(&app_window, token);
+                                        // 状态栏注明备份所在的产物目录
+                                        let backup_note = match (&original_file_path, keep_backups) {
+                                            (Some(path), true) => format!("，备份位于: {}", utils::fs::artifacts_dir(Some(path)).display()),
+                                            _ => String::new(),
+                                        };
+                                        app_window.set_status_message(format!("回写成功，修改了 {} 个字段{}", modified_count, backup_note).into());
+
+                                        // Schema 校验未通过：结果尚未保存，由用户确认是否仍然保存
+                                        if let (false, Some(path), Some(json)) = (violations.is_empty(), original_file_path, updated_json.clone()) {
+                                            Self::confirm_schema_save(&app_window, &violations, PendingSchemaSave::Writeback { path, json, keep_backups });
+                                        }
 
                                         // 触发JSON结构树更新的信号
                                         if updated_json.is_some() {
@@ -1146,6 +2524,8 @@ impl ViewModelBridge {
                                 let _ = slint::invoke_from_event_loop(move || {
                                     if let Some(app_window) = app_window_weak.upgrade() {
                                         Self::append_writeback_log(&app_window, &format!("❌ 回写失败: {}", error_msg));
+                                        vm::writeback_log::end_operation();
+                                        Self::finish_busy_operation(&app_window, token);
                                         app_window.set_status_message(format!("回写失败: {}", error_msg).into());
                                     }
                                 });
@@ -1155,11 +2535,15 @@ impl ViewModelBridge {
                 }
                 Err(e) => {
                     Self::append_writeback_log(app_window, &format!("❌ 文件读取失败: {}", e));
+                    vm::writeback_log::end_operation();
+                    Self::finish_busy_operation(app_window, token);
                     app_window.set_status_message(format!("读取文件失败: {}", e).into());
                 }
             }
         } else {
             Self::append_writeback_log(app_window, "⚠️ 用户取消了文件选择");
+            vm::writeback_log::end_operation();
+            Self::finish_busy_operation(app_window, token);
             app_window.set_status_message("用户取消了文件选择".into());
         }
     }
@@ -1172,8 +2556,14 @@ impl ViewModelBridge {
         intermediate_stage2: &str,
         mut original_json: Option<serde_json::Value>,
         original_file_path: Option<PathBuf>,
+        normalize_text: bool,
+        force_on_mismatch: bool,
+        keep_backups: bool,
+        save_options: utils::fs::SaveOptions,
+        schema_source: Option<serde_json::Value>,
+        cancel: &CancelFlag,
         app_window_weak: &slint::Weak<AppWindow>
-    ) -> Result<(usize, Option<serde_json::Value>), Box<dyn std::error::Error + Send + Sync>> {
+    ) -> Result<(usize, Option<serde_json::Value>, Vec<SchemaViolation>), Box<dyn std::error::Error + Send + Sync>> {
         // 更新日志的闭包（使用invoke_from_event_loop）
         let update_log = |app_window_weak: &slint::Weak<AppWindow>, message: String| {
             let app_window_weak_clone = app_window_weak.clone();
@@ -1188,10 +2578,6 @@ impl ViewModelBridge {
 
         // 解析上传的回写文件
         let writeback_data: serde_json::Value = serde_json::from_str(writeback_content)?;
-        let writeback_obj = writeback_data.as_object()
-            .ok_or("回写文件必须是JSON对象")?;
-
-        update_log(app_window_weak, format!("📊 回写文件包含 {} 个条目", writeback_obj.len()));
 
         update_log(app_window_weak, "🔍 开始解析中间产物2...".to_string());
         // 解析中间产物2
@@ -1202,101 +2588,119 @@ impl ViewModelBridge {
 
         update_log(app_window_weak, format!("📊 中间产物2包含 {} 个条目", items.len()));
 
-        let mut modified_count = 0;
-        let mut skipped_count = 0;
-        let total_entries = writeback_obj.len();
+        // 按序号、按路径或原结构的译文文件统一转换为按序号
+        let shape = model::workflow::detect_final_shape(&writeback_data);
+        let writeback_data = model::workflow::normalize_writeback(&writeback_data, &stage2_data)?;
+        let writeback_obj = writeback_data.as_object()
+            .ok_or("回写文件必须是JSON对象")?;
+
+        update_log(app_window_weak, format!("📊 回写文件（{}）包含 {} 个条目", shape.label(), writeback_obj.len()));
 
-        update_log(app_window_weak, format!("🔄 开始处理 {} 个回写条目...", total_entries));
+        update_log(app_window_weak, format!("🔄 开始处理 {} 个回写条目...", writeback_obj.len()));
 
         // 确保有原始JSON数据
         let json_data = original_json.as_mut()
             .ok_or("缺少原始JSON数据")?;
 
-        // 处理每个回写条目
-        for (key, new_value) in writeback_obj.iter() {
-            // 每处理100个条目就更新进度
-            if (modified_count + skipped_count) % 100 == 0 {
-                let progress = ((modified_count + skipped_count) as f64 / total_entries as f64 * 100.0) as u32;
-                update_log(app_window_weak, format!("📊 进度: {}/{} ({}%)",
-                    modified_count + skipped_count + 1, total_entries, progress));
-            }
-
-            // 解析序号
-            let seq = match key.parse::<usize>() {
-                Ok(s) => s,
-                Err(_) => {
-                    tracing::warn!("跳过无效序号: {}", key);
-                    skipped_count += 1;
-                    continue;
+        // 校验指纹与原文、规范化译文，生成回写计划
+        let plan = WritebackPlan::build(&writeback_data, &stage2_data, json_data, WritebackOptions { normalize_text, force_on_mismatch })?;
+        if let Some(warning) = plan.fingerprint_warning.clone() {
+            update_log(app_window_weak, format!("🚨 {}", warning));
+            let app_window_weak_clone = app_window_weak.clone();
+            let _ = slint::invoke_from_event_loop(move || {
+                if let Some(app_window) = app_window_weak_clone.upgrade() {
+                    app_window.invoke_show_message_dialog("文档指纹不一致".into(), warning.into());
                 }
-            };
+            });
+        }
+        for note in &plan.notes {
+            update_log(app_window_weak, format!("⚠️ {}", note));
+        }
 
-            // 在中间产物2中找到对应的条目
-            if let Some(item) = items.get(seq) {
-                if let Some(source_path) = item.get("source_path").and_then(|v| v.as_str()) {
-                    // 验证新值格式
-                    let new_value_str = match new_value {
-                        serde_json::Value::String(s) => {
-                            let trimmed = s.trim();
-                            if trimmed.is_empty() {
-                                skipped_count += 1;
-                                continue;
-                            }
-                            s.clone()
-                        },
-                        serde_json::Value::Bool(b) => b.to_string(),
-                        serde_json::Value::Number(n) => n.to_string(),
-                        serde_json::Value::Null => {
-                            skipped_count += 1;
-                            continue;
-                        },
-                        serde_json::Value::Object(_) | serde_json::Value::Array(_) => {
-                            skipped_count += 1;
-                            continue;
-                        }
-                    };
+        // 执行回写，按频率限流更新进度
+        let mut throttle = ThrottledProgress::default();
+        let modified_count = plan.apply(json_data, |done, total| {
+            if throttle.should_forward(done as f32 / total as f32) {
+                let progress = (done as f64 / total as f64 * 100.0) as u32;
+                update_log(app_window_weak, format!("📊 进度: {}/{} ({}%)", done + 1, total, progress));
+            }
+        });
+        let skipped_count = plan.skipped + (plan.updates.len() - modified_count);
 
-                    // 使用JSONPath更新原始JSON
-                    match Self::update_json_by_path(json_data, source_path, &new_value_str) {
-                        Ok(_) => {
-                            modified_count += 1;
-                        }
-                        Err(_) => {
-                            skipped_count += 1;
-                        }
-                    }
-                } else {
-                    skipped_count += 1;
-                }
-            } else {
-                skipped_count += 1;
+        update_log(app_window_weak, format!("📈 处理完成: 成功 {} 个，跳过 {} 个", modified_count, skipped_count));
+        if plan.conflicts > 0 {
+            update_log(app_window_weak, format!("🚨 其中 {} 个条目因原文已变化被跳过", plan.conflicts));
+        }
+        if plan.reanchored > 0 || plan.unanchored > 0 {
+            update_log(app_window_weak, format!("⚓ 按锚点重新定位 {} 个条目，{} 个条目无法定位", plan.reanchored, plan.unanchored));
+        }
+
+        // 按 Schema 校验回写结果（回写一律写为字符串，可能破坏数字/布尔字段的类型）
+        let violations = match schema_source.map(CompiledSchema::compile).transpose() {
+            Ok(schema) => schema.map(|schema| schema.validate(json_data)).unwrap_or_default(),
+            Err(e) => {
+                update_log(app_window_weak, format!("⚠️ Schema 编译失败，跳过校验: {}", e));
+                Vec::new()
             }
+        };
+        for violation in &violations {
+            update_log(app_window_weak, format!("🚫 Schema 违规 {}", violation));
         }
 
-        update_log(app_window_weak, format!("📈 处理完成: 成功 {} 个，跳过 {} 个", modified_count, skipped_count));
+        // 取消后不再写入原始文件
+        if cancel.is_cancelled() {
+            return Err(STATUS_CANCELLED.into());
+        }
 
-        // 保存到原始文件
+        // 保存到原始文件（存在 Schema 违规时等待用户确认）
         if let Some(original_path) = original_file_path {
-            update_log(app_window_weak, "💾 开始保存到原始文件...".to_string());
-            let json_string = serde_json::to_string_pretty(json_data)?;
-            std::fs::write(&original_path, json_string)?;
-            update_log(app_window_weak, format!("✅ 已保存到: {}", original_path.display()));
+            if !violations.is_empty() {
+                update_log(app_window_weak, format!("⏸️ {} 处 Schema 违规，暂不保存，等待确认...", violations.len()));
+                return Ok((modified_count, original_json, violations));
+            }
+            Self::write_writeback_output(&original_path, json_data, keep_backups, &save_options, &|message| update_log(app_window_weak, message))?;
 
             // 触发重新加载文件以更新JSON结构树
-            let path_for_reload = original_path.clone();
             let _ = slint::invoke_from_event_loop({
                 let app_window_weak = app_window_weak.clone();
                 move || {
                     if let Some(app_window) = app_window_weak.upgrade() {
-                        Self::append_writeback_log(&app_window, "🔄 触发JSON结构树重新加载...");
-                        // 调用重新加载回调
-                        app_window.invoke_reload_file_after_writeback(path_for_reload.to_string_lossy().to_string().into());
+                        Self::show_reload_prompt(&app_window, &original_path);
                     }
                 }
             });
         }
 
-        Ok((modified_count, original_json))
+        Ok((modified_count, original_json, violations))
+    }
+
+    /// 将回写结果写入原始文件（按需先备份，备份失败则放弃写入）
+    fn write_writeback_output(
+        original_path: &std::path::Path,
+        json: &serde_json::Value,
+        keep_backups: bool,
+        save_options: &utils::fs::SaveOptions,
+        log: &dyn Fn(String),
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if keep_backups {
+            // 备份放在产物目录，避免在源文件旁堆积 .bak 文件
+            let backup_dir = utils::fs::artifacts_dir(Some(original_path));
+            std::fs::create_dir_all(&backup_dir)?;
+            let backup = utils::fs::backup_file(original_path, Some(&backup_dir))?;
+            log(format!("🗄️ 已备份原始文件: {}", backup.display()));
+        }
+        log("💾 开始保存到原始文件...".to_string());
+        utils::fs::write_json_file_atomic_with_options(original_path, json, save_options)?;
+        log(format!("✅ 已保存到: {}", original_path.display()));
+        Ok(())
+    }
+
+    /// 弹出重新加载提示（立即重新加载 / 稍后）
+    fn show_reload_prompt(app_window: &AppWindow, path: &std::path::Path) {
+        Self::append_writeback_log(app_window, "🔄 原始文件已更新，等待确认是否重新加载...");
+        let path_id = RELOAD_PATHS.with(|paths| paths.borrow_mut().register(path));
+        app_window.set_pending_reload_id(path_id);
+        app_window.set_reload_prompt_visible(true);
     }
 
     /// 验证JSON格式是否一致
@@ -1366,33 +2770,6 @@ impl ViewModelBridge {
         }
     }
 
-    /// 使用JSONPath更新JSON值（独立函数，不依赖AppState）
-    fn update_json_by_path(
-        json_data: &mut serde_json::Value,
-        json_path: &str,
-        new_value: &str
-    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        use jsonpath_rust::{JsonPath, query::queryable::Queryable};
-
-        // 查找路径
-        let paths: Vec<String> = json_data
-            .query_only_path(json_path)
-            .map_err(|e| format!("JSONPath查询失败: {}", e))?;
-
-        let Some(p) = paths.into_iter().next() else {
-            return Err(format!("JSONPath未匹配到可更新路径: {}", json_path).into());
-        };
-
-        // 通过 reference_mut 按路径获取可变引用
-        if let Some(slot) = json_data.reference_mut(&p) {
-            *slot = serde_json::Value::String(new_value.to_string());
-        } else {
-            return Err(format!("路径不可更新: {}", p).into());
-        }
-
-        Ok(())
-    }
-
     /// 回写完成后更新JSON结构树
     fn update_json_tree_after_writeback(
         app_window: &AppWindow,
@@ -1402,13 +2779,13 @@ impl ViewModelBridge {
         // 更新AppState中的DOM数据
         {
             let mut state = app_state.borrow_mut();
-            state.dom = Some(updated_json);
-            // 重新构建影子树
-            if let Some(ref dom) = state.dom {
-                state.tree_flat = crate::model::shadow_tree::build_shadow_tree(dom);
-                // 更新可见性
-                state.update_visibility_by_expansion();
+            // 替换DOM并重新构建影子树
+            if let Err(e) = state.replace_dom(updated_json) {
+                tracing::error!("回写后替换DOM失败: {}", e);
+                return;
             }
+            // 更新可见性
+            state.update_visibility_by_expansion();
         }
 
         // 更新UI中的树模型
@@ -1434,13 +2811,13 @@ impl ViewModelBridge {
         // 更新AppState中的DOM数据
         {
             let mut state = app_state.borrow_mut();
-            state.dom = Some(updated_json);
-            // 重新构建影子树
-            if let Some(ref dom) = state.dom {
-                state.tree_flat = crate::model::shadow_tree::build_shadow_tree(dom);
-                // 更新可见性
-                state.update_visibility_by_expansion();
+            // 替换DOM并重新构建影子树
+            if let Err(e) = state.replace_dom(updated_json) {
+                tracing::error!("回写后替换DOM失败: {}", e);
+                return;
             }
+            // 更新可见性
+            state.update_visibility_by_expansion();
         }
 
         // 准备UI更新数据
@@ -1466,22 +2843,48 @@ impl ViewModelBridge {
         });
     }
 
-    /// 处理回写后重新加载文件
+    /// 处理回写后重新加载提示的选择：立即重新加载（保留状态）或稍后（保持磁盘已更新横幅）
     fn handle_reload_file_after_writeback(
         app_window: &AppWindow,
         app_state: &Rc<RefCell<AppState>>,
-        file_path: &str
+        path_id: i32,
+        choice: ReloadChoice
     ) {
-        use std::path::Path;
+        app_window.set_reload_prompt_visible(false);
+
+        // 路径经句柄取回，保持原始 PathBuf（非 UTF-8 路径不经过有损的字符串往返）
+        let Some(path) = RELOAD_PATHS.with(|paths| paths.borrow().get(path_id).map(PathBuf::from)) else {
+            Self::append_writeback_log(app_window, "❌ 找不到待重新加载的文件");
+            return;
+        };
+        let plan = {
+            let state = app_state.borrow();
+            vm::reload::plan_reload(choice, &path, state.original_file_path.as_deref(), state.english_fields_auto_detected)
+        };
+
+        if plan.mark_dirty_on_disk {
+            app_window.set_disk_changed(true);
+            Self::append_writeback_log(app_window, "⏸ 已推迟重新加载，当前视图与磁盘文件不一致");
+            return;
+        }
+        if !plan.reload {
+            app_window.set_disk_changed(false);
+            Self::append_writeback_log(app_window, "⚠️ 当前打开的已不是回写文件，跳过重新加载");
+            return;
+        }
 
-        let path = Path::new(file_path);
         if !path.exists() {
             Self::append_writeback_log(app_window, "❌ 文件不存在，无法重新加载");
             return;
         }
 
-        // 重新加载文件
-        match app_state.borrow_mut().load_file(path) {
+        // 重新加载文件（保留展开/可见状态）
+        let result = if plan.preserve_state {
+            app_state.borrow_mut().reload_preserving_state(&path)
+        } else {
+            app_state.borrow_mut().load_file(&path)
+        };
+        match result {
             Ok(()) => {
                 Self::append_writeback_log(app_window, "✅ 文件重新加载成功");
             }
@@ -1491,10 +2894,16 @@ impl ViewModelBridge {
                 return;
             }
         }
+        app_window.set_disk_changed(false);
 
         // 在借用结束后，重新获取数据更新UI
         Self::rebuild_tree_model(app_window, app_state);
-        app_window.set_current_path(file_path.into());
+        app_window.set_current_path(utils::fs::display_path(&path).into());
+
+        if plan.redetect_english_fields {
+            let detected = Self::handle_detect_english_fields(app_window, app_state);
+            app_state.borrow_mut().english_fields_auto_detected = detected;
+        }
 
         Self::append_writeback_log(app_window, "✅ JSON结构树已更新");
         app_window.set_status_message("JSON结构树更新完成".into());
@@ -1564,6 +2973,155 @@ impl ViewModelBridge {
 
         let model = ModelRc::new(VecModel::from(tree_data));
         app_window.set_tree_model(model);
+        app_window.set_tree_truncated(app_state.borrow().truncated);
+    }
+
+    /// 修改当前项目并立即保存，使历史不因未手动保存而丢失；未打开项目时不做任何事
+    fn update_project(f: impl FnOnce(&mut Project)) {
+        CURRENT_PROJECT.with(|cell| {
+            if let Some((path, project)) = cell.borrow_mut().as_mut() {
+                f(project);
+                if let Err(e) = project.save(path) {
+                    tracing::warn!("项目文件 {} 保存失败: {}", path.display(), e);
+                }
+            }
+        });
+    }
+
+    /// 已打开项目时，将新生成的中间产物2写到项目目录并记入历史
+    fn record_project_extraction(filter: &str, stage2_json: &str) {
+        let Some(project_dir) = CURRENT_PROJECT.with(|cell| cell.borrow().as_ref().and_then(|(path, _)| path.parent().map(PathBuf::from))) else {
+            return;
+        };
+        let stage2: Value = match serde_json::from_str(stage2_json) {
+            Ok(stage2) => stage2,
+            Err(e) => {
+                tracing::warn!("中间产物2解析失败，未记入项目: {}", e);
+                return;
+            }
+        };
+        Self::update_project(|project| {
+            let stage2_path = project_dir.join(utils::fs::artifact_file_name(utils::fs::ArtifactKind::Stage2, Some(project.source.as_path())));
+            match utils::fs::write_json_file_atomic(&stage2_path, &stage2) {
+                Ok(()) => project.record_extraction(&stage2_path, &stage2, filter),
+                Err(e) => tracing::warn!("中间产物2写入项目目录失败: {}", e),
+            }
+        });
+    }
+
+    /// 保存项目：记录当前源文件、过滤条件预设与中间产物2，已打开的项目沿用其历史
+    fn handle_save_project(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>, preview_full_text: &Rc<RefCell<String>>) {
+        let Some(source) = app_state.borrow().original_file_path.clone() else {
+            app_window.set_status_message(format!("{}请先打开源文件", STATUS_ERROR_PREFIX).into());
+            return;
+        };
+        let stem = source.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "untitled".to_string());
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("翻译项目", &["json"])
+            .set_file_name(format!("{}{}", stem, PROJECT_FILE_SUFFIX))
+            .set_title("保存项目")
+            .save_file()
+        else {
+            app_window.set_status_message("未保存项目".into());
+            return;
+        };
+
+        let mut project = CURRENT_PROJECT
+            .with(|cell| cell.borrow().as_ref().map(|(_, project)| project.clone()))
+            .filter(|project| project.source == source)
+            .unwrap_or_else(|| Project::new(&source));
+        project.config = ProjectConfig {
+            filters: parse_filter_list(&app_window.get_search_filter()),
+            leaf_nodes_only: app_window.get_leaf_nodes_only(),
+            sort: Stage2Sort::ALL.get(app_window.get_stage2_sort() as usize).copied().unwrap_or_default(),
+            anchor_field: Some(app_window.get_anchor_field().trim().to_string()).filter(|field| !field.is_empty()),
+        };
+        // 预览区中尚未落盘的中间产物2随项目保存
+        let stage2 = serde_json::from_str::<Value>(&preview_full_text.borrow()).ok().filter(|v| v.get("items").is_some_and(Value::is_array));
+        if let (None, Some(stage2), Some(dir)) = (&project.stage2_path, stage2, path.parent()) {
+            let stage2_path = dir.join(utils::fs::artifact_file_name(utils::fs::ArtifactKind::Stage2, Some(source.as_path())));
+            match utils::fs::write_json_file_atomic(&stage2_path, &stage2) {
+                Ok(()) => project.stage2_path = Some(stage2_path),
+                Err(e) => tracing::warn!("中间产物2写入项目目录失败: {}", e),
+            }
+        }
+
+        match project.save(&path) {
+            Ok(()) => {
+                app_window.set_status_message(format!("项目已保存: {}", path.display()).into());
+                tracing::info!("项目已保存: {}", path.display());
+                CURRENT_PROJECT.with(|cell| *cell.borrow_mut() = Some((path, project)));
+            }
+            Err(e) => app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into()),
+        }
+    }
+
+    /// 打开项目：加载源文件，恢复过滤条件预设、提取选项与中间产物2，并在回写日志中列出历史
+    fn handle_open_project(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>, preview_full_text: &Rc<RefCell<String>>) {
+        let Some(path) = rfd::FileDialog::new().add_filter("翻译项目", &["json"]).set_title("打开项目").pick_file() else {
+            app_window.set_status_message("未选择项目".into());
+            return;
+        };
+        let project = match Project::open(&path) {
+            Ok(project) => project,
+            Err(e) => {
+                app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into());
+                return;
+            }
+        };
+        let missing = project.missing_files();
+        if missing.contains(&project.source) {
+            app_window.invoke_show_message_dialog("项目源文件缺失".into(), format!("找不到源文件: {}", project.source.display()).into());
+            return;
+        }
+        if !Self::load_file_at(app_window, app_state, &project.source) {
+            return;
+        }
+
+        let config = &project.config;
+        app_window.set_leaf_nodes_only(config.leaf_nodes_only);
+        app_window.set_stage2_sort(Stage2Sort::ALL.iter().position(|sort| *sort == config.sort).unwrap_or_default() as i32);
+        app_window.set_anchor_field(config.anchor_field.clone().unwrap_or_default().into());
+        let filter = config.filters.join(", ");
+        app_window.set_search_filter(filter.clone().into());
+        Self::handle_search_changed(app_window, app_state, &filter);
+
+        if let Some(stage2_path) = project.stage2_path.as_ref().filter(|p| !missing.contains(*p)) {
+            match std::fs::read_to_string(stage2_path) {
+                Ok(stage2_json) => {
+                    let (page_text, total_pages) = ViewModelBridge::paginate_text(&stage2_json, 1, 300);
+                    *preview_full_text.borrow_mut() = stage2_json;
+                    app_window.set_preview_text(page_text.into());
+                    app_window.set_preview_current_page(1);
+                    app_window.set_preview_total_pages(total_pages);
+                    app_window.set_selected_json_path("中间产物第二阶段".into());
+                }
+                Err(e) => tracing::warn!("读取中间产物2失败: {}", e),
+            }
+        }
+
+        for entry in &project.history {
+            Self::append_writeback_log(app_window, &format!("📜 {} {}", entry.at, entry.event.describe()));
+        }
+        if !missing.is_empty() {
+            let listed: Vec<String> = missing.iter().map(|p| p.display().to_string()).collect();
+            app_window.invoke_show_message_dialog("项目文件缺失".into(), format!("以下文件已不存在：\n\n{}", listed.join("\n")).into());
+        }
+        app_window.set_status_message(format!("已打开项目: {}", path.display()).into());
+        CURRENT_PROJECT.with(|cell| *cell.borrow_mut() = Some((path, project)));
+    }
+
+    /// 忽略节点上限重建完整的结构树
+    fn handle_load_full_tree(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>) {
+        let start_time = Instant::now();
+        if let Err(e) = app_state.borrow_mut().load_full_tree() {
+            app_window.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into());
+            return;
+        }
+        Self::rebuild_tree_model(app_window, app_state);
+        let node_count = app_state.borrow().tree_flat.len();
+        app_window.set_status_message(format!("已完整加载 {} 个节点", node_count).into());
+        tracing::info!("完整加载结构树: {} 个节点，耗时: {:.2}ms", node_count, start_time.elapsed().as_millis());
     }
 
     /// 检查文本是否匹配字符过滤条件
@@ -1613,6 +3171,74 @@ impl ViewModelBridge {
         app_window.set_status_message(format!("已切换到{}模式", mode_text).into());
     }
 
+    /// 计算各节点的子树大小（耗时与节点数成正比，在后台线程对DOM快照计算并显示进度条），完成后刷新树
+    fn handle_compute_sizes_pressed(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>) {
+        let Some(dom) = app_state.borrow().dom.clone() else {
+            app_window.set_status_message(format!("{}请先加载JSON文件", STATUS_ERROR_PREFIX).into());
+            return;
+        };
+        let Some(token) = Self::begin_busy_operation(app_window, "计算子树大小") else {
+            return;
+        };
+        let cancel = Self::busy_cancel_flag();
+        app_window.invoke_show_progress("正在计算子树大小...".into());
+
+        let app_weak = app_window.as_weak();
+        let app_state = app_state.clone();
+        slint::spawn_local(async move {
+            // 计算期间不持有 AppState 的借用，进度更新经事件循环送回界面
+            let progress_weak = app_weak.clone();
+            let (dom, sizes, timing) = run_in_background(move || {
+                let mut throttle = ThrottledProgress::default();
+                let sizes = model::shadow_tree::compute_subtree_sizes(&dom, |progress, message| {
+                    throttle.report(progress, message, |progress, message, timing| {
+                        let app_weak = progress_weak.clone();
+                        let message = message.to_string();
+                        let _ = slint::invoke_from_event_loop(move || {
+                            if let Some(app) = app_weak.upgrade() {
+                                ViewModelBridge::update_progress(&app, progress, &message, timing);
+                            }
+                        });
+                    });
+                });
+                // 补发被限流丢弃的最后一条进度
+                throttle.flush(|progress, message, timing| {
+                    let app_weak = progress_weak.clone();
+                    let message = message.to_string();
+                    let _ = slint::invoke_from_event_loop(move || {
+                        if let Some(app) = app_weak.upgrade() {
+                            ViewModelBridge::update_progress(&app, progress, &message, timing);
+                        }
+                    });
+                });
+                let timing = throttle.timing(1.0);
+                (dom, sizes, timing)
+            })
+            .await;
+            let Some(app) = app_weak.upgrade() else {
+                return;
+            };
+            // 取消后丢弃结果，不再写入节点大小
+            if cancel.is_cancelled() {
+                tracing::info!("计算子树大小已取消");
+                return;
+            }
+            let result = app_state.borrow_mut().apply_subtree_sizes(&dom, sizes);
+            match result {
+                Ok(total) => {
+                    ViewModelBridge::rebuild_tree_model(&app, &app_state);
+                    app.set_status_message(format!("文档共 {}", format_byte_size(total)).into());
+                    ViewModelBridge::complete_progress_and_auto_hide(&app, timing);
+                }
+                Err(e) => {
+                    app.invoke_hide_progress();
+                    app.set_status_message(format!("{}{}", STATUS_ERROR_PREFIX, e).into());
+                }
+            }
+            ViewModelBridge::finish_busy_operation(&app, token);
+        }).unwrap();
+    }
+
     /// 处理叶子节点过滤切换
     fn handle_toggle_leaf_nodes_only(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>) {
         let current_mode = app_window.get_leaf_nodes_only();
@@ -1620,6 +3246,7 @@ impl ViewModelBridge {
 
         // 重新检测英文字段
         Self::handle_detect_english_fields(app_window, app_state);
+        Self::schedule_match_count(app_window, app_state);
 
         let mode_text = if !current_mode { "叶子节点" } else { "全部节点" };
         app_window.set_status_message(format!("已切换到{}模式", mode_text).into());
@@ -1646,8 +3273,8 @@ impl ViewModelBridge {
         }
     }
 
-    /// 处理智能英文字段检测
-    fn handle_detect_english_fields(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>) {
+    /// 处理智能英文字段检测，返回是否检测到了英文字段
+    fn handle_detect_english_fields(app_window: &AppWindow, app_state: &Rc<RefCell<AppState>>) -> bool {
         let leaf_nodes_only = app_window.get_leaf_nodes_only();
         match app_state.borrow().detect_english_fields(leaf_nodes_only) {
             Ok(english_fields) => {
@@ -1665,11 +3292,13 @@ impl ViewModelBridge {
                 app_window.set_status_message(format!("检测到 {} 个英文字段", field_count).into());
 
                 tracing::info!("英文字段检测完成，找到 {} 个字段", field_count);
+                field_count > 0
             }
             Err(e) => {
                 let error_msg = format!("{}英文字段检测失败: {}", STATUS_ERROR_PREFIX, e);
                 app_window.set_status_message(error_msg.into());
                 tracing::error!("英文字段检测失败: {}", e);
+                false
             }
         }
     }
@@ -1705,18 +3334,184 @@ impl ViewModelBridge {
 }
 
 
+thread_local! {
+    /// panic 钩子访问的应用状态（仅UI线程持有）
+    static CRASH_STATE: RefCell<Weak<RefCell<AppState>>> = RefCell::new(Weak::new());
+    /// 长耗时操作守卫（仅UI线程访问）
+    static OPERATION_GUARD: RefCell<OperationGuard> = RefCell::new(OperationGuard::default());
+    /// 进度条自动隐藏调度（仅UI线程访问）
+    static PROGRESS_AUTO_HIDE: RefCell<ProgressAutoHide> = RefCell::new(ProgressAutoHide::default());
+    /// 最近一次质量检查报告（用于CSV导出）
+    static LAST_QA_REPORT: RefCell<Option<model::qa::QaReport>> = const { RefCell::new(None) };
+    /// Schema 校验未通过、等待用户确认的保存
+    static PENDING_SCHEMA_SAVE: RefCell<Option<PendingSchemaSave>> = const { RefCell::new(None) };
+    /// 重新加载提示等界面回调引用的文件路径（界面只持有 id）
+    static RELOAD_PATHS: RefCell<vm::reload::PathRegistry> = RefCell::new(vm::reload::PathRegistry::default());
+    /// 当前打开的翻译项目及其项目文件路径
+    static CURRENT_PROJECT: RefCell<Option<(PathBuf, Project)>> = const { RefCell::new(None) };
+    /// 输入过滤条件时统计命中数的防抖定时器
+    static MATCH_COUNT_TIMER: slint::Timer = slint::Timer::default();
+    /// 输入过滤条件时刷新中间产物1预览的防抖定时器
+    static STAGE1_PREVIEW_TIMER: slint::Timer = slint::Timer::default();
+}
+
+/// Schema 校验未通过时暂缓的保存
+enum PendingSchemaSave {
+    /// 另存为当前DOM
+    SaveAs(PathBuf),
+    /// 回写结果写入原始文件
+    Writeback { path: PathBuf, json: serde_json::Value, keep_backups: bool },
+}
+
+/// 安装崩溃钩子：保存DOM恢复文件、写出诊断包并尽力弹出原生对话框
+fn install_panic_hook(state: &Rc<RefCell<AppState>>) {
+    CRASH_STATE.with(|cell| *cell.borrow_mut() = Rc::downgrade(state));
+
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let message = utils::crash::panic_message(info.payload());
+        let location = info.location().map(|l| format!("{}:{}", l.file(), l.line()));
+
+        // DOM正被借用（例如在 borrow_mut 期间 panic）时跳过恢复文件
+        let (recovery_file, artifacts_dir) = CRASH_STATE
+            .try_with(|cell| {
+                let state = cell.try_borrow().ok()?.upgrade()?;
+                let state = state.try_borrow().ok()?;
+                let artifacts_dir = utils::fs::artifacts_dir(state.original_file_path.as_deref());
+                let recovery = if state.dom.is_some() { state.save_recovery().ok() } else { None };
+                Some((recovery, artifacts_dir))
+            })
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| (None, utils::fs::artifacts_dir(None)));
+
+        let bundle = utils::crash::CrashBundle {
+            timestamp: utils::crash::unix_timestamp(),
+            message: message.clone(),
+            location,
+            recovery_file: recovery_file.clone(),
+            perf_history: utils::crash::recent_perf_events(),
+            status_events: utils::crash::recent_status_events(),
+        };
+        let _ = std::fs::create_dir_all(&artifacts_dir);
+        let bundle_path = utils::crash::write_crash_bundle(&artifacts_dir, &bundle).ok();
+
+        let mut description = format!("程序发生错误: {}", message);
+        if let Some(p) = &recovery_file {
+            description.push_str(&format!("\n\n已保存恢复文件: {}", p.display()));
+        }
+        if let Some(p) = &bundle_path {
+            description.push_str(&format!("\n诊断信息: {}", p.display()));
+        }
+        let _ = rfd::MessageDialog::new()
+            .set_level(rfd::MessageLevel::Error)
+            .set_title("JSON 翻译工具崩溃")
+            .set_description(description)
+            .show();
+
+        default_hook(info);
+    }));
+}
+
+/// 执行命令行多语言分发，返回退出码（有语言失败或输出违反 `--schema` 时为 1，参数错误为 2）
+fn run_fanout_cli(args: &[String]) -> i32 {
+    let flag = |name: &str| args.iter().position(|a| a == name).and_then(|i| args.get(i + 1)).map(std::path::PathBuf::from);
+    let (Some(source), Some(stage2), Some(translations)) = (flag("--source"), flag("--stage2"), flag("--translations")) else {
+        eprintln!("用法: fanout --source <源文件> --stage2 <中间产物2> --translations <译文目录> [--out <输出目录>] [--schema <JSON Schema>]");
+        return 2;
+    };
+    let schema = match flag("--schema").map(|p| utils::fs::read_json_file(&p).and_then(CompiledSchema::compile)).transpose() {
+        Ok(schema) => schema,
+        Err(e) => {
+            eprintln!("JSON Schema 加载失败: {}", e);
+            return 2;
+        }
+    };
+    let out_dir = flag("--out").unwrap_or_else(|| source.parent().map(std::path::Path::to_path_buf).unwrap_or_default());
+    let opts = model::batch::FanOutOptions {
+        file_stem: source.file_stem().map_or_else(|| "app".to_string(), |s| s.to_string_lossy().to_string()),
+        ..Default::default()
+    };
+    let result = utils::fs::read_json_file(&source).and_then(|dom| {
+        let stage2 = utils::fs::read_json_file(&stage2)?;
+        model::batch::fan_out_dir(&dom, &stage2, &translations, &out_dir, &opts)
+    });
+    match result {
+        Ok(results) => {
+            let mut violated = false;
+            for locale in &results {
+                println!("{}", locale.describe());
+                if let (Some(schema), Ok(path)) = (&schema, &locale.output) {
+                    let violations = utils::fs::read_json_file(path).map(|dom| schema.validate(&dom));
+                    match violations {
+                        Ok(violations) if violations.is_empty() => {}
+                        Ok(violations) => {
+                            violated = true;
+                            eprintln!("{}: {} 处 Schema 违规", locale.locale, violations.len());
+                            for v in &violations {
+                                eprintln!("  {}", v);
+                            }
+                        }
+                        Err(e) => {
+                            violated = true;
+                            eprintln!("{}: 无法读取输出文件校验 Schema: {}", locale.locale, e);
+                        }
+                    }
+                }
+            }
+            if results.iter().all(|r| r.is_ok()) && !violated { 0 } else { 1 }
+        }
+        Err(e) => {
+            eprintln!("多语言分发失败: {}", e);
+            1
+        }
+    }
+}
+
 fn main() {
-    // 初始化日志输出（遵循 message_：可观测性）
-    let _ = SubscriberBuilder::default()
-        .with_max_level(tracing::Level::INFO)
-        .try_init();
+    // 命令行自检：`juzhen_r_fanyi selftest [--json]`，失败时以非零状态退出
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("selftest") {
+        let report = vm::selftest::run();
+        if args.iter().any(|a| a == "--json") {
+            println!("{}", report.to_json());
+        } else {
+            print!("{}", report.render());
+        }
+        std::process::exit(if report.passed() { 0 } else { 1 });
+    }
+    // 命令行多语言分发：`juzhen_r_fanyi fanout --source app.json --stage2 stage2.json --translations dir/ [--out dir/] [--schema schema.json]`
+    if args.first().map(String::as_str) == Some("fanout") {
+        std::process::exit(run_fanout_cli(&args[1..]));
+    }
+
+    // 初始化日志输出（遵循 message_：可观测性）：控制台 + 滚动日志文件，失败时退回仅控制台
+    let logging = match utils::logging::init_logging(&utils::logging::log_dir()) {
+        Ok(guard) => Some(guard),
+        Err(e) => {
+            let _ = SubscriberBuilder::default()
+                .with_max_level(tracing::Level::INFO)
+                .try_init();
+            tracing::warn!("日志文件初始化失败，仅输出到控制台: {}", e);
+            None
+        }
+    };
 
     let app = AppWindow::new().expect("UI 初始化失败");
     let state = Rc::new(RefCell::new(AppState::default()));
+    // `--read-only`：以只读模式启动，禁止编辑与写回源文件
+    let read_only = args.iter().any(|a| a == "--read-only");
+    state.borrow_mut().read_only = read_only;
+    app.set_read_only(read_only);
+    install_panic_hook(&state);
 
     // 创建VM桥接器并绑定UI回调
     let bridge = ViewModelBridge::new(&app, state.clone());
     bridge.initialize_ui(&app);
+    if let Some(guard) = &logging {
+        bridge.setup_log_level(&app, guard.level.clone());
+        app.set_log_file_path(guard.log_dir.to_string_lossy().to_string().into());
+    }
 
     tracing::info!("应用启动成功，UI已初始化");
     app.run().unwrap();
@@ -0,0 +1,136 @@
+//! 数组元素锚点：提取时记录条目所在数组元素的锚点字段值（如 `id`），
+//! 源文件的数组在提取后增删或重排元素时，回写前按锚点重新定位下标
+
+use serde_json::{json, Value};
+
+use crate::model::path::{format_segments, parse_segments, resolve, PathSegment};
+
+/// 中间产物2信封中记录锚点字段名的键
+pub const ANCHOR_FIELD_KEY: &str = "anchor_field";
+
+/// 按锚点重新定位的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reanchor {
+    /// 元素仍在原下标
+    Unchanged,
+    /// 元素已移动，附新路径
+    Moved(String),
+    /// 找不到（或找到多个）锚点值相同的元素
+    Unresolved,
+}
+
+/// 可作锚点的字段值（字符串、数字、布尔）
+fn anchor_value<'a>(element: &'a Value, field: &str) -> Option<&'a Value> {
+    element.get(field).filter(|v| v.is_string() || v.is_number() || v.is_boolean())
+}
+
+/// 计算条目的锚点：路径中每一层元素含锚点字段的数组下标各记一项 `{"depth": 段下标, "value": 锚点值}`，
+/// 由外到内排列；没有任何一层含锚点字段时返回 None
+pub fn find_anchor(dom: &Value, source_path: &str, field: &str) -> Option<Value> {
+    let segments = parse_segments(source_path)?;
+    let levels: Vec<Value> = (0..segments.len())
+        .filter(|&depth| matches!(segments[depth], PathSegment::Index(_)))
+        .filter_map(|depth| {
+            let value = anchor_value(resolve(dom, &segments[..=depth])?, field)?;
+            Some(json!({"depth": depth, "value": value}))
+        })
+        .collect();
+    (!levels.is_empty()).then_some(Value::Array(levels))
+}
+
+/// 在当前文档中按锚点由外到内逐层重新定位 `source_path` 的数组下标
+///
+/// 最内层锚点之外的数组下标都必须有锚点确认，否则外层元素可能已移动而无从得知，视为无法定位；
+/// 最内层锚点之内、元素不含锚点字段的下标保持不变。也接受旧格式的单个 `{"depth", "value"}`
+pub fn reanchor(dom: &Value, source_path: &str, anchor: &Value, field: &str) -> Reanchor {
+    let Some(mut segments) = parse_segments(source_path) else {
+        return Reanchor::Unresolved;
+    };
+    let levels: Option<Vec<(usize, &Value)>> = match anchor {
+        Value::Array(levels) => levels.iter().map(anchor_level).collect(),
+        single => anchor_level(single).map(|level| vec![level]),
+    };
+    let Some(levels) = levels.filter(|levels| !levels.is_empty()) else {
+        return Reanchor::Unresolved;
+    };
+    let innermost = levels.iter().map(|(depth, _)| *depth).max().unwrap_or(0);
+    if innermost >= segments.len() {
+        return Reanchor::Unresolved;
+    }
+
+    let mut moved = false;
+    for depth in 0..=innermost {
+        let PathSegment::Index(old_index) = segments[depth] else {
+            continue;
+        };
+        let Some((_, expected)) = levels.iter().find(|(d, _)| *d == depth) else {
+            return Reanchor::Unresolved;
+        };
+        let Some(items) = resolve(dom, &segments[..depth]).and_then(Value::as_array) else {
+            return Reanchor::Unresolved;
+        };
+        let mut found = items.iter().enumerate().filter(|(_, item)| anchor_value(item, field) == Some(*expected)).map(|(i, _)| i);
+        match (found.next(), found.next()) {
+            (Some(index), None) => {
+                moved |= index != old_index;
+                segments[depth] = PathSegment::Index(index);
+            }
+            _ => return Reanchor::Unresolved,
+        }
+    }
+    if moved {
+        Reanchor::Moved(format_segments(&segments))
+    } else {
+        Reanchor::Unchanged
+    }
+}
+
+/// 解析一层锚点 `{"depth", "value"}`
+fn anchor_level(level: &Value) -> Option<(usize, &Value)> {
+    Some((level.get("depth")?.as_u64()? as usize, level.get("value")?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_anchor_and_reanchor() {
+        let dom = json!({"groups": [{"id": "g", "items": [{"id": 7, "title": "A"}, {"title": "B"}]}]});
+        // 每一层含锚点字段的数组元素，由外到内
+        let anchor = find_anchor(&dom, "$.groups[0].items[0].title", "id").unwrap();
+        assert_eq!(anchor, json!([{"depth": 1, "value": "g"}, {"depth": 3, "value": 7}]));
+        assert_eq!(find_anchor(&dom, "$.groups[0].items[1].title", "id").unwrap(), json!([{"depth": 1, "value": "g"}]));
+        assert_eq!(find_anchor(&dom, "$.groups[0].id", "missing"), None);
+
+        let moved = json!({"groups": [{"id": "g", "items": [{"title": "B"}, {"id": 7, "title": "A"}]}]});
+        assert_eq!(reanchor(&dom, "$.groups[0].items[0].title", &anchor, "id"), Reanchor::Unchanged);
+        assert_eq!(
+            reanchor(&moved, "$.groups[0].items[0].title", &anchor, "id"),
+            Reanchor::Moved("$.groups[0].items[1].title".into())
+        );
+        let duplicated = json!({"groups": [{"id": "g", "items": [{"id": 7}, {"id": 7}]}]});
+        assert_eq!(reanchor(&duplicated, "$.groups[0].items[0].title", &anchor, "id"), Reanchor::Unresolved);
+        assert_eq!(reanchor(&json!({}), "$.groups[0].items[0].title", &anchor, "id"), Reanchor::Unresolved);
+        // 旧格式的单层锚点
+        assert_eq!(reanchor(&dom, "$.groups[0].items[0].title", &json!({"depth": 3, "value": 7}), "id"), Reanchor::Unresolved);
+    }
+
+    #[test]
+    fn test_reanchor_confirms_outer_levels() {
+        let dom = json!({"groups": [{"id": "g1", "items": [{"id": 7, "title": "A"}]}, {"id": "g2", "items": [{"id": 7, "title": "B"}]}]});
+        let anchor = find_anchor(&dom, "$.groups[1].items[0].title", "id").unwrap();
+
+        // 外层分组被重排：内层 id 相同的元素不会被错认
+        let reordered = json!({"groups": [{"id": "g2", "items": [{"id": 0}, {"id": 7, "title": "B"}]}, {"id": "g1", "items": [{"id": 7, "title": "A"}]}]});
+        assert_eq!(
+            reanchor(&reordered, "$.groups[1].items[0].title", &anchor, "id"),
+            Reanchor::Moved("$.groups[0].items[1].title".into())
+        );
+
+        // 外层数组没有锚点字段：无法确认外层元素未移动，视为无法定位
+        let unanchored_outer = json!([[{"id": 7, "title": "A"}]]);
+        let anchor = find_anchor(&unanchored_outer, "$[0][0].title", "id").unwrap();
+        assert_eq!(reanchor(&unanchored_outer, "$[0][0].title", &anchor, "id"), Reanchor::Unresolved);
+    }
+}
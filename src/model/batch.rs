@@ -0,0 +1,335 @@
+//! 多语言分发：一份中间产物2配多份译文（每种语言一份），分别回写源文档并输出 `{stem}.{locale}.json`
+//!
+//! 各语言相互独立：某一语言的译文文件损坏或写入失败只影响该语言的结果
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::model::data_core::{AppError, StateKind};
+use crate::model::placeholder::detect_placeholder_patterns;
+use crate::model::qa::parse_translation_file;
+use crate::model::workflow::{normalize_writeback, WritebackOptions, WritebackPlan};
+use crate::utils::fs::{write_json_file_atomic_with_options, SaveOptions};
+
+/// 一种语言的译文文件：语言代码与解析结果（已规范化为 `{seq: 译文}`）
+pub type TranslationFile = (String, Result<BTreeMap<String, String>, AppError>);
+
+/// 分发选项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FanOutOptions {
+    pub writeback: WritebackOptions,
+    pub save: SaveOptions,
+    /// 输出文件名前缀：`{file_stem}.{locale}.json`
+    pub file_stem: String,
+}
+
+impl Default for FanOutOptions {
+    fn default() -> Self {
+        Self { writeback: WritebackOptions::default(), save: SaveOptions::default(), file_stem: "app".to_string() }
+    }
+}
+
+/// 单个语言的分发结果
+#[derive(Debug)]
+pub struct LocaleResult {
+    pub locale: String,
+    /// 成功时为输出文件路径
+    pub output: Result<PathBuf, AppError>,
+    /// 回写的条目数
+    pub written: usize,
+    /// 被跳过的条目数（含占位符不一致的条目）
+    pub skipped: usize,
+    /// 译文与原文占位符不一致而未回写的序号
+    pub placeholder_mismatches: Vec<usize>,
+}
+
+impl LocaleResult {
+    fn failed(locale: &str, error: AppError) -> Self {
+        LocaleResult { locale: locale.to_string(), output: Err(error), written: 0, skipped: 0, placeholder_mismatches: Vec::new() }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.output.is_ok()
+    }
+
+    /// 日志/终端显示的一行
+    pub fn describe(&self) -> String {
+        match &self.output {
+            Ok(path) => {
+                let mut text = format!("{}: 回写 {} 个，跳过 {} 个 → {}", self.locale, self.written, self.skipped, path.display());
+                if !self.placeholder_mismatches.is_empty() {
+                    let seqs: Vec<String> = self.placeholder_mismatches.iter().map(usize::to_string).collect();
+                    text.push_str(&format!("（占位符不一致: {}）", seqs.join(" ")));
+                }
+                text
+            }
+            Err(e) => format!("{}: 失败: {}", self.locale, e),
+        }
+    }
+}
+
+/// 语言代码只允许字母、数字、`-` 与 `_`，避免写到输出目录之外
+fn validate_locale(locale: &str) -> Result<(), AppError> {
+    if locale.is_empty() || !locale.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(AppError::State(StateKind::Invalid, format!("无效的语言代码: {:?}", locale)));
+    }
+    Ok(())
+}
+
+/// 按序号逐条对比原文与译文的占位符（忽略顺序），返回不一致的序号
+///
+/// 原文按条目自身的 `seq` 字段查找，条目顺序被调整或有删减时同样对得上
+fn placeholder_mismatches(stage2: &Value, translations: &BTreeMap<String, String>) -> Vec<usize> {
+    let sources: HashMap<usize, &str> = stage2
+        .get("items")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|item| Some((usize::try_from(item.get("seq")?.as_u64()?).ok()?, item.get("name")?.as_str()?)))
+        .collect();
+    let placeholders = |text: &str| {
+        let mut found: Vec<String> = detect_placeholder_patterns(text).into_iter().map(|(_, p)| p).collect();
+        found.sort();
+        found
+    };
+    translations
+        .iter()
+        .filter_map(|(seq, text)| {
+            let seq = seq.parse::<usize>().ok()?;
+            let source = sources.get(&seq)?;
+            (!text.trim().is_empty() && placeholders(source) != placeholders(text)).then_some(seq)
+        })
+        .collect()
+}
+
+fn fan_out_locale(
+    source_dom: &Value,
+    stage2: &Value,
+    locale: &str,
+    translations: &BTreeMap<String, String>,
+    out_dir: &Path,
+    opts: &FanOutOptions,
+) -> LocaleResult {
+    if let Err(e) = validate_locale(locale) {
+        return LocaleResult::failed(locale, e);
+    }
+    // 按路径给出的译文先换成序号，占位符检查才能对上原文
+    let translations: Value = translations.iter().map(|(key, text)| (key.clone(), Value::String(text.clone()))).collect();
+    let translations: BTreeMap<String, String> = match normalize_writeback(&translations, stage2) {
+        Ok(Value::Object(map)) => map.into_iter().filter_map(|(seq, v)| Some((seq, v.as_str()?.to_string()))).collect(),
+        Ok(_) => BTreeMap::new(),
+        Err(e) => return LocaleResult::failed(locale, e),
+    };
+    let mismatches = placeholder_mismatches(stage2, &translations);
+    let writeback: serde_json::Map<String, Value> = translations
+        .iter()
+        .filter(|(seq, _)| !matches!(seq.parse::<usize>(), Ok(seq) if mismatches.contains(&seq)))
+        .map(|(seq, text)| (seq.clone(), Value::String(text.clone())))
+        .collect();
+
+    let attempt = || -> Result<(PathBuf, usize, usize), AppError> {
+        let mut dom = source_dom.clone();
+        let plan = WritebackPlan::build(&Value::Object(writeback), stage2, &dom, opts.writeback)?;
+        let written = plan.apply(&mut dom, |_, _| {});
+        let path = out_dir.join(format!("{}.{}.json", opts.file_stem, locale));
+        write_json_file_atomic_with_options(&path, &dom, &opts.save)?;
+        Ok((path, written, plan.skipped))
+    };
+    match attempt() {
+        Ok((path, written, skipped)) => LocaleResult {
+            locale: locale.to_string(),
+            output: Ok(path),
+            written,
+            skipped: skipped + mismatches.len(),
+            placeholder_mismatches: mismatches,
+        },
+        Err(e) => LocaleResult::failed(locale, e),
+    }
+}
+
+/// 为每种语言克隆源文档并回写对应译文，原子写入 `{out_dir}/{file_stem}.{locale}.json`
+///
+/// 占位符与原文不一致的译文不回写（保留原文），记录在结果中；各语言的失败互不影响
+pub fn fan_out(
+    source_dom: &Value,
+    stage2: &Value,
+    translations: &[(String, BTreeMap<String, String>)],
+    out_dir: &Path,
+    opts: &FanOutOptions,
+) -> Vec<LocaleResult> {
+    translations
+        .iter()
+        .map(|(locale, map)| {
+            let result = fan_out_locale(source_dom, stage2, locale, map, out_dir, opts);
+            tracing::info!("多语言分发 {}", result.describe());
+            result
+        })
+        .collect()
+}
+
+/// 读取目录中以语言代码命名的译文文件（`zh-CN.json`、`ja.json` …），按语言代码排序
+///
+/// 任一结构（按序号/按路径/原结构）的译文都按中间产物2规范化为 `{seq: 译文}`；
+/// 跳过以 `{file_stem}.` 开头的文件（分发的输出），以免输出目录与译文目录相同时被当作译文读入
+pub fn read_translations_dir(dir: &Path, file_stem: &str, stage2: &Value) -> Result<Vec<TranslationFile>, AppError> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir).map_err(|e| AppError::io(e, dir))? {
+        let path = entry.map_err(|e| AppError::io(e, dir))?.path();
+        let (Some(locale), Some("json")) = (path.file_stem().and_then(|s| s.to_str()), path.extension().and_then(|s| s.to_str())) else {
+            continue;
+        };
+        if locale.starts_with(&format!("{}.", file_stem)) {
+            continue;
+        }
+        let map = std::fs::read_to_string(&path).map_err(|e| AppError::io(e, &path)).and_then(|text| parse_translation_file(&text, stage2));
+        files.push((locale.to_string(), map));
+    }
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(files)
+}
+
+/// 读取译文目录并分发；无法解析的译文文件作为该语言的失败结果返回
+pub fn fan_out_dir(
+    source_dom: &Value,
+    stage2: &Value,
+    translations_dir: &Path,
+    out_dir: &Path,
+    opts: &FanOutOptions,
+) -> Result<Vec<LocaleResult>, AppError> {
+    fan_out_dir_with_cancel(source_dom, stage2, translations_dir, out_dir, opts, &|| false)
+}
+
+/// 同 [`fan_out_dir`]，每种语言写入前检查 `cancelled`，返回 true 时停止分发（只返回已完成的语言）
+pub fn fan_out_dir_with_cancel(
+    source_dom: &Value,
+    stage2: &Value,
+    translations_dir: &Path,
+    out_dir: &Path,
+    opts: &FanOutOptions,
+    cancelled: &dyn Fn() -> bool,
+) -> Result<Vec<LocaleResult>, AppError> {
+    Ok(read_translations_dir(translations_dir, &opts.file_stem, stage2)?
+        .into_iter()
+        .take_while(|_| !cancelled())
+        .map(|(locale, map)| match map {
+            Ok(map) => fan_out_locale(source_dom, stage2, &locale, &map, out_dir, opts),
+            Err(e) => LocaleResult::failed(&locale, e),
+        })
+        .inspect(|result| tracing::info!("多语言分发 {}", result.describe()))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_fan_out_isolates_failures() {
+        let source = json!({"menu": {"open": "Open {file}", "close": "Close"}});
+        let stage2 = json!({"items": [
+            {"seq": 0, "name": "Open {file}", "source_path": "$.menu.open"},
+            {"seq": 1, "name": "Close", "source_path": "$.menu.close"}
+        ]});
+        let dir = tempfile::tempdir().unwrap();
+        let translations = dir.path().join("translations");
+        std::fs::create_dir(&translations).unwrap();
+        std::fs::write(translations.join("zh-CN.json"), r#"{"0": "打开 {file}", "1": "关闭"}"#).unwrap();
+        // 占位符被译错的条目保留原文
+        std::fs::write(translations.join("ja.json"), r#"{"0": "開く {fichier}", "1": "閉じる"}"#).unwrap();
+        std::fs::write(translations.join("de.json"), r#"{"0": "Öffnen"#).unwrap();
+        std::fs::write(translations.join("notes.txt"), "不是译文").unwrap();
+
+        let out = dir.path().join("out");
+        std::fs::create_dir(&out).unwrap();
+        let results = fan_out_dir(&source, &stage2, &translations, &out, &FanOutOptions::default()).unwrap();
+        let locales: Vec<&str> = results.iter().map(|r| r.locale.as_str()).collect();
+        assert_eq!(locales, ["de", "ja", "zh-CN"]);
+
+        assert!(!results[0].is_ok(), "损坏的译文文件只影响该语言");
+        assert!(!out.join("app.de.json").exists());
+
+        assert_eq!((results[1].written, results[1].skipped, results[1].placeholder_mismatches.clone()), (1, 1, vec![0]));
+        let ja: Value = serde_json::from_str(&std::fs::read_to_string(out.join("app.ja.json")).unwrap()).unwrap();
+        assert_eq!(ja, json!({"menu": {"open": "Open {file}", "close": "閉じる"}}));
+
+        let zh: Value = serde_json::from_str(&std::fs::read_to_string(out.join("app.zh-CN.json")).unwrap()).unwrap();
+        assert_eq!(zh, json!({"menu": {"open": "打开 {file}", "close": "关闭"}}));
+        assert!(results[2].describe().starts_with("zh-CN: 回写 2 个"));
+
+        // 不合法的语言代码不会写到输出目录之外
+        let bad = [("../x".to_string(), BTreeMap::new())];
+        assert!(fan_out(&source, &stage2, &bad, &out, &FanOutOptions::default())[0].output.is_err());
+    }
+
+    #[test]
+    fn test_fan_out_accepts_every_final_shape() {
+        let source = json!({"menu": {"open": "Open {file}", "items": ["x", "Close"]}});
+        let stage2 = json!({"items": [
+            {"seq": 0, "name": "Open {file}", "source_path": "$.menu.open"},
+            {"seq": 1, "name": "Close", "source_path": "$.menu.items[1]"}
+        ]});
+        let dir = tempfile::tempdir().unwrap();
+        // 三种结构各一份，第0条的占位符都被译错
+        std::fs::write(dir.path().join("seq.json"), r#"{"0": "打开 {x}", "1": "关闭"}"#).unwrap();
+        std::fs::write(dir.path().join("path.json"), r#"{"$.menu.open": "打开 {x}", "$.menu.items[1]": "关闭"}"#).unwrap();
+        std::fs::write(dir.path().join("tree.json"), r#"{"menu": {"open": "打开 {x}", "items": [null, "关闭"]}}"#).unwrap();
+
+        let out = dir.path().join("out");
+        std::fs::create_dir(&out).unwrap();
+        let results = fan_out_dir(&source, &stage2, dir.path(), &out, &FanOutOptions::default()).unwrap();
+        assert_eq!(results.len(), 3);
+        for result in &results {
+            assert_eq!((result.written, result.placeholder_mismatches.clone()), (1, vec![0]), "{}", result.describe());
+            let text = std::fs::read_to_string(out.join(format!("app.{}.json", result.locale))).unwrap();
+            let written: Value = serde_json::from_str(&text).unwrap();
+            assert_eq!(written, json!({"menu": {"open": "Open {file}", "items": ["x", "关闭"]}}), "{}", result.locale);
+        }
+
+        // 直接调用 fan_out 时按路径给出的译文同样先规范化
+        let by_path = [("ja".to_string(), BTreeMap::from([("$.menu.open".to_string(), "開く {x}".to_string())]))];
+        assert_eq!(fan_out(&source, &stage2, &by_path, &out, &FanOutOptions::default())[0].placeholder_mismatches, vec![0]);
+    }
+
+    #[test]
+    fn test_placeholder_mismatches_match_items_by_seq() {
+        // 条目按原文排序后顺序与序号不一致，且缺少序号 1
+        let stage2 = json!({"items": [
+            {"seq": 2, "name": "Close"},
+            {"seq": 0, "name": "Open {file}"}
+        ]});
+        let translations = BTreeMap::from([
+            ("0".to_string(), "打开 {file}".to_string()),
+            ("1".to_string(), "{x}".to_string()),
+            ("2".to_string(), "关闭 {x}".to_string()),
+        ]);
+        assert_eq!(placeholder_mismatches(&stage2, &translations), vec![2]);
+    }
+
+    #[test]
+    fn test_fan_out_dir_stops_when_cancelled() {
+        use std::cell::Cell;
+
+        let source = json!({"title": "Open"});
+        let stage2 = json!({"items": [{"seq": 0, "name": "Open", "source_path": "$.title"}]});
+        let dir = tempfile::tempdir().unwrap();
+        for locale in ["de", "ja", "zh"] {
+            std::fs::write(dir.path().join(format!("{}.json", locale)), r#"{"0": "x"}"#).unwrap();
+        }
+        let out = dir.path().join("out");
+        std::fs::create_dir(&out).unwrap();
+
+        // 第一种语言完成后取消：其余语言不再写入
+        let checks = Cell::new(0);
+        let cancelled = || {
+            checks.set(checks.get() + 1);
+            checks.get() > 1
+        };
+        let results = fan_out_dir_with_cancel(&source, &stage2, dir.path(), &out, &FanOutOptions::default(), &cancelled).unwrap();
+        assert_eq!(results.iter().map(|r| r.locale.as_str()).collect::<Vec<_>>(), ["de"]);
+        assert!(out.join("app.de.json").exists());
+        assert!(!out.join("app.ja.json").exists() && !out.join("app.zh.json").exists());
+    }
+}
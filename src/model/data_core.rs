@@ -2,942 +2,6395 @@
 
 use std::path::{Path, PathBuf};
 
+use indexmap::IndexMap;
 use jsonpath_rust::{JsonPath, query::queryable::Queryable}; // 提供 query/query_only_path/reference_mut 等扩展
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use thiserror::Error;
 
-use std::collections::HashSet;
-
-use crate::model::shadow_tree::{build_shadow_tree, NodeKind};
-use crate::utils::fs::{read_json_file, write_json_file};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::model::anchor::{find_anchor, ANCHOR_FIELD_KEY};
+use crate::model::diff::{compare_values, ChangeSummary, DiffEntry, FileDiff};
+use crate::model::effort::{count_words, has_html_tag, EffortEstimate};
+use crate::model::fingerprint::{canonical_form, fingerprint_value, FINGERPRINT_KEY};
+use crate::model::json_schema::{CompiledSchema, SchemaViolation};
+use crate::model::path::{format_segments, insert_creating, parse_segments, query_first, query_first_mut, resolve, PathSegment};
+use crate::model::schema::{analyze_array, SchemaReport};
+use crate::model::search::{compile_path_pattern, fuzzy_score, MatchKind, SearchMatch, SearchMode};
+use crate::model::search_query::{parse_filter_list, SearchQuery};
+use crate::model::subtree_cache::{compact_len, subtree_hash, SubtreeCache};
+use crate::model::placeholder::{detect_placeholder_patterns, PlaceholderStyle};
+use crate::model::watch::{ValueWatchers, WatchCallback, WatchChange, WatcherId};
+use crate::model::transform::{CompiledTransform, PipelineResult, ValueTransform};
+use crate::model::shadow_tree::{
+    build_shadow_tree, build_shadow_tree_limited, build_subtree_nodes, compute_subtree_sizes, field_path, node_preview, load_tree_from_reader, JsonTreeNode, LoadedTree, NodeKind, ShadowTreeOptions,
+};
+use crate::utils::fs::{
+    artifact_path, backup_file, display_path, long_path, to_string_with_options, write_json_file_atomic, write_json_file_atomic_with_options,
+    ArtifactKind, SaveOptions,
+};
+use crate::utils::jsonfmt::{to_string_wrapped_with_indent, DEFAULT_MAX_INLINE_WIDTH};
+use crate::utils::json_comments::strip_json_comments;
+use crate::utils::text::{looks_binary, scan_suspicious};
 
 #[derive(Debug, Default)]
 pub struct AppState {
+    /// 文档的概念来源（界面显示、产物标注）：通常是加载的文件，也可以是 URL、rebase 后的 `<文件>#<路径>` 或自定义标签
     pub source_path: Option<PathBuf>,
+    /// 磁盘上的物理文件，保存与回写的目标；为 None 时不能保存回原文件
     pub original_file_path: Option<PathBuf>,
     pub dom: Option<Value>,
-    pub tree_flat: Vec<crate::model::shadow_tree::JsonTreeNode>,
+    /// 加载时的文档，仅由加载设置、此后不再修改，作为会话改动汇总的基准
+    pub(crate) initial_dom: Option<Value>,
+    pub tree_flat: Vec<JsonTreeNode>,
+    /// 最近一次加载产生的警告（如重复键）
+    pub load_warnings: Vec<String>,
+    /// 当前文档经 [`AppState::load_file_with_comments`] 去除注释后加载；对比磁盘文件与重新加载沿用同一加载方式
+    pub(crate) comments_stripped: bool,
+    /// 源文件存在重复键时拒绝加载（默认仅警告）
+    pub reject_duplicate_keys: bool,
+    /// 影子树构建选项（`track_offsets` 加载文件时生效，`max_nodes` 加载与重建时均生效）
+    pub shadow_tree_options: ShadowTreeOptions,
+    /// 影子树因超过 `max_nodes` 而被截断（末尾为截断标记）；搜索、中间产物与导出另按DOM构建完整节点列表，不受截断影响
+    pub truncated: bool,
+    /// 加载时的自动检测是否选出了英文字段（重新加载时据此决定是否重跑）
+    pub english_fields_auto_detected: bool,
+    /// 节点访问计数（路径 -> 次数），用于统计热点路径
+    pub access_count: RefCell<HashMap<String, u32>>,
+    /// 用户备注（规范化路径 -> 备注），影子树重建后重新附加到同路径的节点；路径不再存在的备注被丢弃
+    pub(crate) annotations: BTreeMap<String, String>,
+    /// 字符串节点值长度缓存（路径 -> 字符数），影子树重建时清空
+    pub(crate) value_len_cache: RefCell<HashMap<String, usize>>,
+    /// 搜索倒排索引（name/path 的字节三元组 -> tree_flat 下标），影子树重建时随之重建
+    pub(crate) search_index: Option<HashMap<[u8; 3], Vec<usize>>>,
+    /// 预览提取缓存（路径+行宽 -> 渲染结果），以子树哈希校验是否仍然有效
+    pub(crate) subtree_cache: RefCell<SubtreeCache>,
+    /// 最近一次使用的搜索策略（过滤条件变化时沿用）
+    pub search_mode: SearchMode,
+    /// 撤销历史（最近的在末尾），每层为结构性编辑前的快照
+    pub(crate) undo_stack: Vec<FrozenState>,
+    /// 加载后是否有尚未保存的结构性编辑（删除/插入节点、重命名键）
+    pub dirty: bool,
+    /// 只读模式：编辑、写入原文件与恢复文件均返回错误，提取、搜索、另存为与导出到其他文件不受影响
+    pub read_only: bool,
+    /// 保存、子树提取与中间产物序列化使用的格式（缩进见 [`AppState::set_indent_size`]）
+    pub(crate) save_options: SaveOptions,
+    /// 数组按键字段建立的索引（"数组路径#键字段" -> 键值 -> 元素路径），影子树重建时清空
+    pub(crate) field_indices: HashMap<String, HashMap<String, String>>,
+    /// 保存前用于校验DOM的 JSON Schema（需启用 `schema` 特性）
+    pub(crate) schema: Option<CompiledSchema>,
+    /// 库模式下按路径注册的值监听（见 [`AppState::watch_value`]）
+    pub(crate) watchers: ValueWatchers,
 }
 
-#[derive(Error, Debug)]
-pub enum AppError {
-    #[error("IO失败: {0}")]
-    Io(#[from] std::io::Error),
-    #[error("JSON解析失败: {0}")]
-    Parse(#[from] serde_json::Error),
-    #[error("JSONPath错误: {0}")]
-    JsonPath(String),
-    #[error("状态错误: {0}")]
-    State(String),
+/// 撤销历史保留的最大层数
+pub const MAX_UNDO_LEVELS: usize = 20;
+
+/// 结构比较中的一处不一致（`expected`/`actual` 为节点类型，路径不存在时为“缺失”）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StructureMismatch {
+    pub path: String,
+    pub expected: String,
+    pub actual: String,
 }
 
-impl AppState {
-    /// 加载JSON文件并构建影子树
-    pub fn load_file(&mut self, p: &Path) -> Result<(), AppError> {
-        let dom = read_json_file(p)?;
-        self.tree_flat = build_shadow_tree(&dom);
-        self.source_path = Some(p.to_path_buf());
-        self.original_file_path = Some(p.to_path_buf()); // 设置原始文件路径
-        self.dom = Some(dom);
-        Ok(())
-    }
+/// 影子树节点的紧凑状态（仅路径与展开/可见标记，不含预览）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrozenNode {
+    pub path: String,
+    pub expanded: bool,
+    pub visible: bool,
+}
 
-    /// 按 JSONPath 提取第一个匹配节点的 pretty 字符串
-    pub fn extract_subtree_pretty(&self, json_path: &str) -> Result<String, AppError> {
-        let dom = self
-            .dom
-            .as_ref()
-            .ok_or_else(|| AppError::State("DOM尚未加载".into()))?;
-        let hits: Vec<&Value> = dom
-            .query(json_path)
-            .map_err(|e| AppError::JsonPath(e.to_string()))?;
-        let first = hits
-            .into_iter()
-            .next()
-            .ok_or_else(|| AppError::JsonPath("未匹配到任何节点".into()))?;
-        Ok(serde_json::to_string_pretty(first)?)
-    }
+/// AppState 的轻量快照：DOM 与树的展开/可见状态，可序列化到磁盘
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrozenState {
+    pub source_path: Option<PathBuf>,
+    pub original_file_path: Option<PathBuf>,
+    pub dom: Option<Value>,
+    pub nodes: Vec<FrozenNode>,
+}
 
-    /// 将 new_json 替换到第一个匹配的 json_path 节点
-    pub fn update_node_from_str(&mut self, json_path: &str, new_json: &str) -> Result<(), AppError> {
-        let dom = self
-            .dom
-            .as_mut()
-            .ok_or_else(|| AppError::State("DOM尚未加载".into()))?;
+/// 源文件的备注旁路文件路径：`{源文件}.annotations.json`
+pub fn annotations_sidecar_path(source: &Path) -> PathBuf {
+    let mut name = source.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+    name.push(".annotations.json");
+    source.with_file_name(name)
+}
 
-        let paths: Vec<String> = dom
-            .query_only_path(json_path)
-            .map_err(|e| AppError::JsonPath(e.to_string()))?;
-        let Some(p) = paths.into_iter().next() else {
-            return Err(AppError::JsonPath("未匹配到可更新路径".into()));
-        };
+/// 影子树快照的统计信息
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TreeSnapshotSummary {
+    pub total_nodes: usize,
+    pub visible_nodes: usize,
+    pub max_depth: u32,
+    pub objects: usize,
+    pub arrays: usize,
+    pub strings: usize,
+    pub numbers: usize,
+    pub bools: usize,
+    pub nulls: usize,
+}
 
-        // 对于字符串值，直接设置为JSON字符串值，不需要解析
-        let replacement: Value = Value::String(new_json.to_string());
-        // 通过 reference_mut 按路径获取可变引用（支持 root/field/index 直接访问段）
-        if let Some(slot) = dom.reference_mut(&p) {
-            *slot = replacement;
-        } else {
-            return Err(AppError::JsonPath(format!("路径不可更新: {}", p)));
+impl TreeSnapshotSummary {
+    /// 统计一组节点
+    pub fn of(nodes: &[JsonTreeNode]) -> Self {
+        let mut summary = Self { total_nodes: nodes.len(), ..Self::default() };
+        for node in nodes {
+            summary.visible_nodes += usize::from(node.visible);
+            summary.max_depth = summary.max_depth.max(node.depth);
+            *match node.kind {
+                NodeKind::Object => &mut summary.objects,
+                NodeKind::Array => &mut summary.arrays,
+                NodeKind::String => &mut summary.strings,
+                NodeKind::Number => &mut summary.numbers,
+                NodeKind::Bool => &mut summary.bools,
+                NodeKind::Null => &mut summary.nulls,
+            } += 1;
         }
-
-        // 变更后重建影子树（后续可优化为局部刷新）
-        self.tree_flat = build_shadow_tree(dom);
-        Ok(())
+        summary
     }
+}
 
-    /// 将当前DOM保存到指定路径
-    pub fn save_to_file(&self, path: &Path) -> Result<(), AppError> {
-        let dom = self
-            .dom
-            .as_ref()
-            .ok_or_else(|| AppError::State("DOM尚未加载".into()))?;
-        write_json_file(path, dom)?;
-        Ok(())
-    }
+/// 影子树快照（不含DOM）：用于远程调试大文件，或在没有原始数据时编写界面测试
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TreeSnapshot {
+    pub summary: TreeSnapshotSummary,
+    pub nodes: Vec<JsonTreeNode>,
+}
 
-    /// 将当前DOM保存到原始文件路径
-    pub fn save_to_original_file(&self) -> Result<(), AppError> {
-        let original_path = self
-            .original_file_path
-            .as_ref()
-            .ok_or_else(|| AppError::State("原始文件路径未设置".into()))?;
-        self.save_to_file(original_path)
-    }
+/// 中间产物2的条目排序方式（在分配 seq 之前应用）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage2Sort {
+    /// 影子树文档顺序
+    #[default]
+    DocumentOrder,
+    /// 按源路径字典序
+    ByPath,
+    /// 按源文本排序（相同文本聚在一起，再按路径）
+    BySourceText,
+}
 
-    /// 应用搜索过滤，只显示匹配路径的节点
-    pub fn apply_search_filter(&mut self, filter: &str) {
-        if filter.trim().is_empty() {
-            // 清空过滤，显示所有节点
-            for node in &mut self.tree_flat {
-                node.visible = true;
-            }
-        } else {
-            // 简化的快速搜索 - 只做简单的字符串匹配
-            for node in &mut self.tree_flat {
-                node.visible = node.path.contains(filter) || node.name.contains(filter);
-            }
+impl Stage2Sort {
+    /// 所有排序方式（界面循环切换顺序）
+    pub const ALL: [Stage2Sort; 3] = [Stage2Sort::DocumentOrder, Stage2Sort::ByPath, Stage2Sort::BySourceText];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Stage2Sort::DocumentOrder => "文档顺序",
+            Stage2Sort::ByPath => "按路径",
+            Stage2Sort::BySourceText => "按原文",
         }
     }
+}
 
-    /// 提取搜索匹配的节点JSON内容，智能限制结果数量以优化性能
-    pub fn extract_search_results(&self, filter: &str) -> Result<String, AppError> {
-        if filter.trim().is_empty() {
-            return Ok("".to_string());
-        }
+/// 超长二进制编码值（base64、data URI）在中间产物2中的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OversizedValues {
+    /// 不生成条目，只在信封中计数
+    #[default]
+    Exclude,
+    /// 生成带截断预览的引用条目（`excluded: true`），不进入最终产物且不可回写
+    Reference,
+}
 
-        // 确保DOM已加载
-        self.dom
-            .as_ref()
-            .ok_or_else(|| AppError::State("DOM尚未加载".into()))?;
+/// 中间产物2的构建选项
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Stage2Options {
+    /// 只匹配属性名包含过滤条件的叶子节点
+    pub leaf_nodes_only: bool,
+    pub sort: Stage2Sort,
+    /// 超过该字符数且像二进制编码（见 [`looks_binary`]）的值不送译；None 表示不限制
+    pub max_value_chars: Option<usize>,
+    pub oversized: OversizedValues,
+    /// 为每个条目记录父对象的其他标量字段（`context`）与上两层的键（`context_path_hint`），帮助译者判断所在界面
+    pub include_context: bool,
+    /// 数组元素的锚点字段（如 `id`）：记录条目所在元素的该字段值，回写时数组增删或重排后据此重新定位
+    pub anchor_field: Option<String>,
+}
 
-        // 收集所有匹配的可见节点
-        let mut matched_nodes = Vec::new();
-        for node in &self.tree_flat {
-            if (node.path.contains(filter) || node.name.contains(filter)) && node.visible {
-                matched_nodes.push(node);
-            }
-        }
+/// 过滤条件命中数的统计选项（见 [`AppState::count_matches`]）
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MatchCountOptions {
+    /// 与中间产物2相同：只统计属性名命中的叶子节点
+    pub leaf_nodes_only: bool,
+    /// 只统计这些类型的节点，为空时不限
+    pub kinds: Vec<NodeKind>,
+    /// 路径或属性名包含任一词的节点不计入
+    pub exclude: Vec<String>,
+}
 
-        if matched_nodes.is_empty() {
-            tracing::warn!("未找到匹配的可见节点，过滤条件: {}", filter);
-            return Ok("{}".to_string());
+/// 过滤条件的命中数
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MatchCounts {
+    pub total: usize,
+    /// 其中字符串类型的节点（即可送译的叶子）
+    pub string_leaves: usize,
+}
+
+impl MatchCounts {
+    /// 搜索框旁显示的一行，如 `将匹配 1,240 个节点（其中字符串叶子 860）`
+    pub fn describe(&self) -> String {
+        format!("将匹配 {} 个节点（其中字符串叶子 {}）", group_thousands(self.total), group_thousands(self.string_leaves))
+    }
+}
+
+/// 千位分隔：`1240` → `1,240`
+fn group_thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
         }
+        out.push(c);
+    }
+    out
+}
 
-        tracing::info!("找到 {} 个匹配节点", matched_nodes.len());
+/// 外部文件合并回子树的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SubtreeMergeMode {
+    /// 整体替换目标子树
+    #[default]
+    Replace,
+    /// 按键递归合并：文件中的键覆盖或新增，文件中没有的键保持不变；数组与标量整体覆盖
+    MergeKeys,
+}
 
-        // 如果只有一个匹配节点，直接返回其完整内容
-        if matched_nodes.len() == 1 {
-            let node = matched_nodes[0];
-            tracing::info!("单个匹配节点: {} (路径: {})", node.name, node.path);
-            let result = self.extract_subtree_pretty(&node.path);
-            tracing::info!("提取结果: {:?}", result.as_ref().map(|s| s.len()));
-            return result;
+/// 子树合并报告（均为完整路径）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubtreeMergeReport {
+    pub added: Vec<String>,
+    pub changed: Vec<String>,
+    pub removed: Vec<String>,
+    /// 两侧类型不同（对象与非对象）而整体覆盖的位置
+    pub type_conflicts: Vec<String>,
+}
+
+impl SubtreeMergeReport {
+    /// 状态栏显示的摘要
+    pub fn summary(&self) -> String {
+        let mut text = format!("新增 {}，修改 {}，删除 {}", self.added.len(), self.changed.len(), self.removed.len());
+        if !self.type_conflicts.is_empty() {
+            text.push_str(&format!("，类型冲突 {}", self.type_conflicts.len()));
         }
+        text
+    }
+}
 
-        // 多个匹配节点：复制全部场景需要完整输出（不限制数量、不截断内容）
-        let display_count = matched_nodes.len();
-        let mut search_results = serde_json::Map::new();
+/// 单个节点的汇总信息（见 [`AppState::get_node_info`]）
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeInfo {
+    /// 规范化后的路径
+    pub path: String,
+    pub kind: NodeKind,
+    /// 根节点为 0
+    pub depth: u32,
+    /// 对象的键数或数组长度，标量为 0
+    pub children_count: u32,
+    pub preview: String,
+    pub full_value: Value,
+    /// 紧凑序列化后的字节数
+    pub byte_size: usize,
+    /// 根节点为 None
+    pub parent_path: Option<String>,
+    /// 在父容器中的位置：数组下标或对象中第几个键
+    pub index_in_parent: Option<usize>,
+}
 
-        for (index, node) in matched_nodes.iter().take(display_count).enumerate() {
-            match self.extract_subtree_pretty(&node.path) {
-                Ok(json_content) => {
+/// 两个路径是否指向同一文件（都存在时按规范化路径比较）
+fn same_path(a: &Path, b: &Path) -> bool {
+    a == b || matches!((a.canonicalize(), b.canonicalize()), (Ok(a), Ok(b)) if a == b)
+}
 
-                    // 解析JSON内容以便重新组织
-                    match serde_json::from_str::<Value>(&json_content) {
-                        Ok(parsed_value) => {
-                            let result_key = format!("match_{}_{}", index + 1, node.name);
-                            let result_entry = serde_json::json!({
-                                "path": node.path,
-                                "name": node.name,
-                                "type": format!("{:?}", node.kind),
-                                "content": parsed_value
-                            });
-                            search_results.insert(result_key, result_entry);
-                        }
-                        Err(_) => {
-                            // 如果解析失败，直接存储为字符串
-                            let result_key = format!("match_{}_{}", index + 1, node.name);
-                            let result_entry = serde_json::json!({
-                                "path": node.path,
-                                "name": node.name,
-                                "type": format!("{:?}", node.kind),
-                                "content": json_content
-                            });
-                            search_results.insert(result_key, result_entry);
-                        }
+/// 对象的键数或数组长度，标量为 0
+fn children_count(value: &Value) -> usize {
+    match value {
+        Value::Object(map) => map.len(),
+        Value::Array(items) => items.len(),
+        _ => 0,
+    }
+}
+
+/// 将 `incoming` 并入 `target`，把变化记录到报告中
+fn merge_into(target: &mut Value, incoming: Value, path: &str, mode: SubtreeMergeMode, report: &mut SubtreeMergeReport) {
+    match (target, incoming) {
+        (Value::Object(old), Value::Object(new)) => {
+            if mode == SubtreeMergeMode::Replace {
+                let removed: Vec<String> = old.keys().filter(|k| !new.contains_key(*k)).cloned().collect();
+                for key in removed {
+                    old.shift_remove(&key);
+                    report.removed.push(field_path(path, &key));
+                }
+            }
+            for (key, value) in new {
+                let child_path = field_path(path, &key);
+                match old.get_mut(&key) {
+                    Some(child) => merge_into(child, value, &child_path, mode, report),
+                    None => {
+                        old.insert(key, value);
+                        report.added.push(child_path);
                     }
                 }
-                Err(e) => {
-                    tracing::error!("提取节点 {} 失败: {}", node.path, e);
-                    let result_key = format!("match_{}_{}_error", index + 1, node.name);
-                    let result_entry = serde_json::json!({
-                        "path": node.path,
-                        "name": node.name,
-                        "type": format!("{:?}", node.kind),
-                        "error": e.to_string()
-                    });
-                    search_results.insert(result_key, result_entry);
+            }
+        }
+        (target, incoming) => {
+            if *target != incoming {
+                if target.is_object() != incoming.is_object() {
+                    report.type_conflicts.push(path.to_string());
                 }
+                report.changed.push(path.to_string());
+                *target = incoming;
             }
         }
+    }
+}
 
-        let final_result = serde_json::json!({
-            "search_filter": filter,
-            "total_matches": matched_nodes.len(),
-            "displayed_matches": display_count,
-            "truncated": false,
-            "results": search_results
+/// 引用条目预览保留的字符数
+const OVERSIZED_PREVIEW_CHARS: usize = 48;
+
+/// 每个条目 `context` 中键与值的字符总数上限
+const CONTEXT_MAX_CHARS: usize = 200;
+
+/// 父对象的上下文（同一父对象的兄弟条目共用）
+struct ParentContext {
+    /// 父对象中的标量字段（单个值超过上限的不收集）
+    fields: Vec<(String, Value)>,
+    /// 祖父节点所在的键（向上跳过数组下标）
+    path_hint: Option<String>,
+}
+
+impl ParentContext {
+    /// 由已定位的父节点值（`parent_value`）与其路径段收集上下文
+    fn collect(parent_value: Option<&Value>, parent: &[PathSegment]) -> Self {
+        let fields = parent_value
+            .and_then(Value::as_object)
+            .map(|map| {
+                map.iter()
+                    .filter(|(_, v)| !v.is_object() && !v.is_array())
+                    .filter(|(_, v)| v.as_str().is_none_or(|s| s.chars().count() <= CONTEXT_MAX_CHARS))
+                    .map(|(k, v)| (k.clone(), v.clone()))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let grandparent = &parent[..parent.len().saturating_sub(1)];
+        let path_hint = grandparent.iter().rev().find_map(|segment| match segment {
+            PathSegment::Key(key) => Some(key.clone()),
+            PathSegment::Index(_) => None,
         });
+        Self { fields, path_hint }
+    }
 
-        let pretty_result = serde_json::to_string_pretty(&final_result)?;
-        tracing::info!("搜索结果构建完成，显示 {}/{} 个匹配，总长度: {} 字符",
-                      display_count, matched_nodes.len(), pretty_result.len());
+    /// 除 `own_key` 外的字段，按文件中的键顺序累计到字符上限为止
+    fn context_for(&self, own_key: Option<&str>) -> serde_json::Map<String, Value> {
+        let mut used = 0;
+        let mut context = serde_json::Map::new();
+        for (key, value) in self.fields.iter().filter(|(k, _)| Some(k.as_str()) != own_key) {
+            let rendered = value.as_str().map_or_else(|| value.to_string(), str::to_string);
+            used += key.chars().count() + rendered.chars().count();
+            if used > CONTEXT_MAX_CHARS {
+                break;
+            }
+            context.insert(key.clone(), value.clone());
+        }
+        context
+    }
+}
 
-        Ok(pretty_result)
+/// 由条目的 `context` 与 `context_path_hint` 生成导出表格的备注，如 `menu › id=open, icon=folder`
+pub(crate) fn stage2_item_notes(item: &Value) -> String {
+    let fields: Vec<String> = item
+        .get("context")
+        .and_then(Value::as_object)
+        .map(|context| {
+            context
+                .iter()
+                .map(|(k, v)| format!("{}={}", k, v.as_str().map_or_else(|| v.to_string(), str::to_string)))
+                .collect()
+        })
+        .unwrap_or_default();
+    let hint = item.get("context_path_hint").and_then(Value::as_str);
+    match (hint, fields.is_empty()) {
+        (Some(hint), false) => format!("{} › {}", hint, fields.join(", ")),
+        (Some(hint), true) => hint.to_string(),
+        (None, _) => fields.join(", "),
     }
+}
 
-    /// 构建“中间产物 第二阶段”：按过滤条件枚举命中项，派生并提取同层级的 name 字段值，生成带连续序号的清单
-    pub fn build_intermediate_stage2<F>(&self, filter: &str, progress_callback: F) -> Result<String, AppError>
-    where
-        F: FnMut(f32, &str),
-    {
-        self.build_intermediate_stage2_with_leaf_filter(filter, false, progress_callback)
+/// 中间产物的节点匹配规则：叶子模式只看属性名且只收录标量节点，否则路径或属性名命中即可；不可见节点不收录
+fn stage_node_matches(node: &JsonTreeNode, query: &SearchQuery, leaf_nodes_only: bool) -> bool {
+    if leaf_nodes_only {
+        node.visible && query.matches(&[&node.name]) && matches!(node.kind, NodeKind::String | NodeKind::Number | NodeKind::Bool | NodeKind::Null)
+    } else {
+        node.visible && query.matches(&[&node.path, &node.name])
     }
+}
 
-    /// 构建"中间产物 第二阶段"：支持叶子节点过滤的版本
-    pub fn build_intermediate_stage2_with_leaf_filter<F>(&self, filter: &str, leaf_nodes_only: bool, mut progress_callback: F) -> Result<String, AppError>
-    where
-        F: FnMut(f32, &str),
-    {
-        if filter.trim().is_empty() {
-            return Ok("".to_string());
-        }
+/// 将过滤条件逐个解析为搜索查询（去掉首尾空白，跳过空条件）
+fn stage_filters(filters: &[String]) -> Vec<(&str, SearchQuery)> {
+    filters
+        .iter()
+        .map(|f| (f.trim(), SearchQuery::parse(f)))
+        .filter(|(_, q)| !q.is_empty())
+        .collect()
+}
 
-        tracing::info!("build_intermediate_stage2: 开始执行");
-        progress_callback(0.1, "开始分析匹配节点...");
-        tracing::info!("build_intermediate_stage2: 进度回调 0.1 调用完成");
+/// 子串搜索的查询：每个过滤条件按 [`SearchQuery`] 语法解析并转为小写，空白条件被忽略
+fn substring_queries(filters: &[String]) -> Vec<SearchQuery> {
+    filters.iter().map(|f| SearchQuery::parse(f).to_lowercase()).filter(|q| !q.is_empty()).collect()
+}
 
-        let dom = self
-            .dom
-            .as_ref()
-            .ok_or_else(|| AppError::State("DOM尚未加载".into()))?;
-        tracing::info!("build_intermediate_stage2: DOM获取成功");
+/// 节点路径或键名（不区分大小写）命中任一子串查询
+fn substring_matches(queries: &[SearchQuery], node: &JsonTreeNode) -> bool {
+    let (path, name) = (node.path.to_lowercase(), node.name.to_lowercase());
+    queries.iter().any(|q| q.matches(&[&path, &name]))
+}
 
-        // 收集所有可见且匹配的节点
-        let match_start = std::time::Instant::now();
-        let mut matched: Vec<&crate::model::shadow_tree::JsonTreeNode> = Vec::new();
-        for node in &self.tree_flat {
-            // 应用叶子节点过滤逻辑
-            let should_include = if leaf_nodes_only {
-                // 叶子节点模式：只匹配属性名包含过滤条件的真正叶子节点（具有简单值的节点）
-                node.visible && node.name.contains(filter) && matches!(node.kind, NodeKind::String | NodeKind::Number | NodeKind::Bool | NodeKind::Null)
-            } else {
-                // 全部节点模式：匹配路径或属性名包含过滤条件的节点
-                node.visible && (node.path.contains(filter) || node.name.contains(filter))
-            };
+/// 将逐节点命中结果整理为匹配列表；模糊策略按得分降序（得分相同时保持影子树顺序）
+fn collect_search_matches(nodes: &[JsonTreeNode], hits: Vec<Option<(f32, MatchKind)>>, mode: SearchMode) -> Vec<SearchMatch> {
+    let mut matches: Vec<SearchMatch> = nodes
+        .iter()
+        .zip(hits)
+        .filter_map(|(node, hit)| {
+            let (score, match_kind) = hit?;
+            Some(SearchMatch { node: JsonTreeNode { visible: true, ..node.clone() }, score, match_kind })
+        })
+        .collect();
+    if mode == SearchMode::Fuzzy {
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+    }
+    matches
+}
 
-            if should_include {
-                matched.push(node);
-            }
+/// 收集可见且命中任一过滤条件的节点，附带命中的过滤条件（中间产物1/2共用）
+fn match_stage_nodes<'a>(nodes: &'a [JsonTreeNode], filters: &[(&'a str, SearchQuery)], leaf_nodes_only: bool) -> Vec<(&'a JsonTreeNode, &'a str)> {
+    let mut matched = Vec::new();
+    for node in nodes {
+        if let Some((filter, _)) = filters.iter().find(|(_, q)| stage_node_matches(node, q, leaf_nodes_only)) {
+            matched.push((node, *filter));
         }
-        let match_time = match_start.elapsed().as_millis();
+    }
+    matched
+}
 
-        tracing::info!("build_intermediate_stage2: 找到 {} 个匹配节点，耗时: {}ms", matched.len(), match_time);
-        // 优化：减少进度回调频率，直接跳到50%
-        progress_callback(0.5, &format!("正在处理 {} 个匹配节点...", matched.len()));
-        tracing::info!("build_intermediate_stage2: 进度回调 0.5 调用完成");
+/// 按排序方式稳定排序中间产物2条目，再按排序后的位置补充从 0 开始的连续 `seq`
+fn number_stage2_items(mut items: Vec<Value>, sort: Stage2Sort) -> Vec<Value> {
+    // 稳定排序：键相同时保持文档顺序，保证两次提取结果逐字节一致
+    let item_key = |item: &serde_json::Value, key: &str| item[key].as_str().unwrap_or_default().to_string();
+    // 路径按解析后的段比较，数组下标按数值排序（`[2]` 在 `[10]` 之前）
+    let path_key = |item: &serde_json::Value| {
+        let path = item_key(item, "source_path");
+        (parse_segments(&path).unwrap_or_default(), path)
+    };
+    match sort {
+        Stage2Sort::DocumentOrder => {}
+        Stage2Sort::ByPath => items.sort_by_cached_key(path_key),
+        Stage2Sort::BySourceText => items.sort_by_cached_key(|item| (item_key(item, "name"), path_key(item))),
+    }
 
-        // 派生 name 的 JSONPath
-        fn derive_name_path(src: &str) -> Option<String> {
-            // 寻找最后一个不在 [] 中的 '.'
-            let mut depth = 0i32;
-            let mut last_dot = None;
-            for (i, ch) in src.chars().enumerate() {
-                match ch {
-                    '[' => depth += 1,
-                    ']' => depth -= 1,
-                    '.' if depth == 0 => last_dot = Some(i),
-                    _ => {}
-                }
+    let seq_start = std::time::Instant::now();
+    let items_with_seq: Vec<Value> = items
+        .into_iter()
+        .enumerate()
+        .map(|(i, mut obj)| {
+            if let serde_json::Value::Object(ref mut map) = obj {
+                map.insert("seq".to_string(), serde_json::json!(i));
             }
-            last_dot.map(|idx| format!("{}{}", &src[..idx], ".name"))
+            obj
+        })
+        .collect();
+    let seq_time = seq_start.elapsed().as_millis();
+    tracing::info!("build_intermediate_stage2: 序号添加完成，耗时: {}ms", seq_time);
+    items_with_seq
+}
+
+/// `root` 的子树（含自身）中是否有 `target` 这个节点（按地址比较）
+fn subtree_contains(root: &Value, target: &Value) -> bool {
+    let mut stack = vec![root];
+    while let Some(value) = stack.pop() {
+        if std::ptr::eq(value, target) {
+            return true;
+        }
+        match value {
+            Value::Object(map) => stack.extend(map.values()),
+            Value::Array(items) => stack.extend(items.iter()),
+            _ => {}
         }
+    }
+    false
+}
 
-        // 批量收集所有需要查询的路径，减少重复查询
-        let mut path_to_value: std::collections::HashMap<String, Option<serde_json::Value>> = std::collections::HashMap::new();
-        let mut paths_to_query: std::collections::HashSet<String> = std::collections::HashSet::new();
+/// CSV 字段转义：含逗号、引号或换行时加引号并双写引号
+pub(crate) fn csv_field(field: &str) -> std::borrow::Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\"")).into()
+    } else {
+        field.into()
+    }
+}
 
-        // 收集所有需要查询的路径
-        for node in &matched {
-            paths_to_query.insert(node.path.clone());
-            if node.name != "name" {
-                if let Some(np) = derive_name_path(&node.path) {
-                    paths_to_query.insert(np);
-                }
+/// LLM 键值对中待翻译的空值占位
+pub const LLM_TRANSLATE_PLACEHOLDER: &str = "[TRANSLATE]";
+
+/// LLM 键值对的值转义：反斜杠与换行转义，保证一对占一行
+fn escape_llm_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+/// [`escape_llm_value`] 的逆操作
+fn unescape_llm_value(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => out.push('\n'),
+            ('\\', Some('r')) => out.push('\r'),
+            ('\\', Some('\\')) => out.push('\\'),
+            _ => {
+                out.push(c);
+                continue;
             }
         }
+        chars.next();
+    }
+    out
+}
 
-        // 批量执行查询，缓存结果
-        let query_start = std::time::Instant::now();
-        progress_callback(0.5, "正在查询JSON路径...");
-        for path in paths_to_query {
-            let value = dom
-                .query(&path)
-                .map_err(|e| AppError::JsonPath(e.to_string()))?
-                .into_iter()
-                .next()
-                .cloned();
-            path_to_value.insert(path, value);
+/// 去掉 LLM 给译文加上的包裹引号，以及原文没有而译文附加的句末标点
+fn strip_llm_decoration(translated: &str, source: &str) -> String {
+    const QUOTES: [(char, char); 6] = [('"', '"'), ('\'', '\''), ('`', '`'), ('“', '”'), ('「', '」'), ('『', '』')];
+    const TRAILING: [char; 8] = ['.', '。', '!', '！', ',', '，', ';', '；'];
+    let strip_trailing = |t: &str| -> String {
+        if source.ends_with(TRAILING) { t.to_string() } else { t.trim_end_matches(TRAILING).to_string() }
+    };
+    // 句末标点可能加在引号外（`"打开"。`），也可能在引号内
+    let outer = strip_trailing(translated.trim());
+    let mut text = outer.as_str();
+    for (open, close) in QUOTES {
+        let wrapped = |t: &str| t.chars().count() >= 2 && t.starts_with(open) && t.ends_with(close);
+        if wrapped(text) && !wrapped(source) {
+            text = &text[open.len_utf8()..text.len() - close.len_utf8()];
+            break;
         }
-        let query_time = query_start.elapsed().as_millis();
-        tracing::info!("build_intermediate_stage2: JSON路径查询完成，耗时: {}ms", query_time);
+    }
+    strip_trailing(text)
+}
 
-        let mut items = Vec::<serde_json::Value>::new();
-        let build_start = std::time::Instant::now();
-        // 优化：减少进度回调，直接跳到90%
-        progress_callback(0.9, "正在构建最终结果...");
-        for node in matched {
-            // 从缓存中获取当前节点的值
-            let current_value_opt = path_to_value.get(&node.path).and_then(|v| v.clone());
+/// 带文件路径上下文的IO错误
+#[derive(Debug)]
+pub struct IoError {
+    pub source: std::io::Error,
+    pub path: Option<PathBuf>,
+}
 
-            let current_value_str = match &current_value_opt {
-                Some(val) => match val {
-                    serde_json::Value::String(s) => s.clone(),
-                    other => other.to_string(),
-                },
-                None => String::new(),
-            };
+impl std::fmt::Display for IoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "IO失败 ({}): {}", display_path(path), self.source),
+            None => write!(f, "IO失败: {}", self.source),
+        }
+    }
+}
 
-            // 派生 name 字段路径和值（从缓存中获取）
-            let (name_path, name_value_opt) = if node.name == "name" {
-                // 本身就是 name 字段
-                (node.path.clone(), current_value_opt.clone())
-            } else if let Some(np) = derive_name_path(&node.path) {
-                let v = path_to_value.get(&np).and_then(|v| v.clone());
-                (np, v)
-            } else {
-                (node.path.clone(), None)
-            };
+impl std::error::Error for IoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
 
-            let name_value_str = match name_value_opt {
-                Some(val) => match val {
-                    serde_json::Value::String(s) => s,
-                    other => other.to_string(),
-                },
-                None => String::new(),
-            };
+/// 错误信息的语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Lang {
+    #[default]
+    Zh,
+    En,
+}
 
-            items.push(serde_json::json!({
-                // seq 在最终序列化时按索引补充
-                "source_path": node.path,
-                "name_path": name_path,
-                "name": current_value_str,  // 使用查询字段的值，而不是 name 字段的值
-                "field_name": node.name,    // 添加字段名信息
-                "name_field_value": name_value_str,  // name 字段的值（用于参考）
-            }));
-        }
+/// `Display` 为 `错误码: 中文信息`，错误码稳定，可用于检索文档与脚本判断
+#[derive(Error, Debug)]
+pub enum AppError {
+    #[error("{}: {}", self.code(), self.localized_message(Lang::Zh))]
+    Io(#[from] IoError),
+    #[error("{}: {}", self.code(), self.localized_message(Lang::Zh))]
+    Parse(#[from] serde_json::Error),
+    #[error("{}: {}", self.code(), self.localized_message(Lang::Zh))]
+    JsonPath(JsonPathKind, String),
+    #[error("{}: {}", self.code(), self.localized_message(Lang::Zh))]
+    State(StateKind, String),
+    #[error("{}: {}", self.code(), self.localized_message(Lang::Zh))]
+    Network(String),
+    #[error("{}: {}", self.code(), self.localized_message(Lang::Zh))]
+    SchemaViolations(Vec<SchemaViolation>),
+}
 
-        // 生成带连续序号的 items（从 0 开始）
-        let build_time = build_start.elapsed().as_millis();
-        tracing::info!("build_intermediate_stage2: 结果项构建完成，耗时: {}ms", build_time);
+/// JSONPath 错误的细分类型，各自对应独立的错误码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonPathKind {
+    /// 路径语法错误或查询失败
+    Invalid,
+    /// 命中节点的类型不符合操作要求
+    WrongType,
+    /// 未匹配到任何节点
+    NoMatch,
+}
 
-        let seq_start = std::time::Instant::now();
-        let items_with_seq: Vec<serde_json::Value> = items
-            .into_iter()
-            .enumerate()
-            .map(|(i, mut obj)| {
-                if let serde_json::Value::Object(ref mut map) = obj {
-                    map.insert("seq".to_string(), serde_json::json!(i));
-                }
-                obj
-            })
-            .collect();
-        let seq_time = seq_start.elapsed().as_millis();
-        tracing::info!("build_intermediate_stage2: 序号添加完成，耗时: {}ms", seq_time);
+/// 状态错误的细分类型，各自对应独立的错误码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateKind {
+    /// 参数或操作无效
+    Invalid,
+    /// 文档尚未加载
+    NotLoaded,
+    /// 只读模式下拒绝修改或写入
+    ReadOnly,
+    /// 与已有的键、节点或条目冲突
+    Conflict,
+    /// 中间产物、回写文件等输入的结构不符合要求
+    Malformed,
+    /// 所需的可选特性未启用
+    FeatureDisabled,
+}
 
-        let format_start = std::time::Instant::now();
-        // 优化：移除中间进度回调，减少UI更新频率
-        let result = serde_json::json!({
-            "stage": "intermediate2",
-            "filter": filter,
-            "count": items_with_seq.len(),
-            "items": items_with_seq,
-        });
-        let format_time = format_start.elapsed().as_millis();
-        tracing::info!("build_intermediate_stage2: JSON格式化完成，耗时: {}ms", format_time);
+impl AppError {
+    /// 文档尚未加载
+    pub fn not_loaded() -> Self {
+        AppError::State(StateKind::NotLoaded, "DOM尚未加载".into())
+    }
 
-        progress_callback(1.0, "完成");
-        tracing::info!("build_intermediate_stage2: 进度回调 1.0 调用完成");
-        let result_str = serde_json::to_string_pretty(&result)?;
-        tracing::info!("build_intermediate_stage2: 执行完成，返回结果");
-        Ok(result_str)
+    /// 附带文件路径的IO错误
+    pub fn io(source: std::io::Error, path: &Path) -> Self {
+        AppError::Io(IoError { source, path: Some(path.to_path_buf()) })
     }
 
-    /// 更新JSON中指定路径的值
-    pub fn update_json_value(&mut self, path: &str, new_value: &str) -> Result<(), AppError> {
-        // 直接使用现有的 update_node_from_str 方法
-        self.update_node_from_str(path, new_value)
+    /// 稳定的错误码（按变体和细分类型划分，新增类型只追加新码）
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Io(_) => "E1001",
+            AppError::Parse(_) => "E1101",
+            AppError::JsonPath(JsonPathKind::Invalid, _) => "E1201",
+            AppError::JsonPath(JsonPathKind::WrongType, _) => "E1202",
+            AppError::JsonPath(JsonPathKind::NoMatch, _) => "E1203",
+            AppError::State(StateKind::Invalid, _) => "E1301",
+            AppError::State(StateKind::NotLoaded, _) => "E1302",
+            AppError::State(StateKind::ReadOnly, _) => "E1303",
+            AppError::State(StateKind::Conflict, _) => "E1304",
+            AppError::State(StateKind::Malformed, _) => "E1305",
+            AppError::State(StateKind::FeatureDisabled, _) => "E1306",
+            AppError::Network(_) => "E1401",
+            AppError::SchemaViolations(_) => "E1501",
+        }
     }
 
-    /// 保存修改后的JSON到文件
-    pub fn save_modified_json(&self, path: &std::path::Path) -> Result<(), AppError> {
-        let dom = self.dom.as_ref()
-            .ok_or_else(|| AppError::State("DOM尚未加载".into()))?;
+    /// 不含错误码的错误信息；附带的细节（路径、原因）保持原样
+    pub fn localized_message(&self, lang: Lang) -> String {
+        match (self, lang) {
+            (AppError::Io(e), Lang::Zh) => e.to_string(),
+            (AppError::Io(IoError { source, path: Some(path) }), Lang::En) => format!("IO failed ({}): {}", path.display(), source),
+            (AppError::Io(IoError { source, path: None }), Lang::En) => format!("IO failed: {}", source),
+            (AppError::Parse(e), Lang::Zh) => format!("JSON解析失败: {}", e),
+            (AppError::Parse(e), Lang::En) => format!("JSON parse failed: {}", e),
+            (AppError::JsonPath(_, e), Lang::Zh) | (AppError::State(_, e), Lang::Zh) => e.clone(),
+            (AppError::JsonPath(kind, e), Lang::En) => {
+                let label = match kind {
+                    JsonPathKind::Invalid => "Invalid JSONPath",
+                    JsonPathKind::WrongType => "Unexpected node type",
+                    JsonPathKind::NoMatch => "No node matched",
+                };
+                format!("{}: {}", label, e)
+            }
+            (AppError::State(kind, e), Lang::En) => {
+                let label = match kind {
+                    StateKind::Invalid => "Invalid operation",
+                    StateKind::NotLoaded => "Document not loaded",
+                    StateKind::ReadOnly => "Read-only mode",
+                    StateKind::Conflict => "Conflict",
+                    StateKind::Malformed => "Malformed input",
+                    StateKind::FeatureDisabled => "Feature disabled",
+                };
+                format!("{}: {}", label, e)
+            }
+            (AppError::Network(e), Lang::Zh) => format!("网络错误: {}", e),
+            (AppError::Network(e), Lang::En) => format!("Network error: {}", e),
+            (AppError::SchemaViolations(v), Lang::Zh) => format!("JSON Schema 校验未通过: {} 处违规", v.len()),
+            (AppError::SchemaViolations(v), Lang::En) => format!("JSON Schema validation failed: {} violation(s)", v.len()),
+        }
+    }
+}
 
-        let json_str = serde_json::to_string_pretty(dom)
-            .map_err(|e| AppError::JsonPath(e.to_string()))?;
+impl From<std::io::Error> for AppError {
+    fn from(source: std::io::Error) -> Self {
+        AppError::Io(IoError { source, path: None })
+    }
+}
 
-        std::fs::write(path, json_str)
-            .map_err(AppError::Io)?;
+impl AppState {
+    /// 加载JSON文件并构建影子树
+    pub fn load_file(&mut self, p: &Path) -> Result<(), AppError> {
+        let file = std::fs::File::open(long_path(p)).map_err(|e| AppError::io(e, p))?;
+        let loaded = load_tree_from_reader(std::io::BufReader::new(file), self.shadow_tree_options).map_err(|e| match e {
+            AppError::Io(IoError { source, path: None }) => AppError::io(source, p),
+            other => other,
+        })?;
+        self.finish_load(p, loaded)
+    }
 
-        tracing::info!("JSON文件已保存到: {}", path.display());
+    /// 加载带 `//`、`/* */` 注释的 JSON 文件（VSCode 设置、构建工具配置等）：先去除注释再解析
+    ///
+    /// 保存时注释不会写回，含注释时会记录一条加载警告
+    pub fn load_file_with_comments(&mut self, p: &Path) -> Result<(), AppError> {
+        let text = std::fs::read_to_string(long_path(p)).map_err(|e| AppError::io(e, p))?;
+        let stripped = strip_json_comments(&text)?;
+        let had_comments = stripped != text;
+        let loaded = load_tree_from_reader(stripped.as_bytes(), self.shadow_tree_options)?;
+        self.finish_load(p, loaded)?;
+        self.comments_stripped = true;
+        if had_comments {
+            self.load_warnings.push("文件含注释：已在解析前去除，保存时不会保留".to_string());
+        }
         Ok(())
     }
 
-    /// 切换节点的展开状态
-    pub fn toggle_node_expanded(&mut self, path: &str) {
-        if let Some(node) = self.tree_flat.iter_mut().find(|n| n.path == path) {
-            node.expanded = !node.expanded;
+    /// 忽略节点上限重建完整的影子树（“仍然完整加载”）；此后本文档的重建也不再截断，下次加载前由调用方重新设置上限
+    pub fn load_full_tree(&mut self) -> Result<(), AppError> {
+        let dom = self.dom.as_ref().ok_or_else(AppError::not_loaded)?;
+        self.shadow_tree_options.max_nodes = None;
+        if !self.truncated {
+            return Ok(());
         }
-        self.update_visibility_by_expansion();
+        let flags = self.node_flags();
+        self.tree_flat = build_shadow_tree(dom);
+        self.on_tree_rebuilt();
+        self.restore_node_flags(flags);
+        Ok(())
     }
 
-    /// 根据展开状态更新节点可见性
-    pub fn update_visibility_by_expansion(&mut self) {
-        // 首先标记所有节点为不可见（除了根节点）
-        for (i, node) in self.tree_flat.iter_mut().enumerate() {
-            if i == 0 {
-                node.visible = true; // 根节点总是可见
-            } else {
-                node.visible = false;
-            }
+    /// 将文档来源设为非文件的 URL 或自定义标签，保存目标 `original_file_path` 不变
+    pub fn set_source_alias(&mut self, alias: &str) {
+        self.source_path = Some(PathBuf::from(alias));
+    }
+
+    /// 以解析结果替换当前文档；加载的文件既是来源也是保存目标
+    fn finish_load(&mut self, p: &Path, LoadedTree { dom, tree, duplicate_keys }: LoadedTree) -> Result<(), AppError> {
+        if self.reject_duplicate_keys && !duplicate_keys.is_empty() {
+            return Err(AppError::State(StateKind::Conflict, format!("存在重复键: {}", duplicate_keys.join(", "))));
         }
+        self.tree_flat = tree;
+        self.annotations.clear();
+        self.on_tree_rebuilt();
+        self.load_warnings = duplicate_keys
+            .into_iter()
+            .map(|p| format!("重复键 {}：仅保留最后一个值", p))
+            .collect();
+        self.source_path = Some(p.to_path_buf());
+        self.original_file_path = Some(p.to_path_buf()); // 设置原始文件路径
+        self.initial_dom = Some(dom.clone());
+        self.dom = Some(dom);
+        self.undo_stack.clear();
+        self.dirty = false;
+        self.comments_stripped = false;
+        self.load_annotation_sidecar(p);
+        Ok(())
+    }
 
-        // 递归显示展开节点的直接子节点
-        for i in 0..self.tree_flat.len() {
-            if self.tree_flat[i].expanded && self.tree_flat[i].visible {
-                let parent_depth = self.tree_flat[i].depth;
-                // 显示直接子节点
-                for j in (i + 1)..self.tree_flat.len() {
-                    if self.tree_flat[j].depth == parent_depth + 1 {
-                        self.tree_flat[j].visible = true;
-                    } else if self.tree_flat[j].depth <= parent_depth {
-                        break; // 已经超出了当前父节点的范围
-                    }
-                }
+    /// 存在 `{源文件}.annotations.json` 时自动导入备注；失败只记为加载警告
+    fn load_annotation_sidecar(&mut self, p: &Path) {
+        let sidecar = annotations_sidecar_path(p);
+        let result = match std::fs::File::open(&sidecar) {
+            Ok(file) => self.import_annotations(&mut std::io::BufReader::new(file)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return,
+            Err(e) => Err(AppError::io(e, &sidecar)),
+        };
+        match result {
+            Ok(count) => tracing::info!("已从 {} 导入 {} 条备注", sidecar.display(), count),
+            Err(e) => {
+                tracing::warn!("导入备注失败: {}", e);
+                self.load_warnings.push(format!("备注文件 {} 导入失败: {}", sidecar.display(), e));
             }
         }
     }
 
-    /// 智能检测JSON中的英文字段，返回纯英文的字段值列表
-    pub fn detect_english_fields(&self, leaf_nodes_only: bool) -> Result<Vec<String>, AppError> {
-        let dom = self
-            .dom
-            .as_ref()
-            .ok_or_else(|| AppError::State("DOM尚未加载".into()))?;
+    /// 设置节点备注，`None` 或空白备注表示删除；备注按路径保存，影子树重建后仍然保留
+    pub fn set_annotation(&mut self, json_path: &str, note: Option<&str>) -> Result<(), AppError> {
+        let (_, segments, _) = self.locate_node(json_path)?;
+        let path = format_segments(&segments);
+        match note.map(str::trim).filter(|note| !note.is_empty()) {
+            Some(note) => self.annotations.insert(path, note.to_string()),
+            None => self.annotations.remove(&path),
+        };
+        self.apply_annotations();
+        Ok(())
+    }
 
-        let mut english_fields = HashSet::new();
+    /// 节点的备注
+    pub fn annotation(&self, json_path: &str) -> Option<&str> {
+        let path = format_segments(&parse_segments(json_path)?);
+        self.annotations.get(&path).map(String::as_str)
+    }
 
-        // 递归遍历JSON值，提取英文属性名
-        Self::extract_english_keys(dom, &mut english_fields, leaf_nodes_only);
+    /// 将备注附加到影子树上同路径的节点
+    fn apply_annotations(&mut self) {
+        for node in &mut self.tree_flat {
+            node.annotation = self.annotations.get(&node.path).cloned();
+        }
+    }
 
-        // 转换为排序的向量，过滤掉不符合条件的字段
-        let mut result: Vec<String> = english_fields
-            .into_iter()
-            .filter(|s| {
-                let trimmed = s.trim();
-                // 基本长度检查
-                if trimmed.len() < 2 || trimmed.len() > 50 {
-                    return false;
-                }
+    /// 以 `{ 路径: 备注 }` JSON 按路径顺序导出所有备注（含影子树截断后未展示的节点），返回导出条数
+    pub fn export_annotations(&self, writer: &mut dyn std::io::Write) -> Result<usize, AppError> {
+        serde_json::to_writer_pretty(&mut *writer, &self.annotations)?;
+        writeln!(writer)?;
+        Ok(self.annotations.len())
+    }
 
-                // 使用更精确的英文检测逻辑
-                Self::is_pure_english_field(trimmed)
-            })
-            .collect();
+    /// 读取 `{ 路径: 备注 }` JSON 并合并到现有备注（路径已不存在的备注被忽略），返回应用条数
+    pub fn import_annotations(&mut self, reader: &mut dyn std::io::Read) -> Result<usize, AppError> {
+        let annotations: HashMap<String, String> = serde_json::from_reader(reader)?;
+        let dom = self.dom.as_ref().ok_or_else(AppError::not_loaded)?;
+        let mut applied = 0;
+        for (path, note) in annotations {
+            let Some(segments) = parse_segments(&path).filter(|segments| resolve(dom, segments).is_some()) else {
+                continue;
+            };
+            self.annotations.insert(format_segments(&segments), note);
+            applied += 1;
+        }
+        self.apply_annotations();
+        Ok(applied)
+    }
 
-        result.sort();
-        result.dedup(); // 去重
+    /// 将备注写入原文件旁的 `{源文件}.annotations.json`（没有备注时删除已有的旁路文件），返回旁路文件路径
+    pub fn save_annotation_sidecar(&self) -> Result<PathBuf, AppError> {
+        let source = self
+            .original_file_path
+            .as_deref()
+            .ok_or_else(|| AppError::State(StateKind::Invalid, "原始文件路径未设置".into()))?;
+        let sidecar = annotations_sidecar_path(source);
+        self.ensure_target_writable(&sidecar)?;
+        if self.annotations.is_empty() {
+            match std::fs::remove_file(&sidecar) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(AppError::io(e, &sidecar)),
+                _ => return Ok(sidecar),
+            }
+        }
+        write_json_file_atomic(&sidecar, &serde_json::to_value(&self.annotations)?)?;
+        Ok(sidecar)
+    }
 
-        // 限制返回数量，避免UI过载
-        if result.len() > 20 {
-            result.truncate(20);
+    /// 只解析不求值地校验 JSONPath 语法，错误信息包含出错位置（从1开始的字符序号）
+    pub fn validate_path_format(json_path: &str) -> Result<(), AppError> {
+        use jsonpath_rust::parser::{errors::JsonPathError, parse_json_path};
+        use pest::error::LineColLocation;
+
+        match parse_json_path(json_path) {
+            Ok(_) => Ok(()),
+            Err(JsonPathError::PestError(e)) => {
+                let column = match e.line_col {
+                    LineColLocation::Pos((_, col)) | LineColLocation::Span((_, col), _) => col,
+                };
+                // 第二、三行为路径与指向出错位置的标记
+                Err(AppError::JsonPath(JsonPathKind::Invalid, format!(
+                    "语法错误（第 {} 个字符）\n{}\n{}^",
+                    column,
+                    json_path,
+                    " ".repeat(column.saturating_sub(1))
+                )))
+            }
+            Err(e) => Err(AppError::JsonPath(JsonPathKind::Invalid, format!("无效路径 {}: {}", json_path, e))),
         }
+    }
 
-        Ok(result)
+    /// 按 JSONPath 提取第一个匹配节点的 pretty 字符串
+    pub fn extract_subtree_pretty(&self, json_path: &str) -> Result<String, AppError> {
+        Self::validate_path_format(json_path)?;
+        to_string_with_options(self.first_match(json_path)?, &self.save_options)
     }
 
-    /// 判断是否为纯英文字段名（排除时间格式、数字等）
-    fn is_pure_english_field(s: &str) -> bool {
-        // 必须包含至少一个英文字母
-        let has_letter = s.chars().any(|c| c.is_ascii_alphabetic());
-        if !has_letter {
-            return false;
+    /// 提取子树用于预览区：短数组/对象保持单行，超过 `max_inline_width` 的换行展开
+    ///
+    /// 结果按路径缓存，子树内容未变（哈希一致）时直接返回缓存
+    pub fn extract_subtree_preview(&self, json_path: &str, max_inline_width: usize) -> Result<String, AppError> {
+        let value = self.first_match(json_path)?;
+        let hash = subtree_hash(value);
+        let key = format!("{}#{}#{}", json_path, max_inline_width, self.save_options.indent_size);
+        if let Some(cached) = self.subtree_cache.borrow_mut().get(&key, hash) {
+            return Ok(cached.to_string());
         }
+        let rendered = to_string_wrapped_with_indent(value, max_inline_width, self.save_options.indent_size);
+        self.subtree_cache.borrow_mut().insert(key, hash, rendered.clone());
+        Ok(rendered)
+    }
 
-        // 排除时间格式 (如: "2023-01-01", "12:34:56", "2023-01-01T12:34:56Z")
-        if Self::is_time_format(s) {
-            return false;
-        }
+    /// 子树紧凑序列化字节的 seahash 值（非加密，用于判断子树是否变化）
+    pub fn compute_subtree_hash(&self, json_path: &str) -> Result<u64, AppError> {
+        Ok(subtree_hash(self.first_match(json_path)?))
+    }
 
-        // 排除版本号格式 (如: "v1.2.3", "1.0.0")
-        if Self::is_version_format(s) {
-            return false;
-        }
+    /// 文档的规范序列化（见 [`canonical_form`]）：键顺序或排版不同、内容相同的文档得到相同的文本
+    pub fn compute_canonical_form(&self) -> Result<String, AppError> {
+        let dom = self.dom.as_ref().ok_or_else(AppError::not_loaded)?;
+        Ok(canonical_form(dom))
+    }
 
-        // 排除主要包含数字的字符串 (如: "123abc")
-        let letter_count = s.chars().filter(|c| c.is_ascii_alphabetic()).count();
-        let digit_count = s.chars().filter(|c| c.is_ascii_digit()).count();
-        if digit_count > letter_count {
-            return false;
+    /// 以 `other` 为旧版本比较两份文档（见 [`compare_values`]）
+    pub fn compare_trees(&self, other: &AppState) -> Result<Vec<DiffEntry>, AppError> {
+        let dom = self.dom.as_ref().ok_or_else(AppError::not_loaded)?;
+        let old = other.dom.as_ref().ok_or_else(|| AppError::State(StateKind::Invalid, "对比的文档尚未加载".into()))?;
+        Ok(compare_values(old, dom))
+    }
+
+    /// 汇总自加载以来对文档的全部改动（以加载时的文档为旧版本）
+    pub fn summarize_changes_since_load(&self) -> Result<ChangeSummary, AppError> {
+        let initial = self.initial_dom.as_ref().ok_or_else(|| AppError::State(StateKind::Invalid, "文档不是从文件加载的，没有改动基准".into()))?;
+        let dom = self.dom.as_ref().ok_or_else(AppError::not_loaded)?;
+        Ok(ChangeSummary::new(compare_values(initial, dom)))
+    }
+
+    /// 按当前文档的加载方式（普通或去除注释）重新加载文件
+    fn load_file_like_current(&mut self, p: &Path, comments_stripped: bool) -> Result<(), AppError> {
+        if comments_stripped {
+            self.load_file_with_comments(p)
+        } else {
+            self.load_file(p)
         }
+    }
 
-        // 只允许英文字母、下划线、连字符（不允许数字、冒号等）
-        s.chars().all(|c| c.is_ascii_alphabetic() || c == '_' || c == '-')
+    /// 将磁盘文件按当前文档的加载方式加载到临时状态中，与当前（可能已编辑的）文档比较
+    pub fn diff_against_file(&self, path: &Path) -> Result<FileDiff, AppError> {
+        let mut on_disk = AppState { shadow_tree_options: self.shadow_tree_options, ..AppState::default() };
+        on_disk.load_file_like_current(path, self.comments_stripped)?;
+        Ok(FileDiff::new(path, self.compare_trees(&on_disk)?))
     }
 
-    /// 判断是否为时间格式（优化版本，避免正则表达式性能问题）
-    fn is_time_format(s: &str) -> bool {
-        let len = s.len();
+    /// 为影子树的每个节点填充 `size_bytes`（子树紧凑序列化字节数估算），返回整个文档的字节数
+    ///
+    /// 耗时与节点数成正比，仅按需调用；影子树重建后大小清零，需重新计算
+    pub fn compute_subtree_sizes(&mut self) -> Result<u64, AppError> {
+        self.compute_subtree_sizes_with_progress(|_, _| {})
+    }
 
-        // 快速长度检查
-        if !(8..=30).contains(&len) {
-            return false;
-        }
+    /// 同 [`Self::compute_subtree_sizes`]，并以 `(0..1, 说明)` 报告进度
+    pub fn compute_subtree_sizes_with_progress(&mut self, progress: impl FnMut(f32, &str)) -> Result<u64, AppError> {
+        let dom = self.dom.as_ref().ok_or_else(AppError::not_loaded)?;
+        let sizes = self.built_prefix_sizes(compute_subtree_sizes(dom, progress))?;
+        Ok(self.set_subtree_sizes(&sizes))
+    }
 
-        // 检查是否包含时间相关字符
-        let has_time_chars = s.contains('-') || s.contains(':') || s.contains('T') || s.contains('Z');
-        if !has_time_chars {
-            return false;
+    /// 写入在借用之外（如后台线程中）对 `computed_for` 计算出的子树大小，返回文档总字节数
+    ///
+    /// 计算期间文档已被修改时拒绝写入，避免大小对不上节点
+    pub fn apply_subtree_sizes(&mut self, computed_for: &Value, sizes: Vec<u64>) -> Result<u64, AppError> {
+        let dom = self.dom.as_ref().ok_or_else(AppError::not_loaded)?;
+        if dom != computed_for {
+            return Err(AppError::State(StateKind::Conflict, "计算期间文档已修改，请重新计算子树大小".into()));
         }
+        let sizes = self.built_prefix_sizes(sizes)?;
+        Ok(self.set_subtree_sizes(&sizes))
+    }
 
-        // 简单模式匹配，避免复杂正则表达式
-        // ISO 8601 格式: 2023-01-01T12:34:56
-        if s.contains('T') && s.contains('-') && s.contains(':') {
-            return true;
+    fn set_subtree_sizes(&mut self, sizes: &[u64]) -> u64 {
+        for (node, size) in self.tree_flat.iter_mut().zip(sizes) {
+            node.size_bytes = *size;
         }
+        sizes.first().copied().unwrap_or_default()
+    }
 
-        // 日期格式: 2023-01-01
-        if s.matches('-').count() == 2 && (8..=12).contains(&len) {
-            let parts: Vec<&str> = s.split('-').collect();
-            if parts.len() == 3 &&
-               parts[0].len() == 4 && parts[0].chars().all(|c| c.is_ascii_digit()) &&
-               parts[1].len() == 2 && parts[1].chars().all(|c| c.is_ascii_digit()) &&
-               parts[2].len() == 2 && parts[2].chars().all(|c| c.is_ascii_digit()) {
-                return true;
-            }
+    /// 将完整文档的先序子树大小截取到影子树已构建的节点上
+    ///
+    /// 截断的影子树恰为完整先序的前缀（末尾另有截断标记），前缀中第 i 个节点与完整先序第 i 个节点路径相同
+    fn built_prefix_sizes(&self, mut sizes: Vec<u64>) -> Result<Vec<u64>, AppError> {
+        let built = self.tree_flat.len() - usize::from(self.truncated);
+        if sizes.len() < built || (!self.truncated && sizes.len() != built) {
+            return Err(AppError::State(StateKind::Invalid, "影子树与DOM不一致，请重新加载文件".into()));
         }
+        sizes.truncate(built);
+        Ok(sizes)
+    }
 
-        // 时间格式: 12:34:56
-        if s.matches(':').count() == 2 && (6..=10).contains(&len) {
-            let parts: Vec<&str> = s.split(':').collect();
-            if parts.len() == 3 && parts.iter().all(|p| p.chars().all(|c| c.is_ascii_digit())) {
-                return true;
-            }
+    /// 搜索、中间产物与导出遍历的节点：影子树被截断时按DOM另建完整的节点列表（已构建部分沿用其可见状态），
+    /// 否则直接借用影子树；两者在已构建部分下标一一对应
+    fn untruncated_nodes(&self) -> Cow<'_, [JsonTreeNode]> {
+        let Some(dom) = self.dom.as_ref().filter(|_| self.truncated) else {
+            return Cow::Borrowed(&self.tree_flat);
+        };
+        let mut nodes = build_shadow_tree(dom);
+        for (node, built) in nodes.iter_mut().zip(self.tree_flat.iter().filter(|n| !n.is_truncation_sentinel())) {
+            node.visible = built.visible;
+            node.expanded = built.expanded;
         }
-
-        false
+        tracing::info!("影子树已截断，按DOM构建完整节点列表（{} 个节点）", nodes.len());
+        Cow::Owned(nodes)
     }
 
-    /// 判断是否为版本号格式（优化版本，避免正则表达式性能问题）
-    fn is_version_format(s: &str) -> bool {
-        let len = s.len();
+    /// 提取子树为单行紧凑 JSON（便于管道传给其他工具）
+    pub fn extract_subtree_compact(&self, json_path: &str) -> Result<String, AppError> {
+        Ok(serde_json::to_string(self.first_match(json_path)?)?)
+    }
 
-        // 快速长度检查
-        if !(3..=20).contains(&len) {
-            return false;
+    /// 提取叶子节点的原始文本：字符串不带引号且转义已解析，数字/布尔按字面量输出
+    pub fn extract_leaf_text(&self, json_path: &str) -> Result<String, AppError> {
+        match self.first_match(json_path)? {
+            Value::String(s) => Ok(s.clone()),
+            v @ (Value::Number(_) | Value::Bool(_)) => Ok(v.to_string()),
+            _ => Err(AppError::State(StateKind::Invalid, format!("节点不是字符串/数字/布尔值: {}", json_path))),
         }
+    }
 
-        // 检查是否包含点号
-        if !s.contains('.') {
-            return false;
-        }
+    /// 提取对象中取值为字符串的条目（按文件中的键顺序），非字符串值跳过；常用于读取语言文件
+    pub fn extract_object_as_string_map(&self, json_path: &str) -> Result<IndexMap<String, String>, AppError> {
+        let Value::Object(map) = self.first_match(json_path)? else {
+            return Err(AppError::State(StateKind::Invalid, format!("节点不是对象: {}", json_path)));
+        };
+        Ok(map.iter().filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string()))).collect())
+    }
 
-        // 移除可能的v前缀
-        let version_str = if s.starts_with('v') || s.starts_with('V') {
-            &s[1..]
-        } else {
-            s
+    /// 提取数组中的字符串元素（按顺序），非字符串元素跳过
+    pub fn extract_array_as_string_vec(&self, json_path: &str) -> Result<Vec<String>, AppError> {
+        let Value::Array(items) = self.first_match(json_path)? else {
+            return Err(AppError::State(StateKind::Invalid, format!("节点不是数组: {}", json_path)));
         };
+        Ok(items.iter().filter_map(|v| Some(v.as_str()?.to_string())).collect())
+    }
 
-        // 检查点号数量（1-3个点号是合理的版本号）
-        let dot_count = version_str.matches('.').count();
-        if !(1..=3).contains(&dot_count) {
-            return false;
+    /// 按 JSONPath 获取第一个匹配节点的值（克隆）
+    pub fn get_value_at_path(&self, json_path: &str) -> Result<Value, AppError> {
+        self.first_match(json_path).cloned()
+    }
+
+    /// 一次取得节点的类型、深度、子节点数、预览、完整值、序列化大小与父节点位置
+    ///
+    /// 按路径段在DOM中定位，影子树被截断时同样可用；路径须为不含通配符的具体路径
+    pub fn get_node_info(&self, json_path: &str) -> Result<NodeInfo, AppError> {
+        let info = self.build_node_info(json_path, true)?;
+        self.record_access(json_path);
+        Ok(info)
+    }
+
+    /// 构建节点汇总信息，不记录访问；`with_value` 为 false 时不克隆子树，`full_value` 留为 `Null`
+    fn build_node_info(&self, json_path: &str, with_value: bool) -> Result<NodeInfo, AppError> {
+        let (dom, segments, value) = self.locate_node(json_path)?;
+        let (parent_path, index_in_parent) = match segments.split_last() {
+            Some((last, parent)) => {
+                let index = match last {
+                    PathSegment::Index(index) => Some(*index),
+                    PathSegment::Key(key) => resolve(dom, parent).and_then(Value::as_object).and_then(|map| map.keys().position(|k| k == key)),
+                };
+                (Some(format_segments(parent)), index)
+            }
+            None => (None, None),
+        };
+        Ok(NodeInfo {
+            path: format_segments(&segments),
+            kind: NodeKind::of(value),
+            depth: segments.len() as u32,
+            children_count: children_count(value) as u32,
+            preview: node_preview(value),
+            full_value: if with_value { value.clone() } else { Value::Null },
+            byte_size: compact_len(value),
+            parent_path,
+            index_in_parent,
+        })
+    }
+
+    /// 选中节点时的状态栏摘要：类型、子节点数、序列化大小与文件字节偏移
+    ///
+    /// 与 [`AppState::get_node_info`] 取自同一份节点信息，但不克隆子树、不记录访问（选中时的访问已由预览提取记录一次）
+    pub fn node_status_line(&self, json_path: &str) -> Result<String, AppError> {
+        let info = self.build_node_info(json_path, false)?;
+        let mut status = format!("{}: {:?}，{} 个子节点，{} 字节", info.path, info.kind, info.children_count, info.byte_size);
+        if let Some(offset) = self.byte_offset_of(json_path) {
+            status.push_str(&format!("，位于文件字节偏移 {}", offset));
         }
+        Ok(status)
+    }
 
-        // 检查是否为数字.数字格式
-        let parts: Vec<&str> = version_str.split('.').collect();
-        if parts.len() >= 2 && parts.len() <= 4 {
-            // 所有部分都应该是数字
-            return parts.iter().all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()));
+    /// 按路径段在DOM中定位具体路径的节点，返回 `(DOM, 路径段, 节点)`；不记录访问
+    fn locate_node(&self, json_path: &str) -> Result<(&Value, Vec<PathSegment>, &Value), AppError> {
+        Self::validate_path_format(json_path)?;
+        let dom = self.dom.as_ref().ok_or_else(AppError::not_loaded)?;
+        let segments = parse_segments(json_path)
+            .ok_or_else(|| AppError::State(StateKind::Invalid, format!("节点信息需要具体路径（不含通配符）: {}", json_path)))?;
+        let value = resolve(dom, &segments).ok_or_else(|| AppError::JsonPath(JsonPathKind::NoMatch, "未匹配到任何节点".into()))?;
+        Ok((dom, segments, value))
+    }
+
+    /// 具体路径（不含通配符）在当前文档中是否存在；按路径段定位，影子树被截断时同样准确
+    pub fn path_exists(&self, json_path: &str) -> bool {
+        let (Some(dom), Some(segments)) = (self.dom.as_ref(), parse_segments(json_path)) else {
+            return false;
+        };
+        resolve(dom, &segments).is_some()
+    }
+
+    /// 列出回写文件中在当前文档里已不存在的源路径（按出现顺序，去重）
+    ///
+    /// 路径取自 `items[].source_path`（中间产物2或含源路径的回写条目）或以 `$` 开头的键（按路径的最终产物）；
+    /// 按序号的最终产物不含路径，结果为空
+    pub fn find_orphaned_paths(&self, writeback_json: &str) -> Result<Vec<String>, AppError> {
+        if self.dom.is_none() {
+            return Err(AppError::not_loaded());
         }
+        let writeback: Value = serde_json::from_str(writeback_json)?;
+        let paths: Vec<&str> = match writeback.get("items").and_then(Value::as_array) {
+            Some(items) => items.iter().filter_map(|item| item.get("source_path")?.as_str()).collect(),
+            None => writeback.as_object().map(|map| map.keys().map(String::as_str).filter(|key| key.starts_with('$')).collect()).unwrap_or_default(),
+        };
+        let mut seen = HashSet::new();
+        Ok(paths
+            .into_iter()
+            .filter(|path| seen.insert(*path) && !self.path_exists(path))
+            .map(str::to_string)
+            .collect())
+    }
 
-        false
+    /// 节点在源文件中的字节偏移（未启用偏移追踪或加载后已修改时为 None）
+    pub fn byte_offset_of(&self, json_path: &str) -> Option<u64> {
+        self.tree_flat.iter().find(|n| n.path == json_path)?.byte_offset
     }
 
-    /// 判断是否为URL格式（优化版本，避免正则表达式性能问题）
-    fn is_url_format(s: &str) -> bool {
-        let len = s.len();
+    /// 第一个匹配节点的引用（记录访问）
+    fn first_match(&self, json_path: &str) -> Result<&Value, AppError> {
+        let dom = self
+            .dom
+            .as_ref()
+            .ok_or_else(AppError::not_loaded)?;
+        self.record_access(json_path);
+        query_first(dom, json_path)?.ok_or_else(|| AppError::JsonPath(JsonPathKind::NoMatch, "未匹配到任何节点".into()))
+    }
 
-        // 快速长度检查
-        if !(7..=2000).contains(&len) {  // 最短的URL如 http://a 至少7个字符
-            return false;
+    /// 记录一次路径访问
+    fn record_access(&self, json_path: &str) {
+        *self
+            .access_count
+            .borrow_mut()
+            .entry(json_path.to_string())
+            .or_insert(0) += 1;
+    }
+
+    /// 访问最多的前 `top_n` 个路径（次数降序，次数相同按路径排序）
+    pub fn profile_hot_paths(&self, top_n: usize) -> Vec<(String, u32)> {
+        let mut entries: Vec<(String, u32)> = self
+            .access_count
+            .borrow()
+            .iter()
+            .map(|(p, &c)| (p.clone(), c))
+            .collect();
+        entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        entries.truncate(top_n);
+        entries
+    }
+
+    /// 以 CSV（`path,access_count`）导出访问统计，按次数降序
+    pub fn export_access_log(&self, writer: &mut dyn std::io::Write) -> Result<(), AppError> {
+        writeln!(writer, "path,access_count")?;
+        for (path, count) in self.profile_hot_paths(usize::MAX) {
+            // 路径可能含逗号或引号（bracket-notation），按 CSV 规则加引号转义
+            writeln!(writer, "{},{}", csv_field(&path), count)?;
         }
+        Ok(())
+    }
 
-        // 检查是否以常见协议开头
-        let lower_s = s.to_lowercase();
-        if lower_s.starts_with("http://") ||
-           lower_s.starts_with("https://") ||
-           lower_s.starts_with("ftp://") ||
-           lower_s.starts_with("ftps://") {
-            // 检查协议后是否有域名部分
-            let protocol_end = if lower_s.starts_with("https://") || lower_s.starts_with("ftps://") {
-                8
-            } else {
-                7
-            };
+    /// 清空访问统计
+    pub fn reset_access_log(&mut self) {
+        self.access_count.get_mut().clear();
+    }
 
-            if s.len() > protocol_end {
-                let domain_part = &s[protocol_end..];
-                // 域名部分应该包含至少一个点或者是localhost
-                return domain_part.contains('.') || domain_part.starts_with("localhost");
+    /// 按 JSONPath 提取所有匹配节点（克隆），适用于通配符路径
+    pub fn extract_all_matching(&self, json_path: &str) -> Result<Vec<Value>, AppError> {
+        let dom = self
+            .dom
+            .as_ref()
+            .ok_or_else(AppError::not_loaded)?;
+        let hits: Vec<&Value> = dom
+            .query(json_path)
+            .map_err(|e| AppError::JsonPath(JsonPathKind::Invalid, e.to_string()))?;
+        Ok(hits.into_iter().cloned().collect())
+    }
+
+    /// 按 JSONPath 提取所有匹配节点，输出为 pretty JSON 数组
+    pub fn extract_all_matching_pretty(&self, json_path: &str) -> Result<String, AppError> {
+        let hits = self.extract_all_matching(json_path)?;
+        Ok(serde_json::to_string_pretty(&hits)?)
+    }
+
+    /// 替换整个DOM并重建影子树
+    pub fn replace_dom(&mut self, dom: Value) -> Result<(), AppError> {
+        self.ensure_writable()?;
+        self.tree_flat = build_shadow_tree_limited(&dom, self.shadow_tree_options.max_nodes);
+        self.on_tree_rebuilt();
+        self.dom = Some(dom);
+        self.notify_watchers(&["$"]);
+        Ok(())
+    }
+
+    /// 获取路径处的字符串值（非字符串或未匹配时返回 None）
+    pub fn get_string_value(&self, json_path: &str) -> Option<String> {
+        let dom = self.dom.as_ref()?;
+        query_first(dom, json_path).ok()??.as_str().map(str::to_string)
+    }
+
+    /// 返回恰好位于 `depth` 层（根为0层）的所有字符串节点的 `(路径, 值)`，按文档顺序
+    ///
+    /// 遍历到目标层即停止下探，比先取全部叶子再过滤更快；结果可在外部翻译后逐条用 [`Self::update_json_value`] 回写
+    pub fn get_string_values_at_depth(&self, depth: u32) -> Vec<(String, String)> {
+        let Some(dom) = self.dom.as_ref() else {
+            return Vec::new();
+        };
+        let mut out = Vec::new();
+        let mut stack: Vec<(&Value, String, u32)> = vec![(dom, "$".to_string(), 0)];
+        while let Some((value, path, level)) = stack.pop() {
+            if level == depth {
+                if let Value::String(s) = value {
+                    out.push((path, s.clone()));
+                }
+                continue;
+            }
+            // 逆序入栈以保持文档顺序
+            match value {
+                Value::Object(map) => {
+                    stack.extend(map.iter().rev().map(|(k, child)| (child, field_path(&path, k), level + 1)));
+                }
+                Value::Array(items) => {
+                    stack.extend(items.iter().enumerate().rev().map(|(i, child)| (child, format!("{}[{}]", path, i), level + 1)));
+                }
+                _ => {}
             }
         }
+        out
+    }
 
-        false
+    /// 所有字符串叶子的 `(路径, 值)`，按文档顺序
+    fn string_leaves(&self) -> Vec<(String, &str)> {
+        let Some(dom) = self.dom.as_ref() else {
+            return Vec::new();
+        };
+        let mut out = Vec::new();
+        let mut stack: Vec<(&Value, String)> = vec![(dom, "$".to_string())];
+        while let Some((value, path)) = stack.pop() {
+            match value {
+                Value::String(s) => out.push((path, s.as_str())),
+                Value::Object(map) => stack.extend(map.iter().rev().map(|(k, child)| (child, field_path(&path, k)))),
+                Value::Array(items) => {
+                    stack.extend(items.iter().enumerate().rev().map(|(i, child)| (child, format!("{}[{}]", path, i))))
+                }
+                _ => {}
+            }
+        }
+        out
     }
 
-    /// 判断是否为叶子节点（具有具体值的节点）
-    fn is_leaf_node(value: &Value) -> bool {
-        matches!(value,
-            Value::String(_) |
-            Value::Number(_) |
-            Value::Bool(_) |
-            Value::Null
-        )
+    /// 估算翻译工作量；提供参考文件（如已翻译的其他语言）时统计同路径字符串完全相同的比例
+    pub fn estimate_translation_effort(&self, reference: Option<&AppState>) -> EffortEstimate {
+        let leaves = self.string_leaves();
+        let mut estimate = EffortEstimate {
+            unique_strings: leaves.iter().map(|(_, s)| *s).collect::<HashSet<_>>().len(),
+            ..EffortEstimate::default()
+        };
+        for (_, text) in &leaves {
+            estimate.total_words += count_words(text);
+            estimate.strings_with_html += usize::from(has_html_tag(text));
+            estimate.strings_with_placeholders += usize::from(!detect_placeholder_patterns(text).is_empty());
+        }
+        estimate.similarity_to_reference = reference.map(|reference| {
+            let reference: HashMap<String, &str> = reference.string_leaves().into_iter().collect();
+            let same = leaves.iter().filter(|(path, text)| reference.get(path) == Some(text)).count();
+            if leaves.is_empty() { 0.0 } else { same as f32 / leaves.len() as f32 }
+        });
+        estimate
     }
 
-    /// 递归提取JSON中的英文属性名（键名），只收集值为字符串且值不是时间格式的属性名
-    /// 对于URL类型的属性值，直接提取URL本身而不是属性名
-    fn extract_english_keys(
-        value: &Value,
-        english_fields: &mut HashSet<String>,
-        leaf_nodes_only: bool,
-    ) {
-        match value {
-            Value::Array(arr) => {
-                for item in arr {
-                    Self::extract_english_keys(item, english_fields, leaf_nodes_only);
-                }
-            }
-            Value::Object(obj) => {
-                for (key, val) in obj {
-                    // 叶子节点过滤：如果开启了叶子节点模式，只处理叶子节点
-                    let is_leaf = Self::is_leaf_node(val);
-
-                    if !leaf_nodes_only || is_leaf {
-                        // 只有当属性值是字符串且不是时间格式时，才收集键名或URL
-                        if let Value::String(string_value) = val {
-                            let trimmed_key = key.trim();
-                            let trimmed_value = string_value.trim();
-
-                            // 检查属性值是否为时间格式或版本号格式
-                            if !trimmed_key.is_empty() &&
-                               !Self::is_time_format(trimmed_value) &&
-                               !Self::is_version_format(trimmed_value) {
-                                // 如果属性值是URL，直接提取URL本身
-                                if Self::is_url_format(trimmed_value) {
-                                    english_fields.insert(trimmed_value.to_string());
-                                } else {
-                                    // 否则提取属性名
-                                    english_fields.insert(trimmed_key.to_string());
-                                }
-                            }
-                        }
+    /// 所有数字节点的 `(路径, 值)`，整数与浮点数统一转为 f64，按影子树顺序
+    pub fn extract_number_values(&self) -> Result<Vec<(String, f64)>, AppError> {
+        self.extract_typed_values(NodeKind::Number, |v| v.as_number().and_then(serde_json::Number::as_f64))
+    }
+
+    /// 所有布尔节点的 `(路径, 值)`，按影子树顺序
+    pub fn extract_bool_values(&self) -> Result<Vec<(String, bool)>, AppError> {
+        self.extract_typed_values(NodeKind::Bool, Value::as_bool)
+    }
+
+    /// 遍历影子树中指定类型的节点，按路径取值并转换
+    fn extract_typed_values<T>(&self, kind: NodeKind, convert: impl Fn(&Value) -> Option<T>) -> Result<Vec<(String, T)>, AppError> {
+        if self.dom.is_none() {
+            return Err(AppError::not_loaded());
+        }
+        let mut out = Vec::new();
+        for node in self.tree_flat.iter().filter(|n| n.kind == kind) {
+            if let Some(v) = convert(&self.get_value_at_path(&node.path)?) {
+                out.push((node.path.clone(), v));
+            }
+        }
+        Ok(out)
+    }
+
+    /// 检查所有字符串值的编码问题，返回 `(路径, 描述)`，按文档顺序
+    ///
+    /// 报告：含 U+FFFD 替换字符（源文件可能有无效转义）的值、含 U+0000 的值。
+    /// 已加载的字符串都是合法 UTF-8，重新编码总能原样还原；孤立代理项的转义在加载时即报错，不会进入 DOM
+    pub fn check_utf8_validity(&self) -> Vec<(String, String)> {
+        let Some(dom) = self.dom.as_ref() else {
+            return Vec::new();
+        };
+        let mut findings = Vec::new();
+        let mut stack: Vec<(&Value, String)> = vec![(dom, "$".to_string())];
+        while let Some((value, path)) = stack.pop() {
+            match value {
+                Value::String(s) => {
+                    if s.contains('\u{FFFD}') {
+                        findings.push((path.clone(), "包含替换字符 U+FFFD（源文件可能含无效的 \\u 转义）".to_string()));
+                    }
+                    if let Some(pos) = s.chars().position(|c| c == '\0') {
+                        findings.push((path, format!("第 {} 个字符为 U+0000，部分工具会在此截断", pos + 1)));
                     }
+                }
+                // 逆序入栈以保持文档顺序
+                Value::Object(map) => stack.extend(map.iter().rev().map(|(k, child)| (child, field_path(&path, k)))),
+                Value::Array(items) => {
+                    stack.extend(items.iter().enumerate().rev().map(|(i, child)| (child, format!("{}[{}]", path, i))))
+                }
+                _ => {}
+            }
+        }
+        findings
+    }
+
+    /// 字符串节点值的字符数（带缓存）
+    fn value_len(&self, json_path: &str) -> Option<usize> {
+        if let Some(&len) = self.value_len_cache.borrow().get(json_path) {
+            return Some(len);
+        }
+        let len = self.get_string_value(json_path)?.chars().count();
+        self.value_len_cache.borrow_mut().insert(json_path.to_string(), len);
+        Some(len)
+    }
 
-                    // 递归检查子结构的键名（无论值是什么类型）
-                    Self::extract_english_keys(val, english_fields, leaf_nodes_only);
+    /// 查找字符串值最长的节点，返回节点与其值
+    pub fn find_longest_value(&self) -> Option<(&JsonTreeNode, String)> {
+        let node = self
+            .tree_flat
+            .iter()
+            .filter(|n| n.kind == NodeKind::String)
+            .filter_map(|n| self.value_len(&n.path).map(|len| (n, len)))
+            .max_by_key(|&(_, len)| len)
+            .map(|(n, _)| n)?;
+        let value = self.get_string_value(&node.path)?;
+        Some((node, value))
+    }
+
+    /// 遍历字符串值长度（字符数）在 `[min_len, max_len]` 内的节点
+    pub fn find_by_value_length_range(&self, min_len: usize, max_len: usize) -> impl Iterator<Item = &JsonTreeNode> + '_ {
+        self.tree_flat.iter().filter(move |n| {
+            n.kind == NodeKind::String
+                && self
+                    .value_len(&n.path)
+                    .is_some_and(|len| (min_len..=max_len).contains(&len))
+        })
+    }
+
+    /// 采样最多 100 个非空字符串值，统计各占位符风格出现次数并返回占优风格
+    ///
+    /// `sample_paths` 为空时从影子树中按顺序采样；未检测到占位符时返回 `PlaceholderStyle::None`
+    pub fn detect_interpolation_style(&self, sample_paths: Option<&[String]>) -> PlaceholderStyle {
+        const MAX_SAMPLES: usize = 100;
+
+        let paths: Vec<&str> = match sample_paths {
+            Some(paths) => paths.iter().map(String::as_str).collect(),
+            None => self
+                .tree_flat
+                .iter()
+                .filter(|n| n.kind == NodeKind::String)
+                .map(|n| n.path.as_str())
+                .collect(),
+        };
+
+        let mut counts: HashMap<PlaceholderStyle, usize> = HashMap::new();
+        let samples = paths
+            .into_iter()
+            .filter_map(|p| self.get_string_value(p))
+            .filter(|v| !v.trim().is_empty())
+            .take(MAX_SAMPLES);
+        for value in samples {
+            for (style, _) in detect_placeholder_patterns(&value) {
+                *counts.entry(style).or_insert(0) += 1;
+            }
+        }
+
+        // 计数相同时按 DETECTABLE 顺序取靠前者
+        PlaceholderStyle::DETECTABLE
+            .iter()
+            .copied()
+            .filter_map(|style| counts.get(&style).map(|&c| (style, c)))
+            .fold(None, |best: Option<(PlaceholderStyle, usize)>, (style, c)| match best {
+                Some((_, best_c)) if best_c >= c => best,
+                _ => Some((style, c)),
+            })
+            .map(|(style, _)| style)
+            .unwrap_or_default()
+    }
+
+    /// 将 new_json 替换到第一个匹配的 json_path 节点
+    pub fn update_node_from_str(&mut self, json_path: &str, new_json: &str) -> Result<(), AppError> {
+        self.ensure_writable()?;
+        Self::validate_path_format(json_path)?;
+        let dom = self
+            .dom
+            .as_mut()
+            .ok_or_else(AppError::not_loaded)?;
+
+        // 对于字符串值，直接设置为JSON字符串值，不需要解析
+        let replacement: Value = Value::String(new_json.to_string());
+        let Some(slot) = query_first_mut(dom, json_path)? else {
+            return Err(AppError::JsonPath(JsonPathKind::NoMatch, "未匹配到可更新路径".into()));
+        };
+        *slot = replacement;
+
+        // 变更后重建影子树（后续可优化为局部刷新）
+        self.tree_flat = build_shadow_tree_limited(dom, self.shadow_tree_options.max_nodes);
+        self.on_tree_rebuilt();
+        self.dirty = true;
+        self.notify_watchers(&[json_path]);
+        Ok(())
+    }
+
+    /// 批量将字符串写入多个路径：只记录一次撤销、只重建一次影子树（保留展开状态），返回成功写入的数量
+    ///
+    /// 未匹配到节点的路径被跳过；没有任何写入时不修改文档
+    pub fn batch_update(&mut self, updates: &[(String, String)]) -> Result<usize, AppError> {
+        self.ensure_writable()?;
+        let dom = self
+            .dom
+            .as_ref()
+            .ok_or_else(AppError::not_loaded)?;
+        if !updates.iter().any(|(path, _)| matches!(query_first(dom, path), Ok(Some(_)))) {
+            return Ok(0);
+        }
+
+        // 确认有可写入的路径后才记录撤销快照，整个批次只复制一次文档
+        self.push_undo();
+        let flags = self.node_flags();
+        let dom = self.dom.as_mut().ok_or_else(AppError::not_loaded)?;
+        let mut applied = Vec::new();
+        for (path, value) in updates {
+            if let Ok(Some(slot)) = query_first_mut(dom, path) {
+                *slot = Value::String(value.clone());
+                applied.push(path.as_str());
+            }
+        }
+        self.tree_flat = build_shadow_tree_limited(dom, self.shadow_tree_options.max_nodes);
+        self.on_tree_rebuilt();
+        self.restore_node_flags(flags);
+        self.dirty = true;
+        self.notify_watchers(&applied);
+        Ok(applied.len())
+    }
+
+    /// 当前DOM的内容指纹（与格式无关），用于校验回写目标是否为提取时的文档
+    pub fn document_fingerprint(&self) -> Result<String, AppError> {
+        let dom = self
+            .dom
+            .as_ref()
+            .ok_or_else(AppError::not_loaded)?;
+        Ok(fingerprint_value(dom))
+    }
+
+    /// 设置格式化输出的缩进空格数（2 或 4，默认 2），用于保存、子树提取与中间产物
+    pub fn set_indent_size(&mut self, spaces: u8) -> Result<(), AppError> {
+        if spaces != 2 && spaces != 4 {
+            return Err(AppError::State(StateKind::Invalid, format!("缩进只支持2或4个空格: {}", spaces)));
+        }
+        self.save_options.indent_size = usize::from(spaces);
+        Ok(())
+    }
+
+    /// 保存当前DOM使用的格式选项（含 [`Self::set_indent_size`] 设置的缩进）
+    pub fn save_options(&self) -> SaveOptions {
+        self.save_options
+    }
+
+    /// 将当前DOM保存到指定路径（格式化输出，缩进见 [`Self::set_indent_size`]）
+    pub fn save_to_file(&self, path: &Path) -> Result<(), AppError> {
+        self.save_to_file_with_options(path, self.save_options)
+    }
+
+    /// 将当前DOM以紧凑格式（无空白）保存到指定路径
+    pub fn save_minified(&self, path: &Path) -> Result<(), AppError> {
+        self.save_to_file_with_options(path, SaveOptions::minified())
+    }
+
+    /// 按保存选项（格式化/缩进/键排序）原子保存当前DOM
+    ///
+    /// 设置了 Schema 时先校验，存在违规则返回 [`AppError::SchemaViolations`] 且不写入
+    pub fn save_to_file_with_options(&self, path: &Path, options: SaveOptions) -> Result<(), AppError> {
+        let violations = self.validate_dom();
+        if !violations.is_empty() {
+            return Err(AppError::SchemaViolations(violations));
+        }
+        self.write_dom(path, &options)
+    }
+
+    /// 跳过 Schema 校验保存当前DOM（用户确认仍要保存时使用）
+    pub fn save_to_file_ignoring_schema(&self, path: &Path) -> Result<(), AppError> {
+        self.write_dom(path, &self.save_options)
+    }
+
+    fn write_dom(&self, path: &Path, options: &SaveOptions) -> Result<(), AppError> {
+        self.ensure_target_writable(path)?;
+        let dom = self
+            .dom
+            .as_ref()
+            .ok_or_else(AppError::not_loaded)?;
+        write_json_file_atomic_with_options(path, dom, options)
+    }
+
+    /// 只读模式下拒绝修改文档
+    fn ensure_writable(&self) -> Result<(), AppError> {
+        if self.read_only {
+            return Err(AppError::State(StateKind::ReadOnly, "只读模式".into()));
+        }
+        Ok(())
+    }
+
+    /// 只读模式下拒绝写入原文件、其备注旁路文件与恢复文件；写到其他路径（另存为、导出）不受影响
+    fn ensure_target_writable(&self, path: &Path) -> Result<(), AppError> {
+        if !self.read_only {
+            return Ok(());
+        }
+        let recovery = artifact_path(ArtifactKind::Recovery, self.original_file_path.as_deref());
+        let sidecar = self.original_file_path.as_deref().map(annotations_sidecar_path);
+        let mut protected = self.original_file_path.as_deref().into_iter().chain(sidecar.as_deref()).chain([recovery.as_path()]);
+        if protected.any(|p| same_path(p, path)) {
+            return Err(AppError::State(StateKind::ReadOnly, format!("只读模式，不能写入: {}", display_path(path))));
+        }
+        Ok(())
+    }
+
+    /// 加载 draft-07 JSON Schema，此后保存前自动校验
+    pub fn set_schema(&mut self, path: &Path) -> Result<(), AppError> {
+        let text = std::fs::read_to_string(path).map_err(|e| AppError::io(e, path))?;
+        self.schema = Some(CompiledSchema::compile(serde_json::from_str(&text)?)?);
+        Ok(())
+    }
+
+    /// 取消 Schema 校验
+    pub fn clear_schema(&mut self) {
+        self.schema = None;
+    }
+
+    /// 当前 Schema 原文（交给后台线程重新编译）
+    pub fn schema_source(&self) -> Option<&Value> {
+        self.schema.as_ref().map(|s| s.source())
+    }
+
+    /// 按当前 Schema 校验DOM；未设置 Schema 或DOM未加载时返回空列表
+    pub fn validate_dom(&self) -> Vec<SchemaViolation> {
+        match (&self.schema, &self.dom) {
+            (Some(schema), Some(dom)) => schema.validate(dom),
+            _ => Vec::new(),
+        }
+    }
+
+    /// 将当前DOM保存到原始文件路径
+    pub fn save_to_original_file(&self) -> Result<(), AppError> {
+        let original_path = self
+            .original_file_path
+            .as_ref()
+            .ok_or_else(|| AppError::State(StateKind::Invalid, "原始文件路径未设置".into()))?;
+        self.save_to_file(original_path)
+    }
+
+    /// 先将原始文件备份为 `{backup_dir}/{文件名}.{时间戳}.bak`，再保存当前DOM到原始文件，返回备份路径
+    ///
+    /// `backup_dir` 为 None 时备份到原始文件所在目录
+    pub fn save_to_original_file_backup(&self, backup_dir: Option<&Path>) -> Result<PathBuf, AppError> {
+        self.ensure_writable()?;
+        let original_path = self
+            .original_file_path
+            .as_ref()
+            .ok_or_else(|| AppError::State(StateKind::Invalid, "原始文件路径未设置".into()))?;
+        let backup = backup_file(original_path, backup_dir)?;
+        self.save_to_original_file()?;
+        Ok(backup)
+    }
+
+    /// 将 `json_path` 处的子树按保存选项原子写入 `path`，返回写入的节点数
+    ///
+    /// 具体路径写入该节点本身；通配符、过滤器等可能命中多个节点的路径写入由全部命中组成的数组
+    pub fn save_subtree(&self, path: &Path, json_path: &str, opts: SaveOptions) -> Result<usize, AppError> {
+        self.ensure_target_writable(path)?;
+        let dom = self
+            .dom
+            .as_ref()
+            .ok_or_else(AppError::not_loaded)?;
+        let not_found = || AppError::JsonPath(JsonPathKind::NoMatch, format!("未匹配到任何节点: {}", json_path));
+        let (value, count) = match parse_segments(json_path) {
+            Some(segments) => (resolve(dom, &segments).ok_or_else(not_found)?.clone(), 1),
+            None => {
+                let hits = dom.query(json_path).map_err(|e| AppError::JsonPath(JsonPathKind::Invalid, e.to_string()))?;
+                if hits.is_empty() {
+                    return Err(not_found());
                 }
+                let count = hits.len();
+                (Value::Array(hits.into_iter().cloned().collect()), count)
+            }
+        };
+        write_json_file_atomic_with_options(path, &value, &opts)?;
+        Ok(count)
+    }
+
+    /// 读取外部 JSON 文件并按 `mode` 并入 `json_path` 处的子树（[`Self::save_subtree`] 的逆操作），返回变化报告
+    ///
+    /// 有变化时记录一次撤销并只重建该子树的影子树节点（保留展开状态）
+    pub fn replace_subtree_from_file(&mut self, json_path: &str, file: &Path, mode: SubtreeMergeMode) -> Result<SubtreeMergeReport, AppError> {
+        self.ensure_writable()?;
+        if self.dom.is_none() {
+            return Err(AppError::not_loaded());
+        }
+        let index = self.tree_index_of(json_path)?;
+        let text = std::fs::read_to_string(file).map_err(|e| AppError::io(e, file))?;
+        let incoming: Value = serde_json::from_str(&text)?;
+
+        let dom = self.dom.as_mut().expect("DOM已在上方校验");
+        let mut merged = Self::first_match_mut(dom, json_path)?.clone();
+        let mut report = SubtreeMergeReport::default();
+        merge_into(&mut merged, incoming, json_path, mode, &mut report);
+        if report.added.is_empty() && report.changed.is_empty() && report.removed.is_empty() {
+            return Ok(report);
+        }
+
+        self.push_undo();
+        let dom = self.dom.as_mut().expect("DOM已在上方校验");
+        *Self::first_match_mut(dom, json_path)? = merged.clone();
+        let end = self.subtree_end(index);
+        let flags: Vec<FrozenNode> = self.node_flags().drain(index..end).collect();
+        let node = &self.tree_flat[index];
+        let mut nodes = build_subtree_nodes(&merged, node.name.clone(), json_path, node.depth);
+        // 新增的节点与子树根同样可见，原有节点随后恢复各自的状态
+        let visible = node.visible;
+        nodes.iter_mut().for_each(|n| n.visible = visible);
+        self.tree_flat.splice(index..end, nodes);
+        self.restore_node_flags(flags);
+        if index > 0 {
+            let parent_index = self.tree_parent_index(index)?;
+            self.refresh_parent_node(parent_index)?;
+        }
+        self.on_tree_rebuilt();
+        self.dirty = true;
+        self.notify_watchers(&[json_path]);
+        Ok(report)
+    }
+
+    /// 批量导出子树：每项为 `(json_path, 输出路径)`，逐项原子写入
+    ///
+    /// 单项失败不影响其余导出，返回失败项及原因；DOM 未加载时整体失败
+    pub fn bulk_export_subtrees(&self, exports: &[(String, PathBuf)]) -> Result<Vec<(PathBuf, AppError)>, AppError> {
+        let dom = self
+            .dom
+            .as_ref()
+            .ok_or_else(AppError::not_loaded)?;
+
+        let mut failures = Vec::new();
+        for (json_path, output) in exports {
+            let result = self
+                .ensure_target_writable(output)
+                .and_then(|_| dom.query(json_path).map_err(|e| AppError::JsonPath(JsonPathKind::Invalid, e.to_string())))
+                .and_then(|hits| {
+                    hits.into_iter()
+                        .next()
+                        .ok_or_else(|| AppError::JsonPath(JsonPathKind::NoMatch, format!("未匹配到任何节点: {}", json_path)))
+                })
+                .and_then(|subtree| write_json_file_atomic(output, subtree));
+            if let Err(e) = result {
+                tracing::warn!("导出 {} -> {} 失败: {}", json_path, output.display(), e);
+                failures.push((output.clone(), e));
             }
-            _ => {} // 忽略其他类型（数字、布尔值、null、字符串值）
         }
+        Ok(failures)
+    }
+
+    /// 将当前DOM写入产物目录下的恢复文件（`<原文件名>.recovery.json`），返回恢复文件路径
+    pub fn save_recovery(&self) -> Result<PathBuf, AppError> {
+        self.ensure_writable()?;
+        let recovery_path = artifact_path(ArtifactKind::Recovery, self.original_file_path.as_deref());
+        if let Some(dir) = recovery_path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| AppError::io(e, dir))?;
+        }
+        // 恢复文件必须写出，不做 Schema 校验
+        self.write_dom(&recovery_path, &self.save_options)?;
+        Ok(recovery_path)
+    }
+
+    /// 冻结当前状态为快照（只克隆DOM与节点标记）
+    pub fn freeze(&self) -> FrozenState {
+        FrozenState {
+            source_path: self.source_path.clone(),
+            original_file_path: self.original_file_path.clone(),
+            dom: self.dom.clone(),
+            nodes: self.node_flags(),
+        }
+    }
+
+    /// 从快照恢复状态：重建影子树并还原展开/可见标记
+    pub fn thaw(frozen: FrozenState, app_state: &mut AppState) -> Result<(), AppError> {
+        let dom = frozen
+            .dom
+            .ok_or_else(|| AppError::State(StateKind::Malformed, "快照中没有DOM".into()))?;
+
+        app_state.tree_flat = build_shadow_tree_limited(&dom, app_state.shadow_tree_options.max_nodes);
+        app_state.on_tree_rebuilt();
+        app_state.restore_node_flags(frozen.nodes);
+        app_state.dom = Some(dom);
+        app_state.source_path = frozen.source_path;
+        app_state.original_file_path = frozen.original_file_path;
+        Ok(())
+    }
+
+    /// 在结构性编辑前记录一层撤销快照（超过上限时丢弃最早的一层）
+    pub(crate) fn push_undo(&mut self) {
+        if self.dom.is_none() {
+            return;
+        }
+        if self.undo_stack.len() >= MAX_UNDO_LEVELS {
+            self.undo_stack.remove(0);
+        }
+        let frozen = self.freeze();
+        self.undo_stack.push(frozen);
+    }
+
+    /// 监听 `json_path` 处的值：编辑触及该路径（自身、祖先或后代）后以最新值调用回调，节点已删除时传入 `null`
+    pub fn watch_value(&mut self, json_path: String, callback: WatchCallback) -> WatcherId {
+        self.watchers.watch(json_path, callback)
+    }
+
+    /// 取消监听；句柄不存在时返回 false
+    pub fn unwatch(&mut self, watcher_id: WatcherId) -> bool {
+        self.watchers.unwatch(watcher_id)
+    }
+
+    /// 编辑完成后通知监听了 `changed` 中路径（自身、祖先或后代）的回调
+    fn notify_watchers(&self, changed: &[&str]) {
+        if !self.watchers.is_empty() {
+            let changes: Vec<WatchChange> = changed.iter().map(|path| WatchChange::Path(path)).collect();
+            self.watchers.notify(self.dom.as_ref(), &changes);
+        }
+    }
+
+    /// 同 [`Self::notify_watchers`]，可描述数组元素下标的整体移位
+    fn notify_watcher_changes(&self, changes: &[WatchChange]) {
+        if !self.watchers.is_empty() {
+            self.watchers.notify(self.dom.as_ref(), changes);
+        }
+    }
+
+    /// 是否有可撤销的编辑
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    /// 撤销最近一次结构性编辑；没有可撤销的编辑时返回 false
+    pub fn undo(&mut self) -> Result<bool, AppError> {
+        self.ensure_writable()?;
+        let Some(frozen) = self.undo_stack.pop() else {
+            return Ok(false);
+        };
+        Self::thaw(frozen, self)?;
+        self.notify_watchers(&["$"]);
+        Ok(true)
+    }
+
+    /// 当前影子树各节点的展开/可见标记
+    pub fn node_flags(&self) -> Vec<FrozenNode> {
+        self.tree_flat
+            .iter()
+            .map(|n| FrozenNode {
+                path: n.path.clone(),
+                expanded: n.expanded,
+                visible: n.visible,
+            })
+            .collect()
+    }
+
+    /// 按路径还原节点的展开/可见标记（路径已不存在的标记被忽略）
+    pub fn restore_node_flags(&mut self, nodes: Vec<FrozenNode>) {
+        let flags: HashMap<String, (bool, bool)> = nodes
+            .into_iter()
+            .map(|n| (n.path, (n.expanded, n.visible)))
+            .collect();
+        for node in &mut self.tree_flat {
+            if let Some(&(expanded, visible)) = flags.get(&node.path) {
+                node.expanded = expanded;
+                node.visible = visible;
+            }
+        }
+    }
+
+    /// 将影子树以紧凑 JSON 导出为快照（`visible_only` 时只含可见节点），返回快照统计
+    pub fn export_tree_snapshot(&self, path: &Path, visible_only: bool) -> Result<TreeSnapshotSummary, AppError> {
+        self.ensure_target_writable(path)?;
+        let nodes: Vec<JsonTreeNode> = self
+            .tree_flat
+            .iter()
+            .filter(|n| !visible_only || n.visible)
+            .cloned()
+            .collect();
+        let snapshot = TreeSnapshot { summary: TreeSnapshotSummary::of(&nodes), nodes };
+        write_json_file_atomic_with_options(path, &serde_json::to_value(&snapshot)?, &SaveOptions::minified())?;
+        Ok(snapshot.summary)
+    }
+
+    /// 从快照恢复影子树（不含DOM，依赖DOM的提取操作会返回“DOM尚未加载”），返回快照统计
+    pub fn import_tree_snapshot(&mut self, path: &Path) -> Result<TreeSnapshotSummary, AppError> {
+        let text = std::fs::read_to_string(path).map_err(|e| AppError::io(e, path))?;
+        let snapshot: TreeSnapshot = serde_json::from_str(&text)?;
+        self.dom = None;
+        self.source_path = None;
+        self.original_file_path = None;
+        self.annotations = snapshot.nodes.iter().filter_map(|n| Some((n.path.clone(), n.annotation.clone()?))).collect();
+        self.tree_flat = snapshot.nodes;
+        self.on_tree_rebuilt();
+        Ok(snapshot.summary)
+    }
+
+    /// 重新加载文件并保留原有的展开/可见状态与备注（路径已不存在的被忽略）
+    pub fn reload_preserving_state(&mut self, p: &Path) -> Result<(), AppError> {
+        let flags = self.node_flags();
+        let annotations = self.annotations.clone();
+        self.load_file_like_current(p, self.comments_stripped)?;
+        self.restore_node_flags(flags);
+        for (path, note) in annotations {
+            if self.path_exists(&path) {
+                self.annotations.insert(path, note);
+            }
+        }
+        self.apply_annotations();
+        self.notify_watchers(&["$"]);
+        Ok(())
+    }
+
+    /// 结构比较：当前DOM与 `expected_json` 在每个路径上的键集合与节点类型是否一致（忽略值），主要用于测试
+    pub fn assert_structure_matches(&self, expected_json: &str) -> Result<(), Vec<StructureMismatch>> {
+        let mismatch = |path: &str, expected: String, actual: String| StructureMismatch { path: path.to_string(), expected, actual };
+        let expected: Value = serde_json::from_str(expected_json)
+            .map_err(|e| vec![mismatch("$", "合法JSON".into(), format!("解析失败: {}", e))])?;
+        let dom = self
+            .dom
+            .as_ref()
+            .ok_or_else(|| vec![mismatch("$", format!("{:?}", NodeKind::of(&expected)), "DOM尚未加载".into())])?;
+
+        let describe = |v: Option<&Value>| v.map_or_else(|| "缺失".to_string(), |v| format!("{:?}", NodeKind::of(v)));
+        let mut mismatches = Vec::new();
+        // 显式栈遍历，逆序入栈以按文档顺序报告
+        let mut stack = vec![(Some(&expected), Some(dom), "$".to_string())];
+        while let Some((exp, act, path)) = stack.pop() {
+            match (exp, act) {
+                (Some(Value::Object(e)), Some(Value::Object(a))) => {
+                    let keys: std::collections::BTreeSet<&String> = e.keys().chain(a.keys()).collect();
+                    stack.extend(keys.into_iter().rev().map(|k| (e.get(k), a.get(k), field_path(&path, k))));
+                }
+                (Some(Value::Array(e)), Some(Value::Array(a))) => {
+                    stack.extend((0..e.len().max(a.len())).rev().map(|i| (e.get(i), a.get(i), format!("{}[{}]", path, i))));
+                }
+                (Some(e), Some(a)) if NodeKind::of(e) == NodeKind::of(a) => {}
+                _ => mismatches.push(mismatch(&path, describe(exp), describe(act))),
+            }
+        }
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(mismatches)
+        }
+    }
+
+    /// 为数组中的每个对象元素注入序号字段，返回被修改的元素数量
+    ///
+    /// 序号为 `start + index * step`；当 `start` 与 `step` 均为 0 时为只增模式，已有该字段的元素保持不变
+    pub fn inject_seq_numbers(&mut self, array_path: &str, key_name: &str, start: usize, step: usize) -> Result<usize, AppError> {
+        self.ensure_writable()?;
+        let dom = self
+            .dom
+            .as_mut()
+            .ok_or_else(AppError::not_loaded)?;
+
+        let add_only = start == 0 && step == 0;
+        let arr = match Self::first_match_mut(dom, array_path)? {
+            Value::Array(arr) => arr,
+            _ => return Err(AppError::JsonPath(JsonPathKind::WrongType, format!("目标不是数组: {}", array_path))),
+        };
+
+        let mut modified = 0;
+        for (index, item) in arr.iter_mut().enumerate() {
+            if let Value::Object(map) = item {
+                if add_only && map.contains_key(key_name) {
+                    continue;
+                }
+                map.insert(key_name.to_string(), serde_json::json!(start + index * step));
+                modified += 1;
+            }
+        }
+
+        self.tree_flat = build_shadow_tree_limited(dom, self.shadow_tree_options.max_nodes);
+        self.on_tree_rebuilt();
+        if modified > 0 {
+            self.dirty = true;
+            self.notify_watchers(&[array_path]);
+        }
+        Ok(modified)
+    }
+
+    /// 以 `new_root_path` 处的子树为新的根节点，重建影子树（所有路径重新以 `$` 开头）
+    ///
+    /// `source_path` 记录为 `<原文件>#<新根路径>`；为避免子树覆盖原文件，同时清除原始文件路径。
+    /// 改动基准（加载时的文档）同样取该路径处的子树，加载时不存在该节点则不再有基准
+    pub fn rebase(&mut self, new_root_path: &str) -> Result<(), AppError> {
+        self.ensure_writable()?;
+        let dom = self
+            .dom
+            .as_mut()
+            .ok_or_else(AppError::not_loaded)?;
+
+        let subtree = std::mem::take(Self::first_match_mut(dom, new_root_path)?);
+        self.tree_flat = build_shadow_tree_limited(&subtree, self.shadow_tree_options.max_nodes);
+        self.on_tree_rebuilt();
+        self.dom = Some(subtree);
+        self.initial_dom = self
+            .initial_dom
+            .take()
+            .and_then(|mut initial| Self::first_match_mut(&mut initial, new_root_path).ok().map(std::mem::take));
+        self.source_path = self.source_path.take().map(|p| {
+            let mut rebased = p.into_os_string();
+            rebased.push(format!("#{}", new_root_path));
+            PathBuf::from(rebased)
+        });
+        self.original_file_path = None;
+        self.notify_watchers(&["$"]);
+        Ok(())
+    }
+
+    /// 将绝对路径转换为相对 `base_path` 的路径（以 `$` 开头），不在其下时返回 None
+    pub fn get_relative_path(base_path: &str, absolute_path: &str) -> Option<String> {
+        let rest = absolute_path.strip_prefix(base_path)?;
+        if rest.is_empty() || rest.starts_with('.') || rest.starts_with('[') {
+            Some(format!("${}", rest))
+        } else {
+            None
+        }
+    }
+
+    /// 将所有匹配 `json_path_pattern` 的数字节点限制在 `[min, max]` 内，返回被修改的节点数量
+    pub fn clamp_number_values(&mut self, json_path_pattern: &str, min: f64, max: f64) -> Result<usize, AppError> {
+        self.ensure_writable()?;
+        if min > max {
+            return Err(AppError::State(StateKind::Invalid, format!("无效区间: [{}, {}]", min, max)));
+        }
+        let dom = self
+            .dom
+            .as_mut()
+            .ok_or_else(AppError::not_loaded)?;
+
+        let paths: Vec<String> = dom
+            .query_only_path(json_path_pattern)
+            .map_err(|e| AppError::JsonPath(JsonPathKind::Invalid, e.to_string()))?;
+
+        let mut modified = Vec::new();
+        for p in paths {
+            let Some(slot) = dom.reference_mut(&p) else { continue };
+            let Some(n) = slot.as_f64() else { continue };
+            let clamped = n.clamp(min, max);
+            if clamped != n {
+                *slot = Self::number_value(clamped)?;
+                modified.push(p);
+            }
+        }
+
+        if !modified.is_empty() {
+            self.tree_flat = build_shadow_tree_limited(dom, self.shadow_tree_options.max_nodes);
+            self.on_tree_rebuilt();
+            self.dirty = true;
+            self.notify_watchers(&modified.iter().map(String::as_str).collect::<Vec<_>>());
+        }
+        Ok(modified.len())
+    }
+
+    /// 将对象键 `old` 重命名为 `new`（保留值与该键在对象中的位置），返回重命名次数
+    ///
+    /// `scope` 为 None 时作用于整个文档，否则只处理其匹配节点之下的对象（含嵌套对象）。
+    /// 任一对象同时含有 `old` 与 `new` 时拒绝重命名，错误中列出所有冲突对象的路径
+    pub fn rename_key(&mut self, old: &str, new: &str, scope: Option<&str>) -> Result<usize, AppError> {
+        self.ensure_writable()?;
+        if old.is_empty() || new.is_empty() || old == new {
+            return Err(AppError::State(StateKind::Invalid, format!("无效的重命名: {} -> {}", old, new)));
+        }
+        let dom = self
+            .dom
+            .as_ref()
+            .ok_or_else(AppError::not_loaded)?;
+        let roots: Vec<String> = dom
+            .query_only_path(scope.unwrap_or("$"))
+            .map_err(|e| AppError::JsonPath(JsonPathKind::Invalid, e.to_string()))?;
+        if roots.is_empty() {
+            return Err(AppError::JsonPath(JsonPathKind::NoMatch, format!("未匹配到任何节点: {}", scope.unwrap_or("$"))));
+        }
+
+        // 先只读遍历：收集被改名的键路径（新旧各一）与冲突
+        let mut renamed = Vec::new();
+        let mut collisions = Vec::new();
+        let mut stack: Vec<(&Value, String)> = roots
+            .iter()
+            .filter_map(|p| dom.reference(p.as_str()).map(|v| (v, p.clone())))
+            .collect();
+        while let Some((value, path)) = stack.pop() {
+            match value {
+                Value::Object(map) => {
+                    if map.contains_key(old) {
+                        if map.contains_key(new) {
+                            collisions.push(path.clone());
+                        } else {
+                            renamed.push(field_path(&path, old));
+                            renamed.push(field_path(&path, new));
+                        }
+                    }
+                    stack.extend(map.iter().map(|(k, v)| (v, field_path(&path, k))));
+                }
+                Value::Array(items) => {
+                    stack.extend(items.iter().enumerate().map(|(i, v)| (v, format!("{}[{}]", path, i))));
+                }
+                _ => {}
+            }
+        }
+        if !collisions.is_empty() {
+            collisions.sort();
+            collisions.dedup();
+            return Err(AppError::State(StateKind::Conflict, format!("目标键 {} 已存在，拒绝重命名: {}", new, collisions.join(", "))));
+        }
+        if renamed.is_empty() {
+            return Ok(0);
+        }
+
+        self.push_undo();
+        let flags = self.node_flags();
+        let dom = self.dom.as_mut().expect("DOM已在上方校验");
+        for root in &roots {
+            let Some(root) = dom.reference_mut(root.as_str()) else { continue };
+            let mut stack: Vec<&mut Value> = vec![root];
+            while let Some(value) = stack.pop() {
+                match value {
+                    Value::Object(map) => {
+                        if let Some(position) = map.keys().position(|k| k == old) {
+                            let moved = map.shift_remove(old).expect("键已在上方找到");
+                            map.shift_insert(position, new.to_string(), moved);
+                        }
+                        stack.extend(map.values_mut());
+                    }
+                    Value::Array(items) => stack.extend(items.iter_mut()),
+                    _ => {}
+                }
+            }
+        }
+
+        self.tree_flat = build_shadow_tree_limited(dom, self.shadow_tree_options.max_nodes);
+        self.on_tree_rebuilt();
+        self.restore_node_flags(flags);
+        self.dirty = true;
+        self.notify_watchers(&renamed.iter().map(String::as_str).collect::<Vec<_>>());
+        Ok(renamed.len() / 2)
+    }
+
+    /// `tree_flat[index]` 的子树结束位置（不含）
+    fn subtree_end(&self, index: usize) -> usize {
+        let depth = self.tree_flat[index].depth;
+        self.tree_flat[index + 1..]
+            .iter()
+            .position(|n| n.depth <= depth)
+            .map_or(self.tree_flat.len(), |offset| index + 1 + offset)
+    }
+
+    /// 影子树中路径为 `path` 的节点下标
+    fn tree_index_of(&self, path: &str) -> Result<usize, AppError> {
+        self.tree_flat
+            .iter()
+            .position(|n| n.path == path)
+            .ok_or_else(|| AppError::JsonPath(JsonPathKind::NoMatch, format!("未找到节点: {}", path)))
+    }
+
+    /// `tree_flat[index]` 的父节点下标（根节点没有父节点）
+    fn tree_parent_index(&self, index: usize) -> Result<usize, AppError> {
+        let depth = self.tree_flat[index].depth;
+        (0..index)
+            .rev()
+            .find(|&i| depth > 0 && self.tree_flat[i].depth == depth - 1)
+            .ok_or_else(|| AppError::State(StateKind::Invalid, format!("未找到父节点: {}", self.tree_flat[index].path)))
+    }
+
+    /// 将 `[start, end)` 内数组 `parent_path` 的直接子元素（下标 >= `from_index`）重新编号 `delta`，并同步其后代路径
+    fn shift_array_children(&mut self, start: usize, end: usize, parent_path: &str, parent_depth: u32, from_index: usize, delta: isize) {
+        let mut prefixes: Option<(String, String)> = None;
+        for node in &mut self.tree_flat[start..end] {
+            if node.depth == parent_depth + 1 {
+                prefixes = node
+                    .name
+                    .trim_start_matches('[')
+                    .trim_end_matches(']')
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&i| i >= from_index)
+                    .map(|i| {
+                        let shifted = i.saturating_add_signed(delta);
+                        node.name = format!("[{}]", shifted);
+                        (format!("{}[{}]", parent_path, i), format!("{}[{}]", parent_path, shifted))
+                    });
+            }
+            if let Some((old, new)) = &prefixes {
+                if let Some(rest) = node.path.strip_prefix(old.as_str()) {
+                    node.path = format!("{}{}", new, rest);
+                }
+            }
+        }
+    }
+
+    /// 结构性编辑后刷新父节点的子元素数与预览
+    fn refresh_parent_node(&mut self, parent_index: usize) -> Result<(), AppError> {
+        let dom = self
+            .dom
+            .as_mut()
+            .ok_or_else(AppError::not_loaded)?;
+        let parent = Self::first_match_mut(dom, &self.tree_flat[parent_index].path)?;
+        let children = match parent {
+            Value::Object(map) => map.len(),
+            Value::Array(items) => items.len(),
+            _ => 0,
+        };
+        let preview = node_preview(parent);
+        let node = &mut self.tree_flat[parent_index];
+        node.children = children as u32;
+        node.preview = preview;
+        Ok(())
+    }
+
+    /// 删除影子树路径为 `path` 的节点，返回被删除的值；影子树局部更新（数组后续元素的下标前移）
+    pub fn delete_node(&mut self, path: &str) -> Result<Value, AppError> {
+        self.ensure_writable()?;
+        if self.dom.is_none() {
+            return Err(AppError::not_loaded());
+        }
+        let index = self.tree_index_of(path)?;
+        let depth = self.tree_flat[index].depth;
+        if depth == 0 {
+            return Err(AppError::State(StateKind::Invalid, "不能删除根节点".into()));
+        }
+        let parent_index = self.tree_parent_index(index)?;
+        let parent_path = self.tree_flat[parent_index].path.clone();
+        let name = self.tree_flat[index].name.clone();
+
+        self.push_undo();
+        let dom = self.dom.as_mut().expect("DOM已在上方校验");
+        let removed = Self::first_match_mut(dom, &parent_path).and_then(|parent| match parent {
+            Value::Object(map) => map.shift_remove(&name).ok_or_else(|| AppError::JsonPath(JsonPathKind::NoMatch, format!("未找到节点: {}", path))),
+            Value::Array(items) => name
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .parse::<usize>()
+                .ok()
+                .filter(|&i| i < items.len())
+                .map(|i| items.remove(i))
+                .ok_or_else(|| AppError::JsonPath(JsonPathKind::NoMatch, format!("未找到节点: {}", path))),
+            _ => Err(AppError::State(StateKind::Invalid, format!("父节点不是对象或数组: {}", parent_path))),
+        });
+        let removed = match removed {
+            Ok(removed) => removed,
+            Err(e) => {
+                self.undo_stack.pop();
+                return Err(e);
+            }
+        };
+
+        let end = self.subtree_end(index);
+        self.tree_flat.drain(index..end);
+        let mut changes = vec![WatchChange::Path(path)];
+        if self.tree_flat[parent_index].kind == NodeKind::Array {
+            let removed_index = name.trim_start_matches('[').trim_end_matches(']').parse::<usize>().unwrap_or(0);
+            let parent_end = self.subtree_end(parent_index);
+            self.shift_array_children(index, parent_end, &parent_path, depth - 1, removed_index + 1, -1);
+            changes.push(WatchChange::Shifted(&parent_path, removed_index));
+        }
+        self.refresh_parent_node(parent_index)?;
+        self.on_tree_rebuilt();
+        self.dirty = true;
+        self.notify_watcher_changes(&changes);
+        Ok(removed)
+    }
+
+    /// 在影子树路径为 `parent_path` 的对象/数组下插入子节点，返回新节点路径
+    ///
+    /// 对象按键名插入（键已存在时报错）；数组按下标插入（空字符串表示追加），后续元素下标后移
+    pub fn insert_child(&mut self, parent_path: &str, key_or_index: &str, value_json: &str) -> Result<String, AppError> {
+        self.ensure_writable()?;
+        if self.dom.is_none() {
+            return Err(AppError::not_loaded());
+        }
+        let value: Value = serde_json::from_str(value_json)?;
+        self.insert_value(parent_path, key_or_index, value)
+    }
+
+    /// 同 [`Self::insert_child`]，插入已解析的值
+    fn insert_value(&mut self, parent_path: &str, key_or_index: &str, value: Value) -> Result<String, AppError> {
+        let parent_index = self.tree_index_of(parent_path)?;
+        let parent = &self.tree_flat[parent_index];
+        let (parent_depth, parent_kind, parent_visible) = (parent.depth, parent.kind, parent.visible);
+        let parent_end = self.subtree_end(parent_index);
+        let direct_children: Vec<usize> = (parent_index + 1..parent_end)
+            .filter(|&i| self.tree_flat[i].depth == parent_depth + 1)
+            .collect();
+
+        let (name, path, position, array_index) = match parent_kind {
+            NodeKind::Object => {
+                if direct_children.iter().any(|&i| self.tree_flat[i].name == key_or_index) {
+                    return Err(AppError::State(StateKind::Conflict, format!("键已存在: {}", key_or_index)));
+                }
+                // 对象保留键的插入顺序，新键追加在末尾
+                (key_or_index.to_string(), field_path(parent_path, key_or_index), parent_end, None)
+            }
+            NodeKind::Array => {
+                let len = self.tree_flat[parent_index].children as usize;
+                let index = match key_or_index.trim() {
+                    "" => len,
+                    text => text
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|&i| i <= len)
+                        .ok_or_else(|| AppError::State(StateKind::Invalid, format!("无效的数组下标: {}（长度 {}）", text, len)))?,
+                };
+                let position = direct_children.get(index).copied().unwrap_or(parent_end);
+                (format!("[{}]", index), format!("{}[{}]", parent_path, index), position, Some(index))
+            }
+            _ => return Err(AppError::State(StateKind::Invalid, format!("只能在对象或数组下插入: {}", parent_path))),
+        };
+
+        self.push_undo();
+        let dom = self.dom.as_mut().expect("DOM已在上方校验");
+        let inserted = Self::first_match_mut(dom, parent_path).and_then(|parent| match parent {
+            Value::Object(map) => {
+                map.insert(name.clone(), value.clone());
+                Ok(())
+            }
+            Value::Array(items) => {
+                items.insert(array_index.unwrap_or(items.len()), value.clone());
+                Ok(())
+            }
+            _ => Err(AppError::State(StateKind::Invalid, format!("只能在对象或数组下插入: {}", parent_path))),
+        });
+        if let Err(e) = inserted {
+            self.undo_stack.pop();
+            return Err(e);
+        }
+
+        if let Some(index) = array_index {
+            self.shift_array_children(position, parent_end, parent_path, parent_depth, index, 1);
+        }
+        let mut nodes = build_subtree_nodes(&value, name, &path, parent_depth + 1);
+        for node in &mut nodes {
+            node.visible = parent_visible;
+        }
+        self.tree_flat.splice(position..position, nodes);
+        self.refresh_parent_node(parent_index)?;
+        self.on_tree_rebuilt();
+        self.dirty = true;
+        match array_index {
+            Some(index) => self.notify_watcher_changes(&[WatchChange::Shifted(parent_path, index)]),
+            None => self.notify_watchers(&[&path]),
+        }
+        Ok(path)
+    }
+
+    /// 生成带上下文的副本（不修改当前DOM）：每个字符串叶子替换为 `{"value": 原文, "context": 上下文}`
+    ///
+    /// 上下文由 `template` 渲染，`{value}` 替换为原文，`{path}` 替换为面包屑路径（如 `ui > items[0] > title`）
+    pub fn apply_context_format(&self, template: &str) -> Result<Value, AppError> {
+        let mut out = self
+            .dom
+            .clone()
+            .ok_or_else(AppError::not_loaded)?;
+
+        let mut stack: Vec<(&mut Value, String)> = vec![(&mut out, String::new())];
+        while let Some((value, breadcrumb)) = stack.pop() {
+            match value {
+                Value::Object(map) => stack.extend(map.iter_mut().map(|(k, v)| {
+                    let crumb = if breadcrumb.is_empty() { k.clone() } else { format!("{} > {}", breadcrumb, k) };
+                    (v, crumb)
+                })),
+                Value::Array(items) => {
+                    stack.extend(items.iter_mut().enumerate().map(|(i, v)| (v, format!("{}[{}]", breadcrumb, i))))
+                }
+                Value::String(text) => {
+                    let path = if breadcrumb.is_empty() { "$" } else { breadcrumb.as_str() };
+                    // 先按 {value} 切分再替换 {path}，原文中的占位符不会被二次替换
+                    let context = template
+                        .split("{value}")
+                        .map(|part| part.replace("{path}", path))
+                        .collect::<Vec<_>>()
+                        .join(text.as_str());
+                    let text = std::mem::take(text);
+                    *value = serde_json::json!({ "value": text, "context": context });
+                }
+                _ => {}
+            }
+        }
+        Ok(out)
+    }
+
+    /// 将 `json_path` 处的数字加上 `delta`，返回新值
+    pub fn increment_number(&mut self, json_path: &str, delta: f64) -> Result<f64, AppError> {
+        self.ensure_writable()?;
+        let dom = self
+            .dom
+            .as_mut()
+            .ok_or_else(AppError::not_loaded)?;
+
+        let slot = Self::first_match_mut(dom, json_path)?;
+        let current = slot
+            .as_f64()
+            .ok_or_else(|| AppError::JsonPath(JsonPathKind::WrongType, "not a number".into()))?;
+        let updated = current + delta;
+        *slot = Self::number_value(updated)?;
+
+        self.tree_flat = build_shadow_tree_limited(dom, self.shadow_tree_options.max_nodes);
+        self.on_tree_rebuilt();
+        self.dirty = true;
+        self.notify_watchers(&[json_path]);
+        Ok(updated)
+    }
+
+    /// 将路径上的字符串转换为数组：按 `split_on` 拆分（各段去除首尾空白、忽略空段），为 None 时作为单元素数组
+    pub fn convert_to_array(&mut self, json_path: &str, split_on: Option<&str>) -> Result<(), AppError> {
+        self.ensure_writable()?;
+        if split_on == Some("") {
+            return Err(AppError::State(StateKind::Invalid, "分隔符不能为空".into()));
+        }
+        self.convert_value_at(json_path, |value| {
+            let Value::String(text) = value else {
+                return Err(AppError::State(StateKind::Invalid, format!("只能将字符串转换为数组: {}", json_path)));
+            };
+            let items = match split_on {
+                Some(sep) => text
+                    .split(sep)
+                    .map(str::trim)
+                    .filter(|part| !part.is_empty())
+                    .map(|part| Value::String(part.to_string()))
+                    .collect(),
+                None => vec![Value::String(text.clone())],
+            };
+            Ok(Value::Array(items))
+        })
+    }
+
+    /// 将路径上的标量包装为对象 `{key_name: 值}`
+    pub fn convert_to_object(&mut self, json_path: &str, key_name: &str) -> Result<(), AppError> {
+        self.ensure_writable()?;
+        if key_name.is_empty() {
+            return Err(AppError::State(StateKind::Invalid, "键名不能为空".into()));
+        }
+        self.convert_value_at(json_path, |value| {
+            if value.is_object() || value.is_array() {
+                return Err(AppError::State(StateKind::Invalid, format!("只能包装标量值: {}", json_path)));
+            }
+            let mut map = serde_json::Map::new();
+            map.insert(key_name.to_string(), value.clone());
+            Ok(Value::Object(map))
+        })
+    }
+
+    /// 以 `convert` 的结果替换第一个匹配节点的值，记录撤销并重建影子树（保留展开状态）
+    fn convert_value_at(&mut self, json_path: &str, convert: impl FnOnce(&Value) -> Result<Value, AppError>) -> Result<(), AppError> {
+        let dom = self
+            .dom
+            .as_mut()
+            .ok_or_else(AppError::not_loaded)?;
+        let converted = convert(Self::first_match_mut(dom, json_path)?)?;
+
+        self.push_undo();
+        let flags = self.node_flags();
+        let dom = self.dom.as_mut().expect("DOM已在上方校验");
+        *Self::first_match_mut(dom, json_path)? = converted;
+        self.tree_flat = build_shadow_tree_limited(dom, self.shadow_tree_options.max_nodes);
+        self.on_tree_rebuilt();
+        self.restore_node_flags(flags);
+        self.dirty = true;
+        self.notify_watchers(&[json_path]);
+        Ok(())
+    }
+
+    /// 交换两个路径处的值（类型可以不同），记录撤销并只重建一次影子树；两个路径指向同一节点时不做任何修改
+    ///
+    /// 一方包含另一方（祖先与后代）时无法交换，返回错误
+    pub fn swap_values(&mut self, path_a: &str, path_b: &str) -> Result<(), AppError> {
+        self.ensure_writable()?;
+        let a = self.first_match(path_a)?;
+        let b = self.first_match(path_b)?;
+        if std::ptr::eq(a, b) {
+            return Ok(());
+        }
+        if subtree_contains(a, b) || subtree_contains(b, a) {
+            return Err(AppError::State(StateKind::Conflict, format!("节点之间存在包含关系，无法交换: {} 与 {}", path_a, path_b)));
+        }
+
+        self.push_undo();
+        let flags = self.node_flags();
+        let dom = self.dom.as_mut().expect("DOM已在上方校验");
+        let value_a = std::mem::take(Self::first_match_mut(dom, path_a)?);
+        let value_b = std::mem::replace(Self::first_match_mut(dom, path_b)?, value_a);
+        *Self::first_match_mut(dom, path_a)? = value_b;
+        self.tree_flat = build_shadow_tree_limited(dom, self.shadow_tree_options.max_nodes);
+        self.on_tree_rebuilt();
+        self.restore_node_flags(flags);
+        self.dirty = true;
+        self.notify_watchers(&[path_a, path_b]);
+        Ok(())
+    }
+
+    /// 数组元素 `path` 在父数组中的下标（从 0 开始）；节点不存在或父节点不是数组时为 None
+    pub fn get_sibling_index(&self, path: &str) -> Option<usize> {
+        let index = self.tree_index_of(path).ok()?;
+        let parent_index = self.tree_parent_index(index).ok()?;
+        if self.tree_flat[parent_index].kind != NodeKind::Array {
+            return None;
+        }
+        self.tree_flat[index].name.trim_start_matches('[').trim_end_matches(']').parse::<usize>().ok()
+    }
+
+    /// 将数组元素 `path` 移到父数组的 `new_index` 处，其间的兄弟元素依次前移或后移；影子树局部更新
+    pub fn reorder_sibling(&mut self, path: &str, new_index: usize) -> Result<(), AppError> {
+        self.ensure_writable()?;
+        if self.dom.is_none() {
+            return Err(AppError::not_loaded());
+        }
+        let index = self.tree_index_of(path)?;
+        let parent_index = self.tree_parent_index(index)?;
+        let parent = &self.tree_flat[parent_index];
+        let (parent_path, parent_depth, len) = (parent.path.clone(), parent.depth, parent.children as usize);
+        if parent.kind != NodeKind::Array {
+            return Err(AppError::JsonPath(JsonPathKind::WrongType, format!("父节点不是数组: {}", parent_path)));
+        }
+        let old_index = self.get_sibling_index(path).ok_or_else(|| AppError::JsonPath(JsonPathKind::NoMatch, format!("未找到节点: {}", path)))?;
+        if new_index >= len {
+            return Err(AppError::State(StateKind::Invalid, format!("目标下标 {} 超出数组长度 {}", new_index, len)));
+        }
+        if new_index == old_index {
+            return Ok(());
+        }
+
+        self.push_undo();
+        let dom = self.dom.as_mut().expect("DOM已在上方校验");
+        match Self::first_match_mut(dom, &parent_path) {
+            Ok(Value::Array(items)) if old_index < items.len() => {
+                let value = items.remove(old_index);
+                items.insert(new_index, value);
+            }
+            _ => {
+                self.undo_stack.pop();
+                return Err(AppError::JsonPath(JsonPathKind::NoMatch, format!("未找到节点: {}", path)));
+            }
+        }
+
+        // 取出被移动的子树，其余元素按删除再插入的方式重新编号
+        let end = self.subtree_end(index);
+        let mut moved: Vec<JsonTreeNode> = self.tree_flat.drain(index..end).collect();
+        let parent_end = self.subtree_end(parent_index);
+        self.shift_array_children(parent_index + 1, parent_end, &parent_path, parent_depth, old_index + 1, -1);
+        self.shift_array_children(parent_index + 1, parent_end, &parent_path, parent_depth, new_index, 1);
+        let (old_prefix, new_prefix) = (format!("{}[{}]", parent_path, old_index), format!("{}[{}]", parent_path, new_index));
+        for node in &mut moved {
+            if let Some(rest) = node.path.strip_prefix(old_prefix.as_str()) {
+                node.path = format!("{}{}", new_prefix, rest);
+            }
+        }
+        moved[0].name = format!("[{}]", new_index);
+        let next_sibling = format!("[{}]", new_index + 1);
+        let position = (parent_index + 1..parent_end)
+            .find(|&i| self.tree_flat[i].depth == parent_depth + 1 && self.tree_flat[i].name == next_sibling)
+            .unwrap_or(parent_end);
+        self.tree_flat.splice(position..position, moved);
+
+        self.refresh_parent_node(parent_index)?;
+        self.on_tree_rebuilt();
+        self.dirty = true;
+        self.notify_watcher_changes(&[WatchChange::Shifted(&parent_path, old_index.min(new_index))]);
+        Ok(())
+    }
+
+    /// 检测DOM中是否存在环（同一节点在根到当前节点的路径上出现两次）
+    ///
+    /// 解析得到的 `Value` 是所有权树，不会成环；此检查用于防御以非常规方式构造的DOM
+    pub fn detect_circular_references(&self) -> bool {
+        let Some(dom) = self.dom.as_ref() else {
+            return false;
+        };
+        // 显式栈的深度优先遍历：(节点, 是否为离开标记)，离开时从当前路径移除
+        let mut stack: Vec<(&Value, bool)> = vec![(dom, false)];
+        let mut on_path: HashSet<*const Value> = HashSet::new();
+        while let Some((value, leaving)) = stack.pop() {
+            let ptr = value as *const Value;
+            if leaving {
+                on_path.remove(&ptr);
+                continue;
+            }
+            if !on_path.insert(ptr) {
+                return true;
+            }
+            stack.push((value, true));
+            match value {
+                Value::Object(map) => stack.extend(map.values().map(|child| (child, false))),
+                Value::Array(items) => stack.extend(items.iter().map(|child| (child, false))),
+                _ => {}
+            }
+        }
+        false
+    }
+
+    /// 深拷贝 `path` 处的节点到同级位置，返回新节点路径
+    ///
+    /// 数组元素复制到紧随其后的下标；对象字段复制到 `new_key`，未指定时自动命名为 `键名_copy`、`键名_copy2`……
+    pub fn duplicate_node(&mut self, path: &str, new_key: Option<String>) -> Result<String, AppError> {
+        self.ensure_writable()?;
+        let value = self.get_value_at_path(path)?;
+        let index = self.tree_index_of(path)?;
+        if self.tree_flat[index].depth == 0 {
+            return Err(AppError::State(StateKind::Invalid, "不能复制根节点".into()));
+        }
+        let parent_index = self.tree_parent_index(index)?;
+        let parent_path = self.tree_flat[parent_index].path.clone();
+        let name = self.tree_flat[index].name.clone();
+
+        match self.tree_flat[parent_index].kind {
+            NodeKind::Array => {
+                if new_key.is_some() {
+                    return Err(AppError::State(StateKind::Invalid, "数组元素不能指定键名".into()));
+                }
+                let position = name.trim_start_matches('[').trim_end_matches(']').parse::<usize>().unwrap_or(0) + 1;
+                self.insert_value(&parent_path, &position.to_string(), value)
+            }
+            _ => {
+                let key = match new_key {
+                    Some(key) => key,
+                    None => {
+                        let parent_end = self.subtree_end(parent_index);
+                        let depth = self.tree_flat[index].depth;
+                        let siblings: HashSet<&str> = self.tree_flat[parent_index + 1..parent_end]
+                            .iter()
+                            .filter(|n| n.depth == depth)
+                            .map(|n| n.name.as_str())
+                            .collect();
+                        (1..)
+                            .map(|n| if n == 1 { format!("{}_copy", name) } else { format!("{}_copy{}", name, n) })
+                            .find(|candidate| !siblings.contains(candidate.as_str()))
+                            .expect("候选键名无穷多")
+                    }
+                };
+                self.insert_value(&parent_path, &key, value)
+            }
+        }
+    }
+
+    fn field_index_key(array_path: &str, key_field: &str) -> String {
+        format!("{}#{}", array_path, key_field)
+    }
+
+    /// 为 `array_path` 处数组按元素的 `key_field` 字段值建立索引，之后可用 [`Self::lookup_by_field`] 常数时间查找
+    ///
+    /// 字符串/数字/布尔键值参与索引，缺少该字段或值为其他类型的元素被跳过；键值重复时报错
+    pub fn index_by_field(&mut self, array_path: &str, key_field: &str) -> Result<(), AppError> {
+        let dom = self
+            .dom
+            .as_ref()
+            .ok_or_else(AppError::not_loaded)?;
+        let hits = dom.query(array_path).map_err(|e| AppError::JsonPath(JsonPathKind::Invalid, e.to_string()))?;
+        let items = hits
+            .into_iter()
+            .next()
+            .ok_or_else(|| AppError::JsonPath(JsonPathKind::NoMatch, format!("未匹配到任何节点: {}", array_path)))?
+            .as_array()
+            .ok_or_else(|| AppError::State(StateKind::Invalid, format!("节点不是数组: {}", array_path)))?;
+
+        let mut index = HashMap::with_capacity(items.len());
+        let mut duplicates = Vec::new();
+        for (i, item) in items.iter().enumerate() {
+            let key = match item.get(key_field) {
+                Some(Value::String(s)) => s.clone(),
+                Some(Value::Number(n)) => n.to_string(),
+                Some(Value::Bool(b)) => b.to_string(),
+                _ => continue,
+            };
+            match index.entry(key) {
+                std::collections::hash_map::Entry::Occupied(entry) => duplicates.push(entry.key().clone()),
+                std::collections::hash_map::Entry::Vacant(entry) => {
+                    entry.insert(format!("{}[{}]", array_path, i));
+                }
+            }
+        }
+        if !duplicates.is_empty() {
+            return Err(AppError::State(StateKind::Conflict, format!("键字段 {} 的值重复: {}", key_field, duplicates.join(", "))));
+        }
+        self.field_indices.insert(Self::field_index_key(array_path, key_field), index);
+        Ok(())
+    }
+
+    /// 按键字段值查找数组元素路径（需先调用 [`Self::index_by_field`]；索引在DOM变更后失效）
+    pub fn lookup_by_field(&self, array_path: &str, key_field: &str, key_value: &str) -> Option<String> {
+        self.field_indices
+            .get(&Self::field_index_key(array_path, key_field))?
+            .get(key_value)
+            .cloned()
+    }
+
+    /// 分析 `path` 处数组的元素结构：各键的出现次数与取值类型，标记缺失或类型不一致的键
+    pub fn array_schema_report(&self, path: &str) -> Result<SchemaReport, AppError> {
+        let dom = self
+            .dom
+            .as_ref()
+            .ok_or_else(AppError::not_loaded)?;
+        let hits = dom.query(path).map_err(|e| AppError::JsonPath(JsonPathKind::Invalid, e.to_string()))?;
+        let target = hits
+            .into_iter()
+            .next()
+            .ok_or_else(|| AppError::JsonPath(JsonPathKind::NoMatch, format!("未匹配到任何节点: {}", path)))?;
+        let items = target
+            .as_array()
+            .ok_or_else(|| AppError::State(StateKind::Invalid, format!("节点不是数组: {}", path)))?;
+        Ok(analyze_array(path, items))
+    }
+
+    /// 统计作用域内字符串叶子值的出现次数，返回前 `top_n` 个（次数降序，次数相同按值排序）
+    ///
+    /// `scope_path` 为 None 时统计整个文档
+    pub fn value_histogram(&self, scope_path: Option<&str>, top_n: usize) -> Result<Vec<(String, usize)>, AppError> {
+        self.value_histogram_with_options(scope_path, top_n, false)
+    }
+
+    /// 同 [`Self::value_histogram`]，`case_fold` 为真时忽略大小写合并计数（结果为小写形式）
+    pub fn value_histogram_with_options(
+        &self,
+        scope_path: Option<&str>,
+        top_n: usize,
+        case_fold: bool,
+    ) -> Result<Vec<(String, usize)>, AppError> {
+        let dom = self
+            .dom
+            .as_ref()
+            .ok_or_else(AppError::not_loaded)?;
+        let mut stack: Vec<&Value> = match scope_path {
+            Some(path) => dom.query(path).map_err(|e| AppError::JsonPath(JsonPathKind::Invalid, e.to_string()))?,
+            None => vec![dom],
+        };
+        if stack.is_empty() {
+            return Err(AppError::JsonPath(JsonPathKind::NoMatch, "未匹配到任何节点".into()));
+        }
+
+        // 计数键借用DOM中的字符串，只有需要大小写折叠时才分配
+        let mut counts: HashMap<std::borrow::Cow<'_, str>, usize> = HashMap::new();
+        while let Some(value) = stack.pop() {
+            match value {
+                Value::String(s) => {
+                    let key = if case_fold && s.chars().any(char::is_uppercase) {
+                        std::borrow::Cow::Owned(s.to_lowercase())
+                    } else {
+                        std::borrow::Cow::Borrowed(s.as_str())
+                    };
+                    *counts.entry(key).or_insert(0) += 1;
+                }
+                Value::Array(items) => stack.extend(items.iter()),
+                Value::Object(map) => stack.extend(map.values()),
+                _ => {}
+            }
+        }
+
+        let mut entries: Vec<_> = counts.into_iter().collect();
+        entries.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        entries.truncate(top_n);
+        Ok(entries.into_iter().map(|(value, count)| (value.into_owned(), count)).collect())
+    }
+
+    /// 字符串内容恰好等于 `value` 的叶子节点数
+    pub fn count_occurrences_of_value(&self, value: &str) -> usize {
+        let Some(dom) = self.dom.as_ref() else {
+            return 0;
+        };
+        let mut count = 0;
+        let mut stack = vec![dom];
+        while let Some(v) = stack.pop() {
+            match v {
+                Value::String(s) if s == value => count += 1,
+                Value::Array(items) => stack.extend(items.iter()),
+                Value::Object(map) => stack.extend(map.values()),
+                _ => {}
+            }
+        }
+        count
+    }
+
+    /// 整个文档中出现最多的 `top_n` 个字符串值（次数降序），可用于发现未翻译的占位文本；未加载时为空
+    pub fn find_most_common_values(&self, top_n: usize) -> Vec<(String, usize)> {
+        self.value_histogram(None, top_n).unwrap_or_default()
+    }
+
+    /// 对DOM中所有字符串值依次应用变换流水线，只重建一次影子树，返回各变换的修改计数
+    pub fn apply_value_pipeline(&mut self, pipeline: Vec<ValueTransform>) -> Result<PipelineResult, AppError> {
+        self.ensure_writable()?;
+        let transforms = CompiledTransform::compile(pipeline)?;
+        let dom = self
+            .dom
+            .as_mut()
+            .ok_or_else(AppError::not_loaded)?;
+
+        let mut result = PipelineResult {
+            changes_per_transform: vec![0; transforms.len()],
+            nodes_changed: 0,
+        };
+        // 显式栈遍历，避免深层嵌套导致栈溢出
+        let mut stack = vec![&mut *dom];
+        while let Some(value) = stack.pop() {
+            match value {
+                Value::String(s) => {
+                    let original = s.clone();
+                    for (i, t) in transforms.iter().enumerate() {
+                        if let Some(changed) = t.apply(s) {
+                            *s = changed;
+                            result.changes_per_transform[i] += 1;
+                        }
+                    }
+                    if *s != original {
+                        result.nodes_changed += 1;
+                    }
+                }
+                Value::Array(items) => stack.extend(items.iter_mut()),
+                Value::Object(map) => stack.extend(map.values_mut()),
+                _ => {}
+            }
+        }
+
+        if result.nodes_changed > 0 {
+            self.tree_flat = build_shadow_tree_limited(dom, self.shadow_tree_options.max_nodes);
+            self.on_tree_rebuilt();
+            self.dirty = true;
+            self.notify_watchers(&["$"]);
+        }
+        Ok(result)
+    }
+
+    /// 将 f64 转为 JSON 数字：整数值保持整数形式
+    fn number_value(n: f64) -> Result<Value, AppError> {
+        if n.fract() == 0.0 && n >= i64::MIN as f64 && n <= i64::MAX as f64 {
+            return Ok(Value::from(n as i64));
+        }
+        serde_json::Number::from_f64(n)
+            .map(Value::Number)
+            .ok_or_else(|| AppError::State(StateKind::Invalid, format!("无法表示的数值: {}", n)))
+    }
+
+    /// 解析 JSONPath 并返回第一个匹配节点的可变引用
+    pub(crate) fn first_match_mut<'a>(dom: &'a mut Value, json_path: &str) -> Result<&'a mut Value, AppError> {
+        query_first_mut(dom, json_path)?.ok_or_else(|| AppError::JsonPath(JsonPathKind::NoMatch, "未匹配到任何节点".into()))
+    }
+
+    /// 应用搜索过滤，只显示匹配路径的节点
+    pub fn apply_search_filter(&mut self, filter: &str) {
+        self.apply_search_filters(&[filter.to_string()]);
+    }
+
+    /// 应用多个搜索过滤条件（任一命中即显示，不区分大小写），空白条件被忽略
+    ///
+    /// 每个条件按 [`SearchQuery`] 语法解析：空格分隔的词需全部命中，`-词` 排除，双引号包裹短语
+    pub(crate) fn apply_search_filters(&mut self, filters: &[String]) {
+        let queries = substring_queries(filters);
+        let hits = |node: &JsonTreeNode| substring_matches(&queries, node);
+        if queries.is_empty() {
+            // 清空过滤，显示所有节点
+            for node in &mut self.tree_flat {
+                node.visible = true;
+            }
+        } else if let Some(candidates) = queries.iter().map(|q| self.query_candidates(q)).collect::<Option<Vec<_>>>() {
+            // 索引命中：只需校验各条件最短倒排表中的候选节点
+            for node in &mut self.tree_flat {
+                node.visible = false;
+            }
+            for i in candidates.into_iter().flatten() {
+                let node = &mut self.tree_flat[i];
+                node.visible = hits(node);
+            }
+        } else {
+            // 简化的快速搜索 - 只做简单的字符串匹配
+            for node in &mut self.tree_flat {
+                node.visible = hits(node);
+            }
+        }
+    }
+
+    /// 按指定策略搜索：匹配的节点设为可见、其余隐藏，并返回匹配列表（空查询时全部可见、返回空列表）
+    ///
+    /// 子串策略的查询按 `,`/`|` 拆分为多个条件且不区分大小写；模糊策略结果按得分降序，其余按影子树顺序。
+    /// 所用策略记录在 [`AppState::search_mode`] 中
+    pub fn search(&mut self, query: &str, mode: SearchMode) -> Result<Vec<SearchMatch>, AppError> {
+        self.search_mode = mode;
+        let query = query.trim();
+        if query.is_empty() {
+            for node in &mut self.tree_flat {
+                node.visible = true;
+            }
+            return Ok(Vec::new());
+        }
+
+        // 影子树截断时在按DOM构建的完整节点列表上匹配，已构建部分按下标同步可见状态
+        let full = self.truncated.then(|| self.untruncated_nodes().into_owned());
+        if mode == SearchMode::Substring && full.is_none() {
+            self.apply_search_filters(&parse_filter_list(query));
+        }
+        let nodes: &[JsonTreeNode] = full.as_deref().unwrap_or(&self.tree_flat);
+        // 未截断时子串命中已由 apply_search_filters（可走索引）写入可见状态
+        let hits = self.search_hits(query, mode, nodes, full.is_none())?;
+
+        for (node, hit) in self.tree_flat.iter_mut().zip(&hits) {
+            if !node.is_truncation_sentinel() {
+                node.visible = hit.is_some();
+            }
+        }
+        let nodes: &[JsonTreeNode] = full.as_deref().unwrap_or(&self.tree_flat);
+        Ok(collect_search_matches(nodes, hits, mode))
+    }
+
+    /// 按指定策略匹配节点但不改变可见状态，结果与 [`AppState::search`] 返回的匹配列表相同
+    pub(crate) fn search_matches(&self, query: &str, mode: SearchMode) -> Result<Vec<SearchMatch>, AppError> {
+        let query = query.trim();
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+        let nodes = self.untruncated_nodes();
+        let hits = self.search_hits(query, mode, &nodes, false)?;
+        Ok(collect_search_matches(&nodes, hits, mode))
+    }
+
+    /// 逐节点的命中结果 `(得分, 命中类型)`；`substring_visible` 为真时子串策略直接沿用节点的可见状态
+    fn search_hits(&self, query: &str, mode: SearchMode, nodes: &[JsonTreeNode], substring_visible: bool) -> Result<Vec<Option<(f32, MatchKind)>>, AppError> {
+        Ok(match mode {
+            SearchMode::Substring => {
+                let queries: Vec<SearchQuery> = parse_filter_list(query).iter().map(|f| SearchQuery::parse(f).to_lowercase()).collect();
+                let active: Vec<&SearchQuery> = queries.iter().filter(|q| !q.is_empty()).collect();
+                nodes
+                    .iter()
+                    .map(|n| {
+                        let (path, name) = (n.path.to_lowercase(), n.name.to_lowercase());
+                        let hit = match substring_visible {
+                            true => n.visible,
+                            false => active.is_empty() || active.iter().any(|q| q.matches(&[&path, &name])),
+                        };
+                        let kind = if queries.iter().any(|q| q.matches(&[&name])) { MatchKind::Key } else { MatchKind::Path };
+                        hit.then_some((1.0, kind))
+                    })
+                    .collect()
+            }
+            SearchMode::Regex => {
+                let re = regex::Regex::new(query)
+                    .map_err(|e| AppError::State(StateKind::Invalid, format!("无效的正则表达式 {}: {}", query, e)))?;
+                nodes
+                    .iter()
+                    .map(|n| {
+                        if re.is_match(&n.name) {
+                            Some((1.0, MatchKind::Key))
+                        } else {
+                            re.is_match(&n.path).then_some((1.0, MatchKind::Path))
+                        }
+                    })
+                    .collect()
+            }
+            SearchMode::Fuzzy => nodes
+                .iter()
+                .map(|n| {
+                    fuzzy_score(query, &n.name)
+                        .map(|score| (score, MatchKind::Key))
+                        // 仅路径命中时降低得分
+                        .or_else(|| fuzzy_score(query, &n.path).map(|score| (score * 0.5, MatchKind::Path)))
+                })
+                .collect(),
+            SearchMode::ExactKey => nodes.iter().map(|n| (n.name == query).then_some((1.0, MatchKind::Key))).collect(),
+            SearchMode::ValueContains => {
+                let paths = self.paths_with_value_containing(query)?;
+                nodes
+                    .iter()
+                    .map(|n| paths.contains(n.path.as_str()).then_some((1.0, MatchKind::Value)))
+                    .collect()
+            }
+            SearchMode::PathPattern => {
+                let pattern = compile_path_pattern(query)
+                    .map_err(|e| AppError::State(StateKind::Invalid, format!("无效的路径模式 {}: {}", query, e)))?;
+                nodes.iter().map(|n| pattern.matches(&n.path).then_some((1.0, MatchKind::Path))).collect()
+            }
+        })
+    }
+
+    /// 按路径通配模式（见 [`SearchMode::PathPattern`]）过滤结构树，只比较路径字符串，返回匹配的节点数
+    pub fn search_by_path_pattern(&mut self, glob: &str) -> Result<usize, AppError> {
+        Ok(self.search(glob, SearchMode::PathPattern)?.len())
+    }
+
+    /// 标量值（字符串/数字/布尔）包含 `needle` 的节点路径
+    fn paths_with_value_containing(&self, needle: &str) -> Result<HashSet<String>, AppError> {
+        let dom = self.dom.as_ref().ok_or_else(AppError::not_loaded)?;
+        let mut out = HashSet::new();
+        let mut stack: Vec<(&Value, String)> = vec![(dom, "$".to_string())];
+        while let Some((value, path)) = stack.pop() {
+            match value {
+                Value::Object(map) => stack.extend(map.iter().map(|(k, child)| (child, field_path(&path, k)))),
+                Value::Array(items) => stack.extend(items.iter().enumerate().map(|(i, child)| (child, format!("{}[{}]", path, i)))),
+                Value::String(s) if s.contains(needle) => {
+                    out.insert(path);
+                }
+                Value::Number(_) | Value::Bool(_) if value.to_string().contains(needle) => {
+                    out.insert(path);
+                }
+                _ => {}
+            }
+        }
+        Ok(out)
+    }
+
+    /// 为 name/path 建立字节三元组倒排索引，加速重复搜索
+    pub fn build_search_index(&mut self) {
+        let mut index: HashMap<[u8; 3], Vec<usize>> = HashMap::new();
+        for (i, node) in self.tree_flat.iter().enumerate() {
+            // 按小写建立索引，与不区分大小写的子串搜索一致
+            for text in [node.name.to_lowercase(), node.path.to_lowercase()] {
+                for w in text.as_bytes().windows(3) {
+                    let postings = index.entry([w[0], w[1], w[2]]).or_default();
+                    if postings.last() != Some(&i) {
+                        postings.push(i);
+                    }
+                }
+            }
+        }
+        self.search_index = Some(index);
+    }
+
+    /// 释放搜索索引占用的内存
+    pub fn drop_search_index(&mut self) {
+        self.search_index = None;
+    }
+
+    /// 查询的候选节点下标：取各包含词中最短的倒排表；只有排除词时返回 None
+    fn query_candidates(&self, query: &SearchQuery) -> Option<Vec<usize>> {
+        query
+            .include
+            .iter()
+            .filter_map(|term| self.search_candidates(term))
+            .min_by_key(Vec::len)
+    }
+
+    /// 通过索引获取可能匹配的节点下标（最短倒排表）；无索引或过滤词不足3字节时返回 None
+    fn search_candidates(&self, filter: &str) -> Option<Vec<usize>> {
+        let index = self.search_index.as_ref()?;
+        let bytes = filter.as_bytes();
+        if bytes.len() < 3 {
+            return None;
+        }
+        let mut shortest: &[usize] = &[];
+        for (n, w) in bytes.windows(3).enumerate() {
+            // 任一三元组不存在即无匹配
+            let postings = index.get(&[w[0], w[1], w[2]]).map(Vec::as_slice).unwrap_or(&[]);
+            if n == 0 || postings.len() < shortest.len() {
+                shortest = postings;
+            }
+            if shortest.is_empty() {
+                break;
+            }
+        }
+        Some(shortest.to_vec())
+    }
+
+    /// 影子树被截断时记录警告：基于影子树的操作不会覆盖未展示的节点
+    /// 影子树重建后清空依赖节点下标/路径的缓存；已启用的搜索索引随之重建
+    fn on_tree_rebuilt(&mut self) {
+        self.truncated = self.tree_flat.last().is_some_and(JsonTreeNode::is_truncation_sentinel);
+        self.value_len_cache.get_mut().clear();
+        // 局部更新的影子树中祖先节点的大小已过时
+        self.tree_flat.iter_mut().for_each(|node| node.size_bytes = 0);
+        self.field_indices.clear();
+        if self.search_index.is_some() {
+            self.build_search_index();
+        }
+        if let Some(dom) = &self.dom {
+            self.annotations.retain(|path, _| parse_segments(path).is_some_and(|segments| resolve(dom, &segments).is_some()));
+        }
+        self.apply_annotations();
+    }
+
+    /// 提取搜索匹配的节点JSON内容，智能限制结果数量以优化性能
+    pub fn extract_search_results(&self, filter: &str) -> Result<String, AppError> {
+        if filter.trim().is_empty() {
+            return Ok("".to_string());
+        }
+
+        // 确保DOM已加载
+        self.dom
+            .as_ref()
+            .ok_or_else(AppError::not_loaded)?;
+
+        // 收集所有匹配的可见节点
+        let matched_nodes = self.search_result_nodes(filter)?;
+
+        if matched_nodes.is_empty() {
+            tracing::warn!("未找到匹配的可见节点，过滤条件: {}", filter);
+            return Ok("{}".to_string());
+        }
+
+        tracing::info!("找到 {} 个匹配节点", matched_nodes.len());
+
+        // 如果只有一个匹配节点，直接返回其完整内容
+        if matched_nodes.len() == 1 {
+            let node = &matched_nodes[0];
+            tracing::info!("单个匹配节点: {} (路径: {})", node.name, node.path);
+            let result = self.extract_subtree_pretty(&node.path);
+            tracing::info!("提取结果: {:?}", result.as_ref().map(|s| s.len()));
+            return result;
+        }
+
+        // 多个匹配节点：复制全部场景需要完整输出（不限制数量、不截断内容）
+        let display_count = matched_nodes.len();
+        let mut search_results = serde_json::Map::new();
+
+        for (index, node) in matched_nodes.iter().take(display_count).enumerate() {
+            match self.extract_subtree_pretty(&node.path) {
+                Ok(json_content) => {
+
+                    // 解析JSON内容以便重新组织
+                    match serde_json::from_str::<Value>(&json_content) {
+                        Ok(parsed_value) => {
+                            let result_key = format!("match_{}_{}", index + 1, node.name);
+                            let result_entry = serde_json::json!({
+                                "path": node.path,
+                                "name": node.name,
+                                "type": format!("{:?}", node.kind),
+                                "content": parsed_value
+                            });
+                            search_results.insert(result_key, result_entry);
+                        }
+                        Err(_) => {
+                            // 如果解析失败，直接存储为字符串
+                            let result_key = format!("match_{}_{}", index + 1, node.name);
+                            let result_entry = serde_json::json!({
+                                "path": node.path,
+                                "name": node.name,
+                                "type": format!("{:?}", node.kind),
+                                "content": json_content
+                            });
+                            search_results.insert(result_key, result_entry);
+                        }
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("提取节点 {} 失败: {}", node.path, e);
+                    let result_key = format!("match_{}_{}_error", index + 1, node.name);
+                    let result_entry = serde_json::json!({
+                        "path": node.path,
+                        "name": node.name,
+                        "type": format!("{:?}", node.kind),
+                        "error": e.to_string()
+                    });
+                    search_results.insert(result_key, result_entry);
+                }
+            }
+        }
+
+        let final_result = serde_json::json!({
+            "search_filter": filter,
+            "total_matches": matched_nodes.len(),
+            "displayed_matches": display_count,
+            "truncated": false,
+            "results": search_results
+        });
+
+        let pretty_result = serde_json::to_string_pretty(&final_result)?;
+        tracing::info!("搜索结果构建完成，显示 {}/{} 个匹配，总长度: {} 字符",
+                      display_count, matched_nodes.len(), pretty_result.len());
+
+        Ok(pretty_result)
+    }
+
+    /// 按当前搜索策略（[`AppState::search_mode`]）命中过滤条件的节点，与界面上 [`AppState::search`] 的结果一致
+    fn search_result_nodes(&self, filter: &str) -> Result<Vec<JsonTreeNode>, AppError> {
+        Ok(self.search_matches(filter, self.search_mode)?.into_iter().map(|m| m.node).collect())
+    }
+
+    /// 将搜索结果（同 [`AppState::extract_search_results`]）原子写入 JSON 文件，返回写入的匹配数
+    pub fn save_search_results(&self, filter: &str, output_path: &Path) -> Result<usize, AppError> {
+        if filter.trim().is_empty() {
+            return Err(AppError::State(StateKind::Invalid, "搜索条件为空".into()));
+        }
+        self.ensure_target_writable(output_path)?;
+        let text = self.extract_search_results(filter)?;
+        let value: Value = serde_json::from_str(&text)?;
+        write_json_file_atomic(output_path, &value)?;
+        Ok(self.search_result_nodes(filter)?.len())
+    }
+
+    /// 构建“中间产物 第二阶段”：按过滤条件枚举命中项，派生并提取同层级的 name 字段值，生成带连续序号的清单
+    pub fn build_intermediate_stage2<F>(&self, filter: &str, progress_callback: F) -> Result<String, AppError>
+    where
+        F: FnMut(f32, &str),
+    {
+        self.build_intermediate_stage2_with_leaf_filter(filter, false, progress_callback)
+    }
+
+    /// 构建"中间产物 第二阶段"：支持叶子节点过滤的版本
+    pub fn build_intermediate_stage2_with_leaf_filter<F>(&self, filter: &str, leaf_nodes_only: bool, progress_callback: F) -> Result<String, AppError>
+    where
+        F: FnMut(f32, &str),
+    {
+        let options = Stage2Options { leaf_nodes_only, ..Stage2Options::default() };
+        self.build_intermediate_stage2_with_options(filter, options, progress_callback)
+    }
+
+    /// 构建"中间产物 第二阶段"：支持叶子节点过滤与排序方式，排序在分配 seq 之前应用
+    pub fn build_intermediate_stage2_with_options<F>(&self, filter: &str, options: Stage2Options, progress_callback: F) -> Result<String, AppError>
+    where
+        F: FnMut(f32, &str),
+    {
+        self.build_intermediate_stage2_multi(&[filter.to_string()], options, progress_callback)
+    }
+
+    /// 构建"中间产物 第二阶段"：多个过滤条件取并集，每个节点只收录一次并在 `matched_by` 中记录首个命中的条件，seq 全局连续
+    pub fn build_intermediate_stage2_multi<F>(&self, filters: &[String], options: Stage2Options, mut progress_callback: F) -> Result<String, AppError>
+    where
+        F: FnMut(f32, &str),
+    {
+        let leaf_nodes_only = options.leaf_nodes_only;
+        let filters = stage_filters(filters);
+        if filters.is_empty() {
+            return Ok("".to_string());
+        }
+
+        tracing::info!("build_intermediate_stage2: 开始执行");
+        progress_callback(0.1, "开始分析匹配节点...");
+        tracing::info!("build_intermediate_stage2: 进度回调 0.1 调用完成");
+
+        let dom = self
+            .dom
+            .as_ref()
+            .ok_or_else(AppError::not_loaded)?;
+        tracing::info!("build_intermediate_stage2: DOM获取成功");
+
+        // 收集所有可见且匹配的节点
+        let match_start = std::time::Instant::now();
+        let nodes = self.untruncated_nodes();
+        let matched = match_stage_nodes(&nodes, &filters, leaf_nodes_only);
+        let match_time = match_start.elapsed().as_millis();
+
+        tracing::info!("build_intermediate_stage2: 找到 {} 个匹配节点，耗时: {}ms", matched.len(), match_time);
+        // 优化：减少进度回调频率，直接跳到50%
+        progress_callback(0.5, &format!("正在处理 {} 个匹配节点...", matched.len()));
+        tracing::info!("build_intermediate_stage2: 进度回调 0.5 调用完成");
+
+        let (items, excluded) = self.stage2_items(dom, matched, &options, &mut progress_callback);
+        let items_with_seq = number_stage2_items(items, options.sort);
+
+        let format_start = std::time::Instant::now();
+        // 优化：移除中间进度回调，减少UI更新频率
+        let mut result = serde_json::json!({
+            "stage": "intermediate2",
+            "filter": filters.iter().map(|(f, _)| *f).collect::<Vec<_>>().join(" | "),
+            "filters": filters.iter().map(|(f, _)| *f).collect::<Vec<_>>(),
+            "sort": options.sort,
+            FINGERPRINT_KEY: fingerprint_value(dom),
+            "count": items_with_seq.len(),
+            "items": items_with_seq,
+        });
+        if let Some(field) = &options.anchor_field {
+            result[ANCHOR_FIELD_KEY] = serde_json::json!(field);
+        }
+        if let Some(max) = options.max_value_chars {
+            result["max_value_chars"] = serde_json::json!(max);
+            result["excluded_count"] = serde_json::json!(excluded);
+        }
+        let format_time = format_start.elapsed().as_millis();
+        tracing::info!("build_intermediate_stage2: JSON格式化完成，耗时: {}ms", format_time);
+
+        progress_callback(1.0, "完成");
+        tracing::info!("build_intermediate_stage2: 进度回调 1.0 调用完成");
+        let result_str = to_string_wrapped_with_indent(&result, DEFAULT_MAX_INLINE_WIDTH, self.save_options.indent_size);
+        tracing::info!("build_intermediate_stage2: 执行完成，返回结果");
+        Ok(result_str)
+    }
+
+    /// 预览中间产物2：扫描结构树统计命中数，只为文档顺序中的前 `limit` 个命中节点查询DOM并构建条目
+    ///
+    /// 过滤文本与正式构建一样按 [`parse_filter_list`] 拆分为多个条件取并集；
+    /// 按路径/原文排序时只在这 `limit` 条之内排序；`estimated_total` 为结构树扫描得到的命中数（未扣除被排除的超长值）
+    pub fn preview_stage2(&self, filter: &str, options: Stage2Options, limit: usize) -> Result<String, AppError> {
+        let filter_list = parse_filter_list(filter);
+        let filters = stage_filters(&filter_list);
+        if filters.is_empty() {
+            return Ok(String::new());
+        }
+        let dom = self
+            .dom
+            .as_ref()
+            .ok_or_else(AppError::not_loaded)?;
+
+        let nodes = self.untruncated_nodes();
+        let mut matched = match_stage_nodes(&nodes, &filters, options.leaf_nodes_only);
+        let estimated_total = matched.len();
+        matched.truncate(limit);
+        let (items, _) = self.stage2_items(dom, matched, &options, &mut |_, _| {});
+        let items = number_stage2_items(items, options.sort);
+        tracing::info!("预览中间产物2: {} 条（共约 {} 个命中）", items.len(), estimated_total);
+
+        let result = serde_json::json!({
+            "stage": "intermediate2_preview",
+            "preview": format!("仅预览前 {} 条，完整结果约 {} 条", items.len(), estimated_total),
+            "filter": filters.iter().map(|(f, _)| *f).collect::<Vec<_>>().join(" | "),
+            "sort": options.sort,
+            "estimated_total": estimated_total,
+            "count": items.len(),
+            "items": items,
+        });
+        Ok(to_string_wrapped_with_indent(&result, DEFAULT_MAX_INLINE_WIDTH, self.save_options.indent_size))
+    }
+
+    /// 按命中节点构建中间产物2条目（查询DOM、派生 `name_path`、上下文与锚点），返回条目与被排除的超长值个数
+    fn stage2_items(
+        &self,
+        dom: &Value,
+        matched: Vec<(&JsonTreeNode, &str)>,
+        options: &Stage2Options,
+        progress_callback: &mut dyn FnMut(f32, &str),
+    ) -> (Vec<Value>, usize) {
+        // 派生 name 的 JSONPath
+        fn derive_name_path(src: &str) -> Option<String> {
+            // 寻找最后一个不在 [] 中的 '.'（按字节下标；引号内的方括号与转义字符不计）
+            let mut depth = 0i32;
+            let mut quote = None;
+            let mut escaped = false;
+            let mut last_dot = None;
+            for (i, ch) in src.char_indices() {
+                match (quote, ch) {
+                    (Some(_), _) if escaped => escaped = false,
+                    (Some(_), '\\') => escaped = true,
+                    (Some(q), c) if c == q => quote = None,
+                    (Some(_), _) => {}
+                    (None, '\'' | '"') if depth > 0 => quote = Some(ch),
+                    (None, '[') => depth += 1,
+                    (None, ']') => depth -= 1,
+                    (None, '.') if depth == 0 => last_dot = Some(i),
+                    _ => {}
+                }
+            }
+            last_dot.map(|idx| format!("{}{}", &src[..idx], ".name"))
+        }
+
+        // 批量解析所有命中节点的值，缓存结果
+        let mut path_to_value: std::collections::HashMap<String, Option<serde_json::Value>> = std::collections::HashMap::new();
+        // 条目上下文（节点路径 -> 上下文与路径提示）；父对象的上下文只收集一次，兄弟条目共用
+        let mut node_contexts: HashMap<&str, (serde_json::Map<String, Value>, Option<String>)> = HashMap::new();
+        let mut parent_contexts: HashMap<Vec<PathSegment>, ParentContext> = HashMap::new();
+
+        let query_start = std::time::Instant::now();
+        progress_callback(0.5, "正在查询JSON路径...");
+        // 单次遍历：每个节点只解析一次路径段，定位父节点后取节点值，并在同一父节点上收集上下文
+        for (node, _) in &matched {
+            let Some(mut segments) = parse_segments(&node.path) else {
+                path_to_value.insert(node.path.clone(), query_first(dom, &node.path).ok().flatten().cloned());
+                continue;
+            };
+            let Some(last) = segments.pop() else {
+                path_to_value.insert(node.path.clone(), Some(dom.clone()));
+                continue;
+            };
+            let parent = resolve(dom, &segments);
+            let value = parent.and_then(|parent| resolve(parent, std::slice::from_ref(&last)));
+            path_to_value.insert(node.path.clone(), value.cloned());
+            if options.include_context {
+                let own_key = match &last {
+                    PathSegment::Key(key) => Some(key.as_str()),
+                    PathSegment::Index(_) => None,
+                };
+                let parent_context = parent_contexts.entry(segments).or_insert_with_key(|segments| ParentContext::collect(parent, segments));
+                node_contexts.insert(node.path.as_str(), (parent_context.context_for(own_key), parent_context.path_hint.clone()));
+            }
+        }
+        // 派生的 name 路径另行查询；可能不是合法路径（键中含方括号时），视为不存在
+        for (node, _) in &matched {
+            if node.name != "name" {
+                if let Some(np) = derive_name_path(&node.path) {
+                    path_to_value.entry(np).or_insert_with_key(|np| query_first(dom, np).ok().flatten().cloned());
+                }
+            }
+        }
+        let query_time = query_start.elapsed().as_millis();
+        tracing::info!("build_intermediate_stage2: JSON路径查询完成，耗时: {}ms", query_time);
+
+        let mut items = Vec::<serde_json::Value>::new();
+        let mut excluded = 0usize;
+        let build_start = std::time::Instant::now();
+        // 优化：减少进度回调，直接跳到90%
+        progress_callback(0.9, "正在构建最终结果...");
+        for (node, matched_by) in matched {
+            // 从缓存中获取当前节点的值
+            let current_value_opt = path_to_value.get(&node.path).and_then(|v| v.clone());
+
+            let current_value_str = match &current_value_opt {
+                Some(val) => match val {
+                    serde_json::Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                },
+                None => String::new(),
+            };
+
+            // 派生 name 字段路径和值（从缓存中获取）
+            let (name_path, name_value_opt) = if node.name == "name" {
+                // 本身就是 name 字段
+                (node.path.clone(), current_value_opt.clone())
+            } else if let Some(np) = derive_name_path(&node.path) {
+                let v = path_to_value.get(&np).and_then(|v| v.clone());
+                (np, v)
+            } else {
+                (node.path.clone(), None)
+            };
+
+            let name_value_str = match name_value_opt {
+                Some(val) => match val {
+                    serde_json::Value::String(s) => s,
+                    other => other.to_string(),
+                },
+                None => String::new(),
+            };
+
+            // 标记源文本中的不可见字符/非NFC序列
+            let suspicious: Vec<String> = scan_suspicious(&current_value_str)
+                .iter()
+                .map(|c| c.describe())
+                .collect();
+
+            // 超长的二进制编码值不送译：排除或替换为引用条目
+            let chars = current_value_str.chars().count();
+            let oversized = matches!(current_value_opt, Some(serde_json::Value::String(_)))
+                && options.max_value_chars.is_some_and(|max| chars > max)
+                && looks_binary(&current_value_str);
+            if oversized {
+                excluded += 1;
+                if options.oversized == OversizedValues::Exclude {
+                    continue;
+                }
+                let preview: String = current_value_str.chars().take(OVERSIZED_PREVIEW_CHARS).collect();
+                items.push(serde_json::json!({
+                    "source_path": node.path,
+                    "name": format!("{}…（共 {} 字符，未送译）", preview, chars),
+                    "field_name": node.name,
+                    "matched_by": matched_by,
+                    "excluded": true,
+                    "original_chars": chars,
+                }));
+                continue;
+            }
+
+            let mut item = serde_json::json!({
+                // seq 在最终序列化时按索引补充
+                "source_path": node.path,
+                "name_path": name_path,
+                "name": current_value_str,  // 使用查询字段的值，而不是 name 字段的值
+                "field_name": node.name,    // 添加字段名信息
+                "name_field_value": name_value_str,  // name 字段的值（用于参考）
+                "matched_by": matched_by,   // 命中的过滤条件
+            });
+            if !suspicious.is_empty() {
+                item["suspicious"] = serde_json::json!(suspicious);
+            }
+            if let Some(anchor) = options.anchor_field.as_deref().and_then(|field| find_anchor(dom, &node.path, field)) {
+                item["anchor"] = anchor;
+            }
+            if let Some((context, hint)) = node_contexts.remove(node.path.as_str()) {
+                if !context.is_empty() {
+                    item["context"] = Value::Object(context);
+                }
+                if let Some(hint) = hint {
+                    item["context_path_hint"] = serde_json::json!(hint);
+                }
+            }
+            items.push(item);
+        }
+        let build_time = build_start.elapsed().as_millis();
+        tracing::info!("build_intermediate_stage2: 结果项构建完成，耗时: {}ms", build_time);
+        (items, excluded)
+    }
+
+
+    /// 只统计过滤条件会命中多少节点（与中间产物2的匹配规则一致），单次遍历节点列表、不查询DOM，用于输入时的即时反馈
+    ///
+    /// 影子树被截断时与搜索一样按DOM构建的完整节点列表统计，截断标记不计入
+    pub fn count_matches(&self, filter: &str, opts: &MatchCountOptions) -> MatchCounts {
+        let queries: Vec<SearchQuery> = parse_filter_list(filter).iter().map(|f| SearchQuery::parse(f)).filter(|q| !q.is_empty()).collect();
+        let mut counts = MatchCounts::default();
+        if queries.is_empty() {
+            return counts;
+        }
+        for node in self.untruncated_nodes().iter() {
+            if !opts.kinds.is_empty() && !opts.kinds.contains(&node.kind) {
+                continue;
+            }
+            if opts.exclude.iter().any(|t| node.path.contains(t.as_str()) || node.name.contains(t.as_str())) {
+                continue;
+            }
+            if queries.iter().any(|q| stage_node_matches(node, q, opts.leaf_nodes_only)) {
+                counts.total += 1;
+                counts.string_leaves += usize::from(node.kind == NodeKind::String);
+            }
+        }
+        counts
+    }
+
+    /// 生成中间产物1：只列出命中节点的 `source_path` 与预览值，不查询DOM、不派生 `name_path`
+    ///
+    /// 用于在生成代价较高的中间产物2之前确认过滤条件会命中哪些节点；过滤条件与 `leaf_nodes_only` 的解释同中间产物2，过滤条件为空时返回空字符串
+    pub fn build_intermediate_stage1(&self, filter: &str, leaf_nodes_only: bool) -> Result<String, AppError> {
+        let filter_list = parse_filter_list(filter);
+        let filters = stage_filters(&filter_list);
+        if filters.is_empty() {
+            return Ok(String::new());
+        }
+        if self.dom.is_none() {
+            return Err(AppError::not_loaded());
+        }
+
+        let nodes = self.untruncated_nodes();
+        let items: Vec<Value> = match_stage_nodes(&nodes, &filters, leaf_nodes_only)
+            .into_iter()
+            .map(|(node, _)| serde_json::json!({"source_path": node.path, "preview": node.preview}))
+            .collect();
+        let result = serde_json::json!({
+            "stage": "intermediate1",
+            "filter": filters.iter().map(|(f, _)| *f).collect::<Vec<_>>().join(" | "),
+            "count": items.len(),
+            "items": items,
+        });
+        Ok(to_string_wrapped_with_indent(&result, DEFAULT_MAX_INLINE_WIDTH, self.save_options.indent_size))
+    }
+
+    /// 以 CSV（`seq,source_path,name`）导出中间产物2，沿用其条目顺序（即构建时的排序方式）
+    ///
+    /// 中间产物带有文档指纹时，首行写入 `# fingerprint: ...` 元数据；条目带有上下文（见 [`Stage2Options::include_context`]）时追加 `notes` 列
+    pub fn export_stage2_csv(stage2_json: &str, writer: &mut dyn std::io::Write) -> Result<(), AppError> {
+        let stage2: Value = serde_json::from_str(stage2_json)?;
+        let items = stage2
+            .get("items")
+            .and_then(|v| v.as_array())
+            .ok_or_else(|| AppError::State(StateKind::Malformed, "中间产物缺少items数组".into()))?;
+        if let Some(fingerprint) = stage2.get(FINGERPRINT_KEY).and_then(|v| v.as_str()) {
+            writeln!(writer, "# {}: {}", FINGERPRINT_KEY, fingerprint)?;
+        }
+        let with_notes = items.iter().any(|item| item.get("context").is_some() || item.get("context_path_hint").is_some());
+        writeln!(writer, "seq,source_path,name{}", if with_notes { ",notes" } else { "" })?;
+        for item in items {
+            let text = |key: &str| item[key].as_str().unwrap_or_default().to_string();
+            write!(writer, "{},{},{}", item["seq"].as_u64().unwrap_or(0), csv_field(&text("source_path")), csv_field(&text("name")))?;
+            if with_notes {
+                write!(writer, ",{}", csv_field(&stage2_item_notes(item)))?;
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+
+    /// 按文档顺序列出所有叶子（标量与空对象/空数组）及其路径段；段区分对象键与数组下标，可经
+    /// [`Self::unflatten_from_key_value_pairs`] 无损还原（`items[0]` 与键为 `"0"` 的对象不会混淆）
+    pub fn flatten_to_key_value_pairs(&self) -> Result<Vec<(Vec<PathSegment>, Value)>, AppError> {
+        let dom = self.dom.as_ref().ok_or_else(AppError::not_loaded)?;
+        let mut pairs = Vec::new();
+        // 显式栈，避免深层嵌套的文档栈溢出
+        let mut stack: Vec<(Vec<PathSegment>, &Value)> = vec![(Vec::new(), dom)];
+        while let Some((segments, value)) = stack.pop() {
+            let children: Vec<(PathSegment, &Value)> = match value {
+                Value::Object(map) if !map.is_empty() => map.iter().map(|(k, v)| (PathSegment::Key(k.clone()), v)).collect(),
+                Value::Array(items) if !items.is_empty() => items.iter().enumerate().map(|(i, v)| (PathSegment::Index(i), v)).collect(),
+                _ => {
+                    pairs.push((segments, value.clone()));
+                    continue;
+                }
+            };
+            for (segment, child) in children.into_iter().rev() {
+                let mut child_segments = segments.clone();
+                child_segments.push(segment);
+                stack.push((child_segments, child));
+            }
+        }
+        Ok(pairs)
+    }
+
+    /// 由 [`Self::flatten_to_key_value_pairs`] 的结果重建文档；缺少的节点按段类型创建为对象或数组
+    ///
+    /// 同一位置既作为键又作为下标（结构冲突）、或下标越过数组末尾过远（见 [`insert_creating`]）时报错
+    pub fn unflatten_from_key_value_pairs(pairs: &[(Vec<PathSegment>, Value)]) -> Result<Value, AppError> {
+        let mut root = Value::Null;
+        for (segments, value) in pairs {
+            if !insert_creating(&mut root, segments, value.clone()) {
+                return Err(AppError::State(StateKind::Conflict, format!("键值对与已有结构冲突: {}", format_segments(segments))));
+            }
+        }
+        Ok(root)
+    }
+
+    /// 导出字符串叶子的 `路径: 值` 键值对（每行一对，按路径段排序），供粘贴到 LLM 提示词中翻译
+    ///
+    /// `filter` 按路径与属性名过滤（语法同搜索框）；最多输出 `max_pairs` 对以适应上下文窗口，
+    /// 空字符串标记为 [`LLM_TRANSLATE_PLACEHOLDER`]；开头的 `#` 注释记录源文件与总数
+    pub fn export_key_value_pairs_for_llm(&self, filter: Option<&str>, max_pairs: usize) -> Result<String, AppError> {
+        let dom = self.dom.as_ref().ok_or_else(AppError::not_loaded)?;
+        let query = filter.map(SearchQuery::parse).filter(|q| !q.is_empty());
+        let nodes = self.untruncated_nodes();
+        let mut pairs: Vec<(Vec<PathSegment>, &str, &str)> = nodes
+            .iter()
+            .filter(|n| n.kind == NodeKind::String && !n.is_truncation_sentinel())
+            .filter(|n| query.as_ref().is_none_or(|q| q.matches(&[&n.path, &n.name])))
+            .filter_map(|n| {
+                let segments = parse_segments(&n.path)?;
+                let value = resolve(dom, &segments)?.as_str()?;
+                Some((segments, n.path.as_str(), value))
+            })
+            .collect();
+        pairs.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+
+        let source = self.source_path.as_ref().map_or_else(|| "(未保存)".to_string(), |p| p.display().to_string());
+        let mut out = format!("# source_path: {}\n# total: {}, shown: {}\n", source, pairs.len(), pairs.len().min(max_pairs));
+        for (_, key, value) in pairs.into_iter().take(max_pairs) {
+            let value = if value.is_empty() { LLM_TRANSLATE_PLACEHOLDER.to_string() } else { escape_llm_value(value) };
+            out.push_str(&format!("{}: {}\n", key, value));
+        }
+        Ok(out)
+    }
+
+    /// 解析 LLM 按 [`Self::export_key_value_pairs_for_llm`] 格式返回的 `键: 译文` 并回写，返回 `(写入数, 无法解析数)`
+    ///
+    /// 键先按路径匹配字符串叶子，否则按属性名匹配所有同名字符串叶子；空行、`#` 注释与仍为
+    /// [`LLM_TRANSLATE_PLACEHOLDER`] 的行被忽略，格式不符或找不到节点的行计入无法解析。
+    /// 容忍行首的列表符号、键两侧的引号，以及 LLM 给译文加上的引号和句末标点
+    pub fn apply_llm_translation_response(&mut self, llm_output: &str) -> Result<(usize, usize), AppError> {
+        self.ensure_writable()?;
+        let dom = self.dom.as_ref().ok_or_else(AppError::not_loaded)?;
+        // 按路径与按属性名的索引各建一次（影子树截断时同样覆盖全部字符串叶子），每个候选键只做哈希查找
+        let nodes = self.untruncated_nodes();
+        let mut by_path: HashSet<&str> = HashSet::new();
+        let mut by_name: HashMap<&str, Vec<&str>> = HashMap::new();
+        for node in nodes.iter().filter(|n| n.kind == NodeKind::String) {
+            by_path.insert(node.path.as_str());
+            by_name.entry(node.name.as_str()).or_default().push(node.path.as_str());
+        }
+        let lookup = |key: &str| -> Vec<&str> {
+            match by_path.get(key) {
+                Some(path) => vec![*path],
+                None => by_name.get(key).cloned().unwrap_or_default(),
+            }
+        };
+
+        let mut updates = Vec::new();
+        let mut unparsed = 0;
+        for line in llm_output.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let line = line.trim_start_matches(['-', '*', '•']).trim_start();
+            // 键本身可能含 ": "（如 `$['a: b']`），取第一个能匹配到节点的分隔位置
+            let parsed = line.match_indices(':').find_map(|(i, _)| {
+                let key = line[..i].trim().trim_matches(['`', '"', '\'']);
+                let paths = lookup(key);
+                (!paths.is_empty()).then(|| (paths, line[i + 1..].trim()))
+            });
+            let Some((paths, translated)) = parsed else {
+                unparsed += 1;
+                continue;
+            };
+            if translated.is_empty() || translated == LLM_TRANSLATE_PLACEHOLDER {
+                continue;
+            }
+            for path in paths {
+                let source = parse_segments(path).and_then(|segments| resolve(dom, &segments)).and_then(Value::as_str).unwrap_or_default();
+                updates.push((path.to_string(), strip_llm_decoration(&unescape_llm_value(translated), source)));
+            }
+        }
+        drop(nodes);
+        let applied = self.batch_update(&updates)?;
+        Ok((applied, unparsed))
+    }
+
+    /// 更新JSON中指定路径的值
+    pub fn update_json_value(&mut self, path: &str, new_value: &str) -> Result<(), AppError> {
+        // 直接使用现有的 update_node_from_str 方法
+        self.update_node_from_str(path, new_value)
+    }
+
+    /// 保存修改后的JSON到文件
+    pub fn save_modified_json(&self, path: &std::path::Path) -> Result<(), AppError> {
+        self.save_to_file(path)?;
+
+        tracing::info!("JSON文件已保存到: {}", path.display());
+        Ok(())
+    }
+
+    /// 切换节点的展开状态
+    pub fn toggle_node_expanded(&mut self, path: &str) {
+        if let Some(node) = self.tree_flat.iter_mut().find(|n| n.path == path) {
+            node.expanded = !node.expanded;
+        }
+        self.update_visibility_by_expansion();
+    }
+
+    /// 根据展开状态更新节点可见性
+    pub fn update_visibility_by_expansion(&mut self) {
+        // 首先标记所有节点为不可见（除了根节点）
+        for (i, node) in self.tree_flat.iter_mut().enumerate() {
+            if i == 0 {
+                node.visible = true; // 根节点总是可见
+            } else {
+                node.visible = false;
+            }
+        }
+
+        // 递归显示展开节点的直接子节点
+        for i in 0..self.tree_flat.len() {
+            if self.tree_flat[i].expanded && self.tree_flat[i].visible {
+                let parent_depth = self.tree_flat[i].depth;
+                // 显示直接子节点
+                for j in (i + 1)..self.tree_flat.len() {
+                    if self.tree_flat[j].depth == parent_depth + 1 {
+                        self.tree_flat[j].visible = true;
+                    } else if self.tree_flat[j].depth <= parent_depth {
+                        break; // 已经超出了当前父节点的范围
+                    }
+                }
+            }
+        }
+    }
+
+    /// 面向显示的可见节点子集：最多 `max_visible` 个节点，超出部分以截断标记代替
+    ///
+    /// 深度不小于 `expand_threshold` 的已展开节点，若其可见后代放不进剩余名额，则整棵子树折叠显示并附截断标记；
+    /// 用于超大文档的首次渲染，避免一次向 UI 提交数十万行
+    pub fn pack_for_display(&self, max_visible: usize, expand_threshold: u32) -> Vec<JsonTreeNode> {
+        let tree = &self.tree_flat;
+        let mut out = Vec::new();
+        let mut emitted = 0;
+        let mut i = 0;
+        while i < tree.len() {
+            let node = &tree[i];
+            if !node.visible {
+                i += 1;
+                continue;
+            }
+            if emitted >= max_visible {
+                let hidden = tree[i..].iter().filter(|n| n.visible).count();
+                out.push(JsonTreeNode::truncation_sentinel(node.depth, hidden, "还有更多节点未显示".into()));
+                break;
+            }
+            if node.expanded && node.depth >= expand_threshold {
+                let end = self.subtree_end(i);
+                let hidden = tree[i + 1..end].iter().filter(|n| n.visible).count();
+                if emitted + 1 + hidden > max_visible {
+                    out.push(JsonTreeNode { expanded: false, ..node.clone() });
+                    out.push(JsonTreeNode::truncation_sentinel(node.depth + 1, hidden, "子树过大，已自动折叠".into()));
+                    emitted += 1;
+                    i = end;
+                    continue;
+                }
+            }
+            out.push(node.clone());
+            emitted += 1;
+            i += 1;
+        }
+        out
+    }
+
+    /// 展开 `path` 的所有祖先节点使其可见
+    pub fn reveal_node(&mut self, path: &str) -> Result<(), AppError> {
+        let mut index = self.tree_index_of(path)?;
+        while self.tree_flat[index].depth > 0 {
+            index = self.tree_parent_index(index)?;
+            self.tree_flat[index].expanded = true;
+        }
+        self.update_visibility_by_expansion();
+        Ok(())
+    }
+
+    /// 体积最大的 `n` 个子树 `(路径, 紧凑序列化字节数)`，按大小降序；DOM未加载时为空
+    ///
+    /// 只列出没有“主导子节点”（占自身一半以上体积的子节点）的节点，列出的节点互不包含：
+    /// 根节点不会独占列表，嵌入的大块数据定位到所在的叶子，由大量小元素组成的数组/对象则整体列出
+    pub fn largest_subtrees(&self, n: usize) -> Vec<(String, u64)> {
+        let Some(dom) = self.dom.as_ref() else {
+            return Vec::new();
+        };
+        let Ok(sizes) = self.built_prefix_sizes(compute_subtree_sizes(dom, |_, _| {})) else {
+            return Vec::new();
+        };
+
+        // 先序遍历中以深度栈找父节点，记录每个节点最大子节点的体积
+        let mut largest_child = vec![0u64; sizes.len()];
+        let mut ancestors: Vec<usize> = Vec::new();
+        for (i, node) in self.tree_flat[..sizes.len()].iter().enumerate() {
+            while ancestors.last().is_some_and(|&a| self.tree_flat[a].depth >= node.depth) {
+                ancestors.pop();
+            }
+            if let Some(&parent) = ancestors.last() {
+                largest_child[parent] = largest_child[parent].max(sizes[i]);
+            }
+            ancestors.push(i);
+        }
+
+        let mut order: Vec<usize> = (0..sizes.len()).filter(|&i| largest_child[i] * 2 <= sizes[i]).collect();
+        // 大小相同时先取更深的节点
+        order.sort_by(|&a, &b| sizes[b].cmp(&sizes[a]).then(self.tree_flat[b].depth.cmp(&self.tree_flat[a].depth)));
+
+        // (下标, 子树结束位置)
+        let mut selected: Vec<(usize, usize)> = Vec::new();
+        for i in order {
+            if selected.len() == n {
+                break;
+            }
+            // 已列出的容器内的节点不再单独列出
+            if selected.iter().any(|&(s, end)| s < i && i < end) {
+                continue;
+            }
+            selected.push((i, self.subtree_end(i)));
+        }
+        selected.into_iter().map(|(i, _)| (self.tree_flat[i].path.clone(), sizes[i])).collect()
+    }
+
+    /// 智能检测JSON中的英文字段，返回纯英文的字段值列表
+    pub fn detect_english_fields(&self, leaf_nodes_only: bool) -> Result<Vec<String>, AppError> {
+        let dom = self
+            .dom
+            .as_ref()
+            .ok_or_else(AppError::not_loaded)?;
+
+        let mut english_fields = HashSet::new();
+
+        // 递归遍历JSON值，提取英文属性名
+        Self::extract_english_keys(dom, &mut english_fields, leaf_nodes_only);
+
+        // 转换为排序的向量，过滤掉不符合条件的字段
+        let mut result: Vec<String> = english_fields
+            .into_iter()
+            .filter(|s| {
+                let trimmed = s.trim();
+                // 基本长度检查
+                if trimmed.len() < 2 || trimmed.len() > 50 {
+                    return false;
+                }
+
+                // 使用更精确的英文检测逻辑
+                Self::is_pure_english_field(trimmed)
+            })
+            .collect();
+
+        result.sort();
+        result.dedup(); // 去重
+
+        // 限制返回数量，避免UI过载
+        if result.len() > 20 {
+            result.truncate(20);
+        }
+
+        Ok(result)
+    }
+
+    /// 判断是否为纯英文字段名（排除时间格式、数字等）
+    fn is_pure_english_field(s: &str) -> bool {
+        // 必须包含至少一个英文字母
+        let has_letter = s.chars().any(|c| c.is_ascii_alphabetic());
+        if !has_letter {
+            return false;
+        }
+
+        // 排除时间格式 (如: "2023-01-01", "12:34:56", "2023-01-01T12:34:56Z")
+        if Self::is_time_format(s) {
+            return false;
+        }
+
+        // 排除版本号格式 (如: "v1.2.3", "1.0.0")
+        if Self::is_version_format(s) {
+            return false;
+        }
+
+        // 排除主要包含数字的字符串 (如: "123abc")
+        let letter_count = s.chars().filter(|c| c.is_ascii_alphabetic()).count();
+        let digit_count = s.chars().filter(|c| c.is_ascii_digit()).count();
+        if digit_count > letter_count {
+            return false;
+        }
+
+        // 只允许英文字母、下划线、连字符（不允许数字、冒号等）
+        s.chars().all(|c| c.is_ascii_alphabetic() || c == '_' || c == '-')
+    }
+
+    /// 判断是否为时间格式（优化版本，避免正则表达式性能问题）
+    fn is_time_format(s: &str) -> bool {
+        let len = s.len();
+
+        // 快速长度检查
+        if !(8..=30).contains(&len) {
+            return false;
+        }
+
+        // 检查是否包含时间相关字符
+        let has_time_chars = s.contains('-') || s.contains(':') || s.contains('T') || s.contains('Z');
+        if !has_time_chars {
+            return false;
+        }
+
+        // 简单模式匹配，避免复杂正则表达式
+        // ISO 8601 格式: 2023-01-01T12:34:56
+        if s.contains('T') && s.contains('-') && s.contains(':') {
+            return true;
+        }
+
+        // 日期格式: 2023-01-01
+        if s.matches('-').count() == 2 && (8..=12).contains(&len) {
+            let parts: Vec<&str> = s.split('-').collect();
+            if parts.len() == 3 &&
+               parts[0].len() == 4 && parts[0].chars().all(|c| c.is_ascii_digit()) &&
+               parts[1].len() == 2 && parts[1].chars().all(|c| c.is_ascii_digit()) &&
+               parts[2].len() == 2 && parts[2].chars().all(|c| c.is_ascii_digit()) {
+                return true;
+            }
+        }
+
+        // 时间格式: 12:34:56
+        if s.matches(':').count() == 2 && (6..=10).contains(&len) {
+            let parts: Vec<&str> = s.split(':').collect();
+            if parts.len() == 3 && parts.iter().all(|p| p.chars().all(|c| c.is_ascii_digit())) {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// 判断是否为版本号格式（优化版本，避免正则表达式性能问题）
+    fn is_version_format(s: &str) -> bool {
+        let len = s.len();
+
+        // 快速长度检查
+        if !(3..=20).contains(&len) {
+            return false;
+        }
+
+        // 检查是否包含点号
+        if !s.contains('.') {
+            return false;
+        }
+
+        // 移除可能的v前缀
+        let version_str = if s.starts_with('v') || s.starts_with('V') {
+            &s[1..]
+        } else {
+            s
+        };
+
+        // 检查点号数量（1-3个点号是合理的版本号）
+        let dot_count = version_str.matches('.').count();
+        if !(1..=3).contains(&dot_count) {
+            return false;
+        }
+
+        // 检查是否为数字.数字格式
+        let parts: Vec<&str> = version_str.split('.').collect();
+        if parts.len() >= 2 && parts.len() <= 4 {
+            // 所有部分都应该是数字
+            return parts.iter().all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()));
+        }
+
+        false
+    }
+
+    /// 判断是否为URL格式（优化版本，避免正则表达式性能问题）
+    fn is_url_format(s: &str) -> bool {
+        let len = s.len();
+
+        // 快速长度检查
+        if !(7..=2000).contains(&len) {  // 最短的URL如 http://a 至少7个字符
+            return false;
+        }
+
+        // 检查是否以常见协议开头
+        let lower_s = s.to_lowercase();
+        if lower_s.starts_with("http://") ||
+           lower_s.starts_with("https://") ||
+           lower_s.starts_with("ftp://") ||
+           lower_s.starts_with("ftps://") {
+            // 检查协议后是否有域名部分
+            let protocol_end = if lower_s.starts_with("https://") || lower_s.starts_with("ftps://") {
+                8
+            } else {
+                7
+            };
+
+            if s.len() > protocol_end {
+                let domain_part = &s[protocol_end..];
+                // 域名部分应该包含至少一个点或者是localhost
+                return domain_part.contains('.') || domain_part.starts_with("localhost");
+            }
+        }
+
+        false
+    }
+
+    /// 判断是否为叶子节点（具有具体值的节点）
+    fn is_leaf_node(value: &Value) -> bool {
+        matches!(value,
+            Value::String(_) |
+            Value::Number(_) |
+            Value::Bool(_) |
+            Value::Null
+        )
+    }
+
+    /// 提取JSON中的英文属性名（键名），只收集值为字符串且值不是时间格式的属性名
+    /// 对于URL类型的属性值，直接提取URL本身而不是属性名
+    fn extract_english_keys(
+        value: &Value,
+        english_fields: &mut HashSet<String>,
+        leaf_nodes_only: bool,
+    ) {
+        // 显式栈遍历，避免深层嵌套导致栈溢出
+        let mut stack = vec![value];
+        while let Some(value) = stack.pop() {
+            match value {
+                Value::Array(arr) => stack.extend(arr.iter()),
+                Value::Object(obj) => {
+                    for (key, val) in obj {
+                        // 叶子节点过滤：如果开启了叶子节点模式，只处理叶子节点
+                        let is_leaf = Self::is_leaf_node(val);
+
+                        if !leaf_nodes_only || is_leaf {
+                            // 只有当属性值是字符串且不是时间格式时，才收集键名或URL
+                            if let Value::String(string_value) = val {
+                                let trimmed_key = key.trim();
+                                let trimmed_value = string_value.trim();
+
+                                // 检查属性值是否为时间格式或版本号格式
+                                if !trimmed_key.is_empty() &&
+                                   !Self::is_time_format(trimmed_value) &&
+                                   !Self::is_version_format(trimmed_value) {
+                                    // 如果属性值是URL，直接提取URL本身
+                                    if Self::is_url_format(trimmed_value) {
+                                        english_fields.insert(trimmed_value.to_string());
+                                    } else {
+                                        // 否则提取属性名
+                                        english_fields.insert(trimmed_key.to_string());
+                                    }
+                                }
+                            }
+                        }
+
+                        // 继续检查子结构的键名（无论值是什么类型）
+                        stack.push(val);
+                    }
+                }
+                _ => {} // 忽略其他类型（数字、布尔值、null、字符串值）
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::diff::DiffKind;
+    use crate::model::test_utils::TestFixtureShape;
+    use crate::utils::fs::read_json_file_checked;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    /// 创建临时JSON文件用于测试
+    fn create_test_json_file(content: &str) -> NamedTempFile {
+        let mut file = NamedTempFile::new().expect("创建临时文件失败");
+        file.write_all(content.as_bytes()).expect("写入临时文件失败");
+        file
+    }
+
+    /// 按形状生成测试文档并写入临时文件
+    fn create_fixture_file(shape: &TestFixtureShape) -> NamedTempFile {
+        create_test_json_file(&AppState::generate_test_fixture(shape).to_string())
+    }
+
+    #[test]
+    fn test_load_simple_json() {
+        let json_content = r#"{"name": "test", "value": 42}"#;
+        let temp_file = create_test_json_file(json_content);
+
+        let mut app_state = AppState::default();
+        let result = app_state.load_file(temp_file.path());
+
+        assert!(result.is_ok(), "加载简单JSON应该成功");
+        assert!(app_state.dom.is_some(), "DOM应该被加载");
+        assert!(!app_state.tree_flat.is_empty(), "影子树应该被构建");
+        assert_eq!(app_state.tree_flat.len(), 3, "应该有3个节点：根、name、value");
+    }
+
+    #[test]
+    fn test_load_nested_json() {
+        let json_content = r#"
+        {
+            "user": {
+                "name": "张三",
+                "age": 30,
+                "address": {
+                    "city": "北京",
+                    "district": "朝阳区"
+                }
+            },
+            "items": [1, 2, 3]
+        }"#;
+        let temp_file = create_test_json_file(json_content);
+
+        let mut app_state = AppState::default();
+        let result = app_state.load_file(temp_file.path());
+
+        assert!(result.is_ok(), "加载嵌套JSON应该成功");
+        assert!(app_state.tree_flat.len() > 5, "嵌套结构应该产生多个节点");
+    }
+
+    #[test]
+    fn test_extract_subtree() {
+        let json_content = r#"{"user": {"name": "张三", "age": 30}}"#;
+        let temp_file = create_test_json_file(json_content);
+
+        let mut app_state = AppState::default();
+        app_state.load_file(temp_file.path()).expect("加载文件失败");
+
+        // 测试提取根节点
+        let root_result = app_state.extract_subtree_pretty("$");
+        assert!(root_result.is_ok(), "提取根节点应该成功");
+
+        // 测试提取用户对象
+        let user_result = app_state.extract_subtree_pretty("$.user");
+        assert!(user_result.is_ok(), "提取用户对象应该成功");
+
+        // 测试提取用户名
+        let name_result = app_state.extract_subtree_pretty("$.user.name");
+        assert!(name_result.is_ok(), "提取用户名应该成功");
+        assert!(name_result.unwrap().contains("张三"), "结果应该包含用户名");
+    }
+
+    #[test]
+    fn test_update_node() {
+        let json_content = r#"{"user": {"name": "张三", "age": 30}}"#;
+        let temp_file = create_test_json_file(json_content);
+
+        let mut app_state = AppState::default();
+        app_state.load_file(temp_file.path()).expect("加载文件失败");
+
+        // 更新用户名
+        let new_name = r#""李四""#;
+        let result = app_state.update_node_from_str("$.user.name", new_name);
+        assert!(result.is_ok(), "更新节点应该成功");
+
+        // 验证更新结果
+        let updated_name = app_state.extract_subtree_pretty("$.user.name").unwrap();
+        assert!(updated_name.contains("李四"), "用户名应该被更新为李四");
+    }
+
+    #[test]
+    fn test_invalid_json_path() {
+        let json_content = r#"{"user": {"name": "张三"}}"#;
+        let temp_file = create_test_json_file(json_content);
+
+        let mut app_state = AppState::default();
+        app_state.load_file(temp_file.path()).expect("加载文件失败");
+
+        // 测试无效路径
+        let result = app_state.extract_subtree_pretty("$.nonexistent");
+        assert!(result.is_err(), "无效路径应该返回错误");
+    }
+
+    #[test]
+    fn test_validate_path_format_reports_position() {
+        assert!(AppState::validate_path_format("$.items[0].name").is_ok());
+        assert!(AppState::validate_path_format("$['key with spaces'][*]").is_ok());
+
+        let err = AppState::validate_path_format("$.items[[0]").unwrap_err().to_string();
+        assert!(err.contains("语法错误（第 9 个字符）"), "{}", err);
+        assert!(err.ends_with("$.items[[0]\n        ^"), "{}", err);
+
+        // 提取与更新在求值前校验
+        let file = create_test_json_file(r#"{"items": [1]}"#);
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
+        assert!(app_state.extract_subtree_pretty("$.items[[0]").unwrap_err().to_string().contains("语法错误"));
+        assert!(app_state.update_node_from_str("items", "x").unwrap_err().to_string().contains("第 1 个字符"));
+    }
+
+    #[test]
+    fn test_load_file_with_comments() {
+        let content = "{\n  // 编辑器字体\n  \"editor.fontSize\": 14, /* 旧值: 12 */\n  \"title\": \"a // b\"\n}";
+        let file = create_test_json_file(content);
+
+        let mut app_state = AppState::default();
+        assert!(app_state.load_file(file.path()).is_err(), "普通加载不接受注释");
+        app_state.load_file_with_comments(file.path()).unwrap();
+        assert_eq!(app_state.dom, Some(serde_json::json!({"editor.fontSize": 14, "title": "a // b"})));
+        assert_eq!(app_state.source_path.as_deref(), Some(file.path()));
+        assert_eq!(app_state.load_warnings.len(), 1);
+
+        // 不含注释的文件照常加载，没有警告
+        let file = create_test_json_file(r#"{"a": "/* 字符串 */"}"#);
+        app_state.load_file_with_comments(file.path()).unwrap();
+        assert!(app_state.load_warnings.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_json_content() {
+        let invalid_json = r#"{"invalid": json content}"#;
+        let temp_file = create_test_json_file(invalid_json);
+
+        let mut app_state = AppState::default();
+        let result = app_state.load_file(temp_file.path());
+
+        assert!(result.is_err(), "无效JSON应该返回错误");
+    }
+
+    #[test]
+    fn test_search_results_single_match() {
+        let json_content = r#"{"user": {"name": "张三", "age": 30}, "config": {"debug": true}}"#;
+        let temp_file = create_test_json_file(json_content);
+
+        let mut app_state = AppState::default();
+        app_state.load_file(temp_file.path()).expect("加载文件失败");
+
+        // 应用搜索过滤
+        app_state.apply_search_filter("name");
+
+        // 提取搜索结果
+        let result = app_state.extract_search_results("name");
+        assert!(result.is_ok(), "搜索结果提取应该成功");
+
+        let search_result = result.unwrap();
+        assert!(search_result.contains("张三"), "搜索结果应该包含匹配的内容");
+        println!("单个匹配搜索结果: {}", search_result);
+    }
+
+    #[test]
+    fn test_search_results_multiple_matches() {
+        let json_content = r#"{"users": [{"name": "张三", "description": "用户1"}, {"name": "李四", "description": "用户2"}], "metadata": {"description": "用户数据"}}"#;
+        let temp_file = create_test_json_file(json_content);
+
+        let mut app_state = AppState::default();
+        app_state.load_file(temp_file.path()).expect("加载文件失败");
+
+        // 应用搜索过滤
+        app_state.apply_search_filter("description");
+
+        // 提取搜索结果
+        let result = app_state.extract_search_results("description");
+        assert!(result.is_ok(), "搜索结果提取应该成功");
+
+        let search_result = result.unwrap();
+        assert!(search_result.contains("search_filter"), "搜索结果应该包含搜索信息");
+        assert!(search_result.contains("total_matches"), "搜索结果应该包含匹配数量");
+        assert!(search_result.contains("displayed_matches"), "搜索结果应该包含显示数量");
+        println!("多个匹配搜索结果: {}", search_result);
+    }
+
+    #[test]
+    fn test_save_search_results() {
+        let json_content = r#"{"users": [{"name": "张三", "description": "用户1"}, {"name": "李四", "description": "用户2"}], "metadata": {"description": "用户数据"}}"#;
+        let temp_file = create_test_json_file(json_content);
+        let mut app_state = AppState::default();
+        app_state.load_file(temp_file.path()).expect("加载文件失败");
+        app_state.apply_search_filter("description");
+
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("results.json");
+        assert_eq!(app_state.save_search_results("description", &output).unwrap(), 3);
+        let saved: Value = serde_json::from_str(&std::fs::read_to_string(&output).unwrap()).unwrap();
+        assert_eq!(saved["total_matches"], 3);
+        assert_eq!(saved["results"].as_object().unwrap().len(), 3);
+
+        assert_eq!(app_state.save_search_results("不存在", &output).unwrap(), 0);
+        assert!(app_state.save_search_results(" ", &output).is_err());
+
+        // 过滤条件与搜索框同样解析：多条件、排除词、不区分大小写
+        app_state.apply_search_filter("");
+        assert_eq!(app_state.save_search_results("Description -metadata", &output).unwrap(), 2);
+        assert_eq!(app_state.save_search_results("metadata.description, name", &output).unwrap(), 3);
+
+        // 正则策略下保存的结果与界面上的搜索结果一致
+        let on_screen: Vec<String> = app_state.search(r"^(name|metadata)$", SearchMode::Regex).unwrap().into_iter().map(|m| m.node.path).collect();
+        assert_eq!(on_screen, ["$.users[0].name", "$.users[1].name", "$.metadata"]);
+        assert_eq!(app_state.save_search_results(r"^(name|metadata)$", &output).unwrap(), 3);
+        let saved: Value = serde_json::from_str(&std::fs::read_to_string(&output).unwrap()).unwrap();
+        let saved_paths: Vec<&str> = saved["results"].as_object().unwrap().values().map(|r| r["path"].as_str().unwrap()).collect();
+        assert_eq!(saved_paths, on_screen);
+    }
+
+    #[test]
+    fn test_update_node_type_change() {
+        let json_content = r#"{"data": {"value": "原始字符串"}}"#;
+        let temp_file = create_test_json_file(json_content);
+
+        let mut app_state = AppState::default();
+        app_state.load_file(temp_file.path()).expect("加载文件失败");
+
+        // 将字符串替换为对象
+        let new_object = r#"{"name": "新对象", "id": 123}"#;
+        let result = app_state.update_node_from_str("$.data.value", new_object);
+        assert!(result.is_ok(), "类型变更应该成功");
+
+        // 验证更新结果
+        let updated_value = app_state.extract_subtree_pretty("$.data.value").unwrap();
+        assert!(updated_value.contains("新对象"), "应该包含新对象的内容");
+        assert!(updated_value.contains("123"), "应该包含新对象的ID");
+    }
+
+    #[test]
+    fn test_update_array_element() {
+        let json_content = r#"{"items": ["第一项", "第二项", "第三项"]}"#;
+        let temp_file = create_test_json_file(json_content);
+
+        let mut app_state = AppState::default();
+        app_state.load_file(temp_file.path()).expect("加载文件失败");
+
+        // 更新数组中的第二个元素
+        let new_item = r#""更新的第二项""#;
+        let result = app_state.update_node_from_str("$.items[1]", new_item);
+        assert!(result.is_ok(), "数组元素更新应该成功");
+
+        // 验证更新结果
+        let updated_item = app_state.extract_subtree_pretty("$.items[1]").unwrap();
+        assert!(updated_item.contains("更新的第二项"), "数组元素应该被更新");
+
+        // 验证其他元素未受影响
+        let first_item = app_state.extract_subtree_pretty("$.items[0]").unwrap();
+        assert!(first_item.contains("第一项"), "第一项应该保持不变");
+    }
+
+    #[test]
+    fn test_update_with_invalid_json() {
+        let json_content = r#"{"data": "原始值"}"#;
+        let temp_file = create_test_json_file(json_content);
+
+        let mut app_state = AppState::default();
+        app_state.load_file(temp_file.path()).expect("加载文件失败");
+
+        // 尝试用无效JSON更新
+        let invalid_json = r#"{"invalid": json content"#;
+        let result = app_state.update_node_from_str("$.data", invalid_json);
+        assert!(result.is_err(), "无效JSON应该导致更新失败");
+
+        // 验证原始值未被修改
+        let original_value = app_state.extract_subtree_pretty("$.data").unwrap();
+        assert!(original_value.contains("原始值"), "原始值应该保持不变");
+    }
+
+    #[test]
+    fn test_update_nonexistent_path() {
+        let json_content = r#"{"data": "值"}"#;
+        let temp_file = create_test_json_file(json_content);
+
+        let mut app_state = AppState::default();
+        app_state.load_file(temp_file.path()).expect("加载文件失败");
+
+        // 尝试更新不存在的路径
+        let new_value = r#""新值""#;
+        let result = app_state.update_node_from_str("$.nonexistent.path", new_value);
+        assert!(result.is_err(), "不存在的路径应该导致更新失败");
+    }
+
+    #[test]
+    fn test_shadow_tree_rebuild_after_update() {
+        let json_content = r#"{"user": {"name": "张三", "age": 30}}"#;
+        let temp_file = create_test_json_file(json_content);
+
+        let mut app_state = AppState::default();
+        app_state.load_file(temp_file.path()).expect("加载文件失败");
+
+        let original_tree_len = app_state.tree_flat.len();
+
+        // 将简单值替换为复杂对象
+        let complex_object = r#"{"profile": {"bio": "个人简介", "skills": ["Rust", "JSON"]}}"#;
+        let result = app_state.update_node_from_str("$.user.name", complex_object);
+        assert!(result.is_ok(), "复杂对象更新应该成功");
+
+        // 验证影子树被重建且节点数量发生变化
+        let new_tree_len = app_state.tree_flat.len();
+        assert_ne!(original_tree_len, new_tree_len, "影子树应该被重建");
+
+        // 验证新路径存在
+        let bio_result = app_state.extract_subtree_pretty("$.user.name.profile.bio");
+        assert!(bio_result.is_ok(), "新的嵌套路径应该可访问");
+    }
+
+    #[test]
+    fn test_freeze_and_thaw_round_trip() {
+        let json_content = r#"{"user": {"name": "张三", "tags": ["a", "b"]}}"#;
+        let temp_file = create_test_json_file(json_content);
+
+        let mut app_state = AppState::default();
+        app_state.load_file(temp_file.path()).expect("加载文件失败");
+        app_state.toggle_node_expanded("$");
+        app_state.toggle_node_expanded("$.user");
+
+        // 快照经过磁盘序列化后仍可恢复
+        let frozen = app_state.freeze();
+        let frozen: FrozenState = serde_json::from_str(&serde_json::to_string(&frozen).unwrap()).unwrap();
+
+        app_state.update_node_from_str("$.user.name", "李四").unwrap();
+        AppState::thaw(frozen, &mut app_state).expect("恢复快照失败");
+
+        assert!(app_state.extract_subtree_pretty("$.user.name").unwrap().contains("张三"));
+        let user = app_state.tree_flat.iter().find(|n| n.path == "$.user").unwrap();
+        assert!(user.expanded && user.visible);
+        let tag = app_state.tree_flat.iter().find(|n| n.path == "$.user.tags[0]").unwrap();
+        assert!(!tag.visible, "未展开节点的子节点应保持不可见");
+    }
+
+    #[test]
+    fn test_thaw_without_dom_fails() {
+        let frozen = AppState::default().freeze();
+        let mut app_state = AppState::default();
+        assert!(AppState::thaw(frozen, &mut app_state).is_err());
+    }
+
+    #[test]
+    fn test_tree_snapshot_round_trip() {
+        let json_content = r#"{"user": {"name": "张三", "age": 30, "tags": ["a", null], "vip": true}}"#;
+        let temp_file = create_test_json_file(json_content);
+
+        let mut app_state = AppState { shadow_tree_options: ShadowTreeOptions { track_offsets: true, ..Default::default() }, ..Default::default() };
+        app_state.load_file(temp_file.path()).expect("加载文件失败");
+        app_state.toggle_node_expanded("$");
+        assert!(app_state.tree_flat.iter().any(|n| n.byte_offset.is_some()));
+
+        let dir = tempfile::tempdir().unwrap();
+        let full = dir.path().join("tree.json");
+        let summary = app_state.export_tree_snapshot(&full, false).unwrap();
+        assert_eq!(summary.total_nodes, app_state.tree_flat.len());
+        assert_eq!((summary.objects, summary.arrays, summary.strings, summary.numbers, summary.bools, summary.nulls), (2, 1, 2, 1, 1, 1));
+        assert_eq!(summary.max_depth, 3);
+
+        // 所有字段（含字节偏移与展开/可见标记）原样恢复
+        let original = app_state.tree_flat.clone();
+        let mut restored = AppState::default();
+        assert_eq!(restored.import_tree_snapshot(&full).unwrap(), summary);
+        assert_eq!(restored.tree_flat, original);
+        assert!(restored.dom.is_none());
+
+        let visible = dir.path().join("visible.json");
+        let summary = app_state.export_tree_snapshot(&visible, true).unwrap();
+        assert_eq!(summary.total_nodes, summary.visible_nodes);
+        assert_eq!(restored.import_tree_snapshot(&visible).unwrap().total_nodes, restored.tree_flat.len());
+        assert!(restored.tree_flat.iter().all(|n| n.visible));
+    }
+
+    #[test]
+    fn test_annotations_round_trip_and_sidecar() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("lang.json");
+        std::fs::write(&source, r#"{"menu": {"open": "Open", "logo": "logo.png"}}"#).unwrap();
+
+        let mut app_state = AppState::default();
+        app_state.load_file(&source).expect("加载文件失败");
+        app_state.set_annotation("$['menu'].open", Some("需复核")).unwrap();
+        app_state.set_annotation("$.menu.logo", Some("不翻译")).unwrap();
+        assert!(app_state.set_annotation("$.missing", Some("x")).is_err());
+        assert_eq!(app_state.annotation("$.menu.open"), Some("需复核"));
+
+        // 影子树重建后备注仍附加在同路径的节点上；删除备注
+        app_state.insert_child("$.menu", "close", r#""Close""#).unwrap();
+        let node = app_state.tree_flat.iter().find(|n| n.path == "$.menu.open").unwrap();
+        assert_eq!(node.annotation.as_deref(), Some("需复核"));
+        app_state.set_annotation("$.menu.close", Some("临时")).unwrap();
+        app_state.set_annotation("$.menu.close", Some("  ")).unwrap();
+        assert_eq!(app_state.annotation("$.menu.close"), None);
+
+        let mut buf = Vec::new();
+        assert_eq!(app_state.export_annotations(&mut buf).unwrap(), 2);
+        let exported: Value = serde_json::from_slice(&buf).unwrap();
+        assert_eq!(exported, serde_json::json!({"$.menu.logo": "不翻译", "$.menu.open": "需复核"}));
+
+        // 写出旁路文件，重新加载时自动导入
+        let sidecar = app_state.save_annotation_sidecar().unwrap();
+        assert_eq!(sidecar, dir.path().join("lang.json.annotations.json"));
+        let mut reloaded = AppState::default();
+        reloaded.load_file(&source).expect("加载文件失败");
+        assert_eq!(reloaded.annotation("$.menu.logo"), Some("不翻译"));
+
+        // 路径已不存在的备注被忽略
+        std::fs::write(&sidecar, r#"{"$.menu.open": "需复核", "$.gone": "x"}"#).unwrap();
+        let mut reloaded = AppState::default();
+        reloaded.load_file(&source).expect("加载文件失败");
+        let annotated: Vec<_> = reloaded.tree_flat.iter().filter(|n| n.annotation.is_some()).map(|n| n.path.as_str()).collect();
+        assert_eq!(annotated, vec!["$.menu.open"]);
+        assert!(reloaded.load_warnings.is_empty());
+
+        // 旁路文件损坏不影响加载，只记为警告
+        std::fs::write(&sidecar, "[1]").unwrap();
+        reloaded.load_file(&source).expect("备注文件损坏不应导致加载失败");
+        assert_eq!(reloaded.load_warnings.len(), 1);
+        assert!(reloaded.import_annotations(&mut "{".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_detect_circular_references() {
+        let mut app_state = AppState::default();
+        assert!(!app_state.detect_circular_references());
+        app_state.replace_dom(serde_json::json!({"a": [1, {"b": [[], {}]}], "c": {"d": null}})).unwrap();
+        assert!(!app_state.detect_circular_references());
+    }
+
+    #[test]
+    fn test_rename_key_nested_scoped_and_undo() {
+        let mut app_state = AppState::default();
+        let original = serde_json::json!({
+            "ui": {"desc": "a", "items": [{"desc": "b", "meta": {"desc": "c"}}]},
+            "help": {"desc": "d"}
+        });
+        app_state.replace_dom(original.clone()).unwrap();
+
+        // 限定作用域：只处理 $.ui 之下（含数组中的嵌套对象）
+        assert_eq!(app_state.rename_key("desc", "description", Some("$.ui")).unwrap(), 3);
+        let dom = app_state.dom.as_ref().unwrap();
+        assert_eq!(dom["ui"]["items"][0]["meta"]["description"], "c");
+        assert_eq!(dom["help"]["desc"], "d");
+        assert!(app_state.tree_flat.iter().any(|n| n.path == "$.ui.items[0].description"));
+        assert!(!app_state.tree_flat.iter().any(|n| n.path == "$.ui.desc"));
+        // 重命名的键保留在原位置
+        let keys: Vec<&str> = dom["ui"].as_object().unwrap().keys().map(String::as_str).collect();
+        assert_eq!(keys, ["description", "items"]);
+        assert_eq!(app_state.tree_flat[2].path, "$.ui.description");
+
+        // 未命中不产生撤销记录
+        assert_eq!(app_state.rename_key("missing", "x", None).unwrap(), 0);
+        assert!(app_state.undo().unwrap());
+        assert_eq!(app_state.dom.as_ref().unwrap(), &original);
+        assert!(!app_state.undo().unwrap());
+    }
+
+    #[test]
+    fn test_rename_key_refuses_collisions() {
+        let mut app_state = AppState::default();
+        let original = serde_json::json!({"a": {"desc": 1, "description": 2}, "b": [{"desc": 3, "description": 4}], "c": {"desc": 5}});
+        app_state.replace_dom(original.clone()).unwrap();
+
+        let err = app_state.rename_key("desc", "description", None).unwrap_err().to_string();
+        assert!(err.contains("$.a") && err.contains("$.b[0]"), "{}", err);
+        assert_eq!(app_state.dom.as_ref().unwrap(), &original, "冲突时不做任何修改");
+        assert!(!app_state.can_undo());
+
+        assert_eq!(app_state.rename_key("desc", "description", Some("$.c")).unwrap(), 1);
+        assert!(app_state.rename_key("desc", "desc", None).is_err());
+        assert!(app_state.rename_key("desc", "x", Some("$.nope")).is_err());
+    }
+
+    #[test]
+    fn test_apply_context_format() {
+        let mut app_state = AppState::default();
+        assert!(app_state.apply_context_format("{path}").is_err());
+
+        let original = serde_json::json!({"ui": {"items": [{"title": "Open {path}"}], "count": 2}});
+        app_state.replace_dom(original.clone()).unwrap();
+        let out = app_state.apply_context_format("{path}: {value}").unwrap();
+        assert_eq!(
+            out,
+            serde_json::json!({"ui": {"items": [{"title": {
+                "value": "Open {path}",
+                "context": "ui > items[0] > title: Open {path}"
+            }}], "count": 2}})
+        );
+        assert_eq!(app_state.dom.as_ref().unwrap(), &original, "不修改当前DOM");
+
+        app_state.replace_dom(serde_json::json!("root")).unwrap();
+        assert_eq!(app_state.apply_context_format("{path}").unwrap()["context"], "$");
+    }
+
+    /// 局部更新后的影子树应与全量重建的结果一致（不比较展开/可见标记）
+    fn assert_tree_in_sync(app_state: &AppState) {
+        let summary = |nodes: &[JsonTreeNode]| -> Vec<(String, String, u32, u32, String)> {
+            nodes.iter().map(|n| (n.path.clone(), n.name.clone(), n.depth, n.children, n.preview.clone())).collect()
+        };
+        let rebuilt = build_shadow_tree(app_state.dom.as_ref().unwrap());
+        assert_eq!(summary(&app_state.tree_flat), summary(&rebuilt));
+    }
+
+    #[test]
+    fn test_delete_node_shifts_array_indices() {
+        let mut app_state = AppState::default();
+        let original = serde_json::json!({"items": [{"name": "a"}, {"name": "b", "tags": ["x"]}, {"name": "c"}], "z": 1});
+        app_state.replace_dom(original.clone()).unwrap();
+
+        let removed = app_state.delete_node("$.items[0]").unwrap();
+        assert_eq!(removed, serde_json::json!({"name": "a"}));
+        assert!(app_state.dirty);
+        assert_tree_in_sync(&app_state);
+        assert!(app_state.tree_flat.iter().any(|n| n.path == "$.items[0].tags[0]" && n.preview == "\"x\""));
+        let items = app_state.tree_flat.iter().find(|n| n.path == "$.items").unwrap();
+        assert_eq!((items.children, items.preview.as_str()), (2, "[..] (2 items)"));
+
+        // 删除末尾元素与对象字段
+        app_state.delete_node("$.items[1]").unwrap();
+        app_state.delete_node("$.z").unwrap();
+        assert_tree_in_sync(&app_state);
+        assert_eq!(app_state.dom.as_ref().unwrap(), &serde_json::json!({"items": [{"name": "b", "tags": ["x"]}]}));
+
+        // 逐层撤销删除
+        assert!(app_state.undo().unwrap());
+        assert!(app_state.undo().unwrap());
+        assert!(app_state.undo().unwrap());
+        assert_eq!(app_state.dom.as_ref().unwrap(), &original);
+        assert_tree_in_sync(&app_state);
+
+        assert!(app_state.delete_node("$").is_err());
+        assert!(app_state.delete_node("$.missing").is_err());
+        assert!(!app_state.can_undo(), "失败的删除不留撤销记录");
+    }
+
+    #[test]
+    fn test_insert_child_into_object_and_array() {
+        let mut app_state = AppState::default();
+        app_state.replace_dom(serde_json::json!({"menu": {"a": 1, "c": 3}, "list": ["x", "y"]})).unwrap();
+
+        assert_eq!(app_state.insert_child("$.menu", "b", r#"{"k": [true]}"#).unwrap(), "$.menu.b");
+        assert_eq!(app_state.insert_child("$.menu", "my key", "null").unwrap(), "$.menu['my key']");
+        assert_tree_in_sync(&app_state);
+
+        assert_eq!(app_state.insert_child("$.list", "0", r#""w""#).unwrap(), "$.list[0]");
+        assert_eq!(app_state.insert_child("$.list", "2", r#"["n"]"#).unwrap(), "$.list[2]");
+        assert_eq!(app_state.insert_child("$.list", "", "5").unwrap(), "$.list[4]");
+        assert_tree_in_sync(&app_state);
+        assert_eq!(app_state.dom.as_ref().unwrap()["list"], serde_json::json!(["w", "x", ["n"], "y", 5]));
+
+        assert!(app_state.insert_child("$.menu", "a", "1").is_err(), "键已存在");
+        assert!(app_state.insert_child("$.list", "9", "1").is_err(), "下标越界");
+        assert!(app_state.insert_child("$.menu.a", "x", "1").is_err(), "标量节点下不能插入");
+        assert!(app_state.insert_child("$.menu", "d", "{").is_err(), "无效JSON");
+
+        // 插入后删除再撤销，结构树仍与DOM一致
+        app_state.delete_node("$.list[2]").unwrap();
+        assert_tree_in_sync(&app_state);
+        assert!(app_state.undo().unwrap());
+        assert_eq!(app_state.dom.as_ref().unwrap()["list"][2], serde_json::json!(["n"]));
+    }
+
+    #[test]
+    fn test_index_by_field() {
+        let mut app_state = AppState::default();
+        app_state.replace_dom(serde_json::json!({"users": [{"id": "u1"}, {"id": 2}, {"name": "无id"}, {"id": "u3"}]})).unwrap();
+        assert_eq!(app_state.lookup_by_field("$.users", "id", "u1"), None, "建立索引前查不到");
+
+        app_state.index_by_field("$.users", "id").unwrap();
+        assert_eq!(app_state.lookup_by_field("$.users", "id", "u3").as_deref(), Some("$.users[3]"));
+        assert_eq!(app_state.lookup_by_field("$.users", "id", "2").as_deref(), Some("$.users[1]"));
+        assert_eq!(app_state.lookup_by_field("$.users", "id", "u9"), None);
+        assert_eq!(app_state.lookup_by_field("$.users", "name", "无id"), None, "未对该字段建立索引");
+
+        // 数组变更后索引失效
+        app_state.delete_node("$.users[0]").unwrap();
+        assert_eq!(app_state.lookup_by_field("$.users", "id", "u3"), None);
+        app_state.index_by_field("$.users", "id").unwrap();
+        assert_eq!(app_state.lookup_by_field("$.users", "id", "u3").as_deref(), Some("$.users[2]"));
+
+        app_state.replace_dom(serde_json::json!({"users": [{"id": 1}, {"id": 1}], "x": {}})).unwrap();
+        assert!(app_state.index_by_field("$.users", "id").unwrap_err().to_string().contains("重复"));
+        assert!(app_state.index_by_field("$.x", "id").is_err());
+    }
+
+    #[test]
+    fn test_duplicate_node() {
+        let mut app_state = AppState::default();
+        app_state.replace_dom(serde_json::json!({
+            "menu": {"item": {"title": "Open"}, "item_copy": 1},
+            "list": [{"name": "a"}, {"name": "b"}]
+        })).unwrap();
+
+        // 对象字段：自动追加后缀并跳过已存在的键
+        assert_eq!(app_state.duplicate_node("$.menu.item", None).unwrap(), "$.menu.item_copy2");
+        assert_eq!(app_state.duplicate_node("$.menu.item", None).unwrap(), "$.menu.item_copy3");
+        assert_eq!(app_state.duplicate_node("$.menu.item", Some("save".into())).unwrap(), "$.menu.save");
+        assert!(app_state.duplicate_node("$.menu.item", Some("save".into())).is_err(), "指定的键已存在");
+        let dom = app_state.dom.as_ref().unwrap();
+        assert_eq!(dom["menu"]["item_copy2"], serde_json::json!({"title": "Open"}));
+        assert_eq!(dom["menu"]["item_copy"], 1);
+
+        // 数组元素：插入到紧随其后的下标，后续元素后移
+        assert_eq!(app_state.duplicate_node("$.list[0]", None).unwrap(), "$.list[1]");
+        assert_eq!(app_state.dom.as_ref().unwrap()["list"], serde_json::json!([{"name": "a"}, {"name": "a"}, {"name": "b"}]));
+        assert!(app_state.duplicate_node("$.list[0]", Some("k".into())).is_err());
+        assert_tree_in_sync(&app_state);
+
+        assert!(app_state.duplicate_node("$", None).is_err());
+        assert!(app_state.undo().unwrap());
+        assert_eq!(app_state.dom.as_ref().unwrap()["list"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_estimate_translation_effort() {
+        let mut app_state = AppState::default();
+        assert_eq!(app_state.estimate_translation_effort(None), EffortEstimate::default());
+
+        app_state.replace_dom(serde_json::json!({
+            "a": "Open file",
+            "b": "Open file",
+            "c": "Hello {name}",
+            "d": "Click <b>here</b>",
+            "n": 1
+        })).unwrap();
+        let mut reference = AppState::default();
+        reference.replace_dom(serde_json::json!({"a": "Open file", "b": "打开文件", "c": "Hello {name}"})).unwrap();
+
+        let estimate = app_state.estimate_translation_effort(Some(&reference));
+        assert_eq!(estimate.total_words, 2 + 2 + 2 + 2);
+        assert_eq!(estimate.unique_strings, 3);
+        assert_eq!(estimate.strings_with_html, 1);
+        assert_eq!(estimate.strings_with_placeholders, 1);
+        assert_eq!(estimate.similarity_to_reference, Some(0.5));
+        assert_eq!(app_state.estimate_translation_effort(None).similarity_to_reference, None);
+    }
+
+    #[test]
+    fn test_array_schema_report() {
+        let mut app_state = AppState::default();
+        app_state.replace_dom(serde_json::json!({"items": [{"name": "a"}, {"id": 2}], "title": "x"})).unwrap();
+        let report = app_state.array_schema_report("$.items").unwrap();
+        assert_eq!(report.elements, 2);
+        assert!(report.keys.iter().all(|k| k.heterogeneous && k.present == 1));
+        assert!(app_state.array_schema_report("$.title").is_err());
+        assert!(app_state.array_schema_report("$.missing").is_err());
+    }
+
+    #[test]
+    fn test_inject_seq_numbers() {
+        let json_content = r#"{"items": [{"name": "a"}, "标量", {"name": "c", "id": 99}]}"#;
+        let temp_file = create_test_json_file(json_content);
+
+        let mut app_state = AppState::default();
+        app_state.load_file(temp_file.path()).expect("加载文件失败");
+
+        let modified = app_state.inject_seq_numbers("$.items", "id", 10, 5).unwrap();
+        assert_eq!(modified, 2, "只有对象元素会被修改");
+        assert_eq!(app_state.extract_subtree_pretty("$.items[0].id").unwrap(), "10");
+        assert_eq!(app_state.extract_subtree_pretty("$.items[2].id").unwrap(), "20");
+        assert!(app_state.tree_flat.iter().any(|n| n.path == "$.items[0].id"), "影子树应被重建");
+
+        // 只增模式：保留已有字段
+        let modified = app_state.inject_seq_numbers("$.items", "seq", 0, 0).unwrap();
+        assert_eq!(modified, 2);
+        let modified = app_state.inject_seq_numbers("$.items", "id", 0, 0).unwrap();
+        assert_eq!(modified, 0);
+        assert_eq!(app_state.extract_subtree_pretty("$.items[2].id").unwrap(), "20");
+
+        assert!(app_state.inject_seq_numbers("$.items[0]", "id", 0, 1).is_err(), "非数组路径应报错");
+    }
+
+    #[test]
+    fn test_save_recovery_in_artifacts_dir() {
+        let dir = tempfile::tempdir().expect("创建临时目录失败");
+        std::fs::write(dir.path().join(crate::utils::fs::PROJECT_CONFIG_FILE), r#"{"artifacts_dir": "out"}"#).unwrap();
+        let source = dir.path().join("big.json");
+        std::fs::write(&source, r#"{"a": "值"}"#).unwrap();
+
+        let mut app_state = AppState::default();
+        app_state.load_file(&source).expect("加载文件失败");
+        app_state.update_node_from_str("$.a", "新值").unwrap();
+
+        let recovery = app_state.save_recovery().expect("写入恢复文件失败");
+        let subdir = crate::utils::fs::source_artifacts_subdir(&source);
+        assert_eq!(recovery, dir.path().join("out").join(subdir).join("big.recovery.json"));
+        assert!(std::fs::read_to_string(&recovery).unwrap().contains("新值"));
+    }
+
+    #[test]
+    fn test_load_deeply_nested_file_fails_gracefully() {
+        let depth = 100_000;
+        let content = format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        let file = create_test_json_file(&content);
+
+        let mut app_state = AppState::default();
+        let result = app_state.load_file(file.path());
+        assert!(result.is_err(), "超深嵌套文件应加载失败而不是栈溢出");
+        assert!(app_state.dom.is_none());
+    }
+
+    #[test]
+    fn test_app_error_codes() {
+        let errors = [
+            AppError::io(std::io::Error::other("磁盘已满"), Path::new("a.json")),
+            AppError::Parse(serde_json::from_str::<Value>("{").unwrap_err()),
+            AppError::JsonPath(JsonPathKind::NoMatch, "未匹配到任何节点 ($.foo)".into()),
+            AppError::JsonPath(JsonPathKind::Invalid, "无效路径".into()),
+            AppError::JsonPath(JsonPathKind::WrongType, "父节点不是数组".into()),
+            AppError::not_loaded(),
+            AppError::State(StateKind::Invalid, "无效参数".into()),
+            AppError::State(StateKind::ReadOnly, "只读模式".into()),
+            AppError::State(StateKind::Conflict, "键已存在".into()),
+            AppError::State(StateKind::Malformed, "中间产物格式错误".into()),
+            AppError::State(StateKind::FeatureDisabled, "未启用".into()),
+            AppError::Network("超时".into()),
+            AppError::SchemaViolations(Vec::new()),
+        ];
+        let codes: HashSet<&str> = errors.iter().map(AppError::code).collect();
+        assert_eq!(codes.len(), errors.len(), "每种错误的错误码唯一");
+        for e in &errors {
+            let (zh, en) = (e.localized_message(Lang::Zh), e.localized_message(Lang::En));
+            assert!(!zh.is_empty() && !en.is_empty() && zh != en, "{}", e.code());
+            assert_eq!(e.to_string(), format!("{}: {}", e.code(), zh));
+        }
+        assert_eq!(errors[2].to_string(), "E1203: 未匹配到任何节点 ($.foo)");
+        assert_eq!(errors[5].to_string(), "E1302: DOM尚未加载");
+        assert_eq!(errors[7].localized_message(Lang::En), "Read-only mode: 只读模式");
+        assert_eq!(errors[0].localized_message(Lang::En), "IO failed (a.json): 磁盘已满");
+    }
+
+    #[test]
+    fn test_watch_value() {
+        use std::sync::{Arc, Mutex};
+
+        let mut app_state = AppState::default();
+        app_state.replace_dom(serde_json::json!({"menu": {"open": "Open", "items": ["a", "b"]}, "other": "x"})).unwrap();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let watch = |app_state: &mut AppState, path: &str| {
+            let seen = seen.clone();
+            let tag = path.to_string();
+            app_state.watch_value(path.to_string(), Box::new(move |v: &Value| seen.lock().unwrap().push((tag.clone(), v.clone()))))
+        };
+        let open = watch(&mut app_state, "$.menu.open");
+        watch(&mut app_state, "$.menu");
+
+        // 自身与祖先都被触及；无关路径不触发
+        app_state.update_node_from_str("$.menu.open", "打开").unwrap();
+        app_state.update_node_from_str("$.other", "y").unwrap();
+        let events = std::mem::take(&mut *seen.lock().unwrap());
+        let mut tags: Vec<&str> = events.iter().map(|(tag, _)| tag.as_str()).collect();
+        tags.sort();
+        assert_eq!(tags, ["$.menu", "$.menu.open"]);
+        assert!(events.contains(&("$.menu.open".to_string(), Value::String("打开".into()))));
+
+        // 批量更新与删除：后代变化通知到祖先，删除后传入 null
+        app_state.batch_update(&[("$.menu.items[0]".into(), "甲".into())]).unwrap();
+        assert_eq!(seen.lock().unwrap().drain(..).map(|(tag, _)| tag).collect::<Vec<_>>(), ["$.menu"]);
+        app_state.delete_node("$.menu.open").unwrap();
+        assert!(seen.lock().unwrap().contains(&("$.menu.open".to_string(), Value::Null)));
+        seen.lock().unwrap().clear();
+
+        assert!(app_state.unwatch(open));
+        assert!(!app_state.unwatch(open));
+        app_state.undo().unwrap();
+        assert_eq!(seen.lock().unwrap().iter().map(|(tag, _)| tag.as_str()).collect::<Vec<_>>(), ["$.menu"], "撤销视为整个文档变化");
+    }
+
+    #[test]
+    fn test_every_mutator_notifies_watchers() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let content = r#"{"menu": {"open": "Open", "n": 1}, "items": [{"id": 1}, {"id": 2}]}"#;
+        let file = create_test_json_file(content);
+        let sub = create_test_json_file(r#"{"open": "x"}"#);
+        type Mutation<'a> = Box<dyn Fn(&mut AppState) -> Result<(), AppError> + 'a>;
+        // (名称, 监听路径, 编辑)；数组元素的插入、删除、移动要通知到下标后移的兄弟
+        let mutations: Vec<(&str, &str, Mutation)> = vec![
+            ("update_node_from_str", "$.menu.open", Box::new(|s| s.update_node_from_str("$.menu.open", "打开"))),
+            ("update_json_value", "$.menu.open", Box::new(|s| s.update_json_value("$.menu.open", "打开"))),
+            ("batch_update", "$.menu.open", Box::new(|s| s.batch_update(&[("$.menu.open".into(), "打开".into())]).map(drop))),
+            ("replace_subtree_from_file", "$.menu.open", Box::new(|s| s.replace_subtree_from_file("$.menu", sub.path(), SubtreeMergeMode::Replace).map(drop))),
+            ("undo", "$.menu.open", Box::new(|s| s.undo().map(drop))),
+            ("inject_seq_numbers", "$.items[0].seq", Box::new(|s| s.inject_seq_numbers("$.items", "seq", 0, 1).map(drop))),
+            ("rebase", "$.open", Box::new(|s| s.rebase("$.menu"))),
+            ("clamp_number_values", "$['menu'].n", Box::new(|s| s.clamp_number_values("$.menu.n", 5.0, 9.0).map(drop))),
+            ("rename_key", "$.menu.opened", Box::new(|s| s.rename_key("open", "opened", None).map(drop))),
+            ("delete_node", "$.items[1].id", Box::new(|s| s.delete_node("$.items[0]").map(drop))),
+            ("insert_child", "$.items[1].id", Box::new(|s| s.insert_child("$.items", "0", "{}").map(drop))),
+            ("increment_number", "$.menu.n", Box::new(|s| s.increment_number("$.menu.n", 1.0).map(drop))),
+            ("convert_to_array", "$.menu.open", Box::new(|s| s.convert_to_array("$.menu.open", None))),
+            ("convert_to_object", "$.menu.n", Box::new(|s| s.convert_to_object("$.menu.n", "v"))),
+            ("swap_values", "$.menu.n", Box::new(|s| s.swap_values("$.menu.open", "$.menu.n"))),
+            ("reorder_sibling", "$.items[1].id", Box::new(|s| s.reorder_sibling("$.items[0]", 1))),
+            ("duplicate_node", "$.items[1].id", Box::new(|s| s.duplicate_node("$.items[0]", None).map(drop))),
+            ("apply_value_pipeline", "$.menu.open", Box::new(|s| s.apply_value_pipeline(vec![ValueTransform::ToUppercase]).map(drop))),
+            ("apply_llm_translation_response", "$.menu.open", Box::new(|s| s.apply_llm_translation_response("$.menu.open: 打开").map(drop))),
+            ("replace_dom", "$.menu.open", Box::new(|s| s.replace_dom(serde_json::json!({"menu": {"open": "x"}})))),
+            ("reload_preserving_state", "$.menu.open", Box::new(|s| s.reload_preserving_state(file.path()))),
+        ];
+        for (name, watched, mutate) in &mutations {
+            let mut app_state = AppState::default();
+            app_state.load_file(file.path()).unwrap();
+            app_state.push_undo();
+            let calls = Arc::new(AtomicUsize::new(0));
+            let counter = calls.clone();
+            app_state.watch_value(watched.to_string(), Box::new(move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }));
+            // 与编辑无关的路径不触发
+            let unrelated = Arc::new(AtomicUsize::new(0));
+            let counter = unrelated.clone();
+            app_state.watch_value("$.nothing".to_string(), Box::new(move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            }));
+            mutate(&mut app_state).unwrap_or_else(|e| panic!("{}: {}", name, e));
+            assert_eq!(calls.load(Ordering::SeqCst), 1, "{} 未通知 {}", name, watched);
+            assert_eq!(unrelated.load(Ordering::SeqCst), usize::from(matches!(*name, "undo" | "rebase" | "apply_value_pipeline" | "replace_dom" | "reload_preserving_state")), "{}", name);
+        }
+
+        // 删除数组元素只通知下标后移的兄弟，之前的兄弟不受影响
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        app_state.watch_value("$.items[0].id".to_string(), Box::new(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }));
+        app_state.delete_node("$.items[1]").unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        assert!(app_state.dirty);
+    }
+
+    #[test]
+    fn test_set_source_alias() {
+        let file = create_test_json_file(r#"{"hello": "Hello"}"#);
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
+        assert_eq!(app_state.source_path, app_state.original_file_path);
+
+        // 别名只改变来源标注，不影响保存目标
+        app_state.set_source_alias("https://example.com/i18n/app.json");
+        assert_eq!(app_state.source_path.as_deref(), Some(Path::new("https://example.com/i18n/app.json")));
+        assert_eq!(app_state.original_file_path.as_deref(), Some(file.path()));
+        assert!(app_state.export_key_value_pairs_for_llm(None, 1).unwrap().starts_with("# source_path: https://example.com/i18n/app.json\n"));
+    }
+
+    #[test]
+    fn test_summarize_changes_since_load() {
+        let file = create_test_json_file(r#"{"menu": {"open": "Open", "close": "Close"}}"#);
+        let mut app_state = AppState::default();
+        assert!(app_state.summarize_changes_since_load().is_err());
+        app_state.load_file(file.path()).unwrap();
+        assert_eq!(app_state.summarize_changes_since_load().unwrap().total, 0);
+
+        app_state.update_node_from_str("$.menu.open", "打开").unwrap();
+        app_state.insert_child("$.menu", "save", r#""Save""#).unwrap();
+        app_state.delete_node("$.menu.close").unwrap();
+        let summary = app_state.summarize_changes_since_load().unwrap();
+        let paths = |entries: &[DiffEntry]| entries.iter().map(|e| e.path.clone()).collect::<Vec<_>>();
+        assert_eq!(summary.total, 3);
+        assert_eq!(paths(&summary.added), ["$.menu.save"]);
+        assert_eq!(paths(&summary.removed), ["$.menu.close"]);
+        assert_eq!(paths(&summary.changed), ["$.menu.open"]);
+        assert!(summary.format_report().starts_with("== 本次会话共 3 处改动"));
+
+        // 撤销结构性编辑后只剩值的修改，基准不随撤销变化
+        app_state.undo().unwrap();
+        app_state.undo().unwrap();
+        let summary = app_state.summarize_changes_since_load().unwrap();
+        assert_eq!((summary.total, paths(&summary.changed)), (1, vec!["$.menu.open".to_string()]));
+    }
+
+    #[test]
+    fn test_read_only_rejects_mutations() {
+        let file = create_test_json_file(r#"{"menu": {"open": "Open", "n": 1}, "items": [{"id": 1}, {"id": 2}]}"#);
+        let sub = create_test_json_file(r#"{"x": 1}"#);
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
+        app_state.read_only = true;
+        let before = app_state.dom.clone();
+
+        let out_dir = tempfile::tempdir().unwrap();
+        let target = out_dir.path().join("out.json");
+        type Mutation<'a> = Box<dyn Fn(&mut AppState) -> Result<(), AppError> + 'a>;
+        let mutations: Vec<(&str, Mutation)> = vec![
+            ("update_node_from_str", Box::new(|s| s.update_node_from_str("$.menu.open", "打开"))),
+            ("update_json_value", Box::new(|s| s.update_json_value("$.menu.open", "打开"))),
+            ("batch_update", Box::new(|s| s.batch_update(&[("$.menu.open".into(), "打开".into())]).map(drop))),
+            ("replace_subtree_from_file", Box::new(|s| s.replace_subtree_from_file("$.menu", sub.path(), SubtreeMergeMode::Replace).map(drop))),
+            ("undo", Box::new(|s| s.undo().map(drop))),
+            ("inject_seq_numbers", Box::new(|s| s.inject_seq_numbers("$.items", "seq", 0, 1).map(drop))),
+            ("rebase", Box::new(|s| s.rebase("$.menu"))),
+            ("clamp_number_values", Box::new(|s| s.clamp_number_values("$.menu.n", 5.0, 9.0).map(drop))),
+            ("rename_key", Box::new(|s| s.rename_key("open", "opened", None).map(drop))),
+            ("delete_node", Box::new(|s| s.delete_node("$.menu.open").map(drop))),
+            ("insert_child", Box::new(|s| s.insert_child("$.menu", "save", r#""Save""#).map(drop))),
+            ("increment_number", Box::new(|s| s.increment_number("$.menu.n", 1.0).map(drop))),
+            ("convert_to_array", Box::new(|s| s.convert_to_array("$.menu.open", None))),
+            ("convert_to_object", Box::new(|s| s.convert_to_object("$.items", "id"))),
+            ("swap_values", Box::new(|s| s.swap_values("$.menu.open", "$.menu.n"))),
+            ("reorder_sibling", Box::new(|s| s.reorder_sibling("$.items[0]", 1))),
+            ("duplicate_node", Box::new(|s| s.duplicate_node("$.menu.open", None).map(drop))),
+            ("apply_value_pipeline", Box::new(|s| s.apply_value_pipeline(vec![ValueTransform::ToUppercase]).map(drop))),
+            ("apply_llm_translation_response", Box::new(|s| s.apply_llm_translation_response(r#"{"$.menu.open": "打开"}"#).map(drop))),
+            ("replace_dom", Box::new(|s| s.replace_dom(serde_json::json!({"menu": {}})))),
+            ("save_to_file", Box::new(|s| s.save_to_file(file.path()))),
+            ("save_to_original_file", Box::new(|s| s.save_to_original_file())),
+            ("save_to_original_file_backup", Box::new(|s| s.save_to_original_file_backup(Some(out_dir.path())).map(drop))),
+            ("save_modified_json", Box::new(|s| s.save_modified_json(file.path()))),
+            ("save_minified", Box::new(|s| s.save_minified(file.path()))),
+            ("save_subtree", Box::new(|s| s.save_subtree(file.path(), "$", SaveOptions::default()).map(drop))),
+            ("export_tree_snapshot", Box::new(|s| s.export_tree_snapshot(file.path(), false).map(drop))),
+            ("save_recovery", Box::new(|s| s.save_recovery().map(drop))),
+            ("save_annotation_sidecar", Box::new(|s| s.save_annotation_sidecar().map(drop))),
+        ];
+        // 没有备注时保存旁路文件会删除它，只读模式下同样不允许
+        let sidecar = annotations_sidecar_path(file.path());
+        std::fs::write(&sidecar, r#"{"$.menu": "旧备注"}"#).unwrap();
+        let original_text = std::fs::read_to_string(file.path()).unwrap();
+        for (name, mutate) in &mutations {
+            let err = mutate(&mut app_state).expect_err(name);
+            assert!(err.to_string().contains("只读模式"), "{}: {}", name, err);
+            assert_eq!(app_state.dom, before, "{} 修改了文档", name);
+        }
+        assert_eq!(std::fs::read_to_string(file.path()).unwrap(), original_text, "原文件不应被改写");
+        assert!(sidecar.exists(), "备注旁路文件不应被删除");
+        std::fs::remove_file(&sidecar).unwrap();
+        let failures = app_state.bulk_export_subtrees(&[("$".to_string(), file.path().to_path_buf())]).unwrap();
+        assert!(failures[0].1.to_string().contains("只读模式"));
+        assert!(!target.exists());
+
+        // 提取、搜索、另存为与导出到其他文件不受影响
+        assert_eq!(app_state.extract_subtree_pretty("$.menu.n").unwrap(), "1");
+        app_state.save_subtree(&target, "$.menu", SaveOptions::default()).unwrap();
+        assert!(target.exists());
+        let save_as = out_dir.path().join("save_as.json");
+        app_state.save_to_file(&save_as).unwrap();
+        assert_eq!(read_json_file_checked(&save_as).unwrap().0, before.clone().unwrap());
+
+        app_state.read_only = false;
+        app_state.update_node_from_str("$.menu.open", "打开").unwrap();
+    }
+
+    #[test]
+    fn test_set_indent_size() {
+        let file = create_test_json_file(r#"{"menu": {"open": "Open", "tags": ["a", "b"]}}"#);
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
+        assert!(app_state.extract_subtree_pretty("$.menu").unwrap().contains("\n  \"open\""), "默认缩进 2 个空格");
+
+        app_state.set_indent_size(4).unwrap();
+        assert!(app_state.extract_subtree_pretty("$.menu").unwrap().contains("\n    \"open\": \"Open\""));
+        let stage2 = app_state.build_intermediate_stage2("open", |_, _| {}).unwrap();
+        assert!(stage2.contains("\n    \"items\": ["), "{}", stage2);
+
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("out.json");
+        app_state.save_to_file(&target).unwrap();
+        assert!(std::fs::read_to_string(&target).unwrap().starts_with("{\n    \"menu\": {\n        \"open\""));
+
+        assert!(app_state.set_indent_size(3).is_err());
+        assert_eq!(app_state.save_options().indent_size, 4);
+    }
+
+    #[test]
+    fn test_preview_stage2_matches_full_run() {
+        let file = create_test_json_file(
+            r#"{"menu": [{"title": "Open", "name": "open"}, {"title": "Save", "name": "save"}, {"title": "Quit", "name": "quit"}], "about": {"title": "About"}}"#,
+        );
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
+        let options = Stage2Options { leaf_nodes_only: true, sort: Stage2Sort::DocumentOrder, ..Stage2Options::default() };
+
+        let full: Value = serde_json::from_str(&app_state.build_intermediate_stage2_with_options("title", options.clone(), |_, _| {}).unwrap()).unwrap();
+        let preview: Value = serde_json::from_str(&app_state.preview_stage2("title", options.clone(), 2).unwrap()).unwrap();
+        assert_eq!(preview["stage"], "intermediate2_preview");
+        assert_eq!(preview["estimated_total"], 4);
+        assert_eq!(preview["count"], 2);
+        assert_eq!(preview["items"].as_array().unwrap()[..], full["items"].as_array().unwrap()[..2]);
+
+        let all: Value = serde_json::from_str(&app_state.preview_stage2("title", options.clone(), 10).unwrap()).unwrap();
+        assert_eq!(all["items"], full["items"]);
+        assert_eq!(app_state.preview_stage2("  ", options.clone(), 2).unwrap(), "");
+
+        // 多个过滤条件与正式构建的拆分方式一致
+        let filters = parse_filter_list("title, name");
+        let full: Value = serde_json::from_str(&app_state.build_intermediate_stage2_multi(&filters, options.clone(), |_, _| {}).unwrap()).unwrap();
+        let preview: Value = serde_json::from_str(&app_state.preview_stage2("title, name", options, 10).unwrap()).unwrap();
+        assert_eq!(preview["estimated_total"], 7);
+        assert_eq!(preview["filter"], "title | name");
+        assert_eq!(preview["items"], full["items"]);
+    }
+
+    #[test]
+    fn test_get_node_info() {
+        let file = create_test_json_file(r#"{"menu": {"open": "Open", "items": ["a", {"label": "B"}]}, "n": 1}"#);
+        let mut app_state = AppState::default();
+        assert!(app_state.get_node_info("$").is_err(), "未加载时应报错");
+        app_state.load_file(file.path()).unwrap();
+
+        let root = app_state.get_node_info("$").unwrap();
+        assert_eq!((root.kind, root.depth, root.children_count), (NodeKind::Object, 0, 2));
+        assert_eq!((root.parent_path, root.index_in_parent), (None, None));
+
+        let item = app_state.get_node_info("$.menu.items[1]").unwrap();
+        assert_eq!(item.path, "$.menu.items[1]");
+        assert_eq!((item.kind, item.depth, item.children_count), (NodeKind::Object, 3, 1));
+        assert_eq!(item.full_value, serde_json::json!({"label": "B"}));
+        assert_eq!(item.byte_size, r#"{"label":"B"}"#.len());
+        assert_eq!((item.parent_path.as_deref(), item.index_in_parent), (Some("$.menu.items"), Some(1)));
+
+        // 对象中的位置按键的顺序
+        let open = app_state.get_node_info("$['menu']['open']").unwrap();
+        assert_eq!((open.path.as_str(), open.kind, open.preview.as_str()), ("$.menu.open", NodeKind::String, "\"Open\""));
+        assert_eq!((open.parent_path.as_deref(), open.index_in_parent), (Some("$.menu"), Some(0)));
+
+        assert!(app_state.get_node_info("$.menu.missing").is_err());
+        assert!(app_state.get_node_info("$.menu.items[*]").is_err(), "通配符路径不是单个节点");
+
+        // 状态栏摘要不克隆子树，也不计入访问统计
+        app_state.reset_access_log();
+        app_state.extract_subtree_preview("$.menu.items[1]", 80).unwrap();
+        assert_eq!(app_state.node_status_line("$.menu.items[1]").unwrap(), "$.menu.items[1]: Object，1 个子节点，13 字节");
+        assert_eq!(app_state.profile_hot_paths(5), [("$.menu.items[1]".to_string(), 1)]);
+        assert!(app_state.node_status_line("$.menu.items[*]").is_err());
+    }
+
+    #[test]
+    fn test_count_matches_options() {
+        let file = create_test_json_file(
+            r#"{"menu": {"title": "Menu", "items": [{"title": "Open", "count": 1}, {"title": "Debug", "debug": true}]}, "about": {"title": {"text": "About"}}}"#,
+        );
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
+        let count = |filter: &str, opts: MatchCountOptions| {
+            let counts = app_state.count_matches(filter, &opts);
+            (counts.total, counts.string_leaves)
+        };
+
+        // 全部节点模式：路径命中 title 的节点（含 $.about.title 对象及其下的 text）
+        assert_eq!(count("title", MatchCountOptions::default()), (5, 4));
+        // 叶子模式只看属性名，且对象不计
+        assert_eq!(count("title", MatchCountOptions { leaf_nodes_only: true, ..Default::default() }), (3, 3));
+        assert_eq!(count("title", MatchCountOptions { kinds: vec![NodeKind::Object], ..Default::default() }), (1, 0));
+        assert_eq!(count("title", MatchCountOptions { exclude: vec!["items".into()], ..Default::default() }), (3, 2));
+        assert_eq!(
+            count("title, debug", MatchCountOptions { leaf_nodes_only: true, kinds: vec![NodeKind::String, NodeKind::Bool], exclude: vec!["menu.title".into()] }),
+            (3, 2)
+        );
+        // 过滤条件自身的排除词同样生效
+        assert_eq!(count("title -about", MatchCountOptions::default()), (3, 3));
+        assert_eq!(count("  ", MatchCountOptions::default()), (0, 0));
+
+        let full: Value = serde_json::from_str(&app_state.build_intermediate_stage2_with_leaf_filter("title", true, |_, _| {}).unwrap()).unwrap();
+        assert_eq!(full["count"], 3, "统计结果与中间产物2一致");
+        assert_eq!(MatchCounts { total: 1240, string_leaves: 860 }.describe(), "将匹配 1,240 个节点（其中字符串叶子 860）");
+        assert_eq!(group_thousands(1_234_567), "1,234,567");
+    }
+
+    #[test]
+    fn test_find_orphaned_paths() {
+        let file = create_test_json_file(r#"{"menu": {"open": "Open", "items": ["a"]}}"#);
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
+        assert!(app_state.path_exists("$.menu.items[0]"));
+        assert!(!app_state.path_exists("$.menu.items[1]"));
+        assert!(!app_state.path_exists("$..open"), "通配路径不视为存在");
+
+        let stage2 = r#"{"items": [
+            {"seq": 0, "source_path": "$.menu.open"},
+            {"seq": 1, "source_path": "$.menu.close"},
+            {"seq": 2, "source_path": "$.menu.close"},
+            {"seq": 3, "source_path": "$.menu.items[3]"}
+        ]}"#;
+        assert_eq!(app_state.find_orphaned_paths(stage2).unwrap(), ["$.menu.close", "$.menu.items[3]"]);
+        assert_eq!(app_state.find_orphaned_paths(r#"{"$.menu.open": "打开", "$.gone": "没了"}"#).unwrap(), ["$.gone"]);
+        assert!(app_state.find_orphaned_paths(r#"{"0": "打开"}"#).unwrap().is_empty());
+        assert!(app_state.find_orphaned_paths("{").is_err());
+        assert!(AppState::default().find_orphaned_paths(stage2).is_err());
+    }
+
+    #[test]
+    fn test_extract_string_collections() {
+        let file = create_test_json_file(r#"{"zh": {"b": "乙", "a": "甲", "n": 1, "o": {}}, "tags": ["x", 2, "y"]}"#);
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
+
+        let map = app_state.extract_object_as_string_map("$.zh").unwrap();
+        // 按文件中的键顺序
+        assert_eq!(map.into_iter().collect::<Vec<_>>(), [("b".to_string(), "乙".to_string()), ("a".to_string(), "甲".to_string())]);
+        assert_eq!(app_state.extract_array_as_string_vec("$.tags").unwrap(), ["x", "y"]);
+        assert!(app_state.extract_object_as_string_map("$.tags").is_err());
+        assert!(app_state.extract_array_as_string_vec("$.zh").is_err());
+        assert!(app_state.extract_array_as_string_vec("$.missing").is_err());
+
+        // 重命名与插入后仍按文档中的键顺序
+        app_state.rename_key("b", "c", Some("$.zh")).unwrap();
+        app_state.insert_child("$.zh", "0", r#""零""#).unwrap();
+        let keys: Vec<String> = app_state.extract_object_as_string_map("$.zh").unwrap().into_keys().collect();
+        assert_eq!(keys, ["c", "a", "0"]);
+    }
+
+    #[test]
+    fn test_max_nodes_and_full_load() {
+        let file = create_test_json_file(r#"{"a": "A", "b": "B", "c": {"d": "D"}}"#);
+        let mut app_state = AppState::default();
+        app_state.shadow_tree_options.max_nodes = Some(3);
+        app_state.load_file(file.path()).unwrap();
+        assert!(app_state.truncated);
+        assert_eq!(app_state.tree_flat.len(), 4);
+        assert_eq!(app_state.tree_flat[3].preview, "2 个节点未展示");
+        // 未展示的节点仍能被搜索、提取、导出与计算大小（按DOM构建完整节点列表）
+        let found = app_state.search("d", SearchMode::Substring).unwrap();
+        assert_eq!(found.iter().map(|m| m.node.path.as_str()).collect::<Vec<_>>(), ["$.c.d"]);
+        assert_eq!(app_state.tree_flat.len(), 4, "搜索不改变展示的影子树");
+        assert_eq!(app_state.extract_leaf_text("$.c.d").unwrap(), "D");
+        assert_eq!(app_state.count_matches("d", &MatchCountOptions::default()).total, 1);
+        assert_eq!(app_state.count_matches("…, 未展示", &MatchCountOptions::default()).total, 0, "截断标记不计入");
+        app_state.search("", SearchMode::Substring).unwrap();
+        let stage2: Value = serde_json::from_str(&app_state.build_intermediate_stage2("d", |_, _| {}).unwrap()).unwrap();
+        assert_eq!(stage2["items"][0]["source_path"], "$.c.d");
+        assert!(app_state.export_key_value_pairs_for_llm(None, 10).unwrap().contains("$.c.d: D"));
+        assert_eq!(app_state.compute_subtree_sizes().unwrap(), 31);
+        assert_eq!(app_state.tree_flat[1].size_bytes, 3);
+        assert_eq!(app_state.largest_subtrees(1).len(), 1);
+
+        // 编辑后的重建同样遵守上限
+        app_state.update_node_from_str("$.a", "A2").unwrap();
+        assert!(app_state.truncated);
+
+        app_state.load_full_tree().unwrap();
+        assert!(!app_state.truncated);
+        assert_eq!(app_state.tree_flat.len(), 5);
+        assert!(!app_state.tree_flat.iter().any(JsonTreeNode::is_truncation_sentinel));
+        assert_eq!(app_state.search("d", SearchMode::Substring).unwrap().len(), 1);
+        app_state.update_node_from_str("$.a", "A3").unwrap();
+        assert!(!app_state.truncated, "完整加载后不再截断");
+    }
+
+    #[test]
+    fn test_rebase_and_relative_path() {
+        let json_content = r#"{"config": {"translations": {"hello": "你好", "list": ["a"]}}, "other": 1}"#;
+        let file = create_test_json_file(json_content);
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
+
+        app_state.rebase("$.config.translations").expect("rebase失败");
+        assert_eq!(app_state.dom.as_ref().unwrap()["hello"], "你好");
+        assert!(app_state.dom.as_ref().unwrap().get("other").is_none());
+        assert_eq!(app_state.tree_flat[0].path, "$");
+        assert!(app_state.tree_flat.iter().any(|n| n.path == "$.list[0]"));
+        assert!(app_state.source_path.as_ref().unwrap().to_string_lossy().ends_with("#$.config.translations"));
+        assert!(app_state.original_file_path.is_none(), "rebase后不应再回写原文件");
+        assert_eq!(app_state.summarize_changes_since_load().unwrap().total, 0, "改动基准应随之换根");
+        app_state.update_node_from_str("$.hello", "您好").unwrap();
+        let summary = app_state.summarize_changes_since_load().unwrap();
+        assert_eq!(summary.changed.iter().map(|e| e.path.as_str()).collect::<Vec<_>>(), ["$.hello"]);
+        assert_eq!(summary.total, 1);
+
+        let base = "$.config.translations";
+        assert_eq!(AppState::get_relative_path(base, "$.config.translations.hello"), Some("$.hello".to_string()));
+        assert_eq!(AppState::get_relative_path(base, "$.config.translations['a b']"), Some("$['a b']".to_string()));
+        assert_eq!(AppState::get_relative_path(base, base), Some("$".to_string()));
+        assert_eq!(AppState::get_relative_path(base, "$.config.translationsX"), None);
+        assert_eq!(AppState::get_relative_path(base, "$.other"), None);
+    }
+
+    #[test]
+    fn test_load_file_reports_duplicate_keys() {
+        let json_content = r#"{"items": [{"name": "旧", "name": "新"}], "title": "a", "title": "b"}"#;
+        let file = create_test_json_file(json_content);
+
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).expect("默认模式下重复键只警告");
+        assert_eq!(app_state.load_warnings.len(), 2);
+        assert!(app_state.load_warnings[0].contains("$.items[0].name"));
+        assert!(app_state.load_warnings[1].contains("$.title"));
+        assert_eq!(app_state.dom.as_ref().unwrap()["title"], "b");
+
+        let mut strict = AppState { reject_duplicate_keys: true, ..Default::default() };
+        let err = strict.load_file(file.path()).expect_err("严格模式下应拒绝加载");
+        assert!(err.to_string().contains("$.items[0].name"));
+        assert!(strict.dom.is_none());
+    }
+
+    #[test]
+    fn test_clamp_and_increment_numbers() {
+        let json_content = r#"{"settings": {"volume": 150, "ratio": -0.5, "name": "x", "level": 3}}"#;
+        let file = create_test_json_file(json_content);
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
+
+        let modified = app_state.clamp_number_values("$.settings.*", 0.0, 100.0).unwrap();
+        assert_eq!(modified, 2);
+        let settings = &app_state.dom.as_ref().unwrap()["settings"];
+        assert_eq!(settings["volume"], 100);
+        assert_eq!(settings["ratio"], 0);
+        assert_eq!(settings["name"], "x");
+        assert_eq!(settings["level"], 3);
+
+        assert_eq!(app_state.increment_number("$.settings.level", 2.0).unwrap(), 5.0);
+        assert_eq!(app_state.increment_number("$.settings.level", 0.5).unwrap(), 5.5);
+        assert_eq!(app_state.dom.as_ref().unwrap()["settings"]["level"], 5.5);
+        assert!(app_state.tree_flat.iter().any(|n| n.path == "$.settings.level" && n.preview == "5.5"));
+
+        let err = app_state.increment_number("$.settings.name", 1.0).unwrap_err();
+        assert!(err.to_string().contains("not a number"));
+        assert!(app_state.clamp_number_values("$.settings.*", 10.0, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_structure_preserved_after_mutations() {
+        let json_content = r#"{"items": [{"id": 1, "title": " 甲 "}, {"id": 2, "title": "乙"}], "meta": {"count": 150, "tag": null}}"#;
+        let file = create_test_json_file(json_content);
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
+
+        app_state.clamp_number_values("$.meta.count", 0.0, 100.0).unwrap();
+        app_state.increment_number("$.items[1].id", 10.0).unwrap();
+        app_state.apply_value_pipeline(vec![ValueTransform::Trim]).unwrap();
+        app_state.update_json_value("$.items[0].title", "甲").unwrap();
+        app_state
+            .assert_structure_matches(r#"{"items": [{"id": 0, "title": ""}, {"id": 0, "title": ""}], "meta": {"count": 0, "tag": null}}"#)
+            .unwrap();
+
+        app_state.inject_seq_numbers("$.items", "seq", 0, 1).unwrap();
+        app_state.update_json_value("$.meta.tag", "标签").unwrap();
+        let mismatches = app_state
+            .assert_structure_matches(r#"{"items": [{"id": 0, "title": ""}, {"id": 0, "title": ""}, {}], "meta": {"count": 0, "tag": null}}"#)
+            .unwrap_err();
+        let summary: Vec<(&str, &str, &str)> =
+            mismatches.iter().map(|m| (m.path.as_str(), m.expected.as_str(), m.actual.as_str())).collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("$.items[0].seq", "缺失", "Number"),
+                ("$.items[1].seq", "缺失", "Number"),
+                ("$.items[2]", "Object", "缺失"),
+                ("$.meta.tag", "Null", "String"),
+            ]
+        );
+        assert!(AppState::default().assert_structure_matches("{}").is_err());
+    }
+
+    #[test]
+    fn test_untouched_number_literals_survive_writeback() {
+        let json_content = r#"{"id": 18446744073709551615, "price": 0.10, "big": 1e21, "small": 1E5, "one": 1.0, "zero": -0, "title": "旧", "items": [{"p": 0.5}, {"p": 0.01}]}"#;
+        let file = create_test_json_file(json_content);
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
+
+        let preview = |state: &AppState, path: &str| {
+            state.tree_flat.iter().find(|n| n.path == path).map(|n| n.preview.clone()).unwrap()
+        };
+        assert_eq!(preview(&app_state, "$.price"), "0.10");
+        assert_eq!(preview(&app_state, "$.id"), "18446744073709551615");
+        assert_eq!(preview(&app_state, "$.big"), "1e21");
+
+        app_state.update_node_from_str("$.title", "新").unwrap();
+        let hits = app_state.dom.as_ref().unwrap().query("$.items[?@.p > 0.05]").unwrap();
+        assert_eq!(hits.len(), 1, "数值过滤仍应可用");
+
+        let out = NamedTempFile::new().unwrap();
+        app_state.save_to_file(out.path()).unwrap();
+        let written = std::fs::read_to_string(out.path()).unwrap();
+        for (key, literal) in [("id", "18446744073709551615"), ("price", "0.10"), ("big", "1e21"), ("small", "1E5"), ("one", "1.0"), ("zero", "-0")] {
+            assert!(written.contains(&format!("\"{}\": {},", key, literal)), "未修改的数字应保持原字面量: {}", literal);
+        }
+    }
+
+    #[test]
+    fn test_save_minified_and_with_options() {
+        let file = create_test_json_file(r#"{"b": {"y": 1, "x": [1, 2]}, "a": "值"}"#);
+        let mut app_state = AppState::default();
+        assert!(app_state.save_minified(file.path()).is_err(), "DOM未加载时应报错");
+        app_state.load_file(file.path()).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let minified = dir.path().join("min.json");
+        app_state.save_minified(&minified).unwrap();
+        let text = std::fs::read_to_string(&minified).unwrap();
+        assert!(!text.contains(char::is_whitespace), "紧凑输出不应包含空白: {}", text);
+
+        assert!(text.starts_with(r#"{"b":{"y":1,"#), "默认保留文件中的键顺序: {}", text);
+
+        let pretty = dir.path().join("pretty.json");
+        let options = SaveOptions { pretty: true, indent_size: 4, sort_keys: true };
+        app_state.save_to_file_with_options(&pretty, options).unwrap();
+        let text = std::fs::read_to_string(&pretty).unwrap();
+        assert!(text.starts_with("{\n    \"a\""));
+        assert_eq!(read_json_file_checked(&pretty).unwrap().0, app_state.dom.clone().unwrap());
+    }
+
+    #[test]
+    fn test_schema_gates_save() {
+        let file = create_test_json_file(r#"{"count": 3}"#);
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
+        assert!(app_state.validate_dom().is_empty(), "未设置 Schema 时不校验");
+
+        let schema = create_test_json_file(r#"{"properties": {"count": {"type": "integer"}}}"#);
+        let out = NamedTempFile::new().unwrap();
+        if cfg!(feature = "schema") {
+            app_state.set_schema(schema.path()).unwrap();
+            app_state.save_to_file(out.path()).unwrap();
+            app_state.dom = Some(serde_json::json!({"count": "3"}));
+            let err = app_state.save_to_file(out.path()).unwrap_err();
+            assert!(matches!(&err, AppError::SchemaViolations(v) if v[0].pointer == "/count"), "{}", err);
+            app_state.save_to_file_ignoring_schema(out.path()).unwrap();
+            assert_eq!(std::fs::read_to_string(out.path()).unwrap().trim(), "{\n  \"count\": \"3\"\n}");
+        } else {
+            assert!(app_state.set_schema(schema.path()).is_err());
+            assert!(app_state.schema_source().is_none());
+        }
+    }
+
+    #[test]
+    fn test_extract_all_matching() {
+        let json_content = r#"{"items": [{"name": "甲"}, {"name": "乙"}, {"id": 3}]}"#;
+        let file = create_test_json_file(json_content);
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
+
+        let hits = app_state.extract_all_matching("$.items[*].name").unwrap();
+        assert_eq!(hits, vec![Value::from("甲"), Value::from("乙")]);
+
+        let pretty = app_state.extract_all_matching_pretty("$.items[*].name").unwrap();
+        let parsed: Value = serde_json::from_str(&pretty).unwrap();
+        assert_eq!(parsed, serde_json::json!(["甲", "乙"]));
+
+        assert!(app_state.extract_all_matching("$.missing[*]").unwrap().is_empty());
+        assert!(app_state.extract_all_matching("$.items[").is_err());
+    }
+
+    #[test]
+    fn test_convert_to_array_and_object() {
+        let file = create_test_json_file(r#"{"langs": "en, zh,,ja", "title": "Open", "count": 3, "tags": ["a"]}"#);
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
+
+        app_state.convert_to_array("$.langs", Some(",")).unwrap();
+        app_state.convert_to_array("$.title", None).unwrap();
+        app_state.convert_to_object("$.count", "value").unwrap();
+        let dom = app_state.dom.clone().unwrap();
+        assert_eq!(dom["langs"], serde_json::json!(["en", "zh", "ja"]));
+        assert_eq!(dom["title"], serde_json::json!(["Open"]));
+        assert_eq!(dom["count"], serde_json::json!({"value": 3}));
+        assert!(app_state.tree_flat.iter().any(|n| n.path == "$.langs[2]"));
+        assert!(app_state.tree_flat.iter().any(|n| n.path == "$.count.value"));
+        assert!(app_state.dirty && app_state.can_undo());
+
+        assert!(app_state.convert_to_array("$.tags", Some(",")).is_err(), "非字符串不能拆分");
+        assert!(app_state.convert_to_array("$.langs[0]", Some("")).is_err());
+        assert!(app_state.convert_to_object("$.tags", "k").is_err(), "容器不能包装");
+        assert!(app_state.convert_to_object("$.missing", "k").is_err());
+        assert_eq!(app_state.dom.as_ref().unwrap(), &dom, "失败的转换不修改DOM");
+    }
+
+    #[test]
+    fn test_build_intermediate_stage1() {
+        let file = create_test_json_file(r#"{"menu": {"title": "Open", "items": [{"title": "A"}]}, "footer": {"text": "x"}}"#);
+        let mut app_state = AppState::default();
+        assert!(app_state.build_intermediate_stage1("title", false).is_err(), "DOM未加载时应报错");
+        app_state.load_file(file.path()).unwrap();
+
+        let stage1: Value = serde_json::from_str(&app_state.build_intermediate_stage1("title", false).unwrap()).unwrap();
+        assert_eq!(stage1["stage"], "intermediate1");
+        assert_eq!(stage1["count"], 2);
+        assert_eq!(stage1["items"][0], serde_json::json!({"source_path": "$.menu.title", "preview": "\"Open\""}));
+        assert_eq!(stage1["items"][1]["source_path"], "$.menu.items[0].title");
+        assert!(stage1["items"][0].get("name_path").is_none());
+
+        // 与中间产物2命中同一批节点
+        let stage2: Value = serde_json::from_str(&app_state.build_intermediate_stage2("title", |_, _| {}).unwrap()).unwrap();
+        assert_eq!(stage2["count"], stage1["count"]);
+        assert_eq!(app_state.build_intermediate_stage1("  ", false).unwrap(), "");
+    }
+
+    #[test]
+    fn test_build_intermediate_stage1_matches_stage2_filters() {
+        let file = create_test_json_file(r#"{"menu": {"title": "Open", "name": {"en": "x"}}, "footer": {"text": "x"}}"#);
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
+
+        // 多个过滤条件按中间产物2的方式拆分
+        let stage1: Value = serde_json::from_str(&app_state.build_intermediate_stage1("title, text", false).unwrap()).unwrap();
+        assert_eq!(stage1["count"], 2);
+        assert_eq!(stage1["filter"], "title | text");
+
+        // 仅叶子节点时不列出命中的容器节点
+        let all: Value = serde_json::from_str(&app_state.build_intermediate_stage1("name", false).unwrap()).unwrap();
+        let leaves: Value = serde_json::from_str(&app_state.build_intermediate_stage1("name", true).unwrap()).unwrap();
+        assert!(all["items"].as_array().unwrap().iter().any(|item| item["source_path"] == "$.menu.name"));
+        assert!(leaves["items"].as_array().unwrap().iter().all(|item| item["source_path"] != "$.menu.name"));
+    }
+
+    #[test]
+    fn test_stage2_flags_suspicious_source_strings() {
+        let json_content = r#"{"items": [{"title": "正常"}, {"title": "隐藏\u200b字符"}]}"#;
+        let file = create_test_json_file(json_content);
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
+
+        let stage2 = app_state.build_intermediate_stage2("title", |_, _| {}).unwrap();
+        let parsed: Value = serde_json::from_str(&stage2).unwrap();
+        let items = parsed["items"].as_array().unwrap();
+        assert_eq!(items.len(), 2);
+        assert!(items[0].get("suspicious").is_none());
+        assert_eq!(items[1]["suspicious"][0], "第2个字符 U+200B(零宽空格)");
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::Write;
-    use tempfile::NamedTempFile;
 
-    /// 创建临时JSON文件用于测试
-    fn create_test_json_file(content: &str) -> NamedTempFile {
-        let mut file = NamedTempFile::new().expect("创建临时文件失败");
-        file.write_all(content.as_bytes()).expect("写入临时文件失败");
-        file
+    #[test]
+    fn test_stage2_name_path_with_multibyte_and_bracket_keys() {
+        let json_content = r#"{"😀.组": {"name": "名", "title": "标题"}, "]x.": {"title": "t"}}"#;
+        let file = create_test_json_file(json_content);
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
+
+        let stage2 = app_state.build_intermediate_stage2("title", |_, _| {}).unwrap();
+        let parsed: Value = serde_json::from_str(&stage2).unwrap();
+        let items = parsed["items"].as_array().unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0]["source_path"], "$['😀.组'].title");
+        assert_eq!(items[0]["name_path"], "$['😀.组'].name");
+        assert_eq!(items[1]["name_path"], "$[']x.'].name");
     }
 
     #[test]
-    fn test_load_simple_json() {
-        let json_content = r#"{"name": "test", "value": 42}"#;
-        let temp_file = create_test_json_file(json_content);
+    fn test_stage2_context_notes() {
+        let long = "长".repeat(CONTEXT_MAX_CHARS);
+        let json_content = format!(
+            r#"{{"screens": {{"login": {{"menu": [
+                {{"id": "open", "icon": "folder", "label": "Open", "meta": {{"x": 1}}}},
+                {{"id": "save", "label": "Save", "tooltip": "{}", "a": "{}", "b": "{}"}}
+            ]}}}}}}"#,
+            long,
+            "x".repeat(CONTEXT_MAX_CHARS / 2),
+            "y".repeat(CONTEXT_MAX_CHARS / 2)
+        );
+        let file = create_test_json_file(&json_content);
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
+
+        let options = Stage2Options { leaf_nodes_only: true, include_context: true, ..Default::default() };
+        let text = app_state.build_intermediate_stage2_with_options("label", options, |_, _| {}).unwrap();
+        let stage2: Value = serde_json::from_str(&text).unwrap();
+        let items = stage2["items"].as_array().unwrap();
+        // 数组中的对象：兄弟标量字段（不含自身与容器），提示为数组所在的键
+        assert_eq!(items[0]["context"], serde_json::json!({"id": "open", "icon": "folder"}));
+        assert_eq!(items[0]["context_path_hint"], "menu");
+        // 超长的值不收集，累计超过上限后截止（按文件中的键顺序）
+        assert_eq!(items[1]["context"], serde_json::json!({"id": "save"}));
+        assert_eq!(stage2_item_notes(&items[0]), "menu › id=open, icon=folder");
+
+        let mut csv = Vec::new();
+        AppState::export_stage2_csv(&text, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.contains("\nseq,source_path,name,notes\n"), "{}", csv);
+        assert!(csv.contains("0,$.screens.login.menu[0].label,Open,\"menu › id=open, icon=folder\"\n"));
+
+        // 未启用上下文时条目不含上下文，CSV 保持原有的三列
+        let plain = app_state.build_intermediate_stage2_with_leaf_filter("label", true, |_, _| {}).unwrap();
+        assert!(!plain.contains("context"));
+        let mut csv = Vec::new();
+        AppState::export_stage2_csv(&plain, &mut csv).unwrap();
+        assert!(String::from_utf8(csv).unwrap().contains("seq,source_path,name\n0,$.screens.login.menu[0].label,Open\n"));
+    }
 
+    #[test]
+    fn test_stage2_excludes_oversized_binary_values() {
+        use crate::model::workflow::{build_final_product, FinalOptions, WritebackNote, WritebackOptions, WritebackPlan};
+
+        let data_uri = format!("data:image/png;base64,{}", "iVBORw0KGgo1".repeat(10));
+        let blob = "QUJDRA1234+/".repeat(10);
+        // 刚超过阈值的自然语言文本仍应送译
+        let prose = "This sentence is long enough. ".repeat(4);
+        let doc = serde_json::json!({"items": [
+            {"title": data_uri}, {"title": blob}, {"title": prose}, {"title": "Open"}
+        ]});
+        let file = create_test_json_file(&doc.to_string());
         let mut app_state = AppState::default();
-        let result = app_state.load_file(temp_file.path());
+        app_state.load_file(file.path()).unwrap();
+        let max = prose.chars().count() - 1;
 
-        assert!(result.is_ok(), "加载简单JSON应该成功");
-        assert!(app_state.dom.is_some(), "DOM应该被加载");
-        assert!(!app_state.tree_flat.is_empty(), "影子树应该被构建");
-        assert_eq!(app_state.tree_flat.len(), 3, "应该有3个节点：根、name、value");
+        let build = |oversized| {
+            let options = Stage2Options { max_value_chars: Some(max), oversized, ..Default::default() };
+            let text = app_state.build_intermediate_stage2_with_options("title", options, |_, _| {}).unwrap();
+            serde_json::from_str::<Value>(&text).unwrap()
+        };
+
+        let stage2 = build(OversizedValues::Exclude);
+        assert_eq!(stage2["excluded_count"], 2);
+        assert_eq!(stage2["max_value_chars"], max);
+        let names: Vec<&str> = stage2["items"].as_array().unwrap().iter().map(|i| i["name"].as_str().unwrap()).collect();
+        assert_eq!(names, vec![prose.as_str(), "Open"]);
+
+        // 引用模式：保留截断预览，不进入最终产物，回写被拒绝
+        let stage2 = build(OversizedValues::Reference);
+        let items = stage2["items"].as_array().unwrap();
+        assert_eq!((items.len(), stage2["excluded_count"].as_u64()), (4, Some(2)));
+        assert_eq!(items[0]["excluded"], true);
+        assert!(items[0]["name"].as_str().unwrap().starts_with("data:image/png;base64,"));
+        assert!(items[0]["name"].as_str().unwrap().len() < data_uri.len());
+        let final_product: Value = serde_json::from_str(&build_final_product(&stage2.to_string(), FinalOptions::default()).unwrap()).unwrap();
+        assert_eq!(final_product.as_object().unwrap().keys().collect::<Vec<_>>(), vec!["2", "3"]);
+
+        let translated = serde_json::json!({"0": "覆盖", "2": "句子", "3": "打开"});
+        let plan = WritebackPlan::build(&translated, &stage2, app_state.dom.as_ref().unwrap(), WritebackOptions::default()).unwrap();
+        assert_eq!((plan.updates.len(), plan.skipped), (2, 1));
+        assert!(matches!(&plan.notes[0], WritebackNote::Excluded { seq: 0, .. }));
+
+        // 未设置阈值时不排除，信封中也没有计数
+        let stage2: Value = serde_json::from_str(&app_state.build_intermediate_stage2("title", |_, _| {}).unwrap()).unwrap();
+        assert_eq!(stage2["count"], 4);
+        assert!(stage2.get("excluded_count").is_none());
     }
 
     #[test]
-    fn test_load_nested_json() {
-        let json_content = r#"
-        {
-            "user": {
-                "name": "张三",
-                "age": 30,
-                "address": {
-                    "city": "北京",
-                    "district": "朝阳区"
-                }
-            },
-            "items": [1, 2, 3]
-        }"#;
-        let temp_file = create_test_json_file(json_content);
+    fn test_stage2_sort_options_are_deterministic() {
+        let json_content = r#"{"z": {"title": "Beta"}, "a": [{"title": "Alpha"}, {"title": "Beta"}]}"#;
+        let file = create_test_json_file(json_content);
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
+
+        let build = |sort: Stage2Sort| {
+            let options = Stage2Options { leaf_nodes_only: true, sort, ..Default::default() };
+            app_state.build_intermediate_stage2_with_options("title", options, |_, _| {}).unwrap()
+        };
+        let column = |stage2: &str, key: &str| -> Vec<String> {
+            let parsed: Value = serde_json::from_str(stage2).unwrap();
+            parsed["items"].as_array().unwrap().iter().map(|i| i[key].as_str().unwrap().to_string()).collect()
+        };
+
+        let by_text = build(Stage2Sort::BySourceText);
+        assert_eq!(by_text, build(Stage2Sort::BySourceText), "两次提取应逐字节一致");
+        assert_eq!(column(&by_text, "name"), ["Alpha", "Beta", "Beta"]);
+        assert_eq!(column(&by_text, "source_path"), ["$.a[0].title", "$.a[1].title", "$.z.title"]);
+        assert!(by_text.contains(r#""sort": "by_source_text""#));
+
+        let by_path = build(Stage2Sort::ByPath);
+        assert_eq!(by_path, build(Stage2Sort::ByPath));
+        assert_eq!(column(&by_path, "source_path"), ["$.a[0].title", "$.a[1].title", "$.z.title"]);
+
+        let document = build(Stage2Sort::DocumentOrder);
+        assert_eq!(document, app_state.build_intermediate_stage2_with_leaf_filter("title", true, |_, _| {}).unwrap());
+        assert!(document.contains(r#""sort": "document_order""#));
+
+        // CSV 导出沿用同一顺序，seq 与排序后的位置一致
+        let mut csv = Vec::new();
+        AppState::export_stage2_csv(&by_text, &mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            format!("# fingerprint: {}\n", app_state.document_fingerprint().unwrap())
+                + "seq,source_path,name\n0,$.a[0].title,Alpha\n1,$.a[1].title,Beta\n2,$.z.title,Beta\n"
+        );
+    }
 
+    #[test]
+    fn test_stage2_sort_by_path_orders_indices_numerically() {
+        let titles: Vec<String> = (0..11).map(|i| format!(r#"{{"title": "同名{}"}}"#, i % 2)).collect();
+        let json_content = format!(r#"{{"z": {{"title": "同名0"}}, "a": [{}]}}"#, titles.join(", "));
+        let file = create_test_json_file(&json_content);
         let mut app_state = AppState::default();
-        let result = app_state.load_file(temp_file.path());
+        app_state.load_file(file.path()).unwrap();
 
-        assert!(result.is_ok(), "加载嵌套JSON应该成功");
-        assert!(app_state.tree_flat.len() > 5, "嵌套结构应该产生多个节点");
+        let source_paths = |sort: Stage2Sort| -> Vec<String> {
+            let options = Stage2Options { leaf_nodes_only: true, sort, ..Default::default() };
+            let stage2: Value = serde_json::from_str(&app_state.build_intermediate_stage2_with_options("title", options, |_, _| {}).unwrap()).unwrap();
+            stage2["items"].as_array().unwrap().iter().map(|i| i["source_path"].as_str().unwrap().to_string()).collect()
+        };
+        let by_path = source_paths(Stage2Sort::ByPath);
+        let expected: Vec<String> = (0..11).map(|i| format!("$.a[{}].title", i)).chain(["$.z.title".to_string()]).collect();
+        assert_eq!(by_path, expected);
+
+        // 原文相同时同样按数值下标排序
+        let by_text = source_paths(Stage2Sort::BySourceText);
+        assert_eq!(&by_text[..7], ["$.a[0].title", "$.a[2].title", "$.a[4].title", "$.a[6].title", "$.a[8].title", "$.a[10].title", "$.z.title"]);
     }
 
     #[test]
-    fn test_extract_subtree() {
-        let json_content = r#"{"user": {"name": "张三", "age": 30}}"#;
-        let temp_file = create_test_json_file(json_content);
+    fn test_stage2_fingerprint_detects_other_document() {
+        use crate::model::fingerprint::{plan_writeback, WritebackMode};
 
+        let file = create_test_json_file(r#"{"a": {"title": "v1"}}"#);
         let mut app_state = AppState::default();
-        app_state.load_file(temp_file.path()).expect("加载文件失败");
+        assert!(app_state.document_fingerprint().is_err());
+        app_state.load_file(file.path()).unwrap();
+
+        let stage2: Value = serde_json::from_str(&app_state.build_intermediate_stage2("title", |_, _| {}).unwrap()).unwrap();
+        let v1 = app_state.document_fingerprint().unwrap();
+        assert_eq!(stage2["fingerprint"], v1);
+        assert_eq!(plan_writeback(&stage2, &v1, false).mode, WritebackMode::Trusted);
+
+        app_state.replace_dom(serde_json::json!({"a": {"title": "v2"}})).unwrap();
+        let v2 = app_state.document_fingerprint().unwrap();
+        assert_ne!(v1, v2);
+        assert_eq!(plan_writeback(&stage2, &v2, false).mode, WritebackMode::PathChecked);
+    }
 
-        // 测试提取根节点
-        let root_result = app_state.extract_subtree_pretty("$");
-        assert!(root_result.is_ok(), "提取根节点应该成功");
+    #[test]
+    fn test_extract_compact_and_leaf_text() {
+        let json_content = r#"{"msg": "第一行\n\u4e2d文 \"引号\"", "n": 1.50, "ok": true, "obj": {"a": [1, 2]}, "nil": null}"#;
+        let file = create_test_json_file(json_content);
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
 
-        // 测试提取用户对象
-        let user_result = app_state.extract_subtree_pretty("$.user");
-        assert!(user_result.is_ok(), "提取用户对象应该成功");
+        assert_eq!(app_state.extract_leaf_text("$.msg").unwrap(), "第一行\n中文 \"引号\"");
+        assert_eq!(app_state.extract_leaf_text("$.n").unwrap(), "1.50");
+        assert_eq!(app_state.extract_leaf_text("$.ok").unwrap(), "true");
+        assert!(app_state.extract_leaf_text("$.obj").is_err());
+        assert!(app_state.extract_leaf_text("$.nil").is_err());
 
-        // 测试提取用户名
-        let name_result = app_state.extract_subtree_pretty("$.user.name");
-        assert!(name_result.is_ok(), "提取用户名应该成功");
-        assert!(name_result.unwrap().contains("张三"), "结果应该包含用户名");
+        assert_eq!(app_state.extract_subtree_compact("$.obj").unwrap(), r#"{"a":[1,2]}"#);
+        assert_eq!(app_state.extract_subtree_compact("$.msg").unwrap(), r#""第一行\n中文 \"引号\"""#);
     }
 
     #[test]
-    fn test_update_node() {
-        let json_content = r#"{"user": {"name": "张三", "age": 30}}"#;
-        let temp_file = create_test_json_file(json_content);
+    fn test_apply_value_pipeline() {
+        let json_content = r#"{"a": "  Hello World  ", "b": ["&lt;B&gt;", "x%20y"], "n": 1, "c": {"d": "ok"}}"#;
+        let file = create_test_json_file(json_content);
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
+
+        let result = app_state
+            .apply_value_pipeline(vec![
+                ValueTransform::Trim,
+                ValueTransform::HtmlUnescape,
+                ValueTransform::UrlDecode,
+                ValueTransform::ToLowercase,
+                ValueTransform::RegexReplace { pattern: "o".into(), replacement: "0".into() },
+                ValueTransform::TruncateAt(7),
+            ])
+            .unwrap();
+        assert_eq!(result.changes_per_transform, vec![1, 1, 1, 2, 2, 1]);
+        assert_eq!(result.nodes_changed, 4);
+        assert_eq!(
+            app_state.dom.as_ref().unwrap(),
+            &serde_json::json!({"a": "hell0 w", "b": ["<b>", "x y"], "n": 1, "c": {"d": "0k"}})
+        );
+        let preview = app_state.tree_flat.iter().find(|n| n.path == "$.c.d").unwrap().preview.clone();
+        assert!(preview.contains("0k"), "影子树应已重建: {}", preview);
+
+        let bad = vec![ValueTransform::Trim, ValueTransform::RegexReplace { pattern: "[".into(), replacement: "".into() }];
+        assert!(app_state.apply_value_pipeline(bad).is_err());
+        assert_eq!(app_state.dom.as_ref().unwrap()["c"]["d"], "0k", "正则无效时不做任何修改");
+    }
 
+    #[test]
+    fn test_extract_subtree_preview_wraps_wide_arrays() {
+        let numbers: Vec<u32> = (0..10_000).collect();
+        let file = create_test_json_file(&serde_json::json!({"data": {"numbers": numbers, "pair": [1, 2]}}).to_string());
         let mut app_state = AppState::default();
-        app_state.load_file(temp_file.path()).expect("加载文件失败");
+        app_state.load_file(file.path()).unwrap();
 
-        // 更新用户名
-        let new_name = r#""李四""#;
-        let result = app_state.update_node_from_str("$.user.name", new_name);
-        assert!(result.is_ok(), "更新节点应该成功");
+        let preview = app_state.extract_subtree_preview("$.data", 80).unwrap();
+        assert!(preview.contains("\"pair\": [1, 2]"));
+        assert!(preview.lines().all(|l| l.chars().count() <= 81));
+        assert_eq!(serde_json::from_str::<Value>(&preview).unwrap(), app_state.get_value_at_path("$.data").unwrap());
+    }
 
-        // 验证更新结果
-        let updated_name = app_state.extract_subtree_pretty("$.user.name").unwrap();
-        assert!(updated_name.contains("李四"), "用户名应该被更新为李四");
+    #[test]
+    fn test_compute_subtree_sizes() {
+        let file = create_fixture_file(&TestFixtureShape::default());
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
+        assert!(app_state.tree_flat.iter().all(|n| n.size_bytes == 0));
+
+        let total = app_state.compute_subtree_sizes().unwrap();
+        let dom = app_state.dom.clone().unwrap();
+        assert_eq!(total, dom.to_string().len() as u64);
+        let items = app_state.tree_flat.iter().find(|n| n.path == "$.child.items").unwrap();
+        assert_eq!(items.size_bytes, dom["child"]["items"].to_string().len() as u64);
+
+        app_state.update_node_from_str("$.str_0", "\"更长的新值\"").unwrap();
+        assert!(app_state.tree_flat.iter().all(|n| n.size_bytes == 0), "编辑后大小应清零");
+        assert!(AppState::default().compute_subtree_sizes().is_err());
+
+        // 借用之外计算的结果：文档未变时写入，期间被修改则拒绝
+        let snapshot = app_state.dom.clone().unwrap();
+        let sizes = compute_subtree_sizes(&snapshot, |_, _| {});
+        assert_eq!(app_state.apply_subtree_sizes(&snapshot, sizes.clone()).unwrap(), snapshot.to_string().len() as u64);
+        assert_eq!(app_state.tree_flat[0].size_bytes, sizes[0]);
+        app_state.update_node_from_str("$.str_0", "\"再次修改\"").unwrap();
+        assert!(matches!(app_state.apply_subtree_sizes(&snapshot, sizes), Err(AppError::State(StateKind::Conflict, _))));
+        assert!(app_state.tree_flat.iter().all(|n| n.size_bytes == 0));
     }
 
     #[test]
-    fn test_invalid_json_path() {
-        let json_content = r#"{"user": {"name": "张三"}}"#;
-        let temp_file = create_test_json_file(json_content);
+    fn test_pack_for_display() {
+        let shape = TestFixtureShape { depth: 2, array_len: 50, ..Default::default() };
+        let file = create_fixture_file(&shape);
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
+        app_state.tree_flat[0].expanded = true;
+        app_state.update_visibility_by_expansion();
+        let visible = app_state.tree_flat.iter().filter(|n| n.visible).count();
+        assert_eq!(app_state.pack_for_display(usize::MAX, 1).len(), visible);
+
+        // 名额不足时截断，末尾为截断标记
+        let packed = app_state.pack_for_display(3, 1);
+        assert_eq!(packed.len(), 4);
+        assert!(packed[3].is_truncation_sentinel());
+        assert!(packed[3].preview.contains(&format!("{} 个节点", visible - 3)));
+
+        // 展开过大的子树被自动折叠
+        app_state.toggle_node_expanded("$.child");
+        app_state.toggle_node_expanded("$.child.items");
+        let items = app_state.tree_flat.iter().position(|n| n.path == "$.child.items").unwrap();
+        let packed = app_state.pack_for_display(20, 2);
+        let at = packed.iter().position(|n| n.path == "$.child.items").unwrap();
+        assert!(!packed[at].expanded && app_state.tree_flat[items].expanded);
+        assert!(packed[at + 1].is_truncation_sentinel());
+        assert!(packed[at + 1].preview.contains("50 个节点"));
+        // 阈值更深时不折叠，直接截断
+        let packed = app_state.pack_for_display(20, 3);
+        assert!(packed.iter().any(|n| n.path == "$.child.items[0]"));
+        assert_eq!(packed.iter().filter(|n| !n.is_truncation_sentinel()).count(), 20);
+    }
 
+    #[test]
+    fn test_largest_subtrees_excludes_ancestors() {
+        let doc = serde_json::json!({
+            "a": {"blob": "x".repeat(1000), "small": "y"},
+            "b": ["z".repeat(300), "w".repeat(200)],
+            "c": "q".repeat(100)
+        });
+        let file = create_test_json_file(&doc.to_string());
         let mut app_state = AppState::default();
-        app_state.load_file(temp_file.path()).expect("加载文件失败");
+        app_state.load_file(file.path()).unwrap();
+
+        let top = app_state.largest_subtrees(3);
+        let paths: Vec<&str> = top.iter().map(|(p, _)| p.as_str()).collect();
+        assert_eq!(paths, vec!["$.a.blob", "$.b[0]", "$.b[1]"]);
+        assert_eq!(top[0].1, 1002);
+        assert_eq!(app_state.largest_subtrees(1), vec![("$.a.blob".to_string(), 1002)]);
+        // 名额足够时列出全部互不包含的叶子
+        assert_eq!(app_state.largest_subtrees(100).len(), 5);
+        assert!(app_state.largest_subtrees(0).is_empty());
+        assert!(AppState::default().largest_subtrees(3).is_empty());
+
+        app_state.reveal_node("$.b[1]").unwrap();
+        assert!(app_state.tree_flat.iter().find(|n| n.path == "$.b[1]").unwrap().visible);
+        assert!(app_state.reveal_node("$.missing").is_err());
+    }
 
-        // 测试无效路径
-        let result = app_state.extract_subtree_pretty("$.nonexistent");
-        assert!(result.is_err(), "无效路径应该返回错误");
+    #[test]
+    fn test_largest_subtrees_reports_containers() {
+        let rows: Vec<Value> = (0..200).map(|i| serde_json::json!({"id": i, "label": "row"})).collect();
+        let doc = serde_json::json!({
+            "rows": rows,
+            "meta": {"blob": "x".repeat(1500), "note": "n"},
+            "names": {"a": "x".repeat(200), "b": "y".repeat(200), "c": "z".repeat(200)}
+        });
+        let file = create_test_json_file(&doc.to_string());
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
+
+        let size = |path: &str| app_state.get_value_at_path(path).unwrap().to_string().len() as u64;
+        let top = app_state.largest_subtrees(3);
+        // 由大量小元素组成的数组与没有主导子节点的对象整体列出，其中的元素不再单独列出
+        assert_eq!(
+            top,
+            vec![("$.rows".to_string(), size("$.rows")), ("$.meta.blob".to_string(), 1502), ("$.names".to_string(), size("$.names"))]
+        );
+        assert!(app_state.largest_subtrees(100).iter().all(|(p, _)| !p.starts_with("$.rows[") && !p.starts_with("$.names.")));
     }
 
     #[test]
-    fn test_invalid_json_content() {
-        let invalid_json = r#"{"invalid": json content}"#;
-        let temp_file = create_test_json_file(invalid_json);
+    fn test_swap_values() {
+        let file = create_test_json_file(r#"{"a": "甲", "b": 2, "list": [{"x": 1}, "y", null], "nested": {"c": [1]}}"#);
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
+        app_state.toggle_node_expanded("$");
+
+        // 对象字段：字符串与数字互换
+        app_state.swap_values("$.a", "$.b").unwrap();
+        assert_eq!(app_state.dom.as_ref().unwrap()["a"], 2);
+        assert_eq!(app_state.dom.as_ref().unwrap()["b"], "甲");
+        assert!(app_state.dirty);
+        assert!(app_state.tree_flat[0].expanded, "展开状态应保留");
+
+        // 数组元素：对象与空值互换，影子树同步
+        app_state.swap_values("$.list[0]", "$.list[2]").unwrap();
+        assert_eq!(app_state.dom.as_ref().unwrap()["list"], serde_json::json!([null, "y", {"x": 1}]));
+        assert!(app_state.tree_flat.iter().any(|n| n.path == "$.list[2].x"));
+        assert!(!app_state.tree_flat.iter().any(|n| n.path == "$.list[0].x"));
+
+        // 跨层级交换
+        app_state.swap_values("$.list[1]", "$.nested.c").unwrap();
+        assert_eq!(app_state.dom.as_ref().unwrap()["nested"]["c"], "y");
+
+        // 同一节点为空操作；包含关系与不存在的路径报错且不修改
+        let before = app_state.dom.clone();
+        app_state.swap_values("$.a", "$['a']").unwrap();
+        assert!(app_state.swap_values("$.nested", "$.nested.c").is_err());
+        assert!(app_state.swap_values("$.a", "$.missing").is_err());
+        assert_eq!(app_state.dom, before);
+    }
 
+    #[test]
+    fn test_export_key_value_pairs_for_llm() {
         let mut app_state = AppState::default();
-        let result = app_state.load_file(temp_file.path());
+        assert!(app_state.export_key_value_pairs_for_llm(None, 10).is_err());
+        app_state.replace_dom(serde_json::json!({
+            "menu": {"title": "Open", "tip": "", "count": 3},
+            "about": {"title": "第一行\n第二行"}
+        })).unwrap();
+
+        let text = app_state.export_key_value_pairs_for_llm(None, 10).unwrap();
+        assert_eq!(
+            text,
+            "# source_path: (未保存)\n# total: 3, shown: 3\n\
+             $.about.title: 第一行\\n第二行\n$.menu.tip: [TRANSLATE]\n$.menu.title: Open\n"
+        );
+
+        let text = app_state.export_key_value_pairs_for_llm(Some("title"), 1).unwrap();
+        assert_eq!(text.lines().collect::<Vec<_>>(), ["# source_path: (未保存)", "# total: 2, shown: 1", "$.about.title: 第一行\\n第二行"]);
+
+        // 数组下标按数值排序
+        let items: Vec<Value> = (0..12).map(|i| Value::String(format!("item {}", i))).collect();
+        app_state.replace_dom(serde_json::json!({"items": items})).unwrap();
+        let text = app_state.export_key_value_pairs_for_llm(None, 4).unwrap();
+        assert_eq!(text.lines().skip(2).collect::<Vec<_>>(), ["$.items[0]: item 0", "$.items[1]: item 1", "$.items[2]: item 2", "$.items[3]: item 3"]);
+        let text = app_state.export_key_value_pairs_for_llm(None, 12).unwrap();
+        assert!(text.ends_with("$.items[9]: item 9\n$.items[10]: item 10\n$.items[11]: item 11\n"));
+    }
 
-        assert!(result.is_err(), "无效JSON应该返回错误");
+    #[test]
+    fn test_apply_llm_translation_response() {
+        let mut app_state = AppState::default();
+        app_state.replace_dom(serde_json::json!({
+            "menu": {"open": "Open", "save": "Save", "tip": "", "count": 3},
+            "about": {"title": "Line 1\nLine 2", "note": "Done."},
+            "dialog": {"save": "Save"},
+            "a: b": "colon"
+        })).unwrap();
+        let exported = app_state.export_key_value_pairs_for_llm(None, 100).unwrap();
+        assert!(exported.contains("$['a: b']: colon\n"));
+
+        let response = "好的，以下是译文：\n\
+            # source_path: (未保存)\n\
+            $.menu.open: \"打开\"。\n\
+            - $.about.title: 第一行\\n第二行\n\
+            `$.about.note`: 完成。\n\
+            $['a: b']: 冒号\n\
+            $.menu.tip: [TRANSLATE]\n\
+            $.menu.count: 三\n\
+            save: 保存\n";
+        let (applied, unparsed) = app_state.apply_llm_translation_response(response).unwrap();
+        // 第一行不是键值对；$.menu.count 不是字符串叶子；save 按属性名命中两个节点
+        assert_eq!((applied, unparsed), (6, 2));
+        let dom = app_state.dom.clone().unwrap();
+        assert_eq!(dom["menu"]["open"], "打开", "去掉包裹引号与原文没有的句号");
+        assert_eq!(dom["about"]["title"], "第一行\n第二行");
+        assert_eq!(dom["about"]["note"], "完成。", "原文有句号时保留");
+        assert_eq!(dom["a: b"], "冒号");
+        assert_eq!((dom["menu"]["tip"].as_str(), dom["menu"]["count"].as_i64()), (Some(""), Some(3)));
+        assert_eq!((dom["menu"]["save"].as_str(), dom["dialog"]["save"].as_str()), (Some("保存"), Some("保存")));
+        assert!(app_state.dirty);
+
+        // 整批只占一个撤销步骤
+        app_state.undo().unwrap();
+        assert_eq!(app_state.dom.as_ref().unwrap()["menu"]["open"], "Open");
+        assert_eq!(app_state.apply_llm_translation_response("无关内容").unwrap(), (0, 1));
+        // 没有任何写入时不记录撤销快照
+        assert!(!app_state.can_undo());
+        assert_eq!(app_state.batch_update(&[("$.missing".into(), "x".into())]).unwrap(), 0);
+        assert!(!app_state.can_undo());
     }
 
     #[test]
-    fn test_search_results_single_match() {
-        let json_content = r#"{"user": {"name": "张三", "age": 30}, "config": {"debug": true}}"#;
-        let temp_file = create_test_json_file(json_content);
+    fn test_reorder_sibling() {
+        let mut app_state = AppState::default();
+        let list: Vec<Value> = (0..12).map(|i| serde_json::json!({"n": i, "tags": [i]})).collect();
+        app_state.replace_dom(serde_json::json!({"list": list, "obj": {"a": 1}})).unwrap();
+        assert_eq!(app_state.get_sibling_index("$.list[10]"), Some(10));
+        assert_eq!(app_state.get_sibling_index("$.obj.a"), None);
+        assert_eq!(app_state.get_sibling_index("$.missing"), None);
+
+        // 向后移动：其间元素前移（含两位数下标）
+        app_state.reorder_sibling("$.list[1]", 10).unwrap();
+        let order = |state: &AppState| -> Vec<i64> {
+            state.dom.as_ref().unwrap()["list"].as_array().unwrap().iter().map(|v| v["n"].as_i64().unwrap()).collect()
+        };
+        assert_eq!(order(&app_state), [0, 2, 3, 4, 5, 6, 7, 8, 9, 10, 1, 11]);
+        assert_tree_in_sync(&app_state);
+        assert!(app_state.dirty);
+
+        // 向前移动，再撤销
+        app_state.reorder_sibling("$.list[11]", 0).unwrap();
+        assert_eq!(order(&app_state), [11, 0, 2, 3, 4, 5, 6, 7, 8, 9, 10, 1]);
+        assert_tree_in_sync(&app_state);
+        assert!(app_state.tree_flat.iter().any(|n| n.path == "$.list[0].tags[0]" && n.preview == "11"));
+        app_state.undo().unwrap();
+        assert_eq!(order(&app_state), [0, 2, 3, 4, 5, 6, 7, 8, 9, 10, 1, 11]);
+
+        let err = app_state.reorder_sibling("$.obj.a", 0).unwrap_err();
+        assert!(matches!(err, AppError::JsonPath(JsonPathKind::WrongType, ref m) if m.contains("父节点不是数组")));
+        assert!(app_state.reorder_sibling("$.list[0]", 12).is_err());
+    }
 
+    #[test]
+    fn test_subtree_hash_invalidates_preview_cache() {
+        let file = create_test_json_file(r#"{"big": {"list": [1, 2, 3]}, "other": "x"}"#);
         let mut app_state = AppState::default();
-        app_state.load_file(temp_file.path()).expect("加载文件失败");
+        app_state.load_file(file.path()).unwrap();
 
-        // 应用搜索过滤
-        app_state.apply_search_filter("name");
+        let hash = app_state.compute_subtree_hash("$.big").unwrap();
+        assert_eq!(hash, app_state.compute_subtree_hash("$.big").unwrap());
+        assert_eq!(app_state.extract_subtree_preview("$.big", 100).unwrap(), "{\"list\": [1, 2, 3]}");
+        assert_eq!(app_state.subtree_cache.borrow().len(), 1);
 
-        // 提取搜索结果
-        let result = app_state.extract_search_results("name");
-        assert!(result.is_ok(), "搜索结果提取应该成功");
+        // 修改其他节点不影响该子树的哈希与缓存
+        app_state.update_node_from_str("$.other", "\"y\"").unwrap();
+        assert_eq!(app_state.compute_subtree_hash("$.big").unwrap(), hash);
 
-        let search_result = result.unwrap();
-        assert!(search_result.contains("张三"), "搜索结果应该包含匹配的内容");
-        println!("单个匹配搜索结果: {}", search_result);
+        app_state.update_node_from_str("$.big.list", "[1]").unwrap();
+        assert_ne!(app_state.compute_subtree_hash("$.big").unwrap(), hash);
+        assert_eq!(app_state.extract_subtree_preview("$.big", 100).unwrap(), "{\"list\": \"[1]\"}");
+        assert!(app_state.compute_subtree_hash("$.missing").is_err());
     }
 
     #[test]
-    fn test_search_results_multiple_matches() {
-        let json_content = r#"{"users": [{"name": "张三", "description": "用户1"}, {"name": "李四", "description": "用户2"}], "metadata": {"description": "用户数据"}}"#;
-        let temp_file = create_test_json_file(json_content);
-
+    fn test_save_to_original_file_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let original = dir.path().join("locale.json");
+        std::fs::write(&original, r#"{"title": "旧"}"#).unwrap();
         let mut app_state = AppState::default();
-        app_state.load_file(temp_file.path()).expect("加载文件失败");
+        app_state.load_file(&original).unwrap();
+        app_state.replace_dom(serde_json::json!({"title": "新"})).unwrap();
 
-        // 应用搜索过滤
-        app_state.apply_search_filter("description");
+        let backup = app_state.save_to_original_file_backup(None).unwrap();
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), r#"{"title": "旧"}"#);
+        assert_eq!(read_json_file_checked(&original).unwrap().0, serde_json::json!({"title": "新"}));
+    }
 
-        // 提取搜索结果
-        let result = app_state.extract_search_results("description");
-        assert!(result.is_ok(), "搜索结果提取应该成功");
+    #[test]
+    fn test_get_string_values_at_depth() {
+        let json_content = r#"{
+            "menu": {"open": "打开", "count": 2, "sub": {"deep": "深层"}},
+            "list": ["甲", {"name": "乙"}],
+            "title": "标题"
+        }"#;
+        let file = create_test_json_file(json_content);
+        let mut app_state = AppState::default();
+        assert!(app_state.get_string_values_at_depth(1).is_empty(), "未加载时返回空");
+        app_state.load_file(file.path()).unwrap();
+
+        assert_eq!(app_state.get_string_values_at_depth(1), vec![("$.title".to_string(), "标题".to_string())]);
+        assert_eq!(
+            app_state.get_string_values_at_depth(2),
+            vec![
+                ("$.menu.open".to_string(), "打开".to_string()),
+                ("$.list[0]".to_string(), "甲".to_string()),
+            ]
+        );
+        let depth3: Vec<String> = app_state.get_string_values_at_depth(3).into_iter().map(|(p, _)| p).collect();
+        assert_eq!(depth3, vec!["$.menu.sub.deep", "$.list[1].name"]);
+        assert!(app_state.get_string_values_at_depth(0).is_empty());
+    }
 
-        let search_result = result.unwrap();
-        assert!(search_result.contains("search_filter"), "搜索结果应该包含搜索信息");
-        assert!(search_result.contains("total_matches"), "搜索结果应该包含匹配数量");
-        assert!(search_result.contains("displayed_matches"), "搜索结果应该包含显示数量");
-        println!("多个匹配搜索结果: {}", search_result);
+    #[test]
+    fn test_extract_number_and_bool_values() {
+        let json_content = r#"{"a": 1, "b": -2.5, "c": {"d": 3e2, "e": true}, "f": [false, "1", null]}"#;
+        let file = create_test_json_file(json_content);
+        let mut app_state = AppState::default();
+        assert!(app_state.extract_number_values().is_err(), "未加载时返回错误");
+        app_state.load_file(file.path()).unwrap();
+
+        assert_eq!(
+            app_state.extract_number_values().unwrap(),
+            vec![("$.a".to_string(), 1.0), ("$.b".to_string(), -2.5), ("$.c.d".to_string(), 300.0)]
+        );
+        assert_eq!(
+            app_state.extract_bool_values().unwrap(),
+            vec![("$.c.e".to_string(), true), ("$.f[0]".to_string(), false)]
+        );
     }
 
     #[test]
-    fn test_update_node_type_change() {
-        let json_content = r#"{"data": {"value": "原始字符串"}}"#;
-        let temp_file = create_test_json_file(json_content);
+    fn test_check_utf8_validity() {
+        let json_content = r#"{"ok": "正常", "nul": ["a\u0000b"], "lossy": {"t": "坏�字"}}"#;
+        let file = create_test_json_file(json_content);
+        let mut app_state = AppState::default();
+        assert!(app_state.check_utf8_validity().is_empty());
+        app_state.load_file(file.path()).unwrap();
+
+        let findings = app_state.check_utf8_validity();
+        let paths: Vec<&str> = findings.iter().map(|(p, _)| p.as_str()).collect();
+        assert_eq!(paths, vec!["$.nul[0]", "$.lossy.t"]);
+        assert!(findings[0].1.starts_with("第 2 个字符为 U+0000"));
+        assert!(findings[1].1.contains("U+FFFD"));
+
+        // 孤立代理项的转义在加载时即被拒绝
+        let lone_surrogate = create_test_json_file(r#"{"s": "\ud800"}"#);
+        assert!(AppState::default().load_file(lone_surrogate.path()).is_err());
+    }
 
+    #[test]
+    fn test_value_histogram() {
+        let json_content = r#"{
+            "a": {"x": "TODO", "y": "todo", "z": "N/A", "n": 1},
+            "b": ["TODO", "未命名", "未命名", {"k": "N/A"}],
+            "c": "TODO"
+        }"#;
+        let file = create_test_json_file(json_content);
         let mut app_state = AppState::default();
-        app_state.load_file(temp_file.path()).expect("加载文件失败");
+        app_state.load_file(file.path()).unwrap();
 
-        // 将字符串替换为对象
-        let new_object = r#"{"name": "新对象", "id": 123}"#;
-        let result = app_state.update_node_from_str("$.data.value", new_object);
-        assert!(result.is_ok(), "类型变更应该成功");
+        let all = app_state.value_histogram(None, 10).unwrap();
+        assert_eq!(all[0], ("TODO".to_string(), 3));
+        assert_eq!(&all[1..3], &[("N/A".to_string(), 2), ("未命名".to_string(), 2)]);
+        assert_eq!(app_state.value_histogram(None, 2).unwrap().len(), 2);
 
-        // 验证更新结果
-        let updated_value = app_state.extract_subtree_pretty("$.data.value").unwrap();
-        assert!(updated_value.contains("新对象"), "应该包含新对象的内容");
-        assert!(updated_value.contains("123"), "应该包含新对象的ID");
+        let scoped = app_state.value_histogram(Some("$.b"), 10).unwrap();
+        assert_eq!(scoped, vec![("未命名".to_string(), 2), ("N/A".to_string(), 1), ("TODO".to_string(), 1)]);
+
+        let folded = app_state.value_histogram_with_options(Some("$.a"), 1, true).unwrap();
+        assert_eq!(folded, vec![("todo".to_string(), 2)]);
+
+        assert!(app_state.value_histogram(Some("$.missing"), 10).is_err());
+
+        assert_eq!(app_state.count_occurrences_of_value("TODO"), 3);
+        assert_eq!(app_state.count_occurrences_of_value("todo"), 1);
+        assert_eq!(app_state.count_occurrences_of_value("1"), 0, "只统计字符串节点");
+        assert_eq!(app_state.find_most_common_values(1), vec![("TODO".to_string(), 3)]);
+        assert!(AppState::default().find_most_common_values(5).is_empty());
     }
 
     #[test]
-    fn test_update_array_element() {
-        let json_content = r#"{"items": ["第一项", "第二项", "第三项"]}"#;
-        let temp_file = create_test_json_file(json_content);
+    fn test_multi_filter_matches_each_node_once() {
+        let json_content = r#"{"items": [
+            {"name": "甲", "title": "标题一", "desc": "说明一"},
+            {"name": "乙", "subtitle": "副标题"}
+        ]}"#;
+        let file = create_test_json_file(json_content);
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
+
+        // "title" 与 "subtitle" 都命中 $.items[1].subtitle，只应收录一次
+        let filters = vec!["title".to_string(), "subtitle".to_string(), "desc".to_string(), " ".to_string()];
+        let stage2 = app_state.build_intermediate_stage2_multi(&filters, Stage2Options::default(), |_, _| {}).unwrap();
+        let stage2: Value = serde_json::from_str(&stage2).unwrap();
+        let items = stage2["items"].as_array().unwrap();
+        let summary: Vec<(u64, &str, &str)> = items
+            .iter()
+            .map(|i| (i["seq"].as_u64().unwrap(), i["source_path"].as_str().unwrap(), i["matched_by"].as_str().unwrap()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (0, "$.items[0].title", "title"),
+                (1, "$.items[0].desc", "desc"),
+                (2, "$.items[1].subtitle", "title"),
+            ]
+        );
+        assert_eq!(stage2["filters"], serde_json::json!(["title", "subtitle", "desc"]));
+
+        app_state.build_search_index();
+        app_state.apply_search_filters(&["desc".to_string(), "subtitle".to_string()]);
+        let visible: Vec<&str> = app_state.tree_flat.iter().filter(|n| n.visible).map(|n| n.path.as_str()).collect();
+        assert_eq!(visible, vec!["$.items[0].desc", "$.items[1].subtitle"]);
+
+        // 排除词：标题类字段中去掉 subtitle
+        app_state.apply_search_filter("title -sub");
+        let visible: Vec<&str> = app_state.tree_flat.iter().filter(|n| n.visible).map(|n| n.path.as_str()).collect();
+        assert_eq!(visible, vec!["$.items[0].title"]);
+        let stage2 = app_state.build_intermediate_stage2("title -sub", |_, _| {}).unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&stage2).unwrap()["count"], 1);
+    }
 
+    #[test]
+    fn test_search_index_matches_linear_scan() {
+        let json_content = r#"{"user": {"name": "甲", "username": "a"}, "items": [{"title": "x"}, {"subtitle": "y"}]}"#;
+        let file = create_test_json_file(json_content);
         let mut app_state = AppState::default();
-        app_state.load_file(temp_file.path()).expect("加载文件失败");
+        app_state.load_file(file.path()).unwrap();
 
-        // 更新数组中的第二个元素
-        let new_item = r#""更新的第二项""#;
-        let result = app_state.update_node_from_str("$.items[1]", new_item);
-        assert!(result.is_ok(), "数组元素更新应该成功");
+        let visible = |state: &AppState| -> Vec<String> {
+            state.tree_flat.iter().filter(|n| n.visible).map(|n| n.path.clone()).collect()
+        };
+        let filters = ["name", "title", "[0]", "ms", "不存在的词", "$.items[1].sub", "USER", "Sub"];
+        let expected: Vec<Vec<String>> = filters
+            .iter()
+            .map(|f| {
+                app_state.apply_search_filter(f);
+                visible(&app_state)
+            })
+            .collect();
 
-        // 验证更新结果
-        let updated_item = app_state.extract_subtree_pretty("$.items[1]").unwrap();
-        assert!(updated_item.contains("更新的第二项"), "数组元素应该被更新");
+        app_state.build_search_index();
+        for (filter, expected) in filters.iter().zip(&expected) {
+            app_state.apply_search_filter(filter);
+            assert_eq!(&visible(&app_state), expected, "索引搜索结果应与线性扫描一致: {}", filter);
+        }
 
-        // 验证其他元素未受影响
-        let first_item = app_state.extract_subtree_pretty("$.items[0]").unwrap();
-        assert!(first_item.contains("第一项"), "第一项应该保持不变");
+        // 影子树重建后索引随之重建，下标不会失效
+        app_state.replace_dom(serde_json::json!({"items": [{"title": "新"}]})).unwrap();
+        app_state.apply_search_filter("subtitle");
+        assert!(visible(&app_state).is_empty());
+        app_state.apply_search_filter("title");
+        assert_eq!(visible(&app_state), ["$.items[0].title"]);
+
+        app_state.drop_search_index();
+        assert!(app_state.search_index.is_none());
     }
 
     #[test]
-    fn test_update_with_invalid_json() {
-        let json_content = r#"{"data": "原始值"}"#;
-        let temp_file = create_test_json_file(json_content);
+    fn test_search_modes() {
+        let json_content = r#"{"menu": {"name": "Open File", "title": "name", "count": 42}, "names": [true]}"#;
+        let file = create_test_json_file(json_content);
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
+
+        let paths = |matches: &[SearchMatch]| matches.iter().map(|m| m.node.path.clone()).collect::<Vec<_>>();
+
+        let matches = app_state.search("name | count", SearchMode::Substring).unwrap();
+        assert_eq!(paths(&matches), ["$.menu.name", "$.menu.count", "$.names", "$.names[0]"]);
+        assert_eq!(matches[0].match_kind, MatchKind::Key);
+        assert_eq!(matches[3].match_kind, MatchKind::Path);
+        let visible: Vec<String> = app_state.tree_flat.iter().filter(|n| n.visible).map(|n| n.path.clone()).collect();
+        assert_eq!(visible, paths(&matches), "可见节点与匹配列表一致");
+        // 子串匹配不区分大小写
+        let matches = app_state.search("NAME | Count", SearchMode::Substring).unwrap();
+        assert_eq!(paths(&matches), ["$.menu.name", "$.menu.count", "$.names", "$.names[0]"]);
+        assert_eq!(matches[0].match_kind, MatchKind::Key);
+
+        let matches = app_state.search("^name$", SearchMode::Regex).unwrap();
+        assert_eq!(paths(&matches), ["$.menu.name"]);
+        assert!(app_state.search("(", SearchMode::Regex).is_err());
+
+        assert_eq!(paths(&app_state.search("name", SearchMode::ExactKey).unwrap()), ["$.menu.name"]);
+
+        let matches = app_state.search("name", SearchMode::ValueContains).unwrap();
+        assert_eq!(paths(&matches), ["$.menu.title"]);
+        assert_eq!(matches[0].match_kind, MatchKind::Value);
+        assert_eq!(paths(&app_state.search("4", SearchMode::ValueContains).unwrap()), ["$.menu.count"]);
+
+        let matches = app_state.search("nms", SearchMode::Fuzzy).unwrap();
+        assert_eq!(matches[0].node.path, "$.names", "得分最高的排在最前");
+        assert!(matches.windows(2).all(|w| w[0].score >= w[1].score));
+        assert_eq!(app_state.search_mode, SearchMode::Fuzzy);
+
+        // 路径模式：只比较路径字符串，`[*]` 匹配任意下标
+        assert_eq!(app_state.search_by_path_pattern("$.names[*]").unwrap(), 1);
+        let visible: Vec<&str> = app_state.tree_flat.iter().filter(|n| n.visible).map(|n| n.path.as_str()).collect();
+        assert_eq!(visible, ["$.names[0]"]);
+        assert_eq!(paths(&app_state.search("$.menu.*e", SearchMode::PathPattern).unwrap()), ["$.menu.name", "$.menu.title"]);
+        assert!(app_state.search("$.**x", SearchMode::PathPattern).is_err());
+
+        assert!(app_state.search("  ", SearchMode::Regex).unwrap().is_empty());
+        assert!(app_state.tree_flat.iter().all(|n| n.visible));
+    }
 
+    #[test]
+    fn test_find_values_by_length() {
+        let json_content = r#"{"a": "短", "b": "中等长度", "c": {"d": "这是最长的一段文本"}, "n": 12345678901}"#;
+        let file = create_test_json_file(json_content);
         let mut app_state = AppState::default();
-        app_state.load_file(temp_file.path()).expect("加载文件失败");
+        app_state.load_file(file.path()).unwrap();
 
-        // 尝试用无效JSON更新
-        let invalid_json = r#"{"invalid": json content"#;
-        let result = app_state.update_node_from_str("$.data", invalid_json);
-        assert!(result.is_err(), "无效JSON应该导致更新失败");
+        let (node, value) = app_state.find_longest_value().expect("应找到最长值");
+        assert_eq!(node.path, "$.c.d");
+        assert_eq!(value, "这是最长的一段文本");
 
-        // 验证原始值未被修改
-        let original_value = app_state.extract_subtree_pretty("$.data").unwrap();
-        assert!(original_value.contains("原始值"), "原始值应该保持不变");
+        let mid: Vec<&str> = app_state.find_by_value_length_range(2, 5).map(|n| n.path.as_str()).collect();
+        assert_eq!(mid, vec!["$.b"]);
+        // 第二次查询命中缓存，结果一致
+        assert_eq!(app_state.find_by_value_length_range(1, 100).count(), 3);
+
+        // 修改后缓存失效
+        app_state.update_node_from_str("$.a", "现在这一段才是全文件中最长的文本").unwrap();
+        assert_eq!(app_state.find_longest_value().unwrap().0.path, "$.a");
     }
 
     #[test]
-    fn test_update_nonexistent_path() {
-        let json_content = r#"{"data": "值"}"#;
-        let temp_file = create_test_json_file(json_content);
+    fn test_bulk_export_subtrees_partial_success() {
+        let json_content = r#"{"en": {"hi": "Hello"}, "zh": {"hi": "你好"}}"#;
+        let file = create_test_json_file(json_content);
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
+
+        let dir = tempfile::tempdir().expect("创建临时目录失败");
+        let exports = vec![
+            ("$.en".to_string(), dir.path().join("en.json")),
+            ("$.ja".to_string(), dir.path().join("ja.json")),
+            ("$.zh".to_string(), dir.path().join("no_such_dir").join("zh.json")),
+            ("$.zh".to_string(), dir.path().join("zh.json")),
+        ];
+        let failures = app_state.bulk_export_subtrees(&exports).unwrap();
+
+        let failed: Vec<&PathBuf> = failures.iter().map(|(p, _)| p).collect();
+        assert_eq!(failed, vec![&exports[1].1, &exports[2].1]);
+        let en: Value = serde_json::from_str(&std::fs::read_to_string(&exports[0].1).unwrap()).unwrap();
+        assert_eq!(en, serde_json::json!({"hi": "Hello"}));
+        assert!(exports[3].1.exists());
+
+        assert!(AppState::default().bulk_export_subtrees(&exports).is_err());
+    }
 
+    #[test]
+    fn test_save_subtree() {
         let mut app_state = AppState::default();
-        app_state.load_file(temp_file.path()).expect("加载文件失败");
+        app_state.replace_dom(serde_json::json!({"ui": {"menus": {"file": {"label": "File"}, "edit": {"label": "Edit"}}}})).unwrap();
+        let dir = tempfile::tempdir().expect("创建临时目录失败");
+        let read = |path: &Path| std::fs::read_to_string(path).unwrap();
 
-        // 尝试更新不存在的路径
-        let new_value = r#""新值""#;
-        let result = app_state.update_node_from_str("$.nonexistent.path", new_value);
-        assert!(result.is_err(), "不存在的路径应该导致更新失败");
+        let nested = dir.path().join("menus.json");
+        assert_eq!(app_state.save_subtree(&nested, "$.ui.menus.file", SaveOptions::minified()).unwrap(), 1);
+        assert_eq!(read(&nested), r#"{"label":"File"}"#);
+
+        // 通配符：保存全部命中组成的数组
+        let labels = dir.path().join("labels.json");
+        assert_eq!(app_state.save_subtree(&labels, "$.ui.menus.*.label", SaveOptions::default()).unwrap(), 2);
+        assert_eq!(serde_json::from_str::<Value>(&read(&labels)).unwrap(), serde_json::json!(["File", "Edit"]));
+
+        assert!(app_state.save_subtree(&labels, "$.ui.missing", SaveOptions::default()).is_err());
+        assert!(app_state.save_subtree(&labels, "$.ui.*.missing", SaveOptions::default()).is_err());
     }
 
     #[test]
-    fn test_shadow_tree_rebuild_after_update() {
-        let json_content = r#"{"user": {"name": "张三", "age": 30}}"#;
-        let temp_file = create_test_json_file(json_content);
+    fn test_replace_subtree_from_file() {
+        let original = serde_json::json!({
+            "ui": {"menus": {"file": {"label": "File", "keys": ["F"]}, "edit": {"label": "Edit"}, "view": "View"}},
+            "other": 1
+        });
+        let file = create_test_json_file(r#"{"file": {"label": "文件", "tip": "提示"}, "view": {"label": "视图"}, "help": "帮助"}"#);
+        let mut app_state = AppState::default();
+        app_state.replace_dom(original.clone()).unwrap();
+        app_state.toggle_node_expanded("$.ui");
+
+        // 按键合并：未出现的键保留，对象与字符串的冲突整体覆盖
+        let report = app_state.replace_subtree_from_file("$.ui.menus", file.path(), SubtreeMergeMode::MergeKeys).unwrap();
+        assert_eq!(report.added, ["$.ui.menus.file.tip", "$.ui.menus.help"]);
+        assert_eq!(report.changed, ["$.ui.menus.file.label", "$.ui.menus.view"]);
+        assert!(report.removed.is_empty());
+        assert_eq!(report.type_conflicts, ["$.ui.menus.view"]);
+        let menus = &app_state.dom.as_ref().unwrap()["ui"]["menus"];
+        assert_eq!(menus["file"], serde_json::json!({"label": "文件", "tip": "提示", "keys": ["F"]}));
+        assert_eq!(menus["edit"]["label"], "Edit");
+        assert_tree_in_sync(&app_state);
+        assert!(app_state.tree_flat.iter().find(|n| n.path == "$.ui").unwrap().expanded, "子树外的展开状态保留");
+        assert!(app_state.dirty);
+
+        // 整体替换：文件中没有的键被删除
+        app_state.undo().unwrap();
+        assert_eq!(app_state.dom.as_ref().unwrap(), &original);
+        let report = app_state.replace_subtree_from_file("$.ui.menus", file.path(), SubtreeMergeMode::Replace).unwrap();
+        assert_eq!(report.removed, ["$.ui.menus.edit", "$.ui.menus.file.keys"]);
+        assert_eq!(report.summary(), "新增 2，修改 2，删除 2，类型冲突 1");
+        assert_eq!(app_state.dom.as_ref().unwrap()["ui"]["menus"], serde_json::from_str::<Value>(&std::fs::read_to_string(file.path()).unwrap()).unwrap());
+        assert_tree_in_sync(&app_state);
+
+        // 数组元素处的类型冲突；无变化时不记录撤销
+        let list = create_test_json_file(r#"[1, 2]"#);
+        let undo_depth = app_state.undo_stack.len();
+        let report = app_state.replace_subtree_from_file("$.other", list.path(), SubtreeMergeMode::MergeKeys).unwrap();
+        assert_eq!((report.changed.len(), report.type_conflicts.len()), (1, 0));
+        assert_eq!(app_state.replace_subtree_from_file("$.other", list.path(), SubtreeMergeMode::MergeKeys).unwrap(), SubtreeMergeReport::default());
+        assert_eq!(app_state.undo_stack.len(), undo_depth + 1);
+        assert!(app_state.replace_subtree_from_file("$.missing", list.path(), SubtreeMergeMode::Replace).is_err());
+    }
 
+    #[test]
+    fn test_diff_against_file() {
+        let file = create_test_json_file(r#"{"title": "Hello", "menu": ["Open", "Save"], "old": 1}"#);
         let mut app_state = AppState::default();
-        app_state.load_file(temp_file.path()).expect("加载文件失败");
+        app_state.load_file(file.path()).unwrap();
+        assert!(app_state.diff_against_file(file.path()).unwrap().entries.is_empty());
+
+        app_state.update_node_from_str("$.title", r#""你好""#).unwrap();
+        app_state.delete_node("$.old").unwrap();
+        app_state.insert_child("$.menu", "", r#""Close""#).unwrap();
+        let diff = app_state.diff_against_file(file.path()).unwrap();
+        let changes: Vec<(&str, DiffKind)> = diff.entries.iter().map(|e| (e.path.as_str(), e.kind)).collect();
+        assert_eq!(changes, [("$.title", DiffKind::Changed), ("$.menu[2]", DiffKind::Added), ("$.old", DiffKind::Removed)]);
+        assert_eq!(diff.entries[0].old, Some(Value::String("Hello".into())));
+        assert!(diff.summary.ends_with("新增 1 处，修改 1 处，删除 1 处"));
+        assert_eq!(diff.file_path, file.path());
+        assert!(app_state.diff_against_file(Path::new("/nonexistent/a.json")).is_err());
+
+        // 去除注释加载的 JSONC 文档，对比时同样去除注释
+        let file = create_test_json_file("{\n  // 标题\n  \"title\": \"Hello\"\n}");
+        app_state.load_file_with_comments(file.path()).unwrap();
+        assert!(app_state.diff_against_file(file.path()).unwrap().entries.is_empty());
+        app_state.update_node_from_str("$.title", r#""你好""#).unwrap();
+        assert_eq!(app_state.diff_against_file(file.path()).unwrap().entries.len(), 1);
+        app_state.reload_preserving_state(file.path()).unwrap();
+        assert_eq!(app_state.dom, Some(serde_json::json!({"title": "Hello"})));
+    }
 
-        let original_tree_len = app_state.tree_flat.len();
+    #[test]
+    fn test_reload_preserving_state() {
+        let file = create_test_json_file(r#"{"a": {"b": "旧"}, "c": [1]}"#);
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
+        app_state.toggle_node_expanded("$.a");
+        app_state.set_annotation("$.a.b", Some("需复核")).unwrap();
+        app_state.set_annotation("$.c", Some("已删除")).unwrap();
+
+        std::fs::write(file.path(), r#"{"a": {"b": "新", "d": 2}}"#).unwrap();
+        app_state.reload_preserving_state(file.path()).unwrap();
+        assert_eq!(app_state.annotation("$.a.b"), Some("需复核"), "备注应被保留");
+        assert_eq!(app_state.annotation("$.c"), None);
+
+        assert_eq!(app_state.dom.as_ref().unwrap()["a"]["b"], "新");
+        let a = app_state.tree_flat.iter().find(|n| n.path == "$.a").unwrap();
+        assert!(a.expanded, "展开状态应被保留");
+        assert!(app_state.tree_flat.iter().all(|n| n.path != "$.c"));
+    }
 
-        // 将简单值替换为复杂对象
-        let complex_object = r#"{"profile": {"bio": "个人简介", "skills": ["Rust", "JSON"]}}"#;
-        let result = app_state.update_node_from_str("$.user.name", complex_object);
-        assert!(result.is_ok(), "复杂对象更新应该成功");
+    #[test]
+    fn test_detect_interpolation_style() {
+        let json_content = r#"{"a": "Hello {name}", "b": "{count} items in {box}", "c": "%s done", "d": "", "e": "plain"}"#;
+        let file = create_test_json_file(json_content);
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
 
-        // 验证影子树被重建且节点数量发生变化
-        let new_tree_len = app_state.tree_flat.len();
-        assert_ne!(original_tree_len, new_tree_len, "影子树应该被重建");
+        assert_eq!(app_state.detect_interpolation_style(None), PlaceholderStyle::Brace);
+        let only_c = vec!["$.c".to_string(), "$.d".to_string()];
+        assert_eq!(app_state.detect_interpolation_style(Some(&only_c)), PlaceholderStyle::Printf);
+        let plain = vec!["$.e".to_string(), "$.missing".to_string()];
+        assert_eq!(app_state.detect_interpolation_style(Some(&plain)), PlaceholderStyle::None);
+    }
 
-        // 验证新路径存在
-        let bio_result = app_state.extract_subtree_pretty("$.user.name.profile.bio");
-        assert!(bio_result.is_ok(), "新的嵌套路径应该可访问");
+    #[test]
+    fn test_access_log() {
+        let file = create_test_json_file(r#"{"a": 1, "b": {"c": 2}, "x,y": 3}"#);
+        let mut app_state = AppState::default();
+        app_state.load_file(file.path()).unwrap();
+
+        for _ in 0..3 {
+            app_state.extract_subtree_pretty("$.b").unwrap();
+        }
+        app_state.get_value_at_path("$.a").unwrap();
+        app_state.get_value_at_path("$['x,y']").unwrap();
+        app_state.get_value_at_path("$['x,y']").unwrap();
+
+        assert_eq!(
+            app_state.profile_hot_paths(2),
+            vec![("$.b".to_string(), 3), ("$['x,y']".to_string(), 2)]
+        );
+
+        let mut csv = Vec::new();
+        app_state.export_access_log(&mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "path,access_count\n$.b,3\n\"$['x,y']\",2\n$.a,1\n"
+        );
+
+        app_state.reset_access_log();
+        assert!(app_state.profile_hot_paths(10).is_empty());
     }
-}
 
+    #[test]
+    fn test_flatten_to_key_value_pairs_round_trip() {
+        let file = create_test_json_file(r#"{"items": [{"name": "a"}, {}], "map": {"0": {"name": "b"}}, "empty": [], "n": null}"#);
+        let mut app_state = AppState::default();
+        assert!(app_state.flatten_to_key_value_pairs().is_err());
+        app_state.load_file(file.path()).unwrap();
+
+        let pairs = app_state.flatten_to_key_value_pairs().unwrap();
+        let key = |k: &str| PathSegment::Key(k.into());
+        assert_eq!(
+            pairs,
+            vec![
+                (vec![key("items"), PathSegment::Index(0), key("name")], serde_json::json!("a")),
+                (vec![key("items"), PathSegment::Index(1)], serde_json::json!({})),
+                (vec![key("map"), key("0"), key("name")], serde_json::json!("b")),
+                (vec![key("empty")], serde_json::json!([])),
+                (vec![key("n")], Value::Null),
+            ]
+        );
+        assert_eq!(AppState::unflatten_from_key_value_pairs(&pairs).unwrap(), *app_state.dom.as_ref().unwrap());
+
+        let conflict = vec![(vec![key("a"), PathSegment::Index(0)], serde_json::json!(1)), (vec![key("a"), key("x")], serde_json::json!(2))];
+        assert!(AppState::unflatten_from_key_value_pairs(&conflict).is_err());
+        let gap = vec![(vec![key("a"), PathSegment::Index(usize::MAX)], serde_json::json!(1))];
+        assert!(AppState::unflatten_from_key_value_pairs(&gap).is_err());
+    }
+}
@@ -0,0 +1,225 @@
+//! 两份文档的结构差异：按对象键与数组下标逐层比较，列出新增、删除与修改的路径
+
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde_json::Value;
+
+use crate::model::shadow_tree::{field_path, node_preview};
+
+/// 预览区最多列出的差异条数
+pub const DIFF_RENDER_LIMIT: usize = 500;
+
+/// 差异类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffKind {
+    /// 仅新版本中存在
+    Added,
+    /// 仅旧版本中存在
+    Removed,
+    /// 两侧都存在但取值（或类型）不同
+    Changed,
+}
+
+/// 一处差异；新增/删除的对象或数组整体记为一条
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffEntry {
+    pub path: String,
+    pub kind: DiffKind,
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+impl DiffEntry {
+    /// 预览区显示的一行
+    pub fn render(&self) -> String {
+        let preview = |v: &Option<Value>| v.as_ref().map(node_preview).unwrap_or_default();
+        match self.kind {
+            DiffKind::Added => format!("+ {}: {}", self.path, preview(&self.new)),
+            DiffKind::Removed => format!("- {}: {}", self.path, preview(&self.old)),
+            DiffKind::Changed => format!("~ {}: {} → {}", self.path, preview(&self.old), preview(&self.new)),
+        }
+    }
+}
+
+/// 比较两份文档，按路径顺序返回差异（对象键按旧版本中的顺序、新增的键随后，数组按下标）
+pub fn compare_values(old: &Value, new: &Value) -> Vec<DiffEntry> {
+    let mut out = Vec::new();
+    collect_diff(old, new, "$", &mut out);
+    out
+}
+
+fn collect_diff(old: &Value, new: &Value, path: &str, out: &mut Vec<DiffEntry>) {
+    let entry = |path: String, kind, old: Option<&Value>, new: Option<&Value>| DiffEntry {
+        path,
+        kind,
+        old: old.cloned(),
+        new: new.cloned(),
+    };
+    match (old, new) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (key, value) in a {
+                match b.get(key) {
+                    Some(other) => collect_diff(value, other, &field_path(path, key), out),
+                    None => out.push(entry(field_path(path, key), DiffKind::Removed, Some(value), None)),
+                }
+            }
+            for (key, value) in b.iter().filter(|(key, _)| !a.contains_key(*key)) {
+                out.push(entry(field_path(path, key), DiffKind::Added, None, Some(value)));
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            for index in 0..a.len().max(b.len()) {
+                let child = format!("{}[{}]", path, index);
+                match (a.get(index), b.get(index)) {
+                    (Some(x), Some(y)) => collect_diff(x, y, &child, out),
+                    (Some(x), None) => out.push(entry(child, DiffKind::Removed, Some(x), None)),
+                    (None, y) => out.push(entry(child, DiffKind::Added, None, y)),
+                }
+            }
+        }
+        _ if old != new => out.push(entry(path.to_string(), DiffKind::Changed, Some(old), Some(new))),
+        _ => {}
+    }
+}
+
+/// 当前文档与磁盘文件的差异（磁盘文件为旧版本）
+#[derive(Debug, Clone)]
+pub struct FileDiff {
+    pub file_path: PathBuf,
+    pub loaded_at: SystemTime,
+    pub entries: Vec<DiffEntry>,
+    /// 状态栏显示的摘要
+    pub summary: String,
+}
+
+impl FileDiff {
+    pub fn new(file_path: &Path, entries: Vec<DiffEntry>) -> Self {
+        let count = |kind| entries.iter().filter(|e| e.kind == kind).count();
+        let summary = if entries.is_empty() {
+            format!("与 {} 一致，没有改动", file_path.display())
+        } else {
+            format!(
+                "与 {} 相比：新增 {} 处，修改 {} 处，删除 {} 处",
+                file_path.display(),
+                count(DiffKind::Added),
+                count(DiffKind::Changed),
+                count(DiffKind::Removed)
+            )
+        };
+        FileDiff { file_path: file_path.to_path_buf(), loaded_at: SystemTime::now(), entries, summary }
+    }
+
+    /// 渲染为预览区文本，最多列出 [`DIFF_RENDER_LIMIT`] 条
+    pub fn render(&self) -> String {
+        let mut out = format!("== {} ==\n", self.summary);
+        for entry in self.entries.iter().take(DIFF_RENDER_LIMIT) {
+            out.push_str(&entry.render());
+            out.push('\n');
+        }
+        if self.entries.len() > DIFF_RENDER_LIMIT {
+            out.push_str(&format!("... 另有 {} 处未列出\n", self.entries.len() - DIFF_RENDER_LIMIT));
+        }
+        out
+    }
+}
+
+/// 自加载以来的改动汇总（按差异类型分组，组内按路径顺序）
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChangeSummary {
+    pub total: usize,
+    /// 新增的键或数组元素
+    pub added: Vec<DiffEntry>,
+    /// 删除的键或数组元素
+    pub removed: Vec<DiffEntry>,
+    /// 只改变了取值（或类型）的节点
+    pub changed: Vec<DiffEntry>,
+}
+
+impl ChangeSummary {
+    pub fn new(entries: Vec<DiffEntry>) -> Self {
+        let mut summary = ChangeSummary { total: entries.len(), ..Self::default() };
+        for entry in entries {
+            match entry.kind {
+                DiffKind::Added => summary.added.push(entry),
+                DiffKind::Removed => summary.removed.push(entry),
+                DiffKind::Changed => summary.changed.push(entry),
+            }
+        }
+        summary
+    }
+
+    /// 可读的改动日志，按新增/删除/修改分节，共列出至多 [`DIFF_RENDER_LIMIT`] 条
+    pub fn format_report(&self) -> String {
+        if self.total == 0 {
+            return "== 本次会话没有改动 ==\n".to_string();
+        }
+        let mut out = format!(
+            "== 本次会话共 {} 处改动：新增 {}，删除 {}，修改 {} ==\n",
+            self.total,
+            self.added.len(),
+            self.removed.len(),
+            self.changed.len()
+        );
+        let mut budget = DIFF_RENDER_LIMIT;
+        for (title, entries) in [("新增", &self.added), ("删除", &self.removed), ("修改", &self.changed)] {
+            if entries.is_empty() {
+                continue;
+            }
+            out.push_str(&format!("\n[{}]\n", title));
+            for entry in entries.iter().take(budget) {
+                out.push_str(&entry.render());
+                out.push('\n');
+            }
+            budget = budget.saturating_sub(entries.len());
+        }
+        if self.total > DIFF_RENDER_LIMIT {
+            out.push_str(&format!("\n... 另有 {} 处未列出\n", self.total - DIFF_RENDER_LIMIT));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_compare_values() {
+        let old = json!({"a": 1, "gone": {"x": 1}, "list": [1, 2, 3], "kind": {"k": 1}});
+        let new = json!({"a": 2, "list": [1, 5], "kind": "flat", "new key": true});
+        let entries = compare_values(&old, &new);
+        let rendered: Vec<String> = entries.iter().map(DiffEntry::render).collect();
+        assert_eq!(
+            rendered,
+            [
+                "~ $.a: 1 → 2",
+                "- $.gone: {..} (1 keys)",
+                "~ $.list[1]: 2 → 5",
+                "- $.list[2]: 3",
+                "~ $.kind: {..} (1 keys) → \"flat\"",
+                "+ $['new key']: true",
+            ]
+        );
+        assert!(compare_values(&new, &new).is_empty());
+
+        let diff = FileDiff::new(Path::new("a.json"), entries);
+        assert_eq!(diff.summary, "与 a.json 相比：新增 1 处，修改 3 处，删除 2 处");
+        assert!(diff.render().starts_with("== 与 a.json 相比"));
+        assert_eq!(FileDiff::new(Path::new("a.json"), Vec::new()).summary, "与 a.json 一致，没有改动");
+    }
+
+    #[test]
+    fn test_change_summary_report() {
+        let old = json!({"a": 1, "gone": true, "list": [1]});
+        let new = json!({"a": 2, "list": [1, 2], "added": "x"});
+        let summary = ChangeSummary::new(compare_values(&old, &new));
+        assert_eq!((summary.total, summary.added.len(), summary.removed.len(), summary.changed.len()), (4, 2, 1, 1));
+        assert_eq!(
+            summary.format_report(),
+            "== 本次会话共 4 处改动：新增 2，删除 1，修改 1 ==\n\n[新增]\n+ $.list[1]: 2\n+ $.added: \"x\"\n\n[删除]\n- $.gone: true\n\n[修改]\n~ $.a: 1 → 2\n"
+        );
+        assert_eq!(ChangeSummary::new(Vec::new()).format_report(), "== 本次会话没有改动 ==\n");
+    }
+}
@@ -0,0 +1,80 @@
+//! 翻译工作量估算：词数、去重、含 HTML/占位符的字符串数，以及与参考语言文件的相似度
+
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+/// 翻译工作量估算结果
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct EffortEstimate {
+    /// 所有字符串值的词数之和（中日韩字符每字计一词）
+    pub total_words: usize,
+    /// 去重后的字符串数
+    pub unique_strings: usize,
+    /// 含 HTML 标签的字符串数
+    pub strings_with_html: usize,
+    /// 含占位符的字符串数
+    pub strings_with_placeholders: usize,
+    /// 与参考文件同路径字符串完全相同的比例（未提供参考文件时为 None）
+    pub similarity_to_reference: Option<f32>,
+}
+
+impl EffortEstimate {
+    /// 渲染为预览区显示的文本
+    pub fn render(&self) -> String {
+        let mut out = String::from("== 翻译工作量估算 ==\n");
+        out.push_str(&format!("总词数: {}\n", self.total_words));
+        out.push_str(&format!("去重字符串: {}\n", self.unique_strings));
+        out.push_str(&format!("含 HTML 标签: {}\n", self.strings_with_html));
+        out.push_str(&format!("含占位符: {}\n", self.strings_with_placeholders));
+        if let Some(similarity) = self.similarity_to_reference {
+            out.push_str(&format!("与参考文件相同: {:.1}%\n", similarity * 100.0));
+        }
+        out
+    }
+}
+
+fn html_tag_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"<[^>]+>").expect("HTML标签正则无效"))
+}
+
+/// 是否含 HTML 标签
+pub(crate) fn has_html_tag(text: &str) -> bool {
+    html_tag_regex().is_match(text)
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF      // 平假名、片假名
+        | 0x3400..=0x4DBF    // CJK 扩展A
+        | 0x4E00..=0x9FFF    // CJK 统一表意文字
+        | 0xAC00..=0xD7AF    // 韩文音节
+        | 0xF900..=0xFAFF)   // CJK 兼容表意文字
+}
+
+/// 词数：以空白分隔的词计一词，中日韩字符每字计一词
+pub(crate) fn count_words(text: &str) -> usize {
+    text.split_whitespace()
+        .map(|word| {
+            let cjk = word.chars().filter(|c| is_cjk(*c)).count();
+            let has_other = word.chars().any(|c| !is_cjk(c) && c.is_alphanumeric());
+            cjk + usize::from(has_other)
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_words_and_html() {
+        assert_eq!(count_words("Open the file"), 3);
+        assert_eq!(count_words("打开文件"), 4);
+        assert_eq!(count_words("共 %d 个file"), 4);
+        assert_eq!(count_words("  -- ... "), 0);
+        assert!(has_html_tag("Click <b>here</b>"));
+        assert!(!has_html_tag("1 < 2"));
+    }
+}
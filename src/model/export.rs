@@ -0,0 +1,216 @@
+//! 中间产物2与 Excel 工作簿互转：译者在表格的 `target` 列填写译文，再读回为回写映射
+//!
+//! 读写依赖 `rust_xlsxwriter` 与 `calamine`，需以 `--features xlsx` 构建；未启用时导出与导入均报错
+
+use std::path::Path;
+
+use serde_json::{Map, Value};
+
+#[cfg(feature = "xlsx")]
+use crate::model::data_core::stage2_item_notes;
+use crate::model::data_core::{AppError, StateKind};
+
+/// 工作表表头（列顺序）
+pub const XLSX_HEADERS: [&str; 5] = ["seq", "path", "source", "target", "notes"];
+
+/// Excel 单元格可容纳的最大字符数，超出时写入会失败
+pub const XLSX_MAX_CELL_CHARS: usize = 32_767;
+
+/// 将超过单元格上限的文本截断到上限，返回截断前的字符数（未超出时为 None）
+#[cfg(feature = "xlsx")]
+fn truncate_cell(text: &mut String) -> Option<usize> {
+    let (end, _) = text.char_indices().nth(XLSX_MAX_CELL_CHARS)?;
+    let chars = text.chars().count();
+    text.truncate(end);
+    Some(chars)
+}
+
+/// `notes` 列：不可回写条目的说明，或条目的上下文（见 [`stage2_item_notes`]）
+#[cfg(feature = "xlsx")]
+fn item_note(item: &Value) -> String {
+    match item.get("original_chars").and_then(Value::as_u64) {
+        Some(chars) if item.get("excluded").and_then(Value::as_bool).unwrap_or(false) => {
+            format!("已排除：超长二进制值（{} 字符），不翻译、不回写", chars)
+        }
+        _ => stage2_item_notes(item),
+    }
+}
+
+#[cfg(feature = "xlsx")]
+fn xlsx_error(e: impl std::fmt::Display) -> AppError {
+    AppError::State(StateKind::Invalid, format!("Excel 读写失败: {}", e))
+}
+
+/// 将中间产物2导出为工作簿（列 `seq / path / source / target / notes`），返回导出的条目数
+///
+/// 表头行冻结、文本自动换行；所有单元格按字符串写入，以 `=` 开头的原文不会被当作公式。
+/// 超过单元格上限的文本被截断，并在该行 `notes` 列注明（完整原文以中间产物为准）
+#[cfg(feature = "xlsx")]
+pub fn stage2_to_xlsx(stage2_json: &str, path: &Path) -> Result<usize, AppError> {
+    use rust_xlsxwriter::{Format, FormatAlign, Workbook};
+
+    let stage2: Value = serde_json::from_str(stage2_json)?;
+    let items = stage2
+        .get("items")
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| AppError::State(StateKind::Malformed, "中间产物缺少items数组".into()))?;
+
+    let mut workbook = Workbook::new();
+    let sheet = workbook.add_worksheet();
+    let header = Format::new().set_bold();
+    let wrap = Format::new().set_text_wrap().set_align(FormatAlign::Top);
+    for (col, (title, width)) in XLSX_HEADERS.iter().zip([8.0, 40.0, 50.0, 50.0, 30.0]).enumerate() {
+        sheet.write_string_with_format(0, col as u16, *title, &header).map_err(xlsx_error)?;
+        sheet.set_column_width(col as u16, width).map_err(xlsx_error)?;
+    }
+    sheet.set_freeze_panes(1, 0).map_err(xlsx_error)?;
+
+    for (index, item) in items.iter().enumerate() {
+        let row = index as u32 + 1;
+        let text = |key: &str| item[key].as_str().unwrap_or_default().to_string();
+        let mut cells = [item["seq"].as_u64().unwrap_or(0).to_string(), text("source_path"), text("name"), String::new(), item_note(item)];
+        let truncated: Vec<String> = cells[..4]
+            .iter_mut()
+            .zip(XLSX_HEADERS)
+            .filter_map(|(cell, title)| Some(format!("{} 超过单元格上限已截断（原 {} 字符）", title, truncate_cell(cell)?)))
+            .collect();
+        if !truncated.is_empty() {
+            let note = &mut cells[4];
+            *note = truncated.into_iter().chain((!note.is_empty()).then(|| note.clone())).collect::<Vec<_>>().join("；");
+            truncate_cell(note);
+        }
+        for (col, cell) in cells.iter().enumerate() {
+            sheet.write_string_with_format(row, col as u16, cell, &wrap).map_err(xlsx_error)?;
+        }
+    }
+    workbook.save(path).map_err(xlsx_error)?;
+    Ok(items.len())
+}
+
+/// 读取工作簿第一张表的 `target` 列，生成回写映射 `{seq: 译文}`
+///
+/// 按表头名称定位 `seq` 与 `target` 列；译文为空白的行视为未翻译，直接跳过
+#[cfg(feature = "xlsx")]
+pub fn from_xlsx(path: &Path) -> Result<Map<String, Value>, AppError> {
+    use calamine::{open_workbook, Reader, Xlsx};
+
+    let mut workbook: Xlsx<_> = open_workbook(path).map_err(xlsx_error)?;
+    let range = workbook
+        .worksheet_range_at(0)
+        .ok_or_else(|| AppError::State(StateKind::Malformed, "工作簿中没有工作表".into()))?
+        .map_err(xlsx_error)?;
+    let mut rows = range.rows();
+    let header: Vec<String> = rows.next().unwrap_or_default().iter().map(|c| c.to_string().trim().to_lowercase()).collect();
+    let column = |name: &str| {
+        header.iter().position(|h| h == name).ok_or_else(|| AppError::State(StateKind::Malformed, format!("工作表缺少 {} 列", name)))
+    };
+    let (seq_col, target_col) = (column("seq")?, column("target")?);
+
+    let mut map = Map::new();
+    for (index, row) in rows.enumerate() {
+        let cell = |col: usize| row.get(col).map(|c| c.to_string()).unwrap_or_default();
+        let target = cell(target_col);
+        if target.trim().is_empty() {
+            continue;
+        }
+        let seq = cell(seq_col);
+        let seq = seq.trim();
+        if seq.parse::<u64>().is_err() {
+            return Err(AppError::State(StateKind::Malformed, format!("第 {} 行的 seq 无效: {:?}", index + 2, seq)));
+        }
+        map.insert(seq.to_string(), Value::String(target));
+    }
+    Ok(map)
+}
+
+/// 未启用 `xlsx` 特性时总是失败
+#[cfg(not(feature = "xlsx"))]
+pub fn stage2_to_xlsx(_stage2_json: &str, _path: &Path) -> Result<usize, AppError> {
+    Err(AppError::State(StateKind::FeatureDisabled, "未启用 xlsx 特性，无法导出 Excel（请以 --features xlsx 构建）".into()))
+}
+
+#[cfg(not(feature = "xlsx"))]
+pub fn from_xlsx(_path: &Path) -> Result<Map<String, Value>, AppError> {
+    Err(AppError::State(StateKind::FeatureDisabled, "未启用 xlsx 特性，无法读取 Excel（请以 --features xlsx 构建）".into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(not(feature = "xlsx"))]
+    #[test]
+    fn test_xlsx_requires_feature() {
+        let path = Path::new("stage2.xlsx");
+        assert!(stage2_to_xlsx("{}", path).unwrap_err().to_string().contains("xlsx 特性"));
+        assert!(from_xlsx(path).unwrap_err().to_string().contains("xlsx 特性"));
+    }
+
+    #[cfg(feature = "xlsx")]
+    #[test]
+    fn test_xlsx_round_trip() {
+        use calamine::{open_workbook, Data, Reader, Xlsx};
+        use rust_xlsxwriter::Workbook;
+        use serde_json::json;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stage2.xlsx");
+        let stage2 = json!({"items": [
+            {"seq": 0, "name": "Open", "source_path": "$.menu[0].name"},
+            {"seq": 1, "name": "第一行\n第二行", "source_path": "$.menu[1].name"},
+            {"seq": 2, "name": "=SUM(A1:A2)", "source_path": "$.menu[2].name"},
+            {"seq": 3, "name": "data:image/png;base64,…", "source_path": "$.icon", "excluded": true, "original_chars": 9000}
+        ]});
+        assert_eq!(stage2_to_xlsx(&stage2.to_string(), &path).unwrap(), 4);
+
+        // 导出的原文都是字符串单元格，没有公式
+        let mut workbook: Xlsx<_> = open_workbook(&path).unwrap();
+        let name = workbook.sheet_names()[0].clone();
+        assert!(workbook.worksheet_formula(&name).unwrap().used_cells().next().is_none());
+        let range = workbook.worksheet_range(&name).unwrap();
+        assert_eq!(range.get((3, 2)), Some(&Data::String("=SUM(A1:A2)".into())));
+        assert_eq!(range.get((2, 2)), Some(&Data::String("第一行\n第二行".into())));
+        assert!(range.get((4, 4)).unwrap().to_string().contains("9000"));
+        // 未填写译文时没有可回写的条目
+        assert!(from_xlsx(&path).unwrap().is_empty());
+
+        // 模拟译者填写 target 列：含换行与公式样式的译文，另有一行空白
+        let mut workbook = Workbook::new();
+        let sheet = workbook.add_worksheet();
+        for (col, title) in ["Seq", "path", "source", "Target", "notes"].iter().enumerate() {
+            sheet.write_string(0, col as u16, *title).unwrap();
+        }
+        let rows = [(0.0, "打开"), (1.0, "甲\n乙"), (2.0, "=SUM(B1:B2)"), (3.0, "  ")];
+        for (row, (seq, target)) in rows.iter().enumerate() {
+            sheet.write_number(row as u32 + 1, 0, *seq).unwrap();
+            sheet.write_string(row as u32 + 1, 3, *target).unwrap();
+        }
+        workbook.save(&path).unwrap();
+
+        let map = from_xlsx(&path).unwrap();
+        assert_eq!(Value::Object(map), json!({"0": "打开", "1": "甲\n乙", "2": "=SUM(B1:B2)"}));
+    }
+
+    #[cfg(feature = "xlsx")]
+    #[test]
+    fn test_xlsx_truncates_oversized_cells() {
+        use calamine::{open_workbook, Reader, Xlsx};
+        use serde_json::json;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stage2.xlsx");
+        let long = "长".repeat(XLSX_MAX_CELL_CHARS + 10);
+        let stage2 = json!({"items": [
+            {"seq": 0, "name": long, "source_path": "$.text", "context_path_hint": "page"},
+            {"seq": 1, "name": "Open", "source_path": "$.open"}
+        ]});
+        assert_eq!(stage2_to_xlsx(&stage2.to_string(), &path).unwrap(), 2);
+
+        let mut workbook: Xlsx<_> = open_workbook(&path).unwrap();
+        let name = workbook.sheet_names()[0].clone();
+        let range = workbook.worksheet_range(&name).unwrap();
+        assert_eq!(range.get((1, 2)).unwrap().to_string().chars().count(), XLSX_MAX_CELL_CHARS);
+        assert_eq!(range.get((1, 4)).unwrap().to_string(), format!("source 超过单元格上限已截断（原 {} 字符）；page", XLSX_MAX_CELL_CHARS + 10));
+        assert_eq!(range.get((2, 2)).unwrap().to_string(), "Open");
+    }
+}
@@ -0,0 +1,227 @@
+//! 文档指纹：提取时记录源文档哈希，回写时校验目标文档是否仍是同一份
+
+use std::io::Write;
+
+use serde_json::Value;
+use xxhash_rust::xxh3::Xxh3;
+
+use crate::model::path::query_first;
+
+/// 中间产物2信封中记录指纹的字段名
+pub const FINGERPRINT_KEY: &str = "fingerprint";
+
+/// 将序列化输出直接送入哈希器，避免为大文档分配完整字符串
+struct HashWriter(Xxh3);
+
+impl Write for HashWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 计算 JSON 值的指纹（`xxh3:` + 16位十六进制）
+///
+/// 以对象键按字节序排序后的紧凑序列化作为规范形式，与原文件的格式/缩进及键顺序无关
+pub fn fingerprint_value(value: &Value) -> String {
+    let mut writer = HashWriter(Xxh3::new());
+    write_sorted_compact(value, &mut writer);
+    format!("xxh3:{:016x}", writer.0.digest())
+}
+
+/// 按键排序的紧凑序列化，直接写入哈希器
+fn write_sorted_compact(value: &Value, writer: &mut HashWriter) {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_unstable_by(|a, b| a.0.as_bytes().cmp(b.0.as_bytes()));
+            writer.0.update(b"{");
+            for (index, (key, child)) in entries.into_iter().enumerate() {
+                if index > 0 {
+                    writer.0.update(b",");
+                }
+                serde_json::to_writer(&mut *writer, key).expect("写入哈希器不会失败");
+                writer.0.update(b":");
+                write_sorted_compact(child, writer);
+            }
+            writer.0.update(b"}");
+        }
+        Value::Array(items) => {
+            writer.0.update(b"[");
+            for (index, child) in items.iter().enumerate() {
+                if index > 0 {
+                    writer.0.update(b",");
+                }
+                write_sorted_compact(child, writer);
+            }
+            writer.0.update(b"]");
+        }
+        _ => serde_json::to_writer(&mut *writer, value).expect("写入哈希器不会失败"),
+    }
+}
+
+/// 规范形式：对象键按字节序递归排序、两空格缩进、LF 换行、行尾无空白且末尾无换行
+///
+/// 不依赖 `Map` 的迭代顺序与序列化器的排版细节，跨版本与平台逐字节可复现
+pub fn canonical_form(value: &Value) -> String {
+    let mut out = String::new();
+    write_canonical(value, 0, &mut out);
+    out
+}
+
+fn write_canonical(value: &Value, depth: usize, out: &mut String) {
+    let indent = |out: &mut String, depth: usize| out.extend(std::iter::repeat_n("  ", depth));
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_unstable_by(|a, b| a.0.as_bytes().cmp(b.0.as_bytes()));
+            out.push('{');
+            for (index, (key, child)) in entries.into_iter().enumerate() {
+                out.push_str(if index == 0 { "\n" } else { ",\n" });
+                indent(out, depth + 1);
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push_str(": ");
+                write_canonical(child, depth + 1, out);
+            }
+            out.push('\n');
+            indent(out, depth);
+            out.push('}');
+        }
+        Value::Array(items) if !items.is_empty() => {
+            out.push('[');
+            for (index, child) in items.iter().enumerate() {
+                out.push_str(if index == 0 { "\n" } else { ",\n" });
+                indent(out, depth + 1);
+                write_canonical(child, depth + 1, out);
+            }
+            out.push('\n');
+            indent(out, depth);
+            out.push(']');
+        }
+        // 标量与空容器：紧凑序列化（数字保留原始字面量）
+        _ => out.push_str(&value.to_string()),
+    }
+}
+
+/// 回写方式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WritebackMode {
+    /// 指纹一致：按 seq 直接回写
+    Trusted,
+    /// 指纹不一致或缺失：逐项校验源路径上的原文未变才回写
+    PathChecked,
+}
+
+/// 回写前的指纹校验结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FingerprintPlan {
+    pub mode: WritebackMode,
+    /// 需要醒目提示给用户的警告
+    pub warning: Option<String>,
+}
+
+/// 比较中间产物2记录的指纹与当前文档指纹，决定回写方式
+///
+/// `force` 为真时即使指纹不一致也按 seq 直接回写（仍给出警告）
+pub fn plan_writeback(stage2: &Value, current_fingerprint: &str, force: bool) -> FingerprintPlan {
+    match stage2.get(FINGERPRINT_KEY).and_then(|v| v.as_str()) {
+        Some(expected) if expected == current_fingerprint => FingerprintPlan {
+            mode: WritebackMode::Trusted,
+            warning: None,
+        },
+        Some(expected) => {
+            let detail = format!("文档指纹不一致（提取时 {}，当前 {}），中间产物可能来自其他版本的文件", expected, current_fingerprint);
+            if force {
+                FingerprintPlan {
+                    mode: WritebackMode::Trusted,
+                    warning: Some(format!("{}；已按用户选择强制回写", detail)),
+                }
+            } else {
+                FingerprintPlan {
+                    mode: WritebackMode::PathChecked,
+                    warning: Some(format!("{}；将逐项校验原文，原文已变化的条目会被跳过", detail)),
+                }
+            }
+        }
+        None => FingerprintPlan {
+            mode: WritebackMode::PathChecked,
+            warning: Some("中间产物没有文档指纹（旧版本生成），将逐项校验原文".to_string()),
+        },
+    }
+}
+
+/// 源路径上的当前值是否已不同于提取时的原文（路径不存在也视为冲突）
+pub fn has_source_conflict(dom: &Value, source_path: &str, extracted_text: &str) -> bool {
+    let current = query_first(dom, source_path).ok().flatten();
+    match current {
+        Some(Value::String(s)) => s != extracted_text,
+        Some(other) => {
+            // 与中间产物一致：非字符串值按 JSON 文本比较
+            let rendered = other.to_string();
+            rendered != extracted_text
+        }
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_fingerprint_ignores_formatting() {
+        let compact: Value = serde_json::from_str(r#"{"b":1,"a":[1,2]}"#).unwrap();
+        let pretty: Value = serde_json::from_str("{\n  \"a\": [1, 2],\n  \"b\": 1\n}").unwrap();
+        assert_eq!(fingerprint_value(&compact), fingerprint_value(&pretty));
+        // 与未保留键顺序时的紧凑序列化哈希一致，旧的中间产物仍可校验
+        assert_eq!(fingerprint_value(&compact), format!("xxh3:{:016x}", xxhash_rust::xxh3::xxh3_64(br#"{"a":[1,2],"b":1}"#)));
+        assert_ne!(fingerprint_value(&compact), fingerprint_value(&json!({"b": 2, "a": [1, 2]})));
+        assert!(fingerprint_value(&compact).starts_with("xxh3:"));
+    }
+
+    #[test]
+    fn test_canonical_form() {
+        let a: Value = serde_json::from_str("{\"b\": {\"y\": [], \"x\": 1.50}, \"a\": [\"\\u4e2d\\n\", {}]}").unwrap();
+        let b: Value = serde_json::from_str("{\r\n\"a\":[\"中\\n\",{}],\r\n\"b\":{\"x\":1.50,\"y\":[]}}").unwrap();
+        let expected = "{\n  \"a\": [\n    \"中\\n\",\n    {}\n  ],\n  \"b\": {\n    \"x\": 1.50,\n    \"y\": []\n  }\n}";
+        assert_eq!(canonical_form(&a), expected);
+        assert_eq!(canonical_form(&b), expected);
+        assert!(canonical_form(&a).lines().all(|line| line == line.trim_end()));
+        assert_eq!(canonical_form(&json!("x")), "\"x\"");
+    }
+
+    #[test]
+    fn test_plan_writeback() {
+        let stage2 = json!({"fingerprint": "xxh3:0000000000000001", "items": []});
+
+        let plan = plan_writeback(&stage2, "xxh3:0000000000000001", false);
+        assert_eq!(plan, FingerprintPlan { mode: WritebackMode::Trusted, warning: None });
+
+        let plan = plan_writeback(&stage2, "xxh3:0000000000000002", false);
+        assert_eq!(plan.mode, WritebackMode::PathChecked);
+        assert!(plan.warning.unwrap().contains("文档指纹不一致"));
+
+        let plan = plan_writeback(&stage2, "xxh3:0000000000000002", true);
+        assert_eq!(plan.mode, WritebackMode::Trusted);
+        assert!(plan.warning.unwrap().contains("强制回写"));
+
+        // 旧版中间产物没有指纹
+        let plan = plan_writeback(&json!({"items": []}), "xxh3:0000000000000001", true);
+        assert_eq!(plan.mode, WritebackMode::PathChecked);
+        assert!(plan.warning.unwrap().contains("旧版本"));
+    }
+
+    #[test]
+    fn test_has_source_conflict() {
+        let dom = json!({"a": {"title": "Hello", "count": 3}});
+        assert!(!has_source_conflict(&dom, "$.a.title", "Hello"));
+        assert!(has_source_conflict(&dom, "$.a.title", "Hello v1"));
+        assert!(!has_source_conflict(&dom, "$.a.count", "3"));
+        assert!(has_source_conflict(&dom, "$.a.missing", "Hello"));
+    }
+}
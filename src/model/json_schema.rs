@@ -0,0 +1,111 @@
+//! JSON Schema（draft-07）校验：保存与回写前检查文档是否仍符合下游要求的结构
+//!
+//! 校验依赖 `jsonschema`，需以 `--features schema` 构建；未启用时无法加载 Schema
+
+use serde_json::Value;
+
+use crate::model::data_core::{AppError, StateKind};
+
+/// 一处 Schema 违规
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaViolation {
+    /// 违规位置（JSON Pointer，根为空字符串）
+    pub pointer: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pointer = if self.pointer.is_empty() { "/" } else { self.pointer.as_str() };
+        write!(f, "{}: {}", pointer, self.message)
+    }
+}
+
+/// 已编译的 Schema（保留原文，便于交给后台线程重新编译）
+pub struct CompiledSchema {
+    source: Value,
+    #[cfg(feature = "schema")]
+    validator: jsonschema::Validator,
+}
+
+impl std::fmt::Debug for CompiledSchema {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompiledSchema").field("source", &self.source).finish_non_exhaustive()
+    }
+}
+
+impl CompiledSchema {
+    /// 按 draft-07 编译 Schema
+    #[cfg(feature = "schema")]
+    pub fn compile(source: Value) -> Result<Self, AppError> {
+        let validator =
+            jsonschema::draft7::new(&source).map_err(|e| AppError::State(StateKind::Malformed, format!("JSON Schema 无效: {}", e)))?;
+        Ok(Self { source, validator })
+    }
+
+    /// 未启用 `schema` 特性时总是失败
+    #[cfg(not(feature = "schema"))]
+    pub fn compile(_source: Value) -> Result<Self, AppError> {
+        Err(AppError::State(StateKind::FeatureDisabled, "未启用 schema 特性，无法加载 JSON Schema（请以 --features schema 构建）".into()))
+    }
+
+    /// Schema 原文
+    pub fn source(&self) -> &Value {
+        &self.source
+    }
+
+    /// 校验实例，返回所有违规（按校验器报告顺序）
+    #[cfg(feature = "schema")]
+    pub fn validate(&self, instance: &Value) -> Vec<SchemaViolation> {
+        self.validator
+            .iter_errors(instance)
+            .map(|e| SchemaViolation { pointer: e.instance_path.to_string(), message: e.to_string() })
+            .collect()
+    }
+
+    #[cfg(not(feature = "schema"))]
+    pub fn validate(&self, _instance: &Value) -> Vec<SchemaViolation> {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[cfg(not(feature = "schema"))]
+    #[test]
+    fn test_compile_requires_feature() {
+        let err = CompiledSchema::compile(json!({"type": "object"})).unwrap_err();
+        assert!(err.to_string().contains("schema 特性"));
+    }
+
+    #[cfg(feature = "schema")]
+    #[test]
+    fn test_writeback_coercion_is_caught() {
+        use crate::model::workflow::{WritebackOptions, WritebackPlan};
+
+        let schema = CompiledSchema::compile(json!({
+            "type": "object",
+            "properties": {"count": {"type": "integer"}, "title": {"type": "string"}},
+            "required": ["count", "title"]
+        }))
+        .unwrap();
+        let mut dom = json!({"count": 3, "title": "Open"});
+        assert!(schema.validate(&dom).is_empty());
+
+        // 回写一律写为字符串：数字字段被改成 "5"
+        let stage2 = json!({"items": [
+            {"seq": 0, "name": "3", "source_path": "$.count"},
+            {"seq": 1, "name": "Open", "source_path": "$.title"},
+        ]});
+        let plan = WritebackPlan::build(&json!({"0": 5, "1": "打开"}), &stage2, &dom, WritebackOptions::default()).unwrap();
+        plan.apply(&mut dom, |_, _| {});
+
+        let violations = schema.validate(&dom);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].pointer, "/count");
+        assert!(CompiledSchema::compile(json!({"type": 1})).is_err());
+    }
+}
@@ -1,4 +1,25 @@
+pub mod anchor;
+pub mod batch;
 pub mod data_core;
+pub mod diff;
 pub mod shadow_tree;
 pub mod performance;
+pub mod placeholder;
+pub mod project;
+pub mod fingerprint;
+pub mod transform;
+pub mod qa;
+pub mod effort;
+pub mod export;
+pub mod json_schema;
+pub mod path;
+pub mod schema;
+pub mod search;
+pub(crate) mod search_query;
+pub mod subtree_cache;
+pub mod test_utils;
+pub mod watch;
+pub mod workflow;
 
+#[cfg(test)]
+mod proptests;
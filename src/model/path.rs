@@ -0,0 +1,227 @@
+//! 具体路径（影子树生成的 `$`、`.key`、`['key']`、`[n]` 组合）的分段解析与直接定位
+//!
+//! jsonpath-rust 不还原引号内的转义、并会剥掉键两端的所有引号（键 `'`、`a\b` 无法命中），
+//! 具体路径因此绕开查询引擎按段访问；通配符、过滤器、切片等仍交给 jsonpath-rust
+
+use jsonpath_rust::{query::queryable::Queryable, JsonPath};
+use serde_json::Value;
+
+use crate::model::data_core::{AppError, JsonPathKind};
+use crate::model::shadow_tree::field_path;
+
+/// 路径的一段（排序时下标按数值比较，`[2]` 在 `[10]` 之前）
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// 读取引号内的字符串（`chars` 位于开引号之后），返回去转义后的内容；未闭合或转义无效时返回 None
+fn parse_quoted(chars: &mut std::iter::Peekable<std::str::Chars>, quote: char) -> Option<String> {
+    let mut out = String::new();
+    loop {
+        match chars.next()? {
+            c if c == quote => return Some(out),
+            '\\' => match chars.next()? {
+                c @ ('\\' | '\'' | '"' | '/') => out.push(c),
+                'b' => out.push('\u{8}'),
+                'f' => out.push('\u{c}'),
+                'n' => out.push('\n'),
+                'r' => out.push('\r'),
+                't' => out.push('\t'),
+                'u' => {
+                    let hex: String = (0..4).map(|_| chars.next()).collect::<Option<_>>()?;
+                    out.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                }
+                _ => return None,
+            },
+            c => out.push(c),
+        }
+    }
+}
+
+/// 将具体路径拆分为段；含通配符、递归下降、过滤器、切片或负下标等时返回 None
+pub fn parse_segments(path: &str) -> Option<Vec<PathSegment>> {
+    let mut chars = path.trim().chars().peekable();
+    if chars.next()? != '$' {
+        return None;
+    }
+    let mut segments = Vec::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '.' => {
+                let mut name = String::new();
+                while let Some(c) = chars.next_if(|&c| c != '.' && c != '[') {
+                    name.push(c);
+                }
+                let valid = name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+                    && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+                if !valid {
+                    return None;
+                }
+                segments.push(PathSegment::Key(name));
+            }
+            '[' => {
+                let segment = match *chars.peek()? {
+                    quote @ ('\'' | '"') => {
+                        chars.next();
+                        PathSegment::Key(parse_quoted(&mut chars, quote)?)
+                    }
+                    '0'..='9' => {
+                        let mut digits = String::new();
+                        while let Some(d) = chars.next_if(char::is_ascii_digit) {
+                            digits.push(d);
+                        }
+                        PathSegment::Index(digits.parse().ok()?)
+                    }
+                    _ => return None,
+                };
+                if chars.next()? != ']' {
+                    return None;
+                }
+                segments.push(segment);
+            }
+            _ => return None,
+        }
+    }
+    Some(segments)
+}
+
+/// 由段拼出具体路径（与影子树生成的路径格式一致）
+pub fn format_segments(segments: &[PathSegment]) -> String {
+    segments.iter().fold("$".to_string(), |path, segment| match segment {
+        PathSegment::Key(key) => field_path(&path, key),
+        PathSegment::Index(index) => format!("{}[{}]", path, index),
+    })
+}
+
+/// 按段定位节点
+pub fn resolve<'a>(root: &'a Value, segments: &[PathSegment]) -> Option<&'a Value> {
+    segments.iter().try_fold(root, |value, segment| match segment {
+        PathSegment::Key(key) => value.as_object()?.get(key),
+        PathSegment::Index(index) => value.as_array()?.get(*index),
+    })
+}
+
+/// 按段定位节点（可变）
+pub fn resolve_mut<'a>(root: &'a mut Value, segments: &[PathSegment]) -> Option<&'a mut Value> {
+    segments.iter().try_fold(root, |value, segment| match segment {
+        PathSegment::Key(key) => value.as_object_mut()?.get_mut(key),
+        PathSegment::Index(index) => value.as_array_mut()?.get_mut(*index),
+    })
+}
+
+/// [`insert_creating`] 在数组末尾之后最多补多少个 `null` 空位
+pub const MAX_ARRAY_GAP: usize = 65_536;
+
+/// 按段写入 `value`，沿途缺少的节点按段类型创建为对象或数组；数组中下标之前的空位填 `null`
+///
+/// 已有节点类型与段不符（如在字符串下写入键）、或下标越过数组末尾超过 [`MAX_ARRAY_GAP`] 时不写入，返回 false；
+/// 下标来自外部文件，不设上限时一个巨大的下标就会耗尽内存
+pub fn insert_creating(root: &mut Value, segments: &[PathSegment], value: Value) -> bool {
+    let Some((first, rest)) = segments.split_first() else {
+        *root = value;
+        return true;
+    };
+    let slot = match first {
+        PathSegment::Key(key) => {
+            if root.is_null() {
+                *root = Value::Object(serde_json::Map::new());
+            }
+            let Some(map) = root.as_object_mut() else {
+                return false;
+            };
+            map.entry(key.clone()).or_insert(Value::Null)
+        }
+        PathSegment::Index(index) => {
+            if root.is_null() {
+                *root = Value::Array(Vec::new());
+            }
+            let Some(items) = root.as_array_mut() else {
+                return false;
+            };
+            if index.saturating_sub(items.len()) > MAX_ARRAY_GAP {
+                return false;
+            }
+            if items.len() <= *index {
+                items.resize(index + 1, Value::Null);
+            }
+            &mut items[*index]
+        }
+    };
+    insert_creating(slot, rest, value)
+}
+
+/// 第一个匹配节点：具体路径直接定位，其余交给 jsonpath-rust；路径语法错误时报错
+pub fn query_first<'a>(root: &'a Value, path: &str) -> Result<Option<&'a Value>, AppError> {
+    match parse_segments(path) {
+        Some(segments) => Ok(resolve(root, &segments)),
+        None => Ok(root.query(path).map_err(|e| AppError::JsonPath(JsonPathKind::Invalid, e.to_string()))?.into_iter().next()),
+    }
+}
+
+/// 第一个匹配节点的可变引用，规则同 [`query_first`]
+pub fn query_first_mut<'a>(root: &'a mut Value, path: &str) -> Result<Option<&'a mut Value>, AppError> {
+    if let Some(segments) = parse_segments(path) {
+        return Ok(resolve_mut(root, &segments));
+    }
+    let paths = root.query_only_path(path).map_err(|e| AppError::JsonPath(JsonPathKind::Invalid, e.to_string()))?;
+    Ok(match paths.into_iter().next() {
+        Some(p) => root.reference_mut(p),
+        None => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_segments() {
+        use PathSegment::*;
+        assert_eq!(parse_segments("$"), Some(vec![]));
+        assert_eq!(
+            parse_segments(r#"$.a[0]['b.c']["d\"e"]['f\\g\'h'][12]"#),
+            Some(vec![Key("a".into()), Index(0), Key("b.c".into()), Key("d\"e".into()), Key("f\\g'h".into()), Index(12)])
+        );
+        assert_eq!(parse_segments("$['\\u4e2d']"), Some(vec![Key("中".into())]));
+        let path = r#"$.a[0]['b.c'][12]"#;
+        assert_eq!(format_segments(&parse_segments(path).unwrap()), path);
+        for pattern in ["$.*", "$..a", "$.a[*]", "$.a[-1]", "$.a[0:2]", "$.a[?@.b]", "$['a'", "$['a\\x']", "$.0", "$.", "a.b"] {
+            assert_eq!(parse_segments(pattern), None, "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn test_query_first_resolves_nasty_keys() {
+        let keys = ["'", "", "a\\b", "'quoted'", "x]y", "$", "*", "0", "😀"];
+        let mut dom = Value::Object(keys.iter().map(|k| (k.to_string(), json!({"v": k}))).collect());
+        for key in keys {
+            let path = format!("{}.v", field_path("$", key));
+            assert_eq!(query_first(&dom, &path).unwrap(), Some(&json!(key)), "{}", path);
+        }
+
+        *query_first_mut(&mut dom, "$['\\'']").unwrap().unwrap() = json!(1);
+        assert_eq!(dom["'"], 1);
+        // 非具体路径交给 jsonpath-rust
+        assert_eq!(query_first(&json!({"a": [5, 6]}), "$.a[-1]").unwrap(), Some(&json!(6)));
+        assert!(query_first(&dom, "$[?").is_err());
+        assert_eq!(query_first(&dom, "$.missing").unwrap(), None);
+    }
+
+    #[test]
+    fn test_insert_creating() {
+        let mut root = Value::Null;
+        assert!(insert_creating(&mut root, &parse_segments("$.menu.items[2].title").unwrap(), json!("c")));
+        assert!(insert_creating(&mut root, &parse_segments("$.menu.items[0].title").unwrap(), json!("a")));
+        assert_eq!(root, json!({"menu": {"items": [{"title": "a"}, null, {"title": "c"}]}}));
+        assert!(!insert_creating(&mut root, &parse_segments("$.menu.items[0].title.x").unwrap(), json!(1)));
+        assert!(!insert_creating(&mut root, &parse_segments("$[0]").unwrap(), json!(1)));
+
+        let mut root = Value::Null;
+        assert!(!insert_creating(&mut root, &[PathSegment::Index(usize::MAX)], json!(1)));
+        assert!(insert_creating(&mut root, &[PathSegment::Index(MAX_ARRAY_GAP)], json!(1)));
+        assert!(!insert_creating(&mut root, &[PathSegment::Index(2 * MAX_ARRAY_GAP + 2)], json!(1)));
+    }
+}
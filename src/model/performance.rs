@@ -4,8 +4,12 @@
 //! 遵循NFR要求：50MB文件≤5秒，UI响应≤200ms，内存≤3倍文件大小
 
 use std::time::Instant;
-use serde_json::{json, Value};
-use crate::model::{data_core::AppState, shadow_tree::build_shadow_tree};
+use serde_json::Value;
+use crate::model::{
+    data_core::AppState,
+    shadow_tree::build_shadow_tree,
+    test_utils::{generate_test_fixture, TestFixtureShape},
+};
 
 /// 性能测试结果
 #[derive(Debug)]
@@ -29,56 +33,6 @@ impl PerformanceResult {
     }
 }
 
-/// 生成大型测试JSON数据
-pub fn generate_large_json(depth: usize, width: usize) -> Value {
-    fn create_nested_object(current_depth: usize, max_depth: usize, width: usize) -> Value {
-        if current_depth >= max_depth {
-            return json!("叶子节点值");
-        }
-        
-        let mut obj = serde_json::Map::new();
-        
-        // 添加各种类型的字段
-        for i in 0..width {
-            let key = format!("field_{}", i);
-            let value = match i % 5 {
-                0 => json!(format!("字符串值_{}", i)),
-                1 => json!(i as i64),
-                2 => json!(i % 2 == 0),
-                3 => json!([1, 2, 3, i]),
-                4 => create_nested_object(current_depth + 1, max_depth, width / 2),
-                _ => json!(null),
-            };
-            obj.insert(key, value);
-        }
-        
-        Value::Object(obj)
-    }
-    
-    let mut root = serde_json::Map::new();
-    root.insert("metadata".to_string(), json!({
-        "generated_at": "2025-01-09T10:00:00Z",
-        "depth": depth,
-        "width": width,
-        "description": "性能测试用大型JSON文档"
-    }));
-    
-    root.insert("data".to_string(), create_nested_object(0, depth, width));
-    
-    // 添加大型数组
-    let large_array: Vec<Value> = (0..width * 10)
-        .map(|i| json!({
-            "id": i,
-            "name": format!("项目_{}", i),
-            "value": i * 2,
-            "active": i % 3 == 0
-        }))
-        .collect();
-    root.insert("items".to_string(), json!(large_array));
-    
-    Value::Object(root)
-}
-
 /// 测试影子树构建性能
 pub fn benchmark_shadow_tree_build(json_data: &Value) -> PerformanceResult {
     let start = Instant::now();
@@ -150,6 +104,30 @@ pub fn benchmark_node_extraction(app_state: &AppState, paths: &[&str]) -> Vec<Pe
     results
 }
 
+/// 测试重复搜索性能：对每个过滤条件执行 `rounds` 轮 `apply_search_filter`
+pub fn benchmark_search(app_state: &mut AppState, filters: &[&str], rounds: usize) -> PerformanceResult {
+    let indexed = app_state.search_index.is_some();
+    let start = Instant::now();
+    for _ in 0..rounds {
+        for filter in filters {
+            app_state.apply_search_filter(filter);
+        }
+    }
+    let duration = start.elapsed();
+
+    PerformanceResult::new(
+        if indexed { "搜索(有索引)" } else { "搜索(无索引)" },
+        duration.as_millis(),
+        true,
+        &format!("{} 个节点上执行 {} 次搜索", app_state.tree_flat.len(), rounds * filters.len())
+    )
+}
+
+/// 基准文档形状：每层 `items` 含 `宽度 × 10` 个元素
+fn fixture_shape(depth: usize, width: usize) -> TestFixtureShape {
+    TestFixtureShape { depth, string_fields: width / 2, array_len: width * 10, ..Default::default() }
+}
+
 /// 运行综合性能测试
 pub fn run_performance_suite() -> Vec<PerformanceResult> {
     let mut results = Vec::new();
@@ -166,7 +144,7 @@ pub fn run_performance_suite() -> Vec<PerformanceResult> {
         
         // 生成测试数据
         let start = Instant::now();
-        let json_data = generate_large_json(depth, width);
+        let json_data = generate_test_fixture(&fixture_shape(depth, width));
         let generation_time = start.elapsed();
         
         results.push(PerformanceResult::new(
@@ -211,7 +189,7 @@ pub fn run_performance_suite() -> Vec<PerformanceResult> {
         ));
         
         // 节点提取测试
-        let test_paths = ["$", "$.metadata", "$.data", "$.items[0]"];
+        let test_paths = ["$", "$.child", "$.child.items", "$.items[0]"];
         let extraction_results = benchmark_node_extraction(&app_state, &test_paths);
         results.extend(extraction_results);
     }
@@ -223,20 +201,11 @@ pub fn run_performance_suite() -> Vec<PerformanceResult> {
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_generate_large_json() {
-        let json = generate_large_json(2, 3);
-        assert!(json.is_object());
-        
-        let obj = json.as_object().unwrap();
-        assert!(obj.contains_key("metadata"));
-        assert!(obj.contains_key("data"));
-        assert!(obj.contains_key("items"));
-    }
+    use serde_json::json;
 
     #[test]
     fn test_performance_benchmarks() {
-        let json = generate_large_json(2, 5);
+        let json = generate_test_fixture(&fixture_shape(2, 5));
         
         // 测试影子树构建
         let tree_result = benchmark_shadow_tree_build(&json);
@@ -249,4 +218,41 @@ mod tests {
         assert!(parse_result.success);
         assert!(parse_result.duration_ms < 1000); // 应该在1秒内完成
     }
+
+    #[test]
+    fn test_search_index_benchmark() {
+        // 10万个节点的扁平文档
+        let mut map = serde_json::Map::new();
+        for i in 0..100_000 {
+            map.insert(format!("key_{}", i), json!(i));
+        }
+        let json_data = Value::Object(map);
+        let mut app_state = AppState {
+            tree_flat: build_shadow_tree(&json_data),
+            dom: Some(json_data),
+            ..Default::default()
+        };
+
+        let filters = ["key_9999", "KEY_12345", "y_100", "missing"];
+        let visible_paths = |app_state: &mut AppState, filter: &str| -> Vec<String> {
+            app_state.apply_search_filter(filter);
+            app_state.tree_flat.iter().filter(|n| n.visible).map(|n| n.path.clone()).collect()
+        };
+        let linear: Vec<Vec<String>> = filters.iter().map(|f| visible_paths(&mut app_state, f)).collect();
+        let linear_run = benchmark_search(&mut app_state, &filters, 34);
+        assert_eq!((linear_run.operation.as_str(), linear_run.success), ("搜索(无索引)", true));
+
+        app_state.build_search_index();
+        let indexed: Vec<Vec<String>> = filters.iter().map(|f| visible_paths(&mut app_state, f)).collect();
+        let indexed_run = benchmark_search(&mut app_state, &filters, 34);
+        assert_eq!((indexed_run.operation.as_str(), indexed_run.success), ("搜索(有索引)", true));
+
+        // 有无索引的命中集合完全一致
+        assert_eq!(indexed, linear);
+        assert_eq!(linear[0], ["$.key_9999", "$.key_99990", "$.key_99991", "$.key_99992", "$.key_99993", "$.key_99994", "$.key_99995", "$.key_99996", "$.key_99997", "$.key_99998", "$.key_99999"]);
+        assert_eq!(linear[1], ["$.key_12345"], "不区分大小写");
+        assert_eq!(linear[2].len(), 111, "key_100、key_100x 与 key_100xx");
+        assert!(linear[3].is_empty());
+        assert_eq!(indexed_run.details, format!("100001 个节点上执行 {} 次搜索", 34 * filters.len()));
+    }
 }
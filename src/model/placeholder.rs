@@ -0,0 +1,108 @@
+//! 占位符识别：检测文本中的插值占位符（printf、{name}、{{name}}、${name}、%{name}）
+
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+/// 插值占位符风格
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PlaceholderStyle {
+    /// 未检测到占位符
+    #[default]
+    None,
+    /// printf 风格：`%s`、`%d`、`%1$s`、`%.2f`
+    Printf,
+    /// 单花括号：`{name}`、`{0}`
+    Brace,
+    /// 双花括号：`{{name}}`
+    DoubleBrace,
+    /// 美元花括号：`${name}`
+    DollarBrace,
+    /// 百分号花括号：`%{name}`
+    PercentBrace,
+}
+
+impl PlaceholderStyle {
+    /// 所有可检测的风格（按优先级排列，计数相同时靠前者优先）
+    pub const DETECTABLE: [PlaceholderStyle; 5] = [
+        PlaceholderStyle::Printf,
+        PlaceholderStyle::Brace,
+        PlaceholderStyle::DoubleBrace,
+        PlaceholderStyle::DollarBrace,
+        PlaceholderStyle::PercentBrace,
+    ];
+
+    /// 风格的简短示例（用于日志与界面）
+    pub fn label(&self) -> &'static str {
+        match self {
+            PlaceholderStyle::None => "无占位符",
+            PlaceholderStyle::Printf => "%s",
+            PlaceholderStyle::Brace => "{name}",
+            PlaceholderStyle::DoubleBrace => "{{name}}",
+            PlaceholderStyle::DollarBrace => "${name}",
+            PlaceholderStyle::PercentBrace => "%{name}",
+        }
+    }
+}
+
+fn placeholder_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        // 分支顺序决定同一位置的匹配优先级；`%%` 为转义的百分号
+        Regex::new(concat!(
+            r"(?P<escaped>%%)",
+            r"|(?P<dollar>\$\{[A-Za-z0-9_.]+\})",
+            r"|(?P<percent>%\{[A-Za-z0-9_.]+\})",
+            r"|(?P<double>\{\{\s*[A-Za-z0-9_.]+\s*\}\})",
+            r"|(?P<brace>\{[A-Za-z0-9_.]+\})",
+            r"|(?P<printf>%(?:[0-9]+\$)?[-+ 0#]*[0-9]*(?:\.[0-9]+)?[sdifuxXeEgGc@])",
+        ))
+        .expect("占位符正则无效")
+    })
+}
+
+/// 提取文本中的所有占位符及其风格（按出现顺序）
+pub fn detect_placeholder_patterns(text: &str) -> Vec<(PlaceholderStyle, String)> {
+    placeholder_regex()
+        .captures_iter(text)
+        .filter_map(|caps| {
+            let (style, m) = if let Some(m) = caps.name("dollar") {
+                (PlaceholderStyle::DollarBrace, m)
+            } else if let Some(m) = caps.name("percent") {
+                (PlaceholderStyle::PercentBrace, m)
+            } else if let Some(m) = caps.name("double") {
+                (PlaceholderStyle::DoubleBrace, m)
+            } else if let Some(m) = caps.name("brace") {
+                (PlaceholderStyle::Brace, m)
+            } else {
+                (PlaceholderStyle::Printf, caps.name("printf")?)
+            };
+            Some((style, m.as_str().to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn styles(text: &str) -> Vec<PlaceholderStyle> {
+        detect_placeholder_patterns(text).into_iter().map(|(s, _)| s).collect()
+    }
+
+    #[test]
+    fn test_detect_placeholder_patterns() {
+        assert_eq!(
+            detect_placeholder_patterns("Hello %s, you have %1$d items (%.2f%%)"),
+            vec![
+                (PlaceholderStyle::Printf, "%s".to_string()),
+                (PlaceholderStyle::Printf, "%1$d".to_string()),
+                (PlaceholderStyle::Printf, "%.2f".to_string()),
+            ]
+        );
+        assert_eq!(styles("你好 {name}，共 {0} 条"), vec![PlaceholderStyle::Brace; 2]);
+        assert_eq!(styles("{{ user.name }} 登录"), vec![PlaceholderStyle::DoubleBrace]);
+        assert_eq!(styles("${count} 个 / %{total}"), vec![PlaceholderStyle::DollarBrace, PlaceholderStyle::PercentBrace]);
+        assert!(detect_placeholder_patterns("100% 完成 {不是占位符}").is_empty());
+    }
+}
@@ -0,0 +1,213 @@
+//! 翻译项目文件：记录源文件、中间产物2、译文与操作历史之间的关系，保存为 `<name>.fanyi-project.json`
+//!
+//! 文件中的路径相对于项目文件所在目录保存（不在其下的保留绝对路径），移动整个目录后仍可打开
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::model::data_core::{AppError, Stage2Sort};
+use crate::model::fingerprint::FINGERPRINT_KEY;
+use crate::utils::fs::{read_json_file, write_json_file_atomic};
+
+/// 项目文件名后缀
+pub const PROJECT_FILE_SUFFIX: &str = ".fanyi-project.json";
+
+/// 项目中保存的提取配置
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectConfig {
+    /// 过滤条件预设（生成中间产物2时取并集）
+    pub filters: Vec<String>,
+    pub leaf_nodes_only: bool,
+    pub sort: Stage2Sort,
+    /// 数组元素锚点字段（如 `id`）
+    pub anchor_field: Option<String>,
+}
+
+/// 历史记录中的一次操作
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectEvent {
+    /// 按过滤条件提取了中间产物2
+    Extracted { filter: String, items: usize },
+    /// 以译文文件回写了源文件
+    Writeback { file: PathBuf, applied: usize },
+}
+
+impl ProjectEvent {
+    /// 历史列表中显示的一行
+    pub fn describe(&self) -> String {
+        match self {
+            ProjectEvent::Extracted { filter, items } => format!("提取 {} 条（过滤条件: {}）", items, filter),
+            ProjectEvent::Writeback { file, applied } => format!("回写 {} 个字段（{}）", applied, file.display()),
+        }
+    }
+}
+
+/// 带时间戳的历史记录
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// 本地时间 `YYYY-MM-DD HH:MM:SS`
+    pub at: String,
+    pub event: ProjectEvent,
+}
+
+/// 翻译项目
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Project {
+    /// 源文件
+    pub source: PathBuf,
+    pub config: ProjectConfig,
+    /// 中间产物2文件
+    pub stage2_path: Option<PathBuf>,
+    /// 用过的译文（回写）文件，按首次使用的顺序
+    pub translation_paths: Vec<PathBuf>,
+    /// 最近一次提取时源文档的指纹（见 [`crate::model::fingerprint`]）
+    pub fingerprint: Option<String>,
+    /// 操作历史（最早的在前）
+    pub history: Vec<HistoryEntry>,
+}
+
+/// 项目文件路径：`{dir}/{name}.fanyi-project.json`
+pub fn project_file_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{}{}", name, PROJECT_FILE_SUFFIX))
+}
+
+fn relative_to(path: &Path, base: &Path) -> PathBuf {
+    path.strip_prefix(base).map(Path::to_path_buf).unwrap_or_else(|_| path.to_path_buf())
+}
+
+impl Project {
+    pub fn new(source: &Path) -> Self {
+        Project { source: source.to_path_buf(), ..Project::default() }
+    }
+
+    /// 读取项目文件，相对路径按项目文件所在目录解析；引用的文件缺失不报错（见 [`Self::missing_files`]）
+    pub fn open(path: &Path) -> Result<Self, AppError> {
+        let mut project: Project = serde_json::from_value(read_json_file(path)?)?;
+        let base = path.parent().unwrap_or(Path::new(""));
+        project.map_paths(|p| base.join(p));
+        Ok(project)
+    }
+
+    /// 原子写入项目文件，项目文件所在目录下的路径保存为相对路径
+    pub fn save(&self, path: &Path) -> Result<(), AppError> {
+        let base = path.parent().unwrap_or(Path::new(""));
+        let mut stored = self.clone();
+        stored.map_paths(|p| relative_to(p, base));
+        write_json_file_atomic(path, &serde_json::to_value(&stored)?)
+    }
+
+    fn map_paths(&mut self, f: impl Fn(&Path) -> PathBuf) {
+        self.source = f(&self.source);
+        if let Some(stage2) = &mut self.stage2_path {
+            *stage2 = f(stage2);
+        }
+        for path in &mut self.translation_paths {
+            *path = f(path);
+        }
+        for entry in &mut self.history {
+            if let ProjectEvent::Writeback { file, .. } = &mut entry.event {
+                *file = f(file);
+            }
+        }
+    }
+
+    /// 以当前本地时间追加一条历史；回写事件同时登记译文文件，提取事件不改变指纹
+    pub fn record_event(&mut self, event: ProjectEvent) {
+        if let ProjectEvent::Writeback { file, .. } = &event {
+            if !self.translation_paths.contains(file) {
+                self.translation_paths.push(file.clone());
+            }
+        }
+        let at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        self.history.push(HistoryEntry { at, event });
+    }
+
+    /// 记录一次提取：更新中间产物2路径与其中的源文档指纹，并追加历史
+    pub fn record_extraction(&mut self, stage2_path: &Path, stage2: &Value, filter: &str) {
+        let items = stage2.get("items").and_then(Value::as_array).map_or(0, Vec::len);
+        self.stage2_path = Some(stage2_path.to_path_buf());
+        self.fingerprint = stage2.get(FINGERPRINT_KEY).and_then(Value::as_str).map(str::to_string);
+        self.record_event(ProjectEvent::Extracted { filter: filter.to_string(), items });
+    }
+
+    /// 项目引用但磁盘上不存在的文件
+    pub fn missing_files(&self) -> Vec<PathBuf> {
+        std::iter::once(&self.source)
+            .chain(&self.stage2_path)
+            .chain(&self.translation_paths)
+            .filter(|p| !p.is_file())
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_project_save_open_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("big.json");
+        let stage2_path = dir.path().join("big.stage2.json");
+        let translations = dir.path().join("zh.json");
+        for path in [&source, &stage2_path, &translations] {
+            std::fs::write(path, "{}").unwrap();
+        }
+
+        let mut project = Project::new(&source);
+        project.config = ProjectConfig {
+            filters: vec!["title".into(), "label".into()],
+            leaf_nodes_only: true,
+            sort: Stage2Sort::ByPath,
+            anchor_field: Some("id".into()),
+        };
+        let stage2 = json!({"fingerprint": "xxh3:0011223344556677", "items": [{"seq": 0}, {"seq": 1}]});
+        project.record_extraction(&stage2_path, &stage2, "title, label");
+        project.record_event(ProjectEvent::Writeback { file: translations.clone(), applied: 2 });
+        project.record_event(ProjectEvent::Writeback { file: translations.clone(), applied: 1 });
+        assert_eq!(project.translation_paths, vec![translations.clone()]);
+        assert_eq!(project.fingerprint.as_deref(), Some("xxh3:0011223344556677"));
+        assert_eq!(project.history[0].event.describe(), "提取 2 条（过滤条件: title, label）");
+
+        let path = project_file_path(dir.path(), "demo");
+        project.save(&path).unwrap();
+        // 项目目录下的路径以相对路径保存
+        let stored = std::fs::read_to_string(&path).unwrap();
+        assert!(stored.contains("\"source\": \"big.json\""), "{}", stored);
+        assert!(stored.contains("\"writeback\": {"));
+
+        let reopened = Project::open(&path).unwrap();
+        assert_eq!(reopened, project);
+        assert!(reopened.missing_files().is_empty());
+    }
+
+    #[test]
+    fn test_project_missing_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("big.json");
+        std::fs::write(&source, "{}").unwrap();
+        let mut project = Project::new(&source);
+        project.stage2_path = Some(dir.path().join("gone.stage2.json"));
+        project.translation_paths.push(dir.path().join("gone.zh.json"));
+        let path = project_file_path(dir.path(), "demo");
+        project.save(&path).unwrap();
+
+        std::fs::remove_file(&source).unwrap();
+        let reopened = Project::open(&path).unwrap();
+        let missing: Vec<String> = reopened
+            .missing_files()
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(missing, ["big.json", "gone.stage2.json", "gone.zh.json"]);
+
+        assert!(Project::open(&dir.path().join("none.fanyi-project.json")).is_err());
+    }
+}
@@ -0,0 +1,124 @@
+//! 路径生成与回写往返的属性测试：随机文档的键含引号、方括号、点号、表情与空串
+
+use proptest::prelude::*;
+use serde_json::{Map, Value};
+
+use crate::model::data_core::{AppState, Stage2Options};
+use crate::model::path::{parse_segments, query_first, resolve};
+use crate::model::shadow_tree::{build_shadow_tree, field_path, NodeKind};
+use crate::model::workflow::{WritebackOptions, WritebackPlan};
+
+/// 有限的用例数，保证 CI 中可在数秒内跑完
+const CASES: u32 = 64;
+
+/// 刁钻的对象键
+fn nasty_key() -> impl Strategy<Value = String> {
+    prop_oneof![
+        Just(String::new()),
+        Just("$".to_string()),
+        Just("*".to_string()),
+        Just("0".to_string()),
+        "[a-z'\"\\[\\]\\.\\\\ $@*?😀中]{1,6}",
+    ]
+}
+
+fn leaf() -> impl Strategy<Value = Value> {
+    prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::Bool),
+        any::<i32>().prop_map(Value::from),
+        "[a-z'\"\\\\ 中😀]{0,8}".prop_map(Value::String),
+    ]
+}
+
+/// 最多 4 层、每层最多 4 个子节点的任意文档（根为对象）
+fn document() -> impl Strategy<Value = Value> {
+    let node = leaf().prop_recursive(4, 64, 4, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..4).prop_map(Value::Array),
+            prop::collection::btree_map(nasty_key(), inner, 0..4).prop_map(|m| Value::Object(m.into_iter().collect())),
+        ]
+    });
+    prop::collection::btree_map(nasty_key(), node, 0..5).prop_map(|m| Value::Object(m.into_iter().collect::<Map<_, _>>()))
+}
+
+fn pseudo_translate(text: &str) -> String {
+    format!("[!! {} !!]", text)
+}
+
+/// 以 "$" 为过滤条件提取全部节点，返回中间产物2与其中字符串叶子条目的 `(序号, 源路径, 原文)`
+fn extract_string_leaves(dom: &Value) -> (Value, Vec<(usize, String, String)>) {
+    let mut state = AppState::default();
+    state.replace_dom(dom.clone()).unwrap();
+    let strings: std::collections::HashSet<String> =
+        state.tree_flat.iter().filter(|n| n.kind == NodeKind::String).map(|n| n.path.clone()).collect();
+    let text = state.build_intermediate_stage2_multi(&["$".to_string()], Stage2Options::default(), |_, _| {}).unwrap();
+    let stage2: Value = serde_json::from_str(&text).unwrap();
+    let leaves = stage2["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|item| {
+            let path = item["source_path"].as_str()?;
+            strings.contains(path).then(|| {
+                (item["seq"].as_u64().unwrap() as usize, path.to_string(), item["name"].as_str().unwrap().to_string())
+            })
+        })
+        .collect();
+    (stage2, leaves)
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(CASES))]
+
+    /// 每个影子树路径都是具体路径，且恰好解析到该节点的值
+    ///
+    /// 路径经 [`query_first`] 解析：jsonpath-rust 本身无法命中含引号的键，具体路径由分段解析器直接定位
+    #[test]
+    fn prop_every_tree_path_resolves_to_one_node(dom in document()) {
+        let tree = build_shadow_tree(&dom);
+        let mut expected: Vec<(String, &Value)> = Vec::new();
+        let mut stack = vec![("$".to_string(), &dom)];
+        while let Some((path, value)) = stack.pop() {
+            match value {
+                Value::Object(map) => stack.extend(map.iter().map(|(k, v)| (field_path(&path, k), v))),
+                Value::Array(items) => stack.extend(items.iter().enumerate().map(|(i, v)| (format!("{}[{}]", path, i), v))),
+                _ => {}
+            }
+            expected.push((path, value));
+        }
+        prop_assert_eq!(tree.len(), expected.len());
+        for node in &tree {
+            let segments = parse_segments(&node.path);
+            prop_assert!(segments.is_some(), "不是具体路径: {}", node.path);
+            let value = expected.iter().find(|(p, _)| *p == node.path).map(|(_, v)| *v);
+            prop_assert_eq!(resolve(&dom, &segments.unwrap()), value, "路径 {} 指向了错误的节点", node.path);
+            prop_assert_eq!(query_first(&dom, &node.path).unwrap(), value);
+        }
+        // 路径互不相同：每个路径只对应一个节点
+        let mut paths: Vec<&str> = tree.iter().map(|n| n.path.as_str()).collect();
+        paths.sort_unstable();
+        paths.dedup();
+        prop_assert_eq!(paths.len(), tree.len());
+    }
+
+    /// 提取全部字符串叶子 → 伪翻译 → 回写 → 重新提取，得到的恰为伪翻译结果
+    #[test]
+    fn prop_writeback_round_trip(dom in document()) {
+        let (stage2, leaves) = extract_string_leaves(&dom);
+        let translated: Map<String, Value> = leaves
+            .iter()
+            .map(|(seq, _, text)| (seq.to_string(), Value::String(pseudo_translate(text))))
+            .collect();
+
+        let mut written = dom.clone();
+        let plan = WritebackPlan::build(&Value::Object(translated), &stage2, &written, WritebackOptions::default()).unwrap();
+        // 空白原文的译文仍非空，全部条目都应回写
+        prop_assert_eq!(plan.apply(&mut written, |_, _| {}), leaves.len());
+
+        let (_, rewritten) = extract_string_leaves(&written);
+        let expected: Vec<(String, String)> = leaves.iter().map(|(_, p, t)| (p.clone(), pseudo_translate(t))).collect();
+        let actual: Vec<(String, String)> = rewritten.into_iter().map(|(_, p, t)| (p, t)).collect();
+        prop_assert_eq!(actual, expected);
+    }
+}
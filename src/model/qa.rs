@@ -0,0 +1,253 @@
+//! 译文质量检查：同一原文出现多种译文、不同原文共用同一译文
+
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+
+use crate::model::data_core::{csv_field, AppError, StateKind};
+use crate::model::workflow::normalize_writeback;
+
+/// 一组冲突中的一个取值（译文或原文），及其出现的序号与源路径
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QaVariant {
+    pub text: String,
+    pub seqs: Vec<u64>,
+    pub paths: Vec<String>,
+}
+
+/// 以某个文本为键、包含两个及以上不同取值的冲突组
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QaGroup {
+    pub key: String,
+    pub variants: Vec<QaVariant>,
+}
+
+/// 质量检查报告
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct QaReport {
+    /// 相同原文对应不同译文（键为原文，取值为各译文）
+    pub inconsistent_translations: Vec<QaGroup>,
+    /// 不同原文对应相同译文（键为译文，取值为各原文）
+    pub shared_translations: Vec<QaGroup>,
+}
+
+/// 键 -> 取值 -> (序号, 源路径)
+type Grouping = BTreeMap<String, BTreeMap<String, (Vec<u64>, Vec<String>)>>;
+
+fn conflicting_groups(grouping: Grouping) -> Vec<QaGroup> {
+    grouping
+        .into_iter()
+        .filter(|(_, variants)| variants.len() > 1)
+        .map(|(key, variants)| QaGroup {
+            key,
+            variants: variants
+                .into_iter()
+                .map(|(text, (seqs, paths))| QaVariant { text, seqs, paths })
+                .collect(),
+        })
+        .collect()
+}
+
+/// 对照中间产物2与译文（`seq` -> 译文）检查译文一致性；没有译文或译文为空的条目不参与检查
+pub fn analyze_final(stage2: &Value, final_map: &BTreeMap<String, String>) -> QaReport {
+    let mut by_source = Grouping::new();
+    let mut by_translation = Grouping::new();
+
+    let items = stage2.get("items").and_then(|v| v.as_array()).map(Vec::as_slice).unwrap_or_default();
+    for item in items {
+        let Some(seq) = item.get("seq").and_then(|s| s.as_u64()) else {
+            continue;
+        };
+        let Some(translation) = final_map.get(&seq.to_string()).filter(|t| !t.trim().is_empty()) else {
+            continue;
+        };
+        let source = item.get("name").and_then(|n| n.as_str()).unwrap_or_default();
+        let path = item.get("source_path").and_then(|p| p.as_str()).unwrap_or_default();
+
+        for (grouping, key, variant) in [
+            (&mut by_source, source, translation.as_str()),
+            (&mut by_translation, translation.as_str(), source),
+        ] {
+            let (seqs, paths) = grouping
+                .entry(key.to_string())
+                .or_default()
+                .entry(variant.to_string())
+                .or_default();
+            seqs.push(seq);
+            paths.push(path.to_string());
+        }
+    }
+
+    QaReport {
+        inconsistent_translations: conflicting_groups(by_source),
+        shared_translations: conflicting_groups(by_translation),
+    }
+}
+
+/// 解析译文文件（最终产物格式：`{"seq": "译文"}`），非字符串值按 JSON 文本处理
+pub fn parse_final_map(text: &str) -> Result<BTreeMap<String, String>, AppError> {
+    final_map_from_value(&serde_json::from_str(text)?)
+}
+
+/// 解析任一结构（[`FinalShape`](crate::model::workflow::FinalShape)）的译文文件：先经 [`normalize_writeback`]
+/// 按中间产物2的源路径转换为 `{"seq": "译文"}`，按路径或原结构导出的译文因此与按序号的译文同样参与检查
+pub fn parse_translation_file(text: &str, stage2: &Value) -> Result<BTreeMap<String, String>, AppError> {
+    let value: Value = serde_json::from_str(text)?;
+    final_map_from_value(&normalize_writeback(&value, stage2)?)
+}
+
+fn final_map_from_value(value: &Value) -> Result<BTreeMap<String, String>, AppError> {
+    let map = value
+        .as_object()
+        .ok_or_else(|| AppError::State(StateKind::Malformed, "译文文件必须是以序号为键的JSON对象".into()))?;
+    Ok(map
+        .iter()
+        .map(|(seq, v)| {
+            let text = match v {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            (seq.clone(), text)
+        })
+        .collect())
+}
+
+fn join_seqs(seqs: &[u64]) -> String {
+    seqs.iter().map(u64::to_string).collect::<Vec<_>>().join(" ")
+}
+
+impl QaReport {
+    pub fn is_empty(&self) -> bool {
+        self.inconsistent_translations.is_empty() && self.shared_translations.is_empty()
+    }
+
+    fn sections(&self) -> [(&'static str, &'static str, &[QaGroup]); 2] {
+        [
+            ("inconsistent_translation", "相同原文、译文不一致", &self.inconsistent_translations),
+            ("shared_translation", "不同原文、译文相同", &self.shared_translations),
+        ]
+    }
+
+    /// 渲染为预览区显示的文本报告
+    pub fn render(&self) -> String {
+        if self.is_empty() {
+            return "质量检查通过：未发现译文冲突".to_string();
+        }
+        let mut out = String::new();
+        for (_, title, groups) in self.sections() {
+            out.push_str(&format!("== {}（{} 组）==\n", title, groups.len()));
+            for group in groups {
+                out.push_str(&format!("「{}」\n", group.key));
+                for variant in &group.variants {
+                    out.push_str(&format!("  -> 「{}」 seq: {}\n", variant.text, join_seqs(&variant.seqs)));
+                    for path in &variant.paths {
+                        out.push_str(&format!("       {}\n", path));
+                    }
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// 以 CSV（`kind,key,variant,seqs,paths`）导出，每个取值一行
+    pub fn write_csv(&self, writer: &mut dyn std::io::Write) -> Result<(), AppError> {
+        writeln!(writer, "kind,key,variant,seqs,paths")?;
+        for (kind, _, groups) in self.sections() {
+            for group in groups {
+                for variant in &group.variants {
+                    writeln!(
+                        writer,
+                        "{},{},{},{},{}",
+                        kind,
+                        csv_field(&group.key),
+                        csv_field(&variant.text),
+                        join_seqs(&variant.seqs),
+                        csv_field(&variant.paths.join(" "))
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn final_map(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_analyze_final_collisions() {
+        let stage2 = json!({"items": [
+            {"seq": 0, "name": "Open", "source_path": "$.a.title"},
+            {"seq": 1, "name": "Open", "source_path": "$.b.title"},
+            {"seq": 2, "name": "Open", "source_path": "$.c.title"},
+            {"seq": 3, "name": "Start", "source_path": "$.d.title"},
+            {"seq": 4, "name": "Close", "source_path": "$.e.title"},
+            {"seq": 5, "name": "Exit", "source_path": "$.f.title"},
+        ]});
+        let translations = final_map(&[("0", "打开"), ("1", "开启"), ("2", "打开"), ("3", "开启"), ("4", "关闭"), ("5", "")]);
+
+        let report = analyze_final(&stage2, &translations);
+        assert_eq!(
+            report.inconsistent_translations,
+            vec![QaGroup {
+                key: "Open".into(),
+                variants: vec![
+                    QaVariant { text: "开启".into(), seqs: vec![1], paths: vec!["$.b.title".into()] },
+                    QaVariant { text: "打开".into(), seqs: vec![0, 2], paths: vec!["$.a.title".into(), "$.c.title".into()] },
+                ],
+            }]
+        );
+        assert_eq!(report.shared_translations.len(), 1);
+        let shared = &report.shared_translations[0];
+        assert_eq!(shared.key, "开启");
+        assert_eq!(shared.variants.iter().map(|v| v.text.as_str()).collect::<Vec<_>>(), vec!["Open", "Start"]);
+
+        let mut csv = Vec::new();
+        report.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.contains("inconsistent_translation,Open,打开,0 2,$.a.title $.c.title\n"));
+        assert!(csv.contains("shared_translation,开启,Start,3,$.d.title\n"));
+        assert!(report.render().contains("seq: 0 2"));
+    }
+
+    #[test]
+    fn test_analyze_final_clean_and_parse() {
+        let stage2 = json!({"items": [
+            {"seq": 0, "name": "Open", "source_path": "$.a"},
+            {"seq": 1, "name": "Open", "source_path": "$.b"},
+        ]});
+        let translations = parse_final_map(r#"{"0": "打开", "1": "打开", "9": 3}"#).unwrap();
+        assert_eq!(translations["9"], "3");
+        assert!(analyze_final(&stage2, &translations).is_empty());
+        assert!(parse_final_map("[]").is_err());
+    }
+
+    #[test]
+    fn test_parse_translation_file_every_shape() {
+        let stage2 = json!({"items": [
+            {"seq": 0, "name": "Open", "source_path": "$.menu.open"},
+            {"seq": 1, "name": "Open", "source_path": "$.menu.items[1]"},
+        ]});
+        let shapes = [
+            r#"{"0": "打开", "1": "开启"}"#,
+            r#"{"$.menu.open": "打开", "$.menu.items[1]": "开启"}"#,
+            r#"{"menu": {"open": "打开", "items": [null, "开启"]}}"#,
+        ];
+        for text in shapes {
+            let translations = parse_translation_file(text, &stage2).unwrap();
+            assert_eq!(translations.get("0").map(String::as_str), Some("打开"), "{}", text);
+            // 原结构的叶子不会被当作 JSON 文本整体比较
+            let report = analyze_final(&stage2, &translations);
+            assert_eq!(report.inconsistent_translations.len(), 1, "{}", text);
+            assert_eq!(report.inconsistent_translations[0].key, "Open");
+        }
+        assert!(parse_translation_file("\"x\"", &stage2).is_err());
+    }
+}
@@ -0,0 +1,131 @@
+//! 数组元素结构分析：统计各键的出现次数与取值类型，找出缺失或类型不一致的键
+
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+
+use crate::model::shadow_tree::NodeKind;
+
+/// 单个键（嵌套对象中的键以 `.` 连接）在数组元素中的统计
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyStats {
+    pub key: String,
+    /// 包含该键的元素个数
+    pub present: usize,
+    /// 观察到的取值类型（按首次出现顺序）
+    pub kinds: Vec<NodeKind>,
+    /// 并非所有对象元素都包含该键，或取值类型不止一种
+    pub heterogeneous: bool,
+}
+
+/// 数组元素结构报告
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SchemaReport {
+    pub path: String,
+    /// 数组元素总数
+    pub elements: usize,
+    /// 其中不是对象的元素个数（不参与键统计）
+    pub non_object_elements: usize,
+    /// 按键名排序
+    pub keys: Vec<KeyStats>,
+}
+
+impl SchemaReport {
+    /// 所有对象元素结构一致且没有非对象元素
+    pub fn is_homogeneous(&self) -> bool {
+        self.non_object_elements == 0 && self.keys.iter().all(|k| !k.heterogeneous)
+    }
+
+    /// 渲染为预览区显示的文本
+    pub fn render(&self) -> String {
+        let objects = self.elements - self.non_object_elements;
+        let mut out = format!("== 结构分析: {} ==\n", self.path);
+        out.push_str(&format!("元素 {} 个，其中对象 {} 个", self.elements, objects));
+        if self.non_object_elements > 0 {
+            out.push_str(&format!("，非对象 {} 个", self.non_object_elements));
+        }
+        out.push('\n');
+        out.push_str(if self.is_homogeneous() { "结构一致\n\n" } else { "结构不一致（标记 ! 的键）\n\n" });
+        for stats in &self.keys {
+            let kinds: Vec<String> = stats.kinds.iter().map(|k| format!("{:?}", k)).collect();
+            let flag = if stats.heterogeneous { "!" } else { " " };
+            out.push_str(&format!("{} {}  {}/{}  {}\n", flag, stats.key, stats.present, objects, kinds.join(" | ")));
+        }
+        out
+    }
+}
+
+/// 遍历一次数组，统计对象元素中各键（含嵌套对象的键）的出现次数与取值类型
+pub fn analyze_array(path: &str, items: &[Value]) -> SchemaReport {
+    let mut stats: BTreeMap<String, (usize, Vec<NodeKind>)> = BTreeMap::new();
+    let mut non_object_elements = 0;
+    for item in items {
+        let Value::Object(map) = item else {
+            non_object_elements += 1;
+            continue;
+        };
+        let mut stack: Vec<(String, &serde_json::Map<String, Value>)> = vec![(String::new(), map)];
+        while let Some((prefix, map)) = stack.pop() {
+            for (key, value) in map {
+                let key = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                let (present, kinds) = stats.entry(key.clone()).or_default();
+                *present += 1;
+                let kind = NodeKind::of(value);
+                if !kinds.contains(&kind) {
+                    kinds.push(kind);
+                }
+                if let Value::Object(child) = value {
+                    stack.push((key, child));
+                }
+            }
+        }
+    }
+
+    let objects = items.len() - non_object_elements;
+    let keys = stats
+        .into_iter()
+        .map(|(key, (present, kinds))| KeyStats { heterogeneous: present < objects || kinds.len() > 1, key, present, kinds })
+        .collect();
+    SchemaReport { path: path.to_string(), elements: items.len(), non_object_elements, keys }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_analyze_mixed_shapes_and_nested_objects() {
+        let items = json!([
+            {"id": 1, "name": "a", "meta": {"author": "x"}},
+            {"id": 2, "name": null, "meta": {"author": "y", "tags": []}},
+            {"id": 3, "meta": {"author": "z"}},
+            "标量"
+        ]);
+        let report = analyze_array("$.items", items.as_array().unwrap());
+        assert_eq!((report.elements, report.non_object_elements), (4, 1));
+        assert!(!report.is_homogeneous());
+
+        let get = |key: &str| report.keys.iter().find(|k| k.key == key).unwrap();
+        assert_eq!(report.keys.iter().map(|k| k.key.as_str()).collect::<Vec<_>>(), vec!["id", "meta", "meta.author", "meta.tags", "name"]);
+        assert!(!get("id").heterogeneous);
+        assert!(!get("meta.author").heterogeneous);
+        assert_eq!((get("meta.tags").present, get("meta.tags").heterogeneous), (1, true));
+        let name = get("name");
+        assert_eq!((name.present, name.kinds.as_slice()), (2, [NodeKind::String, NodeKind::Null].as_slice()));
+        assert!(name.heterogeneous);
+
+        let text = report.render();
+        assert!(text.contains("非对象 1 个"));
+        assert!(text.contains("! name  2/3  String | Null"));
+    }
+
+    #[test]
+    fn test_analyze_homogeneous_array() {
+        let items = json!([{"name": "a"}, {"name": "b"}]);
+        let report = analyze_array("$", items.as_array().unwrap());
+        assert!(report.is_homogeneous());
+        assert!(report.render().contains("结构一致"));
+        assert!(analyze_array("$", &[]).is_homogeneous());
+    }
+}
@@ -0,0 +1,134 @@
+//! 统一搜索：多种匹配策略（子串、正则、模糊、精确键名、值包含、路径模式）共用一个入口
+
+use crate::model::shadow_tree::JsonTreeNode;
+
+/// 搜索策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchMode {
+    /// 路径/键名子串（支持多条件、`-排除词` 与引号短语）
+    #[default]
+    Substring,
+    /// 键名或路径匹配正则表达式
+    Regex,
+    /// 键名（其次路径）按字符顺序模糊匹配，结果按得分降序
+    Fuzzy,
+    /// 键名完全相等
+    ExactKey,
+    /// 标量值（字符串/数字/布尔）包含查询文本
+    ValueContains,
+    /// 完整路径匹配通配模式（如 `$.users[*].name`），不做 JSONPath 求值
+    PathPattern,
+}
+
+impl SearchMode {
+    /// 所有搜索策略（界面循环切换顺序）
+    pub const ALL: [SearchMode; 6] = [
+        SearchMode::Substring,
+        SearchMode::Regex,
+        SearchMode::Fuzzy,
+        SearchMode::ExactKey,
+        SearchMode::ValueContains,
+        SearchMode::PathPattern,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SearchMode::Substring => "子串",
+            SearchMode::Regex => "正则",
+            SearchMode::Fuzzy => "模糊",
+            SearchMode::ExactKey => "精确键名",
+            SearchMode::ValueContains => "值包含",
+            SearchMode::PathPattern => "路径模式",
+        }
+    }
+}
+
+/// 命中的位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchKind {
+    /// 键名
+    Key,
+    /// 完整路径
+    Path,
+    /// 节点值
+    Value,
+}
+
+/// 单个搜索结果
+#[derive(Debug, Clone)]
+pub struct SearchMatch {
+    pub node: JsonTreeNode,
+    /// 匹配得分（0.0-1.0），非模糊策略恒为 1.0
+    pub score: f32,
+    pub match_kind: MatchKind,
+}
+
+/// 编译路径通配模式：`*` 匹配任意字符（含 `.`）、`?` 匹配单个字符；`[`、`]` 按字面匹配路径中的下标与引号键，
+/// 因此 `$.users[*].name` 匹配 `$.users[0].name` 与 `$.users[17].name`
+pub(crate) fn compile_path_pattern(pattern: &str) -> Result<glob::Pattern, glob::PatternError> {
+    let escaped: String = pattern
+        .chars()
+        .map(|c| match c {
+            '[' => "[[]".to_string(),
+            ']' => "[]]".to_string(),
+            c => c.to_string(),
+        })
+        .collect();
+    glob::Pattern::new(&escaped)
+}
+
+/// 模糊匹配得分：`query` 的字符按顺序出现在 `text` 中（不区分大小写）时返回 (0, 1]，否则 None
+///
+/// 匹配越紧凑得分越高，从开头匹配额外加分
+pub(crate) fn fuzzy_score(query: &str, text: &str) -> Option<f32> {
+    let query: Vec<char> = query.to_lowercase().chars().collect();
+    if query.is_empty() {
+        return None;
+    }
+    let mut qi = 0;
+    let mut first = None;
+    let mut last = 0;
+    for (i, c) in text.to_lowercase().chars().enumerate() {
+        if c == query[qi] {
+            first.get_or_insert(i);
+            last = i;
+            qi += 1;
+            if qi == query.len() {
+                break;
+            }
+        }
+    }
+    if qi < query.len() {
+        return None;
+    }
+    let first = first.unwrap_or(0);
+    let compactness = query.len() as f32 / (last - first + 1) as f32;
+    let prefix_bonus = if first == 0 { 1.0 } else { 0.8 };
+    Some(compactness * prefix_bonus)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_score_ordering() {
+        assert_eq!(fuzzy_score("name", "name"), Some(1.0));
+        assert_eq!(fuzzy_score("NM", "name"), Some(2.0 / 3.0));
+        assert!(fuzzy_score("nm", "display_name").unwrap() < fuzzy_score("nm", "name").unwrap());
+        assert!(fuzzy_score("tl", "title").unwrap() > fuzzy_score("tl", "total_label").unwrap());
+        assert_eq!(fuzzy_score("xyz", "name"), None);
+        assert_eq!(fuzzy_score("", "name"), None);
+    }
+
+    #[test]
+    fn test_compile_path_pattern() {
+        let pattern = compile_path_pattern("$.users[*].name").unwrap();
+        assert!(pattern.matches("$.users[0].name"));
+        assert!(pattern.matches("$.users[17].name"));
+        assert!(!pattern.matches("$.users[0].email"));
+        assert!(compile_path_pattern("$.a?c").unwrap().matches("$.abc"));
+        assert!(compile_path_pattern("$['a b']*").unwrap().matches("$['a b'].c"));
+        assert!(compile_path_pattern("$.**x").is_err());
+    }
+}
@@ -0,0 +1,159 @@
+//! 搜索查询：空格分隔的多个词（全部命中），`-词` 排除，双引号包裹含空格的短语
+
+/// 解析后的单条搜索查询
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SearchQuery {
+    /// 必须全部出现的词
+    pub include: Vec<String>,
+    /// 任一出现即排除的词
+    pub exclude: Vec<String>,
+}
+
+/// 切分查询文本：返回 `(是否排除, 词)`
+///
+/// 引号内支持 `\"` 与 `\\` 转义，未闭合的引号取到末尾；单独的 `-` 被忽略
+fn tokenize(input: &str) -> Vec<(bool, String)> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let Some(&first) = chars.peek() else {
+            break;
+        };
+        let negated = first == '-';
+        if negated {
+            chars.next();
+        }
+
+        let mut term = String::new();
+        if chars.next_if_eq(&'"').is_some() {
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' => break,
+                    '\\' => match chars.next_if(|&n| n == '"' || n == '\\') {
+                        Some(escaped) => term.push(escaped),
+                        None => term.push('\\'),
+                    },
+                    _ => term.push(c),
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                term.push(c);
+            }
+        }
+        if !term.is_empty() {
+            tokens.push((negated, term));
+        }
+    }
+    tokens
+}
+
+impl SearchQuery {
+    pub fn parse(input: &str) -> Self {
+        let mut query = SearchQuery::default();
+        for (negated, term) in tokenize(input) {
+            let list = if negated { &mut query.exclude } else { &mut query.include };
+            if !list.contains(&term) {
+                list.push(term);
+            }
+        }
+        query
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// 所有词转为小写的查询，与小写的字段配合实现不区分大小写的匹配
+    pub fn to_lowercase(&self) -> SearchQuery {
+        let lower = |terms: &[String]| terms.iter().map(|t| t.to_lowercase()).collect();
+        SearchQuery { include: lower(&self.include), exclude: lower(&self.exclude) }
+    }
+
+    /// 每个包含词都出现在某个字段中，且任何字段都不含排除词
+    pub fn matches(&self, fields: &[&str]) -> bool {
+        self.include.iter().all(|t| fields.iter().any(|f| f.contains(t.as_str())))
+            && !self.exclude.iter().any(|t| fields.iter().any(|f| f.contains(t.as_str())))
+    }
+
+    /// 解析结果的可读描述（用于状态栏回显）
+    pub fn describe(&self) -> String {
+        let quote = |terms: &[String]| terms.iter().map(|t| format!("「{}」", t)).collect::<String>();
+        match (self.include.is_empty(), self.exclude.is_empty()) {
+            (true, true) => "（空）".to_string(),
+            (false, true) => format!("包含{}", quote(&self.include)),
+            (true, false) => format!("排除{}", quote(&self.exclude)),
+            (false, false) => format!("包含{}，排除{}", quote(&self.include), quote(&self.exclude)),
+        }
+    }
+}
+
+/// 解析搜索框中的多个过滤条件（`,` 或 `|` 分隔，去空白、去重并保持顺序）
+///
+/// 以 `$` 开头的 JSONPath 表达式整体作为一个条件，不做拆分；双引号内的分隔符不拆分
+pub fn parse_filter_list(input: &str) -> Vec<String> {
+    let input = input.trim();
+    if input.starts_with('$') {
+        return vec![input.to_string()];
+    }
+    let mut parts = Vec::new();
+    let (mut start, mut in_quotes, mut escaped) = (0, false, false);
+    for (i, c) in input.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            ',' | '|' if !in_quotes => {
+                parts.push(&input[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&input[start..]);
+
+    let mut filters: Vec<String> = Vec::new();
+    for part in parts.into_iter().map(str::trim).filter(|p| !p.is_empty()) {
+        if !filters.iter().any(|f| f == part) {
+            filters.push(part.to_string());
+        }
+    }
+    filters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_terms_phrases_and_exclusions() {
+        let q = SearchQuery::parse(r#"name -icon  "key with spaces" -"debug mode" name"#);
+        assert_eq!(q.include, vec!["name", "key with spaces"]);
+        assert_eq!(q.exclude, vec!["icon", "debug mode"]);
+        assert_eq!(q.describe(), "包含「name」「key with spaces」，排除「icon」「debug mode」");
+
+        // 转义引号与反斜杠；未闭合引号取到末尾
+        let q = SearchQuery::parse(r#""say \"hi\"" "a\\b" "unterminated phrase"#);
+        assert_eq!(q.include, vec![r#"say "hi""#, r"a\b", "unterminated phrase"]);
+
+        // 单独的 - 与末尾的 - 被忽略；词中间的 - 保持原样
+        let q = SearchQuery::parse("key-name - -");
+        assert_eq!(q, SearchQuery { include: vec!["key-name".into()], exclude: vec![] });
+        assert!(SearchQuery::parse("  ").is_empty());
+    }
+
+    #[test]
+    fn test_matches_fields() {
+        let q = SearchQuery::parse("name -icon -debug");
+        assert!(q.matches(&["$.menu.name", "name"]));
+        assert!(!q.matches(&["$.icon.name", "name"]));
+        assert!(!q.matches(&["$.menu.debug_name", "debug_name"]));
+        assert!(!q.matches(&["$.menu.title", "title"]));
+        assert!(SearchQuery::parse("-debug").matches(&["$.title", "title"]));
+
+        let lower = SearchQuery::parse("Name -ICON").to_lowercase();
+        assert_eq!(lower, SearchQuery::parse("name -icon"));
+        assert!(!SearchQuery::parse("Name").matches(&["$.menu.name"]), "matches 本身区分大小写");
+    }
+}
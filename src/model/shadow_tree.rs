@@ -1,9 +1,22 @@
 //! 影子树（Shadow Tree）：仅存结构与路径，不复制大值，助于大文件性能导航
 
+use std::io::Read;
+
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use crate::model::data_core::{AppError, StateKind};
+use crate::utils::fs::{find_value_offsets, parse_json_checked};
+
+/// 影子树允许的默认最大嵌套深度
+pub const DEFAULT_MAX_DEPTH: u32 = 1024;
+
+/// `build_shadow_tree` 的硬性深度上限：超过即视为病态数据并 panic
+pub const SHADOW_TREE_PANIC_DEPTH: u32 = 10_000;
+
 /// JSON 节点类型（与 UI 展示解耦）
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum NodeKind {
     Object,
     Array,
@@ -13,7 +26,21 @@ pub enum NodeKind {
     Null,
 }
 
-#[derive(Debug, Clone)]
+impl NodeKind {
+    /// JSON 值对应的节点类型
+    pub fn of(v: &Value) -> Self {
+        match v {
+            Value::Object(_) => NodeKind::Object,
+            Value::Array(_) => NodeKind::Array,
+            Value::String(_) => NodeKind::String,
+            Value::Number(_) => NodeKind::Number,
+            Value::Bool(_) => NodeKind::Bool,
+            Value::Null => NodeKind::Null,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct JsonTreeNode {
     /// 节点在父级中的键名或索引的字符串形式
     pub name: String,
@@ -31,39 +58,70 @@ pub struct JsonTreeNode {
     pub expanded: bool,
     /// 是否可见（用于搜索过滤）
     pub visible: bool,
+    /// 节点在源文件中的起始字节偏移（仅在启用偏移追踪且树对应磁盘文件时有值）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub byte_offset: Option<u64>,
+    /// 用户备注（如“需复核”“不翻译”），随 `{源文件}.annotations.json` 旁路文件保存
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotation: Option<String>,
+    /// 子树紧凑序列化的字节数估算（按需由 `AppState::compute_subtree_sizes` 填充，未计算时为 0）
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub size_bytes: u64,
 }
 
-/// 从根 Value 构建全树影子索引（可后续做懒加载/分页）
-pub fn build_shadow_tree(root: &Value) -> Vec<JsonTreeNode> {
-    let mut out = Vec::with_capacity(1024);
-    fn kind_of(v: &Value) -> NodeKind {
-        match v {
-            Value::Object(_) => NodeKind::Object,
-            Value::Array(_) => NodeKind::Array,
-            Value::String(_) => NodeKind::String,
-            Value::Number(_) => NodeKind::Number,
-            Value::Bool(_) => NodeKind::Bool,
-            Value::Null => NodeKind::Null,
+fn is_zero(n: &u64) -> bool {
+    *n == 0
+}
+
+impl JsonTreeNode {
+    /// 显示截断标记：代表 `hidden` 个未显示的节点（路径为空，不对应任何 JSON 值）
+    pub fn truncation_sentinel(depth: u32, hidden: usize, preview: String) -> Self {
+        Self {
+            name: "…".to_string(),
+            path: String::new(),
+            kind: NodeKind::Null,
+            children: 0,
+            preview: format!("{}（{} 个节点）", preview, hidden),
+            depth,
+            expanded: false,
+            visible: true,
+            byte_offset: None,
+            annotation: None,
+            size_bytes: 0,
         }
     }
-    fn preview_of(v: &Value) -> String {
-        match v {
-            Value::String(s) => {
-                let s = s.trim();
-                if s.chars().count() > 32 {
-                    let truncated: String = s.chars().take(32).collect();
-                    format!("\"{}...\"", truncated)
-                } else {
-                    format!("\"{}\"", s)
-                }
-            }
-            Value::Number(n) => n.to_string(),
-            Value::Bool(b) => b.to_string(),
-            Value::Null => "null".to_string(),
-            Value::Object(m) => format!("{{..}} ({} keys)", m.len()),
-            Value::Array(a) => format!("[..] ({} items)", a.len()),
-        }
+
+    /// 是否为显示截断标记
+    pub fn is_truncation_sentinel(&self) -> bool {
+        self.path.is_empty()
     }
+}
+
+/// 影子树构建选项
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ShadowTreeOptions {
+    /// 加载时记录每个节点在源文件中的字节偏移（需额外扫描一遍文本）
+    pub track_offsets: bool,
+    /// 节点数软上限：超过时只构建前若干节点并在末尾追加截断标记（None 为不限）
+    pub max_nodes: Option<usize>,
+}
+
+/// 从根 Value 构建全树影子索引（可后续做懒加载/分页）
+///
+/// # Panics
+///
+/// 嵌套深度超过 [`SHADOW_TREE_PANIC_DEPTH`] 时 panic；不可信输入请使用 [`try_build_shadow_tree`]
+pub fn build_shadow_tree(root: &Value) -> Vec<JsonTreeNode> {
+    build_shadow_tree_limited(root, None)
+}
+
+/// 按先序构建至多 `max_nodes` 个节点；超出时停止构建，末尾追加一个“… N 个节点未展示”的截断标记
+///
+/// # Panics
+///
+/// 同 [`build_shadow_tree`]
+pub fn build_shadow_tree_limited(root: &Value, max_nodes: Option<usize>) -> Vec<JsonTreeNode> {
+    let mut out = Vec::with_capacity(max_nodes.unwrap_or(1024).min(1024));
     fn push_node(out: &mut Vec<JsonTreeNode>, name: String, path: String, v: &Value, depth: u32) {
         let children = match v {
             Value::Object(m) => m.len() as u32,
@@ -73,47 +131,301 @@ pub fn build_shadow_tree(root: &Value) -> Vec<JsonTreeNode> {
         out.push(JsonTreeNode {
             name,
             path,
-            kind: kind_of(v),
+            kind: NodeKind::of(v),
             children,
-            preview: preview_of(v),
+            preview: node_preview(v),
             depth,
             expanded: false,  // 默认折叠
             visible: true,    // 默认可见
+            byte_offset: None,
+            annotation: None,
+            size_bytes: 0,
         });
     }
-    fn walk(out: &mut Vec<JsonTreeNode>, v: &Value, path: &str, name: &str, depth: u32) {
-        push_node(out, name.to_string(), path.to_string(), v, depth);
+    // 显式栈迭代（先序遍历），避免深层嵌套导致栈溢出
+    let mut stack: Vec<(&Value, String, String, u32)> = vec![(root, "$".to_string(), "$".to_string(), 0)];
+    while let Some((v, path, name, depth)) = stack.pop() {
+        if max_nodes.is_some_and(|limit| out.len() >= limit) {
+            let hidden = count_nodes(v) + stack.iter().map(|(v, ..)| count_nodes(v)).sum::<usize>();
+            let mut marker = JsonTreeNode::truncation_sentinel(1, hidden, String::new());
+            marker.preview = format!("{} 个节点未展示", hidden);
+            out.push(marker);
+            break;
+        }
+        assert!(
+            depth <= SHADOW_TREE_PANIC_DEPTH,
+            "影子树嵌套深度超过 {} 层（疑似病态或循环构造的数据），请使用 try_build_shadow_tree 限制深度",
+            SHADOW_TREE_PANIC_DEPTH
+        );
         match v {
             Value::Object(map) => {
-                for (k, child) in map {
-                    // JSONPath 字段含特殊字符时使用 bracket-notation
-                    let field_path = if k.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' ) {
-                        format!("{}.{}", path, k)
-                    } else {
-                        format!("{}['{}']", path, k.replace('\'', "\\'"))
-                    };
-                    walk(out, child, &field_path, k, depth + 1);
+                // 逆序入栈以保持文档顺序
+                for (k, child) in map.iter().rev() {
+                    stack.push((child, field_path(&path, k), k.clone(), depth + 1));
                 }
             }
             Value::Array(arr) => {
-                for (idx, child) in arr.iter().enumerate() {
+                for (idx, child) in arr.iter().enumerate().rev() {
                     let item_path = format!("{}[{}]", path, idx);
-                    walk(out, child, &item_path, &format!("[{}]", idx), depth + 1);
+                    stack.push((child, item_path, format!("[{}]", idx), depth + 1));
                 }
             }
             _ => {}
         }
+        push_node(&mut out, name, path, v, depth);
     }
-
-    walk(&mut out, root, "$", "$", 0);
     out
 }
 
+/// 子树的节点数（含自身）
+fn count_nodes(root: &Value) -> usize {
+    let mut count = 0;
+    let mut stack = vec![root];
+    while let Some(v) = stack.pop() {
+        count += 1;
+        match v {
+            Value::Object(map) => stack.extend(map.values()),
+            Value::Array(arr) => stack.extend(arr.iter()),
+            _ => {}
+        }
+    }
+    count
+}
+
+/// 节点的轻量预览文本（字符串截断、数字/布尔/空的简短描述、容器的子元素数）
+pub(crate) fn node_preview(v: &Value) -> String {
+    match v {
+        Value::String(s) => {
+            let s = s.trim();
+            if s.chars().count() > 32 {
+                let truncated: String = s.chars().take(32).collect();
+                format!("\"{}...\"", truncated)
+            } else {
+                format!("\"{}\"", s)
+            }
+        }
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Null => "null".to_string(),
+        Value::Object(m) => format!("{{..}} ({} keys)", m.len()),
+        Value::Array(a) => format!("[..] ({} items)", a.len()),
+    }
+}
+
+/// 为挂载到 `path` 处（深度 `depth`）的值构建影子树节点，路径与深度按挂载位置重定位
+pub(crate) fn build_subtree_nodes(value: &Value, name: String, path: &str, depth: u32) -> Vec<JsonTreeNode> {
+    let mut nodes = build_shadow_tree(value);
+    for node in &mut nodes {
+        // 子树内路径均以 "$" 开头，替换为挂载路径
+        node.path = format!("{}{}", path, &node.path[1..]);
+        node.depth += depth;
+    }
+    if let Some(root) = nodes.first_mut() {
+        root.name = name;
+    }
+    nodes
+}
+
+/// 拼接对象字段的 JSONPath：字段为空、以数字开头或含特殊字符时使用 bracket-notation（转义反斜杠与单引号）
+pub fn field_path(parent: &str, key: &str) -> String {
+    let simple = key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if simple {
+        format!("{}.{}", parent, key)
+    } else {
+        format!("{}['{}']", parent, key.replace('\\', "\\\\").replace('\'', "\\'"))
+    }
+}
+
+/// 计算 JSON 的最大嵌套深度；超过 `limit` 时提前返回（返回值大于 `limit`）
+pub fn max_depth(root: &Value, limit: u32) -> u32 {
+    let mut deepest = 0;
+    let mut stack: Vec<(&Value, u32)> = vec![(root, 0)];
+    while let Some((v, depth)) = stack.pop() {
+        deepest = deepest.max(depth);
+        if deepest > limit {
+            break;
+        }
+        match v {
+            Value::Object(map) => stack.extend(map.values().map(|c| (c, depth + 1))),
+            Value::Array(arr) => stack.extend(arr.iter().map(|c| (c, depth + 1))),
+            _ => {}
+        }
+    }
+    deepest
+}
+
+/// 带深度上限的影子树构建：嵌套深度超过 `max_allowed_depth` 时返回错误而不构建
+pub fn try_build_shadow_tree(root: &Value, max_allowed_depth: u32) -> Result<Vec<JsonTreeNode>, AppError> {
+    if max_depth(root, max_allowed_depth) > max_allowed_depth {
+        return Err(AppError::State(StateKind::Invalid, format!("嵌套过深: 超过上限 {} 层", max_allowed_depth)));
+    }
+    Ok(build_shadow_tree(root))
+}
+
+/// 按选项构建影子树：启用 `track_offsets` 时从源文本中补充各节点的字节偏移，设置 `max_nodes` 时超出部分截断
+pub fn try_build_shadow_tree_with_options(
+    root: &Value,
+    source_text: &str,
+    max_allowed_depth: u32,
+    options: ShadowTreeOptions,
+) -> Result<Vec<JsonTreeNode>, AppError> {
+    if max_depth(root, max_allowed_depth) > max_allowed_depth {
+        return Err(AppError::State(StateKind::Invalid, format!("嵌套过深: 超过上限 {} 层", max_allowed_depth)));
+    }
+    let mut tree = build_shadow_tree_limited(root, options.max_nodes);
+    if options.track_offsets {
+        let offsets = find_value_offsets(source_text);
+        for node in &mut tree {
+            node.byte_offset = offsets.get(&node.path).copied();
+        }
+    }
+    Ok(tree)
+}
+
+/// 字符串紧凑序列化后的字节数（含两端引号与转义）
+fn escaped_len(s: &str) -> u64 {
+    let escapes: usize = s
+        .bytes()
+        .map(|b| match b {
+            b'"' | b'\\' | b'\x08' | b'\x0c' | b'\n' | b'\r' | b'\t' => 1,
+            0..=0x1f => 5,
+            _ => 0,
+        })
+        .sum();
+    (s.len() + escapes + 2) as u64
+}
+
+/// 按影子树的先序顺序计算每个节点子树的紧凑序列化字节数
+///
+/// 只做一次自底向上的累加：叶子按字面量计长，容器为括号、逗号、键（含引号与冒号）与子节点大小之和，
+/// 不重复序列化子树；`progress` 以 `(0..1, 说明)` 报告进度
+pub fn compute_subtree_sizes(root: &Value, mut progress: impl FnMut(f32, &str)) -> Vec<u64> {
+    // 先序遍历（与 build_shadow_tree 顺序一致），记录 (自身字节, 键开销, 深度)
+    let mut entries: Vec<(u64, u64, usize)> = Vec::new();
+    let mut stack: Vec<(&Value, u64, usize)> = vec![(root, 0, 0)];
+    while let Some((v, key_overhead, depth)) = stack.pop() {
+        let own = match v {
+            Value::Null => 4,
+            Value::Bool(b) => if *b { 4 } else { 5 },
+            Value::Number(n) => n.to_string().len() as u64,
+            Value::String(s) => escaped_len(s),
+            Value::Object(map) => {
+                stack.extend(map.iter().rev().map(|(k, child)| (child, escaped_len(k) + 1, depth + 1)));
+                2 + map.len().saturating_sub(1) as u64
+            }
+            Value::Array(arr) => {
+                stack.extend(arr.iter().rev().map(|child| (child, 0, depth + 1)));
+                2 + arr.len().saturating_sub(1) as u64
+            }
+        };
+        entries.push((own, key_overhead, depth));
+        if entries.len().is_multiple_of(10_000) {
+            progress(0.5 * (entries.len() as f32 / (entries.len() + stack.len()) as f32), "正在遍历节点...");
+        }
+    }
+
+    // 逆先序：处理某节点时其后代均已处理，acc[d + 1] 恰为其子节点（含键开销）之和
+    let total = entries.len();
+    let mut sizes = vec![0u64; total];
+    let mut acc: Vec<u64> = Vec::new();
+    for (i, &(own, key_overhead, depth)) in entries.iter().enumerate().rev() {
+        if acc.len() < depth + 2 {
+            acc.resize(depth + 2, 0);
+        }
+        let size = own + std::mem::take(&mut acc[depth + 1]);
+        acc[depth] += size + key_overhead;
+        sizes[i] = size;
+        if (total - i).is_multiple_of(10_000) {
+            progress(0.5 + 0.5 * ((total - i) as f32 / total as f32), "正在累加子树大小...");
+        }
+    }
+    progress(1.0, "完成");
+    sizes
+}
+
+/// 从读取源加载的 DOM、影子树及重复键路径
+pub(crate) struct LoadedTree {
+    pub dom: Value,
+    pub tree: Vec<JsonTreeNode>,
+    pub duplicate_keys: Vec<String>,
+}
+
+/// 读取并解析 JSON、检测重复键、按选项构建影子树（`AppState::load_file` 经由此处加载）
+pub(crate) fn load_tree_from_reader<R: Read>(mut reader: R, options: ShadowTreeOptions) -> Result<LoadedTree, AppError> {
+    // 重复键检测与字节偏移都需扫描源文本，因此内部仍完整读入
+    let mut text = String::new();
+    reader.read_to_string(&mut text)?;
+    let (dom, duplicate_keys) = parse_json_checked(&text)?;
+    let tree = try_build_shadow_tree_with_options(&dom, &text, DEFAULT_MAX_DEPTH, options)?;
+    Ok(LoadedTree { dom, tree, duplicate_keys })
+}
+
+/// 从任意读取源（文件、解压流、网络流等）解析 JSON 并构建影子树，返回 `(DOM, 影子树)`
+///
+/// 与 `AppState::load_file` 使用同一加载流程，选项的效果一致
+pub fn build_shadow_tree_from_reader<R: Read>(reader: R, options: ShadowTreeOptions) -> Result<(Value, Vec<JsonTreeNode>), AppError> {
+    let loaded = load_tree_from_reader(reader, options)?;
+    Ok((loaded.dom, loaded.tree))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use serde_json::json;
 
+    #[test]
+    fn test_track_offsets_option() {
+        let text = r#"{"z": "后", "a": {"k": [true]}}"#;
+        let root: Value = serde_json::from_str(text).unwrap();
+
+        let plain = try_build_shadow_tree_with_options(&root, text, DEFAULT_MAX_DEPTH, ShadowTreeOptions::default()).unwrap();
+        assert!(plain.iter().all(|n| n.byte_offset.is_none()));
+
+        let options = ShadowTreeOptions { track_offsets: true, ..Default::default() };
+        let tree = try_build_shadow_tree_with_options(&root, text, DEFAULT_MAX_DEPTH, options).unwrap();
+        let offset = |path: &str| tree.iter().find(|n| n.path == path).and_then(|n| n.byte_offset).unwrap() as usize;
+        assert_eq!(offset("$"), 0);
+        assert_eq!(&text[offset("$.z")..offset("$.z") + 5], "\"后\"");
+        assert!(text[offset("$.a.k[0]")..].starts_with("true"));
+    }
+
+    #[test]
+    fn test_max_nodes_truncation() {
+        let root = json!({"a": {"x": 1, "y": 2}, "b": [1, 2, 3]});
+        assert_eq!(build_shadow_tree_limited(&root, Some(100)), build_shadow_tree(&root));
+
+        let tree = build_shadow_tree_limited(&root, Some(3));
+        let paths: Vec<&str> = tree.iter().map(|n| n.path.as_str()).collect();
+        assert_eq!(paths, ["$", "$.a", "$.a.x", ""]);
+        // 标记位于根下一层，计入其余全部节点
+        let marker = tree.last().unwrap();
+        assert!(marker.is_truncation_sentinel());
+        assert_eq!((marker.depth, marker.name.as_str(), marker.preview.as_str()), (1, "…", "5 个节点未展示"));
+        assert_eq!(tree[0].children, 2, "根节点仍报告真实的子节点数");
+
+        let options = ShadowTreeOptions { max_nodes: Some(3), ..Default::default() };
+        let text = root.to_string();
+        assert_eq!(try_build_shadow_tree_with_options(&root, &text, DEFAULT_MAX_DEPTH, options).unwrap(), tree);
+    }
+
+    #[test]
+    fn test_build_from_reader() {
+        let items: Vec<Value> = (0..50).map(|i| json!({"id": i, "name": format!("item{}", i)})).collect();
+        let text = serde_json::to_string(&json!({"items": items})).unwrap();
+
+        let options = ShadowTreeOptions { track_offsets: true, ..Default::default() };
+        let (dom, tree) = build_shadow_tree_from_reader(std::io::Cursor::new(text.as_bytes()), options).unwrap();
+        assert_eq!(dom["items"][49]["name"], "item49");
+        assert_eq!(tree.len(), 2 + 50 * 3);
+        let node = tree.iter().find(|n| n.path == "$.items[7].name").unwrap();
+        assert!(text[node.byte_offset.unwrap() as usize..].starts_with("\"item7\""));
+
+        assert!(build_shadow_tree_from_reader(std::io::Cursor::new("{\"a\":"), options).is_err());
+        let invalid_utf8: &[u8] = &[b'"', 0xff, b'"'];
+        assert!(build_shadow_tree_from_reader(invalid_utf8, options).is_err());
+    }
+
     #[test]
     fn test_simple_object_shadow_tree() {
         let json = json!({
@@ -243,5 +555,74 @@ mod tests {
             }
         }
     }
-}
 
+    /// 构造指定深度的嵌套数组 `[[[...]]]`
+    fn nested_array(depth: usize) -> Value {
+        let mut v = Value::Null;
+        for _ in 0..depth {
+            v = Value::Array(vec![v]);
+        }
+        v
+    }
+
+    /// 逐层拆解深层嵌套值，避免递归 Drop 导致栈溢出
+    fn dismantle(mut v: Value) {
+        while let Value::Array(mut arr) = v {
+            v = arr.pop().unwrap_or(Value::Null);
+        }
+    }
+
+    #[test]
+    fn test_deeply_nested_array_fails_gracefully() {
+        let deep = nested_array(100_000);
+
+        assert_eq!(max_depth(&deep, u32::MAX), 100_000);
+        let err = try_build_shadow_tree(&deep, DEFAULT_MAX_DEPTH).expect_err("超深嵌套应返回错误");
+        assert!(err.to_string().contains("嵌套过深"));
+
+        let shallow = nested_array(50);
+        let tree = try_build_shadow_tree(&shallow, DEFAULT_MAX_DEPTH).expect("浅层嵌套应构建成功");
+        assert_eq!(tree.len(), 51);
+        assert_eq!(tree.last().unwrap().depth, 50);
+
+        dismantle(deep);
+    }
+
+    #[test]
+    fn test_build_shadow_tree_panics_beyond_hard_limit() {
+        let limit = nested_array(SHADOW_TREE_PANIC_DEPTH as usize);
+        assert_eq!(build_shadow_tree(&limit).len(), SHADOW_TREE_PANIC_DEPTH as usize + 1);
+        dismantle(limit);
+
+        let deep = nested_array(SHADOW_TREE_PANIC_DEPTH as usize + 1);
+        let result = std::panic::catch_unwind(|| build_shadow_tree(&deep));
+        let message = result.expect_err("超过硬性上限应 panic").downcast::<String>().unwrap();
+        assert!(message.contains("影子树嵌套深度超过 10000 层"));
+        dismantle(deep);
+    }
+
+    #[test]
+    fn test_iterative_walk_keeps_document_order() {
+        let json = json!({"a": [1, {"b": 2}], "c": 3});
+        let paths: Vec<String> = build_shadow_tree(&json).into_iter().map(|n| n.path).collect();
+        assert_eq!(paths, vec!["$", "$.a", "$.a[0]", "$.a[1]", "$.a[1].b", "$.c"]);
+    }
+
+    #[test]
+    fn test_compute_subtree_sizes_matches_serialization() {
+        let doc = json!({
+            "a\"b": ["x\ny", 1.50, -3, null, true, false, {}, []],
+            "中文": {"k": "\u{1}\t😀", "n": 12345678901234567890u64}
+        });
+        let tree = build_shadow_tree(&doc);
+        let mut reports = 0;
+        let sizes = compute_subtree_sizes(&doc, |_, _| reports += 1);
+        assert_eq!(sizes.len(), tree.len());
+        assert!(reports > 0);
+        for (node, size) in tree.iter().zip(&sizes) {
+            let actual = crate::model::path::query_first(&doc, &node.path).unwrap().unwrap().to_string().len() as u64;
+            // 估算与实际序列化长度的偏差不超过 1%
+            assert!(size.abs_diff(actual) <= actual / 100, "{}: 估算 {} 实际 {}", node.path, size, actual);
+        }
+    }
+}
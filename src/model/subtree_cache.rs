@@ -0,0 +1,128 @@
+//! 子树提取缓存：按路径缓存渲染结果，以子树内容哈希作为有效性校验
+
+use std::collections::VecDeque;
+use std::hash::Hasher;
+use std::io::Write;
+
+use seahash::SeaHasher;
+use serde_json::Value;
+
+/// 缓存的最大条目数
+pub const SUBTREE_CACHE_CAPACITY: usize = 32;
+
+/// 将序列化输出直接送入哈希器，避免为大子树分配完整字符串
+struct HashWriter(SeaHasher);
+
+impl Write for HashWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.write(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 子树紧凑序列化字节的 seahash 值
+pub fn subtree_hash(value: &Value) -> u64 {
+    let mut writer = HashWriter(SeaHasher::new());
+    serde_json::to_writer(&mut writer, value).expect("写入哈希器不会失败");
+    writer.0.finish()
+}
+
+/// 只计数不保存的写入器，用于求序列化长度
+struct CountingWriter(usize);
+
+impl Write for CountingWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// 子树紧凑序列化后的字节数（不分配完整字符串）
+pub fn compact_len(value: &Value) -> usize {
+    let mut writer = CountingWriter(0);
+    serde_json::to_writer(&mut writer, value).expect("写入计数器不会失败");
+    writer.0
+}
+
+struct CacheEntry {
+    key: String,
+    hash: u64,
+    rendered: String,
+}
+
+/// 最近最少使用（LRU）缓存：命中需键与子树哈希同时一致
+#[derive(Default)]
+pub struct SubtreeCache {
+    /// 队首为最近使用
+    entries: VecDeque<CacheEntry>,
+}
+
+impl std::fmt::Debug for SubtreeCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SubtreeCache").field("len", &self.entries.len()).finish()
+    }
+}
+
+impl SubtreeCache {
+    /// 查找缓存；哈希不一致（子树已修改）时移除旧条目并返回 None
+    pub fn get(&mut self, key: &str, hash: u64) -> Option<&str> {
+        let pos = self.entries.iter().position(|e| e.key == key)?;
+        let entry = self.entries.remove(pos)?;
+        if entry.hash != hash {
+            return None;
+        }
+        self.entries.push_front(entry);
+        self.entries.front().map(|e| e.rendered.as_str())
+    }
+
+    /// 写入缓存，超出容量时淘汰最久未使用的条目
+    pub fn insert(&mut self, key: String, hash: u64, rendered: String) {
+        self.entries.retain(|e| e.key != key);
+        self.entries.push_front(CacheEntry { key, hash, rendered });
+        self.entries.truncate(SUBTREE_CACHE_CAPACITY);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_cache_validates_hash_and_evicts_lru() {
+        let mut cache = SubtreeCache::default();
+        let before = subtree_hash(&json!({"a": [1, 2]}));
+        let after = subtree_hash(&json!({"a": [1, 3]}));
+        assert_ne!(before, after);
+
+        cache.insert("$.x".into(), before, "旧".into());
+        assert_eq!(cache.get("$.x", before), Some("旧"));
+        assert_eq!(cache.get("$.x", after), None, "哈希不一致视为失效");
+        assert!(cache.is_empty());
+
+        for i in 0..=SUBTREE_CACHE_CAPACITY {
+            cache.insert(format!("$[{}]", i), i as u64, i.to_string());
+            // 保持 $[0] 为最近使用
+            cache.get("$[0]", 0);
+        }
+        assert_eq!(cache.len(), SUBTREE_CACHE_CAPACITY);
+        assert_eq!(cache.get("$[0]", 0), Some("0"));
+        assert_eq!(cache.get("$[1]", 1), None, "最久未使用的条目被淘汰");
+    }
+}
@@ -0,0 +1,127 @@
+//! 可复现的测试数据：按形状描述与种子生成 JSON 文档（单元测试、性能基准与自检共用）
+
+use serde_json::{Map, Value};
+
+use crate::model::data_core::AppState;
+
+/// 字符串字段取值的词表（混合中英文，便于覆盖多字节字符）
+const WORDS: [&str; 8] = ["打开", "保存", "设置", "帮助", "Open", "Save", "Settings", "Help"];
+
+/// 文档形状
+///
+/// 每个对象含 `str_0..str_{n}` 字符串字段与可选的 `null`、`flag`、`count` 字段；
+/// 深度未到底的对象另含 `items`（同形状的扁平对象数组）与下一层的 `child` 对象
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestFixtureShape {
+    /// 嵌套层数（0 为单个扁平对象）
+    pub depth: usize,
+    /// 每个对象的字符串字段数
+    pub string_fields: usize,
+    /// 每层 `items` 数组的元素数
+    pub array_len: usize,
+    pub include_null: bool,
+    pub include_bool: bool,
+    pub include_number: bool,
+    /// 随机种子：相同形状与种子总是生成相同的文档
+    pub seed: u64,
+}
+
+impl Default for TestFixtureShape {
+    fn default() -> Self {
+        Self { depth: 2, string_fields: 2, array_len: 3, include_null: true, include_bool: true, include_number: true, seed: 0 }
+    }
+}
+
+impl TestFixtureShape {
+    /// 文档中的对象数（含根与 `items` 中的元素）
+    pub fn object_count(&self) -> usize {
+        self.depth + 1 + self.depth * self.array_len
+    }
+
+    /// 影子树节点数
+    pub fn node_count(&self) -> usize {
+        let scalars = [self.include_null, self.include_bool, self.include_number].iter().filter(|&&b| b).count();
+        let object = 1 + self.string_fields + scalars;
+        // 每个对象自身的节点，加上每层的 items 数组节点
+        self.object_count() * object + self.depth
+    }
+}
+
+/// SplitMix64：无需外部依赖的确定性伪随机数
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+fn flat_object(shape: &TestFixtureShape, rng: &mut SplitMix64) -> Map<String, Value> {
+    let mut obj = Map::new();
+    for i in 0..shape.string_fields {
+        let r = rng.next();
+        let word = WORDS[(r % WORDS.len() as u64) as usize];
+        obj.insert(format!("str_{}", i), Value::String(format!("{}_{:04x}", word, (r >> 32) & 0xffff)));
+    }
+    if shape.include_null {
+        obj.insert("null".to_string(), Value::Null);
+    }
+    if shape.include_bool {
+        obj.insert("flag".to_string(), Value::Bool(rng.next() & 1 == 1));
+    }
+    if shape.include_number {
+        obj.insert("count".to_string(), Value::from(rng.next() % 10_000));
+    }
+    obj
+}
+
+fn nested_object(shape: &TestFixtureShape, level: usize, rng: &mut SplitMix64) -> Value {
+    let mut obj = flat_object(shape, rng);
+    if level < shape.depth {
+        let items = (0..shape.array_len).map(|_| Value::Object(flat_object(shape, rng))).collect();
+        obj.insert("items".to_string(), Value::Array(items));
+        obj.insert("child".to_string(), nested_object(shape, level + 1, rng));
+    }
+    Value::Object(obj)
+}
+
+/// 按形状生成文档
+pub fn generate_test_fixture(shape: &TestFixtureShape) -> Value {
+    nested_object(shape, 0, &mut SplitMix64(shape.seed))
+}
+
+impl AppState {
+    /// 按形状生成测试文档，见 [`generate_test_fixture`]
+    pub fn generate_test_fixture(shape: &TestFixtureShape) -> Value {
+        generate_test_fixture(shape)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::shadow_tree::build_shadow_tree;
+
+    #[test]
+    fn test_generate_test_fixture() {
+        let shape = TestFixtureShape::default();
+        let doc = AppState::generate_test_fixture(&shape);
+        assert_eq!(doc, generate_test_fixture(&shape), "相同种子应生成相同文档");
+        assert_ne!(doc, generate_test_fixture(&TestFixtureShape { seed: 1, ..shape.clone() }));
+
+        assert_eq!(build_shadow_tree(&doc).len(), shape.node_count());
+        assert_eq!(doc["items"].as_array().unwrap().len(), 3);
+        assert!(doc["child"]["child"].is_object());
+        assert!(doc["child"]["child"].get("child").is_none());
+        assert!(doc["str_1"].is_string() && doc["null"].is_null() && doc["flag"].is_boolean() && doc["count"].is_number());
+
+        let flat = TestFixtureShape { depth: 0, string_fields: 1, include_null: false, include_bool: false, include_number: false, ..shape };
+        let doc = generate_test_fixture(&flat);
+        assert_eq!(doc.as_object().unwrap().keys().collect::<Vec<_>>(), vec!["str_0"]);
+        assert_eq!((flat.object_count(), flat.node_count()), (1, 2));
+    }
+}
@@ -0,0 +1,199 @@
+//! 字符串值变换：可串联的值级变换（修剪、大小写、正则替换、解码、截断）
+
+use regex::Regex;
+
+use crate::model::data_core::{AppError, StateKind};
+
+/// 单个值级变换
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueTransform {
+    /// 去除首尾空白
+    Trim,
+    ToLowercase,
+    ToUppercase,
+    /// 正则替换（`replacement` 支持 `$1`/`${name}` 引用）
+    RegexReplace { pattern: String, replacement: String },
+    /// URL 百分号解码（解码结果不是合法 UTF-8 时保持原值）
+    UrlDecode,
+    /// HTML 实体反转义（常用命名实体与数字实体）
+    HtmlUnescape,
+    /// Base64 解码（非法输入或非 UTF-8 结果时保持原值）
+    Base64Decode,
+    /// 截断到指定字符数
+    TruncateAt(usize),
+}
+
+/// 变换流水线的执行结果
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PipelineResult {
+    /// 每个变换实际改变的字符串节点数（与流水线顺序一致）
+    pub changes_per_transform: Vec<usize>,
+    /// 最终值发生变化的字符串节点数
+    pub nodes_changed: usize,
+}
+
+/// 预编译后的变换（正则只编译一次）
+pub(crate) enum CompiledTransform {
+    Regex(Regex, String),
+    Plain(ValueTransform),
+}
+
+impl CompiledTransform {
+    /// 编译流水线；无效正则直接报错，不做任何修改
+    pub(crate) fn compile(pipeline: Vec<ValueTransform>) -> Result<Vec<Self>, AppError> {
+        pipeline
+            .into_iter()
+            .map(|t| match t {
+                ValueTransform::RegexReplace { pattern, replacement } => Regex::new(&pattern)
+                    .map(|re| CompiledTransform::Regex(re, replacement))
+                    .map_err(|e| AppError::State(StateKind::Invalid, format!("无效的正则表达式 {}: {}", pattern, e))),
+                other => Ok(CompiledTransform::Plain(other)),
+            })
+            .collect()
+    }
+
+    /// 应用到字符串，未变化时返回 None
+    pub(crate) fn apply(&self, s: &str) -> Option<String> {
+        let out = match self {
+            CompiledTransform::Regex(re, replacement) => re.replace_all(s, replacement.as_str()).into_owned(),
+            CompiledTransform::Plain(t) => match t {
+                ValueTransform::Trim => s.trim().to_string(),
+                ValueTransform::ToLowercase => s.to_lowercase(),
+                ValueTransform::ToUppercase => s.to_uppercase(),
+                ValueTransform::UrlDecode => url_decode(s)?,
+                ValueTransform::HtmlUnescape => html_unescape(s),
+                ValueTransform::Base64Decode => base64_decode(s)?,
+                ValueTransform::TruncateAt(n) => s.chars().take(*n).collect(),
+                ValueTransform::RegexReplace { .. } => unreachable!("正则变换已预编译"),
+            },
+        };
+        (out != s).then_some(out)
+    }
+}
+
+fn hex_val(b: u8) -> Option<u8> {
+    (b as char).to_digit(16).map(|d| d as u8)
+}
+
+/// 百分号解码；存在非法转义或结果不是 UTF-8 时返回 None
+fn url_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hi = hex_val(*bytes.get(i + 1)?)?;
+            let lo = hex_val(*bytes.get(i + 2)?)?;
+            out.push(hi << 4 | lo);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+/// HTML 实体反转义；无法识别的实体原样保留
+fn html_unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        // 实体最长按 10 个字节查找分号
+        let decoded = rest[1..]
+            .char_indices()
+            .take(10)
+            .find(|&(_, c)| c == ';')
+            .and_then(|(semi, _)| {
+                let entity = &rest[1..1 + semi];
+                let c = match entity {
+                    "amp" => Some('&'),
+                    "lt" => Some('<'),
+                    "gt" => Some('>'),
+                    "quot" => Some('"'),
+                    "apos" => Some('\''),
+                    "nbsp" => Some('\u{a0}'),
+                    _ => entity
+                        .strip_prefix("#x")
+                        .or_else(|| entity.strip_prefix("#X"))
+                        .map(|hex| u32::from_str_radix(hex, 16))
+                        .or_else(|| entity.strip_prefix('#').map(|dec| dec.parse::<u32>()))
+                        .and_then(|code| code.ok())
+                        .and_then(char::from_u32),
+                };
+                c.map(|c| (c, semi + 2))
+            });
+        match decoded {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// 标准 Base64 解码（允许省略填充）；非法输入或非 UTF-8 结果返回 None
+fn base64_decode(s: &str) -> Option<String> {
+    fn sextet(c: u8) -> Option<u32> {
+        match c {
+            b'A'..=b'Z' => Some((c - b'A') as u32),
+            b'a'..=b'z' => Some((c - b'a' + 26) as u32),
+            b'0'..=b'9' => Some((c - b'0' + 52) as u32),
+            b'+' => Some(62),
+            b'/' => Some(63),
+            _ => None,
+        }
+    }
+
+    let data = s.trim().trim_end_matches('=').as_bytes();
+    if data.is_empty() || data.len() % 4 == 1 {
+        return None;
+    }
+    let mut out = Vec::with_capacity(data.len() * 3 / 4);
+    for chunk in data.chunks(4) {
+        let mut acc = 0u32;
+        for (i, &c) in chunk.iter().enumerate() {
+            acc |= sextet(c)? << (18 - 6 * i);
+        }
+        let bytes = acc.to_be_bytes();
+        out.extend_from_slice(&bytes[1..chunk.len()]);
+    }
+    String::from_utf8(out).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(t: ValueTransform, s: &str) -> String {
+        let compiled = CompiledTransform::compile(vec![t]).unwrap();
+        compiled[0].apply(s).unwrap_or_else(|| s.to_string())
+    }
+
+    #[test]
+    fn test_decoders() {
+        assert_eq!(run(ValueTransform::UrlDecode, "a%20b%E4%B8%AD"), "a b中");
+        assert_eq!(run(ValueTransform::UrlDecode, "100%"), "100%", "非法转义保持原值");
+        assert_eq!(run(ValueTransform::HtmlUnescape, "&lt;b&gt; &amp;&#20013;&#x6587; &unknown; &"), "<b> &中文 &unknown; &");
+        assert_eq!(run(ValueTransform::Base64Decode, "5Lit5paH"), "中文");
+        assert_eq!(run(ValueTransform::Base64Decode, "aGk="), "hi");
+        assert_eq!(run(ValueTransform::Base64Decode, "not base64!"), "not base64!");
+        assert_eq!(run(ValueTransform::TruncateAt(2), "中文字符"), "中文");
+    }
+
+    #[test]
+    fn test_regex_compile_error() {
+        let bad = ValueTransform::RegexReplace { pattern: "(".into(), replacement: "".into() };
+        assert!(CompiledTransform::compile(vec![bad]).is_err());
+        let ok = ValueTransform::RegexReplace { pattern: r"(\d+)".into(), replacement: "<$1>".into() };
+        assert_eq!(run(ok, "第12项"), "第<12>项");
+    }
+}
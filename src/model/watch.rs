@@ -0,0 +1,129 @@
+//! 值监听：库模式下按路径注册回调，编辑触及该路径（自身、祖先或后代）后以最新值调用
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::model::path::{format_segments, parse_segments, query_first, PathSegment};
+
+/// 监听句柄，用于取消监听
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WatcherId(u64);
+
+/// 监听回调：参数为路径上的最新值，节点已不存在时为 `null`
+pub type WatchCallback = Box<dyn Fn(&Value) + Send + Sync>;
+
+/// 一次编辑触及的范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchChange<'a> {
+    /// 该路径自身被改写：触及其祖先与后代
+    Path(&'a str),
+    /// 数组中下标 `>= from` 的元素因插入、删除或移动而换位：触及数组自身、其祖先与这些元素的后代
+    Shifted(&'a str, usize),
+}
+
+/// 一个被监听的路径：具体路径按段保存，通配符等非具体路径为 None（任何编辑都视为触及）
+struct WatchedPath {
+    segments: Option<Vec<PathSegment>>,
+    callbacks: Vec<(WatcherId, WatchCallback)>,
+}
+
+/// 按路径登记的监听回调；键为规范化后的路径，`$['a']` 与 `$.a` 视为同一路径
+#[derive(Default)]
+pub struct ValueWatchers {
+    next_id: u64,
+    by_path: HashMap<String, WatchedPath>,
+}
+
+impl std::fmt::Debug for ValueWatchers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ValueWatchers").field("paths", &self.by_path.keys().collect::<Vec<_>>()).finish()
+    }
+}
+
+/// 监听路径 `watched` 是否被 `change` 触及；任一方不是具体路径时按触及处理
+fn is_touched(watched: Option<&[PathSegment]>, change: WatchChange) -> bool {
+    let (path, shifted_from) = match change {
+        WatchChange::Path(path) => (path, None),
+        WatchChange::Shifted(path, from) => (path, Some(from)),
+    };
+    let (Some(watched), Some(changed)) = (watched, parse_segments(path)) else {
+        return true;
+    };
+    if changed.starts_with(watched) {
+        return true;
+    }
+    match shifted_from {
+        None => watched.starts_with(&changed),
+        Some(from) => watched.starts_with(&changed) && matches!(watched.get(changed.len()), Some(PathSegment::Index(i)) if *i >= from),
+    }
+}
+
+impl ValueWatchers {
+    pub fn watch(&mut self, json_path: String, callback: WatchCallback) -> WatcherId {
+        self.next_id += 1;
+        let id = WatcherId(self.next_id);
+        let segments = parse_segments(&json_path);
+        let key = segments.as_deref().map_or(json_path, format_segments);
+        self.by_path
+            .entry(key)
+            .or_insert_with(|| WatchedPath { segments, callbacks: Vec::new() })
+            .callbacks
+            .push((id, callback));
+        id
+    }
+
+    /// 取消监听；句柄不存在时返回 false
+    pub fn unwatch(&mut self, id: WatcherId) -> bool {
+        let mut removed = false;
+        self.by_path.retain(|_, watched| {
+            let before = watched.callbacks.len();
+            watched.callbacks.retain(|(watcher, _)| *watcher != id);
+            removed |= watched.callbacks.len() != before;
+            !watched.callbacks.is_empty()
+        });
+        removed
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_path.is_empty()
+    }
+
+    /// 编辑完成后，调用所有被 `changes` 触及路径上的回调（每个回调至多一次）
+    pub fn notify(&self, dom: Option<&Value>, changes: &[WatchChange]) {
+        for (path, watched) in &self.by_path {
+            if !changes.iter().any(|&change| is_touched(watched.segments.as_deref(), change)) {
+                continue;
+            }
+            let value = dom.and_then(|dom| query_first(dom, path).ok().flatten()).unwrap_or(&Value::Null);
+            for (_, callback) in &watched.callbacks {
+                callback(value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_touched() {
+        let touched = |watched: &str, change| is_touched(parse_segments(watched).as_deref(), change);
+        assert!(touched("$.a.b", WatchChange::Path("$.a")));
+        assert!(touched("$.a[0]", WatchChange::Path("$.a")));
+        assert!(touched("$.a", WatchChange::Path("$.a")));
+        assert!(touched("$.a", WatchChange::Path("$")));
+        assert!(touched("$.a", WatchChange::Path("$.a.b")));
+        assert!(!touched("$.ab", WatchChange::Path("$.a")));
+        // 按段比较：引号写法与点写法等价，键中的 `.` 不会被当作分隔符
+        assert!(touched("$['a'].b", WatchChange::Path("$.a")));
+        assert!(!touched("$['a.b']", WatchChange::Path("$.a")));
+
+        assert!(touched("$.a[3].x", WatchChange::Shifted("$.a", 1)));
+        assert!(!touched("$.a[0].x", WatchChange::Shifted("$.a", 1)));
+        assert!(touched("$", WatchChange::Shifted("$.a", 1)));
+        assert!(!touched("$.b[3]", WatchChange::Shifted("$.a", 1)));
+        assert!(touched("$..x", WatchChange::Path("$.b")));
+    }
+}
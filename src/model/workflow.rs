@@ -0,0 +1,449 @@
+//! 非界面工作流：中间产物2转换为最终产物、按译文生成并执行回写计划
+
+use serde_json::Value;
+
+use crate::model::anchor::{reanchor, Reanchor, ANCHOR_FIELD_KEY};
+use crate::model::data_core::{AppError, AppState, JsonPathKind, StateKind};
+use crate::model::fingerprint::{fingerprint_value, has_source_conflict, plan_writeback, WritebackMode, FINGERPRINT_KEY};
+use crate::model::path::{insert_creating, parse_segments, resolve};
+use crate::utils::jsonfmt::{to_string_wrapped, DEFAULT_MAX_INLINE_WIDTH};
+use crate::utils::text::{sanitize, scan_suspicious};
+
+/// 最终产物的结构
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FinalShape {
+    /// `{"seq": "原文"}`
+    #[default]
+    SeqMap,
+    /// `{"$.源路径": "原文"}`
+    PathMap,
+    /// 只含提取叶子的最小 JSON 树，叶子位于原路径；数组中未提取的下标填 `null`
+    NestedTree,
+}
+
+impl FinalShape {
+    /// 所有结构（界面循环切换顺序）
+    pub const ALL: [FinalShape; 3] = [FinalShape::SeqMap, FinalShape::PathMap, FinalShape::NestedTree];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            FinalShape::SeqMap => "按序号",
+            FinalShape::PathMap => "按路径",
+            FinalShape::NestedTree => "原结构",
+        }
+    }
+}
+
+/// 最终产物的生成选项
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FinalOptions {
+    /// 输出 JSON 的单行最大宽度
+    pub max_inline_width: usize,
+    pub shape: FinalShape,
+}
+
+impl Default for FinalOptions {
+    fn default() -> Self {
+        Self { max_inline_width: DEFAULT_MAX_INLINE_WIDTH, shape: FinalShape::default() }
+    }
+}
+
+/// 将中间产物2转换为最终产物（默认 `{"seq": "原文"}`，结构见 [`FinalShape`]）
+pub fn build_final_product(stage2_json: &str, options: FinalOptions) -> Result<String, AppError> {
+    build_final_product_with_progress(stage2_json, options, |_, _| {})
+}
+
+/// 同 [`build_final_product`]，每处理一个条目回调一次 `(已完成比例, 描述)`
+pub fn build_final_product_with_progress<F>(stage2_json: &str, options: FinalOptions, mut progress: F) -> Result<String, AppError>
+where
+    F: FnMut(f32, &str),
+{
+    let stage2: Value = serde_json::from_str(stage2_json)?;
+    let mut out = match options.shape {
+        FinalShape::SeqMap | FinalShape::PathMap => Value::Object(serde_json::Map::new()),
+        FinalShape::NestedTree => Value::Null,
+    };
+    if let Some(items) = stage2.get("items").and_then(|x| x.as_array()) {
+        let total = items.len();
+        for (index, item) in items.iter().enumerate() {
+            progress((index + 1) as f32 / total as f32, &format!("处理项目 {}/{}", index + 1, total));
+            // 未送译的引用条目不进入最终产物
+            if is_excluded(item) {
+                continue;
+            }
+            let seq = item.get("seq").and_then(|s| s.as_u64()).unwrap_or(0);
+            let name = Value::String(item.get("name").and_then(|n| n.as_str()).unwrap_or("").to_string());
+            let source_path = item.get("source_path").and_then(Value::as_str).unwrap_or_default();
+            match options.shape {
+                FinalShape::SeqMap => out[seq.to_string()] = name,
+                FinalShape::PathMap => out[source_path] = name,
+                FinalShape::NestedTree => {
+                    let segments = parse_segments(source_path)
+                        .ok_or_else(|| AppError::JsonPath(JsonPathKind::NoMatch, format!("序号 {} 的源路径无法定位: {}", seq, source_path)))?;
+                    if !insert_creating(&mut out, &segments, name) {
+                        return Err(AppError::State(StateKind::Conflict, format!("序号 {} 的源路径与其他条目冲突: {}", seq, source_path)));
+                    }
+                }
+            }
+        }
+    }
+    if out.is_null() {
+        out = Value::Object(serde_json::Map::new());
+    }
+    Ok(to_string_wrapped(&out, options.max_inline_width))
+}
+
+/// 识别译文文件的结构：键全为 `$` 开头的路径时为 `PathMap`；值均为标量且至少一个键是序号时为 `SeqMap`
+/// （无效序号照常在回写时跳过）；其余为 `NestedTree`
+///
+/// 根为对象、键为数字、值为标量的源文档导出的 `NestedTree` 与 `SeqMap` 无法区分，按 `SeqMap` 处理
+pub fn detect_final_shape(writeback: &Value) -> FinalShape {
+    let Some(map) = writeback.as_object() else {
+        return FinalShape::NestedTree;
+    };
+    if !map.is_empty() && map.keys().all(|key| key.starts_with('$')) {
+        FinalShape::PathMap
+    } else if map.is_empty() || (map.values().all(|v| !v.is_object() && !v.is_array()) && map.keys().any(|k| k.parse::<usize>().is_ok())) {
+        FinalShape::SeqMap
+    } else {
+        FinalShape::NestedTree
+    }
+}
+
+/// 将任一结构的译文文件按中间产物2的源路径转换为 `{"seq": "译文"}`；找不到对应条目的路径被忽略
+pub fn normalize_writeback(writeback: &Value, stage2: &Value) -> Result<Value, AppError> {
+    let shape = detect_final_shape(writeback);
+    if shape == FinalShape::SeqMap {
+        return Ok(writeback.clone());
+    }
+    if !writeback.is_object() && !writeback.is_array() {
+        return Err(AppError::State(StateKind::Malformed, "回写文件必须是JSON对象".into()));
+    }
+    let items = stage2
+        .get("items")
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| AppError::State(StateKind::Malformed, "中间产物2格式错误：缺少items数组".into()))?;
+    let mut out = serde_json::Map::new();
+    for item in items {
+        let (Some(seq), Some(source_path)) = (item.get("seq").and_then(Value::as_u64), item.get("source_path").and_then(Value::as_str)) else {
+            continue;
+        };
+        let value = match shape {
+            FinalShape::PathMap => writeback.get(source_path),
+            _ => parse_segments(source_path).and_then(|segments| resolve(writeback, &segments)),
+        };
+        if let Some(value) = value.filter(|v| !v.is_null()) {
+            out.insert(seq.to_string(), value.clone());
+        }
+    }
+    // 只有根为数组的源文档才会导出数组形式的 NestedTree
+    if writeback.is_array() && out.is_empty() {
+        return Err(AppError::State(StateKind::Malformed, "回写文件必须是JSON对象，或与根为数组的源文档结构一致".into()));
+    }
+    Ok(Value::Object(out))
+}
+
+/// 中间产物2条目是否为未送译的引用条目（超长二进制编码值）
+fn is_excluded(item: &Value) -> bool {
+    item.get("excluded").and_then(Value::as_bool).unwrap_or(false)
+}
+
+/// 回写选项
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct WritebackOptions {
+    /// 删除译文中的不可见字符并规范化为 NFC
+    pub normalize_text: bool,
+    /// 文档指纹不一致时仍按序号直接回写
+    pub force_on_mismatch: bool,
+}
+
+/// 一条待执行的回写
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedUpdate {
+    pub seq: usize,
+    pub source_path: String,
+    pub value: String,
+}
+
+/// 生成回写计划时需要告知用户的情况
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WritebackNote {
+    /// 源路径上的原文已变化，条目被跳过
+    SourceChanged { seq: usize, source_path: String },
+    /// 译文含不可见字符或非 NFC 序列
+    Suspicious { seq: usize, details: Vec<String>, cleaned: bool },
+    /// 条目是未送译的超长值引用，拒绝回写
+    Excluded { seq: usize, source_path: String },
+    /// 数组元素已移动，按锚点改写源路径
+    Reanchored { seq: usize, from: String, to: String },
+    /// 按锚点找不到所在数组元素（已删除或锚点值重复），条目被跳过
+    Unanchored { seq: usize, source_path: String },
+}
+
+impl std::fmt::Display for WritebackNote {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WritebackNote::SourceChanged { seq, source_path } => write!(f, "序号 {} 原文已变化，跳过: {}", seq, source_path),
+            WritebackNote::Suspicious { seq, details, cleaned } => {
+                let action = if *cleaned { "已清理" } else { "未处理" };
+                write!(f, "序号 {} 含可疑字符（{}）: {}", seq, action, details.join(", "))
+            }
+            WritebackNote::Excluded { seq, source_path } => write!(f, "序号 {} 是未送译的超长值，拒绝回写: {}", seq, source_path),
+            WritebackNote::Reanchored { seq, from, to } => write!(f, "序号 {} 按锚点重新定位: {} → {}", seq, from, to),
+            WritebackNote::Unanchored { seq, source_path } => write!(f, "序号 {} 按锚点无法定位，跳过: {}", seq, source_path),
+        }
+    }
+}
+
+/// 回写计划：校验译文文件与中间产物2后得到的待更新条目（不修改文档）
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct WritebackPlan {
+    pub updates: Vec<PlannedUpdate>,
+    /// 被跳过的条目数（无效序号、空译文、非标量值、原文已变化等）
+    pub skipped: usize,
+    /// 其中因原文已变化而跳过的条目数
+    pub conflicts: usize,
+    /// 按锚点改写了源路径的条目数
+    pub reanchored: usize,
+    /// 其中按锚点无法定位而跳过的条目数
+    pub unanchored: usize,
+    pub notes: Vec<WritebackNote>,
+    /// 文档指纹校验的警告
+    pub fingerprint_warning: Option<String>,
+}
+
+impl WritebackPlan {
+    /// 由译文、中间产物2与目标文档生成回写计划；译文可以是 [`FinalShape`] 中的任一结构
+    pub fn build(writeback: &Value, stage2: &Value, dom: &Value, options: WritebackOptions) -> Result<Self, AppError> {
+        let writeback = normalize_writeback(writeback, stage2)?;
+        let entries = writeback
+            .as_object()
+            .ok_or_else(|| AppError::State(StateKind::Malformed, "回写文件必须是JSON对象".into()))?;
+        let items = stage2
+            .get("items")
+            .and_then(|v| v.as_array())
+            .ok_or_else(|| AppError::State(StateKind::Malformed, "中间产物2格式错误：缺少items数组".into()))?;
+
+        // 校验文档指纹：中间产物来自其他版本的文件时降级为逐项校验原文
+        let fingerprint = fingerprint_value(dom);
+        let fingerprint_plan = plan_writeback(stage2, &fingerprint, options.force_on_mismatch);
+        let check_paths = fingerprint_plan.mode == WritebackMode::PathChecked;
+        // 文档已变化且提取时记录了锚点：数组元素可能增删或重排，先按锚点重新定位
+        let anchor_field = stage2
+            .get(ANCHOR_FIELD_KEY)
+            .and_then(Value::as_str)
+            .filter(|_| stage2.get(FINGERPRINT_KEY).and_then(Value::as_str) != Some(fingerprint.as_str()));
+        let mut plan = WritebackPlan { fingerprint_warning: fingerprint_plan.warning, ..Default::default() };
+
+        for (key, new_value) in entries {
+            let Ok(seq) = key.parse::<usize>() else {
+                tracing::warn!("跳过无效序号: {}", key);
+                plan.skipped += 1;
+                continue;
+            };
+            let Some(item) = items.get(seq) else {
+                plan.skipped += 1;
+                continue;
+            };
+            let Some(mut source_path) = item.get("source_path").and_then(|v| v.as_str()).map(str::to_string) else {
+                plan.skipped += 1;
+                continue;
+            };
+
+            if is_excluded(item) {
+                plan.notes.push(WritebackNote::Excluded { seq, source_path });
+                plan.skipped += 1;
+                continue;
+            }
+
+            if let (Some(field), Some(anchor)) = (anchor_field, item.get("anchor")) {
+                match reanchor(dom, &source_path, anchor, field) {
+                    Reanchor::Unchanged => {}
+                    Reanchor::Moved(to) => {
+                        plan.notes.push(WritebackNote::Reanchored { seq, from: std::mem::replace(&mut source_path, to.clone()), to });
+                        plan.reanchored += 1;
+                    }
+                    Reanchor::Unresolved => {
+                        plan.notes.push(WritebackNote::Unanchored { seq, source_path });
+                        plan.unanchored += 1;
+                        plan.skipped += 1;
+                        continue;
+                    }
+                }
+            }
+
+            // 逐项校验：源路径上的原文已变化则跳过
+            let extracted_text = item.get("name").and_then(|v| v.as_str()).unwrap_or_default();
+            if check_paths && has_source_conflict(dom, &source_path, extracted_text) {
+                plan.notes.push(WritebackNote::SourceChanged { seq, source_path });
+                plan.conflicts += 1;
+                plan.skipped += 1;
+                continue;
+            }
+
+            let value = match new_value {
+                Value::String(s) if s.trim().is_empty() => None,
+                Value::String(s) => {
+                    // 标记不可见字符/非NFC序列，按需自动清理
+                    let suspicious = scan_suspicious(s);
+                    if !suspicious.is_empty() {
+                        let details = suspicious.iter().map(|c| c.describe()).collect();
+                        plan.notes.push(WritebackNote::Suspicious { seq, details, cleaned: options.normalize_text });
+                    }
+                    Some(if options.normalize_text && !suspicious.is_empty() { sanitize(s) } else { s.clone() })
+                }
+                Value::Bool(b) => Some(b.to_string()),
+                Value::Number(n) => Some(n.to_string()),
+                Value::Null | Value::Object(_) | Value::Array(_) => None,
+            };
+            match value {
+                Some(value) => plan.updates.push(PlannedUpdate { seq, source_path, value }),
+                None => plan.skipped += 1,
+            }
+        }
+        Ok(plan)
+    }
+
+    /// 执行回写（值一律写为字符串），每处理一条回调 `(已处理, 总数)`；返回成功更新的条目数
+    pub fn apply(&self, dom: &mut Value, mut progress: impl FnMut(usize, usize)) -> usize {
+        let mut modified = 0;
+        for (done, update) in self.updates.iter().enumerate() {
+            progress(done, self.updates.len());
+            match AppState::first_match_mut(dom, &update.source_path) {
+                Ok(slot) => {
+                    *slot = Value::String(update.value.clone());
+                    modified += 1;
+                }
+                Err(e) => tracing::warn!("序号 {} 回写失败: {}", update.seq, e),
+            }
+        }
+        modified
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_build_final_product() {
+        let stage2 = r#"{"items": [{"seq": 0, "name": "Open"}, {"seq": 1, "name": "Close"}]}"#;
+        let mut calls = Vec::new();
+        let text = build_final_product_with_progress(stage2, FinalOptions::default(), |p, _| calls.push(p)).unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&text).unwrap(), json!({"0": "Open", "1": "Close"}));
+        assert_eq!(calls, vec![0.5, 1.0]);
+        assert!(build_final_product("{", FinalOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_final_shapes_round_trip() {
+        let stage2 = json!({"items": [
+            {"seq": 0, "name": "Open", "source_path": "$.menu[0].label"},
+            {"seq": 1, "name": "Quit", "source_path": "$.menu[3].label"},
+            {"seq": 2, "name": "Title", "source_path": "$['app name']"},
+            {"seq": 3, "name": "data:…", "source_path": "$.icon", "excluded": true}
+        ]});
+        let build = |shape| {
+            let text = build_final_product(&stage2.to_string(), FinalOptions { shape, ..Default::default() }).unwrap();
+            serde_json::from_str::<Value>(&text).unwrap()
+        };
+        assert_eq!(build(FinalShape::PathMap), json!({"$.menu[0].label": "Open", "$.menu[3].label": "Quit", "$['app name']": "Title"}));
+        // 稀疏数组：未提取的下标填 null
+        let nested = build(FinalShape::NestedTree);
+        assert_eq!(nested, json!({"menu": [{"label": "Open"}, null, null, {"label": "Quit"}], "app name": "Title"}));
+
+        let dom = json!({"menu": [{"label": "Open"}, {"label": "Save"}, {"label": "Close"}, {"label": "Quit"}], "app name": "Title", "icon": "data:…"});
+        for shape in [FinalShape::SeqMap, FinalShape::PathMap, FinalShape::NestedTree] {
+            let product = build(shape);
+            assert_eq!(detect_final_shape(&product), shape);
+            let plan = WritebackPlan::build(&product, &stage2, &dom, WritebackOptions::default()).unwrap();
+            assert_eq!(plan.updates.len(), 3, "{:?}", shape);
+        }
+
+        let translated = json!({"menu": [{"label": "打开"}, null, null, {"label": "退出"}]});
+        assert_eq!(normalize_writeback(&translated, &stage2).unwrap(), json!({"0": "打开", "1": "退出"}));
+        let conflicting = json!({"items": [
+            {"seq": 0, "name": "a", "source_path": "$.a"},
+            {"seq": 1, "name": "b", "source_path": "$.a.b"}
+        ]});
+        let options = FinalOptions { shape: FinalShape::NestedTree, ..Default::default() };
+        assert!(build_final_product(&conflicting.to_string(), options).is_err());
+    }
+
+    #[test]
+    fn test_writeback_plan_skips_and_applies() {
+        let mut dom = json!({"a": {"title": "Open"}, "b": {"title": "Changed"}, "n": 1});
+        // 无指纹：逐项校验原文
+        let stage2 = json!({"items": [
+            {"seq": 0, "name": "Open", "source_path": "$.a.title"},
+            {"seq": 1, "name": "Close", "source_path": "$.b.title"},
+            {"seq": 2, "name": "1", "source_path": "$.n"},
+            {"seq": 3, "name": "x", "source_path": "$.missing"},
+        ]});
+        let writeback = json!({"0": "打开\u{200b}", "1": "关闭", "2": 5, "3": "无", "9": "越界", "x": "无效", "0 ": null});
+
+        let options = WritebackOptions { normalize_text: true, force_on_mismatch: false };
+        let plan = WritebackPlan::build(&writeback, &stage2, &dom, options).unwrap();
+        assert!(plan.fingerprint_warning.is_some());
+        assert_eq!(plan.conflicts, 2, "原文已变化与路径不存在都视为冲突");
+        assert_eq!(plan.skipped, 5);
+        assert_eq!(plan.updates.iter().map(|u| u.seq).collect::<Vec<_>>(), vec![0, 2]);
+        assert_eq!(plan.updates[0].value, "打开");
+        assert!(plan.notes.iter().any(|n| n.to_string().contains("已清理")));
+
+        let mut seen = Vec::new();
+        assert_eq!(plan.apply(&mut dom, |done, total| seen.push((done, total))), 2);
+        assert_eq!(seen, vec![(0, 2), (1, 2)]);
+        assert_eq!(dom, json!({"a": {"title": "打开"}, "b": {"title": "Changed"}, "n": "5"}));
+
+        // 指纹不一致时强制回写：不再逐项校验，路径不存在的条目在执行时失败
+        let mut stage2 = stage2;
+        stage2["fingerprint"] = json!("xxh3:0000000000000000");
+        let options = WritebackOptions { normalize_text: false, force_on_mismatch: true };
+        let plan = WritebackPlan::build(&writeback, &stage2, &dom, options).unwrap();
+        assert_eq!(plan.conflicts, 0);
+        assert_eq!(plan.updates.len(), 4);
+        assert_eq!(plan.apply(&mut dom, |_, _| {}), 3);
+        assert_eq!(dom["b"]["title"], "关闭");
+
+        assert!(WritebackPlan::build(&json!([]), &stage2, &dom, options).is_err());
+    }
+
+    #[test]
+    fn test_writeback_reanchors_array_elements() {
+        use crate::model::data_core::Stage2Options;
+
+        let original = json!({"items": [{"id": "a", "title": "A"}, {"id": "b", "title": "B"}, {"id": "c", "title": "C"}]});
+        let mut state = AppState::default();
+        state.replace_dom(original).unwrap();
+        let options = Stage2Options { leaf_nodes_only: true, anchor_field: Some("id".into()), ..Default::default() };
+        let stage2: Value = serde_json::from_str(&state.build_intermediate_stage2_with_options("title", options, |_, _| {}).unwrap()).unwrap();
+        assert_eq!(stage2["anchor_field"], "id");
+        assert_eq!(stage2["items"][1]["anchor"], json!([{"depth": 1, "value": "b"}]));
+        let writeback = json!({"0": "甲", "1": "乙", "2": "丙"});
+
+        let run = |mut dom: Value| -> (WritebackPlan, Value) {
+            let plan = WritebackPlan::build(&writeback, &stage2, &dom, WritebackOptions::default()).unwrap();
+            plan.apply(&mut dom, |_, _| {});
+            (plan, dom)
+        };
+
+        // 插入：b、c 后移
+        let (plan, dom) = run(json!({"items": [{"id": "a", "title": "A"}, {"id": "new", "title": "N"}, {"id": "b", "title": "B"}, {"id": "c", "title": "C"}]}));
+        assert_eq!((plan.reanchored, plan.unanchored, plan.conflicts), (2, 0, 0));
+        assert_eq!(dom["items"], json!([{"id": "a", "title": "甲"}, {"id": "new", "title": "N"}, {"id": "b", "title": "乙"}, {"id": "c", "title": "丙"}]));
+        assert!(plan.notes.iter().any(|n| n.to_string().contains("$.items[1].title → $.items[2].title")));
+
+        // 删除：a 无法定位，其余前移
+        let (plan, dom) = run(json!({"items": [{"id": "b", "title": "B"}, {"id": "c", "title": "C"}]}));
+        assert_eq!((plan.reanchored, plan.unanchored, plan.skipped), (2, 1, 1));
+        assert!(matches!(&plan.notes[0], WritebackNote::Unanchored { seq: 0, .. }));
+        assert_eq!(dom["items"], json!([{"id": "b", "title": "乙"}, {"id": "c", "title": "丙"}]));
+
+        // 重排：逐项校验原文后写到各自的元素
+        let (plan, dom) = run(json!({"items": [{"id": "c", "title": "C"}, {"id": "a", "title": "A"}, {"id": "b", "title": "B"}]}));
+        assert_eq!((plan.reanchored, plan.updates.len()), (3, 3));
+        assert_eq!(dom["items"], json!([{"id": "c", "title": "丙"}, {"id": "a", "title": "甲"}, {"id": "b", "title": "乙"}]));
+    }
+}
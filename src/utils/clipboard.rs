@@ -1,26 +1,119 @@
-//! Clipboard  cross-platform clipboard helpers
+//! Clipboard  cross-platform clipboard helpers
 
+use std::time::Duration;
+
+use copypasta::ClipboardProvider;
 use thiserror::Error;
 
+/// 复制失败后的重试间隔（指数退避）
+pub const CLIPBOARD_RETRY_DELAYS: [Duration; 3] =
+    [Duration::from_millis(50), Duration::from_millis(100), Duration::from_millis(200)];
+
+/// 剪贴板后端（copypasta）操作的返回类型
+type ProviderResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// 平台相关的排查提示，附加在超时错误后
+#[cfg(target_os = "macos")]
+const PLATFORM_HINT: &str = "（macOS：应用未获得焦点时无法创建剪贴板上下文，请切回本窗口后重试）";
+#[cfg(all(unix, not(target_os = "macos")))]
+const PLATFORM_HINT: &str = "（X11：请确认 X 服务可用，必要时启动剪贴板管理器）";
+#[cfg(not(unix))]
+const PLATFORM_HINT: &str = "";
+
 #[derive(Error, Debug)]
 pub enum ClipboardError {
     #[error("clipboard error: {0}")]
     Clip(String),
+    /// 重试全部失败
+    #[error("剪贴板操作超时：共尝试 {attempts} 次，最后一次错误：{last_error}{hint}", hint = PLATFORM_HINT)]
+    Timeout { attempts: usize, last_error: String },
 }
 
-/// 将文本复制到系统剪贴板
+/// 将文本复制到系统剪贴板；失败时按 [`CLIPBOARD_RETRY_DELAYS`] 退避重试
+///
+/// 重试期间阻塞当前线程，界面中应使用 [`copy_to_clipboard_scheduled`]
 pub fn copy_to_clipboard(text: &str) -> Result<(), ClipboardError> {
-    use copypasta::{ClipboardContext, ClipboardProvider};
-    let mut ctx = ClipboardContext::new().map_err(|e| ClipboardError::Clip(e.to_string()))?;
-    ctx.set_contents(text.to_string())
-        .map_err(|e| ClipboardError::Clip(e.to_string()))
+    copy_with_retry(text, copypasta::ClipboardContext::new, std::thread::sleep)
+}
+
+/// 不阻塞的复制：失败时由 `schedule(延迟, 重试)` 安排下一次尝试（界面中传入 `slint::Timer`），结束时以结果调用 `done`
+pub fn copy_to_clipboard_scheduled<S>(text: &str, schedule: S, done: impl FnOnce(Result<(), ClipboardError>) + 'static)
+where
+    S: Fn(Duration, Box<dyn FnOnce()>) + Clone + 'static,
+{
+    drive_copy(CopyAttempts::new(text), copypasta::ClipboardContext::new, schedule, Box::new(done));
+}
+
+/// 一次复制尝试之后的下一步
+#[derive(Debug)]
+enum CopyStep {
+    Done(Result<(), ClipboardError>),
+    RetryAfter(Duration),
+}
+
+/// 复制的重试状态：已尝试次数与最后一次错误
+#[derive(Debug)]
+struct CopyAttempts {
+    text: String,
+    attempts: usize,
+}
+
+impl CopyAttempts {
+    fn new(text: &str) -> Self {
+        Self { text: text.to_string(), attempts: 0 }
+    }
+
+    /// 尝试一次；每次都重新创建剪贴板上下文（上下文创建本身也可能瞬时失败）
+    fn try_once<P: ClipboardProvider>(&mut self, connect: impl FnOnce() -> ProviderResult<P>) -> CopyStep {
+        let last_error = match connect().and_then(|mut ctx| ctx.set_contents(self.text.clone())) {
+            Ok(()) => return CopyStep::Done(Ok(())),
+            Err(e) => e.to_string(),
+        };
+        match CLIPBOARD_RETRY_DELAYS.get(self.attempts) {
+            Some(&delay) => {
+                self.attempts += 1;
+                tracing::warn!("剪贴板操作失败，{}ms 后重试: {}", delay.as_millis(), last_error);
+                CopyStep::RetryAfter(delay)
+            }
+            None => CopyStep::Done(Err(ClipboardError::Timeout { attempts: self.attempts + 1, last_error })),
+        }
+    }
+}
+
+fn copy_with_retry<P, C, S>(text: &str, mut connect: C, mut sleep: S) -> Result<(), ClipboardError>
+where
+    P: ClipboardProvider,
+    C: FnMut() -> ProviderResult<P>,
+    S: FnMut(Duration),
+{
+    let mut attempts = CopyAttempts::new(text);
+    loop {
+        match attempts.try_once(&mut connect) {
+            CopyStep::Done(result) => return result,
+            CopyStep::RetryAfter(delay) => sleep(delay),
+        }
+    }
+}
+
+fn drive_copy<P, C, S>(mut attempts: CopyAttempts, connect: C, schedule: S, done: Box<dyn FnOnce(Result<(), ClipboardError>)>)
+where
+    P: ClipboardProvider,
+    C: Fn() -> ProviderResult<P> + 'static,
+    S: Fn(Duration, Box<dyn FnOnce()>) + Clone + 'static,
+{
+    match attempts.try_once(&connect) {
+        CopyStep::Done(result) => done(result),
+        CopyStep::RetryAfter(delay) => {
+            let next = schedule.clone();
+            schedule(delay, Box::new(move || drive_copy(attempts, connect, next, done)));
+        }
+    }
 }
 
 /// 从系统剪贴板获取文本（用于测试）
 #[cfg(test)]
 pub fn get_clipboard_contents() -> Result<String, ClipboardError> {
-    use copypasta::{ClipboardContext, ClipboardProvider};
-    let mut ctx = ClipboardContext::new().map_err(|e| ClipboardError::Clip(e.to_string()))?;
+    let mut ctx = copypasta::ClipboardContext::new().map_err(|e| ClipboardError::Clip(e.to_string()))?;
     ctx.get_contents()
         .map_err(|e| ClipboardError::Clip(e.to_string()))
 }
@@ -28,6 +121,8 @@ pub fn get_clipboard_contents() -> Result<String, ClipboardError> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
 
     #[test]
     fn test_clipboard_copy_and_get() {
@@ -66,5 +161,118 @@ mod tests {
         let clipboard_content = get_clipboard_contents().unwrap();
         assert_eq!(clipboard_content, unicode_text, "剪贴板应该正确处理Unicode字符");
     }
-}
 
+    /// 模拟剪贴板：前 `fail_times` 次写入失败
+    struct MockProvider {
+        fail_times: Arc<AtomicUsize>,
+        written: Arc<Mutex<Option<String>>>,
+    }
+
+    impl ClipboardProvider for MockProvider {
+        fn get_contents(&mut self) -> ProviderResult<String> {
+            Ok(self.written.lock().unwrap().clone().unwrap_or_default())
+        }
+
+        fn set_contents(&mut self, text: String) -> ProviderResult<()> {
+            if self.fail_times.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1)).is_ok() {
+                return Err("X server busy".into());
+            }
+            *self.written.lock().unwrap() = Some(text);
+            Ok(())
+        }
+    }
+
+    fn run_mock(fail_times: usize) -> (Result<(), ClipboardError>, Vec<Duration>, Option<String>) {
+        let fail_times = Arc::new(AtomicUsize::new(fail_times));
+        let written = Arc::new(Mutex::new(None));
+        let mut sleeps = Vec::new();
+        let result = copy_with_retry(
+            "内容",
+            || Ok(MockProvider { fail_times: fail_times.clone(), written: written.clone() }),
+            |d| sleeps.push(d),
+        );
+        let written = written.lock().unwrap().clone();
+        (result, sleeps, written)
+    }
+
+    #[test]
+    fn test_copy_retries_with_backoff() {
+        let (result, sleeps, written) = run_mock(0);
+        assert!(result.is_ok());
+        assert!(sleeps.is_empty(), "首次成功不应等待");
+        assert_eq!(written.as_deref(), Some("内容"));
+
+        let (result, sleeps, written) = run_mock(2);
+        assert!(result.is_ok());
+        assert_eq!(sleeps, CLIPBOARD_RETRY_DELAYS[..2]);
+        assert_eq!(written.as_deref(), Some("内容"));
+
+        let (result, sleeps, written) = run_mock(10);
+        assert_eq!(sleeps, CLIPBOARD_RETRY_DELAYS);
+        assert!(written.is_none());
+        match result {
+            Err(ClipboardError::Timeout { attempts, last_error }) => {
+                assert_eq!(attempts, 4);
+                assert_eq!(last_error, "X server busy");
+            }
+            other => panic!("应返回超时错误: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_scheduled_copy_retries_without_blocking() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        type Queue = Rc<RefCell<Vec<(Duration, Box<dyn FnOnce()>)>>>;
+        let queue: Queue = Rc::default();
+        let schedule = {
+            let queue = queue.clone();
+            move |delay: Duration, retry: Box<dyn FnOnce()>| queue.borrow_mut().push((delay, retry))
+        };
+        let fail_times = Arc::new(AtomicUsize::new(2));
+        let written = Arc::new(Mutex::new(None));
+        let result = Rc::new(RefCell::new(None));
+        let connect = {
+            let (fail_times, written) = (fail_times.clone(), written.clone());
+            move || Ok(MockProvider { fail_times: fail_times.clone(), written: written.clone() })
+        };
+        let done = {
+            let result = result.clone();
+            Box::new(move |r: Result<(), ClipboardError>| *result.borrow_mut() = Some(r.is_ok()))
+        };
+        drive_copy(CopyAttempts::new("内容"), connect, schedule, done);
+
+        // 失败后只安排重试并立即返回，由调度方（事件循环定时器）到期后继续
+        let mut delays = Vec::new();
+        loop {
+            let next = queue.borrow_mut().pop();
+            let Some((delay, retry)) = next else {
+                break;
+            };
+            assert!(result.borrow().is_none());
+            delays.push(delay);
+            retry();
+        }
+        assert_eq!(delays, CLIPBOARD_RETRY_DELAYS[..2]);
+        assert_eq!(*result.borrow(), Some(true));
+        assert_eq!(written.lock().unwrap().as_deref(), Some("内容"));
+    }
+
+    #[test]
+    fn test_copy_retries_context_creation_failure() {
+        let mut calls = 0;
+        let mut sleeps = Vec::new();
+        let result = copy_with_retry(
+            "x",
+            || -> ProviderResult<MockProvider> {
+                calls += 1;
+                Err("no focus".into())
+            },
+            |d| sleeps.push(d),
+        );
+        assert_eq!(calls, 4);
+        assert_eq!(sleeps.len(), 3);
+        assert!(result.unwrap_err().to_string().contains("no focus"));
+    }
+}
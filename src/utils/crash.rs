@@ -0,0 +1,158 @@
+//! 崩溃诊断：记录最近的状态/性能事件，并在 panic 时写出诊断包
+
+use std::{
+    any::Any,
+    collections::VecDeque,
+    fs::File,
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+use crate::model::data_core::AppError;
+
+/// 诊断包中保留的最大事件条数
+pub const MAX_CRASH_EVENTS: usize = 200;
+
+/// 固定容量的事件环形缓冲区（超出容量时丢弃最早的事件）
+#[derive(Debug)]
+pub struct EventRing {
+    capacity: usize,
+    items: VecDeque<String>,
+}
+
+impl EventRing {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            items: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// 追加一条事件
+    pub fn push(&mut self, event: String) {
+        if self.capacity == 0 {
+            return;
+        }
+        while self.items.len() >= self.capacity {
+            self.items.pop_front();
+        }
+        self.items.push_back(event);
+    }
+
+    /// 按时间顺序返回所有事件
+    pub fn snapshot(&self) -> Vec<String> {
+        self.items.iter().cloned().collect()
+    }
+}
+
+fn status_ring() -> &'static Mutex<EventRing> {
+    static RING: OnceLock<Mutex<EventRing>> = OnceLock::new();
+    RING.get_or_init(|| Mutex::new(EventRing::new(MAX_CRASH_EVENTS)))
+}
+
+fn perf_ring() -> &'static Mutex<EventRing> {
+    static RING: OnceLock<Mutex<EventRing>> = OnceLock::new();
+    RING.get_or_init(|| Mutex::new(EventRing::new(MAX_CRASH_EVENTS)))
+}
+
+/// 记录一条状态事件（用于崩溃诊断）
+pub fn record_status_event(message: &str) {
+    if let Ok(mut ring) = status_ring().lock() {
+        ring.push(format!("[{}] {}", unix_timestamp(), message));
+    }
+}
+
+/// 记录一条性能信息（用于崩溃诊断）
+pub fn record_perf_event(message: &str) {
+    if let Ok(mut ring) = perf_ring().lock() {
+        ring.push(format!("[{}] {}", unix_timestamp(), message));
+    }
+}
+
+/// 获取最近的状态事件；锁被占用时返回空列表，避免在 panic 钩子中死锁
+pub fn recent_status_events() -> Vec<String> {
+    status_ring().try_lock().map(|r| r.snapshot()).unwrap_or_default()
+}
+
+/// 获取最近的性能信息；锁被占用时返回空列表
+pub fn recent_perf_events() -> Vec<String> {
+    perf_ring().try_lock().map(|r| r.snapshot()).unwrap_or_default()
+}
+
+/// 当前 Unix 时间戳（秒）
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// 从 panic 负载中提取可读消息
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        (*s).to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "未知 panic".to_string()
+    }
+}
+
+/// 崩溃诊断包
+#[derive(Debug, Serialize)]
+pub struct CrashBundle {
+    pub timestamp: u64,
+    pub message: String,
+    pub location: Option<String>,
+    pub recovery_file: Option<PathBuf>,
+    pub perf_history: Vec<String>,
+    pub status_events: Vec<String>,
+}
+
+/// 将诊断包写入 `dir/crash-<timestamp>.json`，返回写入路径
+pub fn write_crash_bundle(dir: &Path, bundle: &CrashBundle) -> Result<PathBuf, AppError> {
+    let path = dir.join(format!("crash-{}.json", bundle.timestamp));
+    let f = File::create(&path)?;
+    serde_json::to_writer_pretty(f, bundle)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn test_event_ring_keeps_latest() {
+        let mut ring = EventRing::new(3);
+        for i in 0..5 {
+            ring.push(format!("事件{}", i));
+        }
+        assert_eq!(ring.snapshot(), vec!["事件2", "事件3", "事件4"]);
+    }
+
+    #[test]
+    fn test_write_crash_bundle() {
+        let dir = tempfile::tempdir().expect("创建临时目录失败");
+        let bundle = CrashBundle {
+            timestamp: 1_700_000_000,
+            message: "index out of bounds".to_string(),
+            location: Some("src/main.rs:42".to_string()),
+            recovery_file: Some(dir.path().join("a.json.recovery.json")),
+            perf_history: vec!["加载: 12ms".to_string()],
+            status_events: (0..MAX_CRASH_EVENTS).map(|i| format!("状态{}", i)).collect(),
+        };
+
+        let path = write_crash_bundle(dir.path(), &bundle).expect("写入诊断包失败");
+        assert_eq!(path.file_name().unwrap(), "crash-1700000000.json");
+
+        let written: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written["message"], "index out of bounds");
+        assert_eq!(written["location"], "src/main.rs:42");
+        assert_eq!(written["perf_history"][0], "加载: 12ms");
+        assert_eq!(written["status_events"].as_array().unwrap().len(), MAX_CRASH_EVENTS);
+    }
+}
@@ -1,21 +1,715 @@
 //! IO helper: safe file read/write for JSON
 
-use std::{fs::File, io::BufReader, path::Path};
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsStr,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
 
-use serde_json::Value;
-use crate::model::data_core::AppError;
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_json::{
+    ser::{CompactFormatter, PrettyFormatter},
+    Number, Value,
+};
+use crate::model::data_core::{AppError, StateKind};
+use crate::model::shadow_tree::field_path;
 
-/// 从文件读取JSON数据
+/// 从文件读取JSON数据（启用 arbitrary_precision，数字保留原始字面量）
 pub fn read_json_file(p: &Path) -> Result<Value, AppError> {
-    let f = File::open(p)?;
-    let rdr = BufReader::new(f);
-    let v: Value = serde_json::from_reader(rdr)?;
+    let text = std::fs::read_to_string(long_path(p)).map_err(|e| AppError::io(e, p))?;
+    // 保留 serde_json 默认的递归深度限制（128层），超深嵌套直接返回解析错误而不会栈溢出
+    let mut v: Value = serde_json::from_str(&text)?;
+    restore_number_literals(&mut v, &text);
     Ok(v)
 }
 
+/// 从文件读取JSON数据，同时返回重复键的 JSONPath 列表（serde_json 只保留最后一个重复键）
+pub fn read_json_file_checked(p: &Path) -> Result<(Value, Vec<String>), AppError> {
+    let text = std::fs::read_to_string(long_path(p)).map_err(|e| AppError::io(e, p))?;
+    parse_json_checked(&text)
+}
+
+/// 解析JSON文本，同时返回重复键的 JSONPath 列表
+pub fn parse_json_checked(text: &str) -> Result<(Value, Vec<String>), AppError> {
+    // 先解析：非法 JSON 直接报错，扫描只需处理合法输入
+    let mut v: Value = serde_json::from_str(text)?;
+    restore_number_literals(&mut v, text);
+    Ok((v, find_duplicate_keys(text)))
+}
+
+/// 将带指数的数字恢复为源文本中的原始字面量
+///
+/// arbitrary_precision 保留大整数、`-0`、`0.10` 等字面量，但会把指数部分规范化（`1e21` → `1e+21`、`1E5` → `1e+5`）；
+/// 规范化后的字面量总含 `e`，没有这类数字时不扫描源文本
+fn restore_number_literals(value: &mut Value, text: &str) {
+    let mut stack = vec![&*value];
+    let mut has_exponent = false;
+    while let Some(v) = stack.pop() {
+        match v {
+            Value::Number(n) if n.as_str().contains('e') => {
+                has_exponent = true;
+                break;
+            }
+            Value::Object(map) => stack.extend(map.values()),
+            Value::Array(items) => stack.extend(items),
+            _ => {}
+        }
+    }
+    if has_exponent {
+        restore_exponent_literals(value, "$".to_string(), text, &find_value_offsets(text));
+    }
+}
+
+fn restore_exponent_literals(value: &mut Value, path: String, text: &str, offsets: &HashMap<String, u64>) {
+    match value {
+        Value::Number(n) if n.as_str().contains('e') => {
+            let Some(&offset) = offsets.get(&path) else {
+                return;
+            };
+            let rest = &text[offset as usize..];
+            let end = rest
+                .find(|c: char| !(c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')))
+                .unwrap_or(rest.len());
+            let literal = &rest[..end];
+            // serde_json 只能经 `from_string_unchecked` 构造保留字面量的 `Number`，因此先校验字面量与解析结果等值
+            if literal != n.as_str() && literal.parse::<Number>().is_ok_and(|parsed| parsed == *n) {
+                *n = Number::from_string_unchecked(literal.to_string());
+            }
+        }
+        Value::Object(map) => {
+            for (key, child) in map.iter_mut() {
+                restore_exponent_literals(child, field_path(&path, key), text, offsets);
+            }
+        }
+        Value::Array(items) => {
+            for (i, child) in items.iter_mut().enumerate() {
+                restore_exponent_literals(child, format!("{}[{}]", path, i), text, offsets);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 扫描过程中的容器帧
+enum ScanFrame {
+    Object { path: String, keys: HashSet<String>, key: Option<String>, expect_key: bool },
+    Array { path: String, index: usize },
+}
+
+/// 当前将要出现的值的路径
+fn value_path(stack: &[ScanFrame]) -> String {
+    match stack.last() {
+        Some(ScanFrame::Object { path, key: Some(key), .. }) => field_path(path, key),
+        Some(ScanFrame::Object { path, .. }) => path.clone(),
+        Some(ScanFrame::Array { path, index }) => format!("{}[{}]", path, index),
+        None => "$".to_string(),
+    }
+}
+
+/// 跳过从 `i`（开引号）开始的字符串，返回闭引号之后的位置
+fn skip_string(bytes: &[u8], mut i: usize) -> usize {
+    i += 1;
+    while i < bytes.len() && bytes[i] != b'"' {
+        if bytes[i] == b'\\' {
+            i += 1;
+        }
+        i += 1;
+    }
+    (i + 1).min(bytes.len())
+}
+
+/// 轻量扫描 JSON 文本，返回每个值起始位置的字节偏移（JSONPath -> 偏移）
+///
+/// serde_json 只在顶层值之间暴露 `byte_offset`，逐节点偏移需自行扫描；重复键与解析结果一致取最后一个
+pub fn find_value_offsets(text: &str) -> HashMap<String, u64> {
+    let bytes = text.as_bytes();
+    let mut stack: Vec<ScanFrame> = Vec::new();
+    let mut offsets = HashMap::new();
+    // 下一个非空白字符是否为值的开头（根值同样如此）
+    let mut expect_value = true;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let b = bytes[i];
+        if b.is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+        if expect_value && b != b']' {
+            offsets.insert(value_path(&stack), i as u64);
+        }
+        expect_value = false;
+        match b {
+            b'"' => {
+                let end = skip_string(bytes, i);
+                if let Some(ScanFrame::Object { key, expect_key, .. }) = stack.last_mut() {
+                    if *expect_key {
+                        let raw = &text[i..end];
+                        *key = Some(serde_json::from_str(raw).unwrap_or_else(|_| raw.trim_matches('"').to_string()));
+                        *expect_key = false;
+                    }
+                }
+                i = end;
+                continue;
+            }
+            b'{' => {
+                let path = value_path(&stack);
+                stack.push(ScanFrame::Object { path, keys: HashSet::new(), key: None, expect_key: true });
+            }
+            b'[' => {
+                let path = value_path(&stack);
+                stack.push(ScanFrame::Array { path, index: 0 });
+                expect_value = true;
+            }
+            b'}' | b']' => {
+                stack.pop();
+            }
+            b':' => expect_value = true,
+            b',' => match stack.last_mut() {
+                Some(ScanFrame::Object { expect_key, .. }) => *expect_key = true,
+                Some(ScanFrame::Array { index, .. }) => {
+                    *index += 1;
+                    expect_value = true;
+                }
+                None => {}
+            },
+            _ => {}
+        }
+        i += 1;
+    }
+    offsets
+}
+
+/// 轻量扫描 JSON 文本，返回所有重复键的 JSONPath（按出现顺序）
+pub fn find_duplicate_keys(text: &str) -> Vec<String> {
+    let bytes = text.as_bytes();
+    let mut stack: Vec<ScanFrame> = Vec::new();
+    let mut duplicates = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => {
+                let start = i;
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    if bytes[i] == b'\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                let end = (i + 1).min(bytes.len());
+                if let Some(ScanFrame::Object { path, keys, key, expect_key }) = stack.last_mut() {
+                    if *expect_key {
+                        let raw = &text[start..end];
+                        let name: String = serde_json::from_str(raw).unwrap_or_else(|_| raw.trim_matches('"').to_string());
+                        if !keys.insert(name.clone()) {
+                            duplicates.push(field_path(path, &name));
+                        }
+                        *key = Some(name);
+                        *expect_key = false;
+                    }
+                }
+            }
+            b'{' => {
+                let path = value_path(&stack);
+                stack.push(ScanFrame::Object { path, keys: HashSet::new(), key: None, expect_key: true });
+            }
+            b'[' => {
+                let path = value_path(&stack);
+                stack.push(ScanFrame::Array { path, index: 0 });
+            }
+            b'}' | b']' => {
+                stack.pop();
+            }
+            b',' => match stack.last_mut() {
+                Some(ScanFrame::Object { expect_key, .. }) => *expect_key = true,
+                Some(ScanFrame::Array { index, .. }) => *index += 1,
+                None => {}
+            },
+            _ => {}
+        }
+        i += 1;
+    }
+    duplicates
+}
+
 /// 将JSON数据保存到文件（格式化输出）
 pub fn write_json_file(p: &Path, value: &Value) -> Result<(), AppError> {
-    let f = File::create(p)?;
+    let f = File::create(long_path(p)).map_err(|e| AppError::io(e, p))?;
     serde_json::to_writer_pretty(f, value)?;
     Ok(())
-}
\ No newline at end of file
+}
+
+/// JSON保存选项
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SaveOptions {
+    /// 是否格式化输出（false 为无空白的紧凑输出）
+    pub pretty: bool,
+    /// 格式化缩进空格数（仅支持 2 或 4）
+    pub indent_size: usize,
+    /// 是否按键名排序对象
+    pub sort_keys: bool,
+}
+
+impl Default for SaveOptions {
+    fn default() -> Self {
+        Self { pretty: true, indent_size: 2, sort_keys: false }
+    }
+}
+
+impl SaveOptions {
+    /// 紧凑输出（文件体积最小）
+    pub fn minified() -> Self {
+        Self { pretty: false, ..Self::default() }
+    }
+}
+
+/// 序列化时按键名排序对象（不克隆DOM）
+struct SortedKeys<'a>(&'a Value);
+
+impl Serialize for SortedKeys<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            Value::Object(map) => {
+                let mut entries: Vec<_> = map.iter().collect();
+                entries.sort_by(|a, b| a.0.cmp(b.0));
+                let mut ser_map = serializer.serialize_map(Some(entries.len()))?;
+                for (k, v) in entries {
+                    ser_map.serialize_entry(k, &SortedKeys(v))?;
+                }
+                ser_map.end()
+            }
+            Value::Array(items) => serializer.collect_seq(items.iter().map(SortedKeys)),
+            other => other.serialize(serializer),
+        }
+    }
+}
+
+/// 按保存选项将JSON写入 writer
+fn write_json_with_options<W: Write>(writer: W, value: &Value, options: &SaveOptions) -> Result<(), AppError> {
+    if options.indent_size != 2 && options.indent_size != 4 {
+        return Err(AppError::State(StateKind::Invalid, format!("缩进只支持2或4个空格: {}", options.indent_size)));
+    }
+    let indent = vec![b' '; options.indent_size];
+    if options.pretty {
+        let mut ser = serde_json::Serializer::with_formatter(writer, PrettyFormatter::with_indent(&indent));
+        if options.sort_keys { SortedKeys(value).serialize(&mut ser) } else { value.serialize(&mut ser) }?;
+    } else {
+        let mut ser = serde_json::Serializer::with_formatter(writer, CompactFormatter);
+        if options.sort_keys { SortedKeys(value).serialize(&mut ser) } else { value.serialize(&mut ser) }?;
+    }
+    Ok(())
+}
+
+/// 按保存选项序列化为字符串
+pub fn to_string_with_options(value: &Value, options: &SaveOptions) -> Result<String, AppError> {
+    let mut out = Vec::new();
+    write_json_with_options(&mut out, value, options)?;
+    Ok(String::from_utf8(out).expect("serde_json 输出总是合法 UTF-8"))
+}
+
+/// 原子写入JSON：先写入同目录临时文件并落盘，再重命名覆盖目标文件
+pub fn write_json_file_atomic(p: &Path, value: &Value) -> Result<(), AppError> {
+    write_json_file_atomic_with_options(p, value, &SaveOptions::default())
+}
+
+/// 按保存选项原子写入JSON
+pub fn write_json_file_atomic_with_options(p: &Path, value: &Value, options: &SaveOptions) -> Result<(), AppError> {
+    let name = p
+        .file_name()
+        .ok_or_else(|| AppError::State(StateKind::Invalid, format!("无效的输出路径: {}", p.display())))?;
+    let mut tmp_name = std::ffi::OsString::from(".");
+    tmp_name.push(name);
+    tmp_name.push(".tmp");
+    let tmp = long_path(&p.with_file_name(tmp_name));
+
+    let result = (|| -> Result<(), AppError> {
+        let mut writer = BufWriter::new(File::create(&tmp).map_err(|e| AppError::io(e, &tmp))?);
+        write_json_with_options(&mut writer, value, options)?;
+        writer.flush().map_err(|e| AppError::io(e, &tmp))?;
+        writer.get_ref().sync_all().map_err(|e| AppError::io(e, &tmp))?;
+        std::fs::rename(&tmp, long_path(p)).map_err(|e| AppError::io(e, p))?;
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// 将文件复制为带时间戳的备份 `{backup_dir}/{文件名}.{时间戳}.bak`，返回备份路径
+///
+/// `backup_dir` 为 None 时备份到源文件所在目录
+pub fn backup_file(p: &Path, backup_dir: Option<&Path>) -> Result<PathBuf, AppError> {
+    let name = p
+        .file_name()
+        .ok_or_else(|| AppError::State(StateKind::Invalid, format!("无效的文件路径: {}", p.display())))?;
+    let dir = match backup_dir {
+        Some(dir) => dir.to_path_buf(),
+        None => p.parent().map(Path::to_path_buf).unwrap_or_default(),
+    };
+    let mut backup_name = name.to_os_string();
+    backup_name.push(format!(".{}.bak", chrono::Local::now().format("%Y%m%d%H%M%S%3f")));
+    let backup = dir.join(backup_name);
+    std::fs::copy(long_path(p), long_path(&backup)).map_err(|e| AppError::io(e, p))?;
+    Ok(backup)
+}
+
+/// Windows 传统 API 的路径长度上限（UTF-16 单元）
+#[cfg(windows)]
+const MAX_PATH: usize = 260;
+
+/// 绝对路径转为扩展长度形式时需添加的前缀与需去掉的前导字符数：盘符路径加 `\\?\`，UNC 路径 `\\server\share` 改为 `\\?\UNC\server\share`
+///
+/// 已是扩展长度形式或不是 Windows 绝对路径时返回 None
+#[cfg_attr(not(windows), allow(dead_code))]
+fn verbatim_prefix(path: &str) -> Option<(&'static str, usize)> {
+    let bytes = path.as_bytes();
+    if path.starts_with(r"\\?\") {
+        None
+    } else if path.starts_with(r"\\") {
+        Some((r"\\?\UNC\", 2))
+    } else if bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && bytes[2] == b'\\' {
+        Some((r"\\?\", 0))
+    } else {
+        None
+    }
+}
+
+/// 在 Windows 上为超过 MAX_PATH 的绝对路径加扩展长度前缀（按 UTF-16 无损处理，含非法代理项的路径同样适用）；其他平台原样返回
+pub fn long_path(p: &Path) -> PathBuf {
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::{OsStrExt, OsStringExt};
+        let wide: Vec<u16> = p.as_os_str().encode_wide().collect();
+        // 前缀判断只看 ASCII 字符，有损转换不影响结果
+        if let Some((prefix, skip)) = (wide.len() >= MAX_PATH).then(|| verbatim_prefix(&p.to_string_lossy())).flatten() {
+            let prefixed: Vec<u16> = prefix.encode_utf16().chain(wide.into_iter().skip(skip)).collect();
+            return PathBuf::from(std::ffi::OsString::from_wide(&prefixed));
+        }
+    }
+    p.to_path_buf()
+}
+
+/// 无法按 UTF-8 表示的部分转义为 `\xNN`（Unix 字节）或 `\u{DXXX}`（Windows 未配对代理项），其余原样保留
+fn escape_os_str(s: &OsStr) -> String {
+    if let Some(text) = s.to_str() {
+        return text.to_string();
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        let mut out = String::new();
+        for chunk in s.as_bytes().utf8_chunks() {
+            out.push_str(chunk.valid());
+            for byte in chunk.invalid() {
+                out.push_str(&format!("\\x{:02X}", byte));
+            }
+        }
+        out
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::OsStrExt;
+        char::decode_utf16(s.encode_wide())
+            .map(|c| c.map(String::from).unwrap_or_else(|e| format!("\\u{{{:04X}}}", e.unpaired_surrogate())))
+            .collect()
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        s.to_string_lossy().into_owned()
+    }
+}
+
+/// 界面显示用的路径文本：去掉扩展长度前缀，非 UTF-8 部分转义显示而不是替换为 �
+///
+/// 仅用于显示；需要再次打开文件时应保留原 `PathBuf`
+pub fn display_path(p: &Path) -> String {
+    let text = escape_os_str(p.as_os_str());
+    if let Some(unc) = text.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{}", unc)
+    } else if let Some(rest) = text.strip_prefix(r"\\?\") {
+        rest.to_string()
+    } else {
+        text
+    }
+}
+
+/// 项目配置文件名（放在源文件所在目录或其任一上级目录）
+pub const PROJECT_CONFIG_FILE: &str = ".fanyi.json";
+
+/// 生成的产物种类，决定产物文件名后缀
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArtifactKind {
+    /// 中间产物2
+    Stage2,
+    /// 最终产物
+    FinalProduct,
+    /// 回写报告
+    WritebackReport,
+    /// 崩溃时写出的DOM恢复文件
+    Recovery,
+    /// 另存为的输出
+    Output,
+    /// 自检报告
+    SelftestReport,
+}
+
+impl ArtifactKind {
+    fn suffix(self) -> &'static str {
+        match self {
+            ArtifactKind::Stage2 => "stage2",
+            ArtifactKind::FinalProduct => "final",
+            ArtifactKind::WritebackReport => "writeback-report",
+            ArtifactKind::Recovery => "recovery",
+            ArtifactKind::Output => "output",
+            ArtifactKind::SelftestReport => "selftest-report",
+        }
+    }
+}
+
+/// 默认产物目录（平台数据目录下）
+pub fn default_artifacts_dir() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("juzhen_r_fanyi")
+        .join("artifacts")
+}
+
+/// 从 `start` 向上查找 `.fanyi.json`，读取其中的 `artifacts_dir`（相对路径相对于配置文件所在目录）
+///
+/// 配置不存在、无法解析或未设置该项时返回 None
+pub fn project_artifacts_dir(start: &Path) -> Option<PathBuf> {
+    let config = start.ancestors().map(|dir| dir.join(PROJECT_CONFIG_FILE)).find(|p| p.is_file())?;
+    let value = match read_json_file(&config) {
+        Ok(value) => value,
+        Err(e) => {
+            tracing::warn!("项目配置 {} 读取失败: {}", config.display(), e);
+            return None;
+        }
+    };
+    let dir = PathBuf::from(value.get("artifacts_dir")?.as_str()?);
+    Some(if dir.is_absolute() { dir } else { config.parent().unwrap_or(Path::new("")).join(dir) })
+}
+
+/// 源文件专属的产物子目录名：`{源文件名去扩展名}-{绝对路径哈希}`，不同目录下的同名源文件互不覆盖
+pub fn source_artifacts_subdir(source_file: &Path) -> String {
+    let absolute = std::path::absolute(source_file).unwrap_or_else(|_| source_file.to_path_buf());
+    let hash = xxhash_rust::xxh3::xxh3_64(absolute.as_os_str().as_encoded_bytes());
+    let stem = source_file.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "untitled".to_string());
+    format!("{}-{:08x}", stem, hash >> 32)
+}
+
+/// 源文件对应的产物目录：项目配置优先，否则为平台数据目录；有源文件时再按 [`source_artifacts_subdir`] 分子目录
+pub fn artifacts_dir(source_file: Option<&Path>) -> PathBuf {
+    let base = source_file
+        .and_then(|p| p.parent())
+        .and_then(project_artifacts_dir)
+        .unwrap_or_else(default_artifacts_dir);
+    match source_file {
+        Some(p) => base.join(source_artifacts_subdir(p)),
+        None => base,
+    }
+}
+
+/// 产物文件名：`{源文件名去扩展名}.{种类}.json`，无源文件时为 `untitled.{种类}.json`
+pub fn artifact_file_name(kind: ArtifactKind, source_file: Option<&Path>) -> String {
+    let stem = source_file
+        .and_then(|p| p.file_stem())
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "untitled".to_string());
+    format!("{}.{}.json", stem, kind.suffix())
+}
+
+/// 产物的完整路径（确定性命名，同一源文件的同类产物总是覆盖同一文件）
+pub fn artifact_path(kind: ArtifactKind, source_file: Option<&Path>) -> PathBuf {
+    artifacts_dir(source_file).join(artifact_file_name(kind, source_file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_duplicate_keys_nested() {
+        let text = r#"{"a": 1, "b": {"x": "1", "y": "2", "x": "3"}, "a": 2, "s": "a,{\"b\"", "odd key": {"k": 1, "k": 2}}"#;
+        assert_eq!(
+            find_duplicate_keys(text),
+            vec!["$.b.x", "$.a", "$['odd key'].k"]
+        );
+    }
+
+    #[test]
+    fn test_find_duplicate_keys_in_array_of_objects() {
+        let text = r#"[{"name": "a", "name": "b"}, {"name": "c"}, [1, {"id": 1, "id": 2}]]"#;
+        assert_eq!(find_duplicate_keys(text), vec!["$[0].name", "$[2][1].id"]);
+        assert!(find_duplicate_keys(r#"{"a": {"k": 1}, "b": {"k": 1}}"#).is_empty(), "不同对象中的同名键不算重复");
+    }
+
+    #[test]
+    fn test_find_value_offsets() {
+        let text = "{\n  \"b\": [1, {\"k\": \"x]\"}],\n  \"a b\": []\n}";
+        let offsets = find_value_offsets(text);
+        let at = |path: &str| &text[offsets[path] as usize..];
+        assert_eq!(offsets["$"], 0);
+        assert!(at("$.b").starts_with("[1,"));
+        assert!(at("$.b[0]").starts_with("1,"));
+        assert!(at("$.b[1]").starts_with("{\"k\""));
+        assert!(at("$.b[1].k").starts_with("\"x]\""));
+        assert!(at("$['a b']").starts_with("[]"));
+        assert_eq!(offsets.len(), 6, "空数组不产生元素偏移");
+    }
+
+    #[test]
+    fn test_number_literals_round_trip() {
+        let dir = tempfile::tempdir().expect("创建临时目录失败");
+        let src = dir.path().join("numbers.json");
+        let dst = dir.path().join("out.json");
+        let literals = ["18446744073709551615", "-0", "0.10", "1.0", "1e21", "1E5", "2.5e-3", "1e+21", "3.14159265358979323846264338327950288"];
+        let body: Vec<String> = literals.iter().enumerate().map(|(i, l)| format!("\"n{}\": {}", i, l)).collect();
+        std::fs::write(&src, format!("{{{}}}", body.join(", "))).unwrap();
+
+        let value = read_json_file(&src).unwrap();
+        assert_eq!(value["n0"].as_u64(), Some(u64::MAX));
+        assert_eq!(value["n5"].as_f64(), Some(1e5), "保留原字面量后数值不变");
+        write_json_file(&dst, &value).unwrap();
+
+        let written = std::fs::read_to_string(&dst).unwrap();
+        for (i, l) in literals.iter().enumerate() {
+            assert!(written.contains(&format!("\"n{}\": {},", i, l)) || written.contains(&format!("\"n{}\": {}\n", i, l)), "数字字面量应原样保留: {}", l);
+        }
+        // 解析结果与直接读取文件一致
+        assert_eq!(parse_json_checked(&std::fs::read_to_string(&src).unwrap()).unwrap().0, value);
+    }
+
+    #[test]
+    fn test_write_json_file_atomic() {
+        let dir = tempfile::tempdir().expect("创建临时目录失败");
+        let target = dir.path().join("out.json");
+        std::fs::write(&target, "旧内容").unwrap();
+
+        write_json_file_atomic(&target, &serde_json::json!({"k": "v"})).unwrap();
+        assert_eq!(read_json_file(&target).unwrap(), serde_json::json!({"k": "v"}));
+        assert!(!dir.path().join(".out.json.tmp").exists(), "临时文件应已被重命名");
+
+        let missing_dir = dir.path().join("missing").join("out.json");
+        let err = write_json_file_atomic(&missing_dir, &serde_json::json!(1)).unwrap_err();
+        assert!(err.to_string().starts_with("E1001: IO失败 ("), "错误信息应包含文件路径: {}", err);
+        assert!(err.to_string().contains(".out.json.tmp"));
+    }
+
+    #[test]
+    fn test_backup_file() {
+        let dir = tempfile::tempdir().expect("创建临时目录失败");
+        let src = dir.path().join("data.json");
+        std::fs::write(&src, "原始内容").unwrap();
+
+        let backup = backup_file(&src, None).unwrap();
+        assert_eq!(backup.parent(), Some(dir.path()));
+        let name = backup.file_name().unwrap().to_string_lossy().to_string();
+        assert!(name.starts_with("data.json.") && name.ends_with(".bak"), "{}", name);
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), "原始内容");
+
+        let other = tempfile::tempdir().unwrap();
+        assert_eq!(backup_file(&src, Some(other.path())).unwrap().parent(), Some(other.path()));
+        assert!(backup_file(&dir.path().join("missing.json"), None).is_err());
+    }
+
+    #[test]
+    fn test_io_error_includes_path() {
+        let missing = Path::new("missing_dir_for_test/file.json");
+        let err = read_json_file(missing).unwrap_err();
+        assert!(matches!(&err, AppError::Io(e) if e.path.as_deref() == Some(missing)));
+        assert!(err.to_string().starts_with("E1001: IO失败 (missing_dir_for_test/file.json): "), "{}", err);
+
+        let err: AppError = std::io::Error::other("磁盘已满").into();
+        assert_eq!(err.to_string(), "E1001: IO失败: 磁盘已满");
+    }
+
+    #[test]
+    fn test_write_json_with_options() {
+        let value = serde_json::json!({"b": [1, {"d": 2, "c": 3}], "a": "x"});
+        let render = |options: SaveOptions| {
+            let mut out = Vec::new();
+            write_json_with_options(&mut out, &value, &options).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let minified = render(SaveOptions { sort_keys: true, ..SaveOptions::minified() });
+        assert_eq!(minified, r#"{"a":"x","b":[1,{"c":3,"d":2}]}"#);
+        assert_eq!(render(SaveOptions::minified()), r#"{"b":[1,{"d":2,"c":3}],"a":"x"}"#, "不排序时保留键顺序");
+
+        let pretty = render(SaveOptions { indent_size: 4, ..SaveOptions::default() });
+        assert!(pretty.contains("\n    \"a\": \"x\""));
+        assert_eq!(serde_json::from_str::<Value>(&pretty).unwrap(), value);
+
+        let mut out = Vec::new();
+        let bad = SaveOptions { indent_size: 3, ..SaveOptions::default() };
+        assert!(write_json_with_options(&mut out, &value, &bad).is_err());
+    }
+
+    #[test]
+    fn test_verbatim_prefix_and_display_path() {
+        assert_eq!(verbatim_prefix(r"C:\data\a.json"), Some((r"\\?\", 0)));
+        assert_eq!(verbatim_prefix(r"\\server\share\a.json"), Some((r"\\?\UNC\", 2)));
+        assert_eq!(verbatim_prefix(r"\\?\C:\data\a.json"), None);
+        assert_eq!(verbatim_prefix("data/a.json"), None);
+        assert_eq!(long_path(Path::new("a.json")), PathBuf::from("a.json"));
+
+        assert_eq!(display_path(Path::new(r"\\?\C:\长路径\a.json")), r"C:\长路径\a.json");
+        assert_eq!(display_path(Path::new(r"\\?\UNC\server\share\a.json")), r"\\server\share\a.json");
+        assert_eq!(display_path(Path::new("/data/очень/a.json")), "/data/очень/a.json");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_display_path_escapes_non_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+        let path = Path::new(OsStr::from_bytes(b"/data/\xFFdir/a\xC3.json"));
+        assert_eq!(display_path(path), "/data/\\xFFdir/a\\xC3.json");
+        // 转换只影响显示，路径本身保持不变
+        assert_eq!(long_path(path).as_os_str().as_bytes(), b"/data/\xFFdir/a\xC3.json");
+    }
+
+    #[test]
+    fn test_artifact_file_name() {
+        let name = |kind, p: &str| artifact_file_name(kind, Some(Path::new(p)));
+        assert_eq!(name(ArtifactKind::Stage2, "/data/big.json"), "big.stage2.json");
+        assert_eq!(name(ArtifactKind::WritebackReport, "/data/big.json"), "big.writeback-report.json");
+        assert_eq!(name(ArtifactKind::Recovery, "/我的 项目/语言 包.zh-CN.json"), "语言 包.zh-CN.recovery.json");
+        assert_eq!(name(ArtifactKind::Output, "no_extension"), "no_extension.output.json");
+        assert_eq!(artifact_file_name(ArtifactKind::FinalProduct, None), "untitled.final.json");
+    }
+
+    #[test]
+    fn test_artifact_path_uses_project_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("子 目录");
+        std::fs::create_dir(&nested).unwrap();
+        let source = nested.join("界面 文本.json");
+
+        std::fs::write(dir.path().join(PROJECT_CONFIG_FILE), r#"{"artifacts_dir": "build/产物"}"#).unwrap();
+        assert_eq!(
+            artifact_path(ArtifactKind::Stage2, Some(&source)),
+            dir.path().join("build/产物").join(source_artifacts_subdir(&source)).join("界面 文本.stage2.json")
+        );
+
+        // 未设置 artifacts_dir 时退回平台数据目录
+        std::fs::write(dir.path().join(PROJECT_CONFIG_FILE), r#"{"other": 1}"#).unwrap();
+        assert_eq!(artifacts_dir(Some(&source)), default_artifacts_dir().join(source_artifacts_subdir(&source)));
+        assert_eq!(artifacts_dir(None), default_artifacts_dir());
+    }
+
+    #[test]
+    fn test_artifacts_dir_separates_same_named_sources() {
+        let a = Path::new("/projects/a/strings.json");
+        let b = Path::new("/projects/b/strings.json");
+        assert!(source_artifacts_subdir(a).starts_with("strings-"));
+        assert_eq!(source_artifacts_subdir(a), source_artifacts_subdir(a), "同一源文件总是同一子目录");
+        assert_ne!(artifact_path(ArtifactKind::Output, Some(a)), artifact_path(ArtifactKind::Output, Some(b)));
+        assert_ne!(artifact_path(ArtifactKind::Recovery, Some(a)), artifact_path(ArtifactKind::Recovery, Some(b)));
+    }
+}
@@ -0,0 +1,82 @@
+//! 带注释的 JSON（JSONC，如 VSCode 设置与各类构建工具配置）预处理
+
+use crate::model::data_core::{AppError, StateKind};
+
+/// 删除 `//` 单行注释与 `/* */` 块注释，字符串内的内容原样保留
+///
+/// 注释按字节替换为空格（换行保留），解析错误的行列号与字节偏移仍对应原文件；块注释未闭合时报错
+pub fn strip_json_comments(input: &str) -> Result<String, AppError> {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut in_string = false;
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        if in_string {
+            out.push(b);
+            match b {
+                b'\\' if i + 1 < bytes.len() => {
+                    out.push(bytes[i + 1]);
+                    i += 1;
+                }
+                b'"' => in_string = false,
+                _ => {}
+            }
+            i += 1;
+            continue;
+        }
+        match (b, bytes.get(i + 1)) {
+            (b'"', _) => {
+                in_string = true;
+                out.push(b);
+                i += 1;
+            }
+            (b'/', Some(b'/')) => {
+                let end = bytes[i..].iter().position(|&c| c == b'\n').map_or(bytes.len(), |p| i + p);
+                out.resize(out.len() + (end - i), b' ');
+                i = end;
+            }
+            (b'/', Some(b'*')) => {
+                let line = input[..i].matches('\n').count() + 1;
+                let close = input[i + 2..]
+                    .find("*/")
+                    .ok_or_else(|| AppError::State(StateKind::Malformed, format!("块注释未闭合（第 {} 行）", line)))?;
+                let end = i + 2 + close + 2;
+                out.extend(bytes[i..end].iter().map(|&c| if c == b'\n' { b'\n' } else { b' ' }));
+                i = end;
+            }
+            _ => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    // 注释整段按字节替换，其余字节原样保留，结果总是合法 UTF-8
+    String::from_utf8(out).map_err(|e| AppError::State(StateKind::Malformed, format!("去除注释后编码无效: {}", e)))
+}
+
+/// 文本中是否含有（字符串之外的）注释
+pub fn detect_json_comments(input: &str) -> bool {
+    strip_json_comments(input).map_or(true, |stripped| stripped != input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_json_comments() {
+        let input = "{\n  // 注释：编辑器设置\n  \"url\": \"http://a/*b*/c\", /* 块\n注释 */ \"k\": \"\\\"//\"\n}";
+        let stripped = strip_json_comments(input).unwrap();
+        assert_eq!(stripped.len(), input.len(), "按字节替换，偏移不变");
+        assert_eq!(stripped.lines().count(), input.lines().count());
+        let value: serde_json::Value = serde_json::from_str(&stripped).unwrap();
+        assert_eq!(value, serde_json::json!({"url": "http://a/*b*/c", "k": "\"//"}));
+        assert!(detect_json_comments(input));
+
+        let plain = r#"{"a": "// not a comment", "b": "/* nor this */"}"#;
+        assert_eq!(strip_json_comments(plain).unwrap(), plain);
+        assert!(!detect_json_comments(plain));
+        assert!(strip_json_comments("{\n/* 未闭合").unwrap_err().to_string().contains("第 2 行"));
+    }
+}
@@ -0,0 +1,189 @@
+//! JSON 格式化：按行宽控制的美化输出，短数组/对象保持单行，超长的换行展开
+
+use serde_json::Value;
+
+/// 预览区与中间产物/最终产物默认的单行最大宽度（字符数）
+pub const DEFAULT_MAX_INLINE_WIDTH: usize = 100;
+
+/// 默认每级缩进的空格数
+pub const DEFAULT_INDENT: usize = 2;
+
+/// 美化输出 JSON：容器连同行首缩进与键名不超过 `max_inline_width` 时保持单行，
+/// 否则换行展开；只含标量的长数组按行宽紧凑折行（行宽不含行尾逗号）
+pub fn to_string_wrapped(value: &Value, max_inline_width: usize) -> String {
+    to_string_wrapped_with_indent(value, max_inline_width, DEFAULT_INDENT)
+}
+
+/// 同 [`to_string_wrapped`]，每级缩进 `indent` 个空格
+pub fn to_string_wrapped_with_indent(value: &Value, max_inline_width: usize, indent: usize) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value, 0, 0, max_inline_width, indent);
+    out
+}
+
+fn scalar(value: &Value) -> String {
+    serde_json::to_string(value).expect("标量序列化不会失败")
+}
+
+fn is_scalar(value: &Value) -> bool {
+    !matches!(value, Value::Array(_) | Value::Object(_))
+}
+
+/// 单行形式，超出 `budget` 个字符时提前放弃
+fn inline(value: &Value, budget: usize) -> Option<String> {
+    let mut out = String::new();
+    let mut len = 0;
+    fn push(out: &mut String, len: &mut usize, s: &str, budget: usize) -> Option<()> {
+        *len += s.chars().count();
+        out.push_str(s);
+        (*len <= budget).then_some(())
+    }
+    match value {
+        Value::Array(items) => {
+            push(&mut out, &mut len, "[", budget)?;
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    push(&mut out, &mut len, ", ", budget)?;
+                }
+                let s = inline(item, budget.saturating_sub(len))?;
+                push(&mut out, &mut len, &s, budget)?;
+            }
+            push(&mut out, &mut len, "]", budget)?;
+        }
+        Value::Object(map) => {
+            push(&mut out, &mut len, "{", budget)?;
+            for (i, (k, v)) in map.iter().enumerate() {
+                if i > 0 {
+                    push(&mut out, &mut len, ", ", budget)?;
+                }
+                push(&mut out, &mut len, &scalar(&Value::String(k.clone())), budget)?;
+                push(&mut out, &mut len, ": ", budget)?;
+                let s = inline(v, budget.saturating_sub(len))?;
+                push(&mut out, &mut len, &s, budget)?;
+            }
+            push(&mut out, &mut len, "}", budget)?;
+        }
+        other => push(&mut out, &mut len, &scalar(other), budget)?,
+    }
+    Some(out)
+}
+
+/// `indent` 为当前行缩进，`prefix` 为同一行上已输出的键名长度（含 `": "`），`step` 为每级缩进
+fn write_value(out: &mut String, value: &Value, indent: usize, prefix: usize, width: usize, step: usize) {
+    let empty = match value {
+        Value::Array(items) => items.is_empty(),
+        Value::Object(map) => map.is_empty(),
+        _ => true,
+    };
+    if empty {
+        out.push_str(&scalar(value));
+        return;
+    }
+    if let Some(s) = inline(value, width.saturating_sub(indent + prefix)) {
+        out.push_str(&s);
+        return;
+    }
+
+    let pad = " ".repeat(indent + step);
+    match value {
+        Value::Array(items) if items.iter().all(is_scalar) => {
+            // 标量数组：按行宽紧凑折行
+            out.push_str("[\n");
+            let mut line = String::new();
+            let mut line_len = 0;
+            for (i, item) in items.iter().enumerate() {
+                let s = scalar(item);
+                let s_len = s.chars().count();
+                if !line.is_empty() {
+                    if indent + step + line_len + 1 + s_len > width {
+                        out.push_str(&pad);
+                        out.push_str(&line);
+                        out.push('\n');
+                        line.clear();
+                        line_len = 0;
+                    } else {
+                        line.push(' ');
+                        line_len += 1;
+                    }
+                }
+                line.push_str(&s);
+                line_len += s_len;
+                if i + 1 < items.len() {
+                    line.push(',');
+                    line_len += 1;
+                }
+            }
+            out.push_str(&pad);
+            out.push_str(&line);
+            out.push('\n');
+            out.push_str(&" ".repeat(indent));
+            out.push(']');
+        }
+        Value::Array(items) => {
+            out.push_str("[\n");
+            for (i, item) in items.iter().enumerate() {
+                out.push_str(&pad);
+                write_value(out, item, indent + step, 0, width, step);
+                out.push_str(if i + 1 < items.len() { ",\n" } else { "\n" });
+            }
+            out.push_str(&" ".repeat(indent));
+            out.push(']');
+        }
+        Value::Object(map) => {
+            out.push_str("{\n");
+            for (i, (k, v)) in map.iter().enumerate() {
+                let key = scalar(&Value::String(k.clone()));
+                out.push_str(&pad);
+                out.push_str(&key);
+                out.push_str(": ");
+                write_value(out, v, indent + step, key.chars().count() + 2, width, step);
+                out.push_str(if i + 1 < map.len() { ",\n" } else { "\n" });
+            }
+            out.push_str(&" ".repeat(indent));
+            out.push('}');
+        }
+        _ => unreachable!("标量已在上方输出"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_inline_boundary_width() {
+        let value = json!([1, 2, 3]);
+        // "[1, 2, 3]" 恰好 9 个字符
+        assert_eq!(to_string_wrapped(&value, 9), "[1, 2, 3]");
+        assert_eq!(to_string_wrapped(&value, 8), "[\n  1, 2,\n  3\n]");
+
+        let value = json!({"a": 1, "k": [10, 20]});
+        assert_eq!(to_string_wrapped(&value, 23), "{\"a\": 1, \"k\": [10, 20]}");
+        // 行 `  "k": [10, 20]` 宽度为 15
+        assert_eq!(to_string_wrapped(&value, 15), "{\n  \"a\": 1,\n  \"k\": [10, 20]\n}");
+        assert_eq!(to_string_wrapped(&value, 14), "{\n  \"a\": 1,\n  \"k\": [\n    10, 20\n  ]\n}");
+        assert_eq!(
+            to_string_wrapped_with_indent(&value, 14, 4),
+            "{\n    \"a\": 1,\n    \"k\": [\n        10, 20\n    ]\n}"
+        );
+    }
+
+    #[test]
+    fn test_nested_mix_and_long_scalar_array() {
+        let value = json!({
+            "short": {"a": 1},
+            "nested": [{"name": "甲"}, [1, 2], "一段很长很长的文本内容"],
+            "numbers": (0..1000).collect::<Vec<_>>(),
+        });
+        let text = to_string_wrapped(&value, 30);
+        assert_eq!(serde_json::from_str::<Value>(&text).unwrap(), value, "输出必须是合法且等价的 JSON");
+        assert!(text.contains("\"short\": {\"a\": 1}"));
+        assert!(text.contains("    {\"name\": \"甲\"},\n    [1, 2],\n"));
+        assert!(text.lines().all(|l| l.trim_end_matches(',').chars().count() <= 30), "{}", text);
+        assert!(text.lines().count() < 300, "标量数组应紧凑折行而不是每行一个");
+
+        assert_eq!(to_string_wrapped(&json!([]), 0), "[]");
+        assert_eq!(to_string_wrapped(&json!("x"), 0), "\"x\"");
+    }
+}
@@ -0,0 +1,154 @@
+//! 日志：按天滚动写入数据目录的日志文件，并支持运行时调整日志级别
+
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::{filter::LevelFilter, fmt, prelude::*, reload, Registry};
+
+use crate::model::data_core::{AppError, StateKind};
+
+/// 日志文件名前缀（滚动文件为 `<前缀>.yyyy-MM-dd`）
+pub const LOG_FILE_PREFIX: &str = "juzhen_r_fanyi.log";
+/// 日志文件保留天数
+pub const LOG_RETENTION_DAYS: u64 = 7;
+
+/// 平台数据目录下的日志目录
+pub fn log_dir() -> PathBuf {
+    dirs::data_local_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("juzhen_r_fanyi")
+        .join("logs")
+}
+
+/// 删除修改时间早于 `max_age` 的日志文件，返回删除数量
+pub fn prune_old_logs(dir: &Path, max_age: Duration, now: SystemTime) -> Result<usize, AppError> {
+    let mut removed = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if !entry.file_name().to_string_lossy().starts_with(LOG_FILE_PREFIX) {
+            continue;
+        }
+        let modified = entry.metadata()?.modified()?;
+        let expired = now
+            .duration_since(modified)
+            .map(|age| age > max_age)
+            .unwrap_or(false);
+        if expired {
+            std::fs::remove_file(entry.path())?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// 将 UI 传入的级别名解析为过滤级别
+pub fn parse_level(level: &str) -> Option<LevelFilter> {
+    match level.trim().to_ascii_lowercase().as_str() {
+        "trace" => Some(LevelFilter::TRACE),
+        "debug" => Some(LevelFilter::DEBUG),
+        "info" => Some(LevelFilter::INFO),
+        "warn" => Some(LevelFilter::WARN),
+        "error" => Some(LevelFilter::ERROR),
+        "off" => Some(LevelFilter::OFF),
+        _ => None,
+    }
+}
+
+/// 运行时日志级别控制句柄
+#[derive(Clone)]
+pub struct LogLevelHandle(reload::Handle<LevelFilter, Registry>);
+
+impl LogLevelHandle {
+    pub fn new(handle: reload::Handle<LevelFilter, Registry>) -> Self {
+        Self(handle)
+    }
+
+    /// 调整日志级别
+    pub fn set_level(&self, level: &str) -> Result<(), AppError> {
+        let filter = parse_level(level)
+            .ok_or_else(|| AppError::State(StateKind::Invalid, format!("未知日志级别: {}", level)))?;
+        self.0
+            .reload(filter)
+            .map_err(|e| AppError::State(StateKind::Invalid, format!("日志级别切换失败: {}", e)))
+    }
+
+    /// 当前日志级别
+    pub fn current(&self) -> Option<LevelFilter> {
+        self.0.clone_current()
+    }
+}
+
+/// 日志初始化结果：级别句柄、文件写入守卫（需持有至程序退出）与日志目录
+pub struct LoggingGuard {
+    pub level: LogLevelHandle,
+    pub log_dir: PathBuf,
+    _worker: WorkerGuard,
+}
+
+/// 初始化日志：控制台 + 按天滚动的日志文件，启动时清理过期日志
+pub fn init_logging(dir: &Path) -> Result<LoggingGuard, AppError> {
+    std::fs::create_dir_all(dir)?;
+    if let Err(e) = prune_old_logs(dir, Duration::from_secs(LOG_RETENTION_DAYS * 24 * 3600), SystemTime::now()) {
+        eprintln!("清理过期日志失败: {}", e);
+    }
+
+    let file_appender = tracing_appender::rolling::daily(dir, LOG_FILE_PREFIX);
+    let (file_writer, worker) = tracing_appender::non_blocking(file_appender);
+    let (level_layer, handle) = reload::Layer::new(LevelFilter::INFO);
+
+    tracing_subscriber::registry()
+        .with(level_layer)
+        .with(fmt::layer())
+        .with(fmt::layer().with_writer(file_writer).with_ansi(false))
+        .try_init()
+        .map_err(|e| AppError::State(StateKind::Invalid, format!("日志初始化失败: {}", e)))?;
+
+    Ok(LoggingGuard {
+        level: LogLevelHandle::new(handle),
+        log_dir: dir.to_path_buf(),
+        _worker: worker,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prune_old_logs() {
+        let dir = tempfile::tempdir().expect("创建临时目录失败");
+        let now = SystemTime::now();
+        let old = dir.path().join(format!("{}.2020-01-01", LOG_FILE_PREFIX));
+        let fresh = dir.path().join(format!("{}.2020-01-09", LOG_FILE_PREFIX));
+        let other = dir.path().join("notes.txt");
+        for p in [&old, &fresh, &other] {
+            std::fs::write(p, "log").unwrap();
+        }
+        let ten_days_ago = now - Duration::from_secs(10 * 24 * 3600);
+        for p in [&old, &other] {
+            std::fs::File::options().write(true).open(p).unwrap().set_modified(ten_days_ago).unwrap();
+        }
+
+        let removed = prune_old_logs(dir.path(), Duration::from_secs(LOG_RETENTION_DAYS * 24 * 3600), now).unwrap();
+        assert_eq!(removed, 1);
+        assert!(!old.exists(), "过期日志应被删除");
+        assert!(fresh.exists(), "未过期日志应保留");
+        assert!(other.exists(), "非日志文件不应被删除");
+    }
+
+    #[test]
+    fn test_level_reload_handle() {
+        let (layer, handle) = reload::Layer::<LevelFilter, Registry>::new(LevelFilter::INFO);
+        let handle = LogLevelHandle::new(handle);
+
+        handle.set_level("debug").expect("切换到debug应成功");
+        assert_eq!(handle.current(), Some(LevelFilter::DEBUG));
+        handle.set_level(" WARN ").expect("级别名应忽略大小写与空白");
+        assert_eq!(handle.current(), Some(LevelFilter::WARN));
+        assert!(handle.set_level("verbose").is_err());
+        drop(layer);
+    }
+}
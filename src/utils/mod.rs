@@ -1,3 +1,7 @@
 pub mod clipboard;
+pub mod crash;
 pub mod fs;
-
+pub mod json_comments;
+pub mod jsonfmt;
+pub mod logging;
+pub mod text;
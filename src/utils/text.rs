@@ -0,0 +1,169 @@
+//! 文本检查：检测译文中的不可见字符、双向控制符与非 NFC 规范化序列，以及不应送译的二进制编码文本
+
+use unicode_normalization::{char::compose, UnicodeNormalization};
+
+/// 可疑字符类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuspiciousKind {
+    /// 零宽空格 U+200B
+    ZeroWidthSpace,
+    /// 零宽非连接符 U+200C
+    ZeroWidthNonJoiner,
+    /// 字节顺序标记 U+FEFF
+    Bom,
+    /// 双向文本控制符（LRM/RLM/嵌入/覆盖/隔离等）
+    BidiControl,
+    /// 非 NFC 规范化的字符（可与前一字符组合或存在规范等价形式）
+    NonNfc,
+}
+
+impl SuspiciousKind {
+    /// 类别的中文名称
+    pub fn label(&self) -> &'static str {
+        match self {
+            SuspiciousKind::ZeroWidthSpace => "零宽空格",
+            SuspiciousKind::ZeroWidthNonJoiner => "零宽非连接符",
+            SuspiciousKind::Bom => "BOM",
+            SuspiciousKind::BidiControl => "双向控制符",
+            SuspiciousKind::NonNfc => "非NFC字符",
+        }
+    }
+
+    /// 是否为可直接删除的不可见字符
+    pub fn is_invisible(&self) -> bool {
+        !matches!(self, SuspiciousKind::NonNfc)
+    }
+}
+
+/// 一个可疑字符：`index` 为字符（非字节）下标
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuspiciousChar {
+    pub index: usize,
+    pub codepoint: u32,
+    pub kind: SuspiciousKind,
+}
+
+impl SuspiciousChar {
+    /// 简短描述，用于日志与中间产物标记
+    pub fn describe(&self) -> String {
+        format!("第{}个字符 U+{:04X}({})", self.index, self.codepoint, self.kind.label())
+    }
+}
+
+/// 判断不可见字符类别
+fn invisible_kind(c: char) -> Option<SuspiciousKind> {
+    match c {
+        '\u{200B}' => Some(SuspiciousKind::ZeroWidthSpace),
+        '\u{200C}' => Some(SuspiciousKind::ZeroWidthNonJoiner),
+        '\u{FEFF}' => Some(SuspiciousKind::Bom),
+        '\u{061C}' | '\u{200E}' | '\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}' => {
+            Some(SuspiciousKind::BidiControl)
+        }
+        _ => None,
+    }
+}
+
+/// 扫描文本中的可疑字符
+pub fn scan_suspicious(text: &str) -> Vec<SuspiciousChar> {
+    let mut found = Vec::new();
+    let mut prev: Option<char> = None;
+    for (index, c) in text.chars().enumerate() {
+        let kind = invisible_kind(c).or_else(|| {
+            // 字符自身不是 NFC（如 U+212B 埃符号），或可与前一字符组合（如 e + U+0301）
+            let composable = prev.is_some_and(|p| compose(p, c).is_some());
+            let not_nfc = !std::iter::once(c).nfc().eq(std::iter::once(c));
+            (composable || not_nfc).then_some(SuspiciousKind::NonNfc)
+        });
+        if let Some(kind) = kind {
+            found.push(SuspiciousChar { index, codepoint: c as u32, kind });
+        }
+        prev = Some(c);
+    }
+    found
+}
+
+/// 删除不可见字符并规范化为 NFC
+pub fn sanitize(text: &str) -> String {
+    text.chars()
+        .filter(|&c| invisible_kind(c).is_none())
+        .nfc()
+        .collect()
+}
+
+/// 判定为 base64/十六进制编码块所需的最少字符数
+const BINARY_MIN_CHARS: usize = 32;
+
+/// 是否像二进制数据的文本编码（data URI，或不含空白、只由 base64/base64url 字符组成的长串）
+pub fn looks_binary(text: &str) -> bool {
+    if let Some(rest) = text.strip_prefix("data:") {
+        return rest.split_once(',').is_some_and(|(meta, _)| meta.ends_with(";base64"));
+    }
+    let body = text.trim_end_matches('=');
+    body.len() >= BINARY_MIN_CHARS
+        && body.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'/' | b'-' | b'_'))
+        && body.bytes().any(|b| b.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(text: &str) -> Vec<(usize, SuspiciousKind)> {
+        scan_suspicious(text).into_iter().map(|s| (s.index, s.kind)).collect()
+    }
+
+    #[test]
+    fn test_scan_invisible_characters() {
+        assert!(scan_suspicious("普通文本 plain").is_empty());
+        assert_eq!(kinds("a\u{200B}b"), vec![(1, SuspiciousKind::ZeroWidthSpace)]);
+        assert_eq!(kinds("a\u{200C}b"), vec![(1, SuspiciousKind::ZeroWidthNonJoiner)]);
+        assert_eq!(kinds("\u{FEFF}标题"), vec![(0, SuspiciousKind::Bom)]);
+        assert_eq!(
+            kinds("\u{202E}abc\u{202C}\u{2067}x\u{2069}"),
+            vec![
+                (0, SuspiciousKind::BidiControl),
+                (4, SuspiciousKind::BidiControl),
+                (5, SuspiciousKind::BidiControl),
+                (7, SuspiciousKind::BidiControl),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_non_nfc_sequences() {
+        // e + 组合锐音符（NFD），应为 é
+        assert_eq!(kinds("caf\u{0065}\u{0301}"), vec![(4, SuspiciousKind::NonNfc)]);
+        // 埃符号单字符的 NFC 形式为 Å
+        assert_eq!(kinds("1\u{212B}"), vec![(1, SuspiciousKind::NonNfc)]);
+        assert!(scan_suspicious("café").is_empty(), "已组合字符不应被标记");
+    }
+
+    #[test]
+    fn test_mixed_rtl_sample_and_sanitize() {
+        let text = "\u{FEFF}مرحبا\u{200F} world\u{200B} e\u{0301}";
+        let found = kinds(text);
+        assert_eq!(
+            found,
+            vec![
+                (0, SuspiciousKind::Bom),
+                (6, SuspiciousKind::BidiControl),
+                (13, SuspiciousKind::ZeroWidthSpace),
+                (16, SuspiciousKind::NonNfc),
+            ]
+        );
+        assert_eq!(sanitize(text), "مرحبا world é");
+        assert!(scan_suspicious(&sanitize(text)).is_empty());
+        assert_eq!(scan_suspicious("\u{200B}")[0].describe(), "第0个字符 U+200B(零宽空格)");
+    }
+
+    #[test]
+    fn test_looks_binary() {
+        assert!(looks_binary("data:image/png;base64,iVBORw0KGgo="));
+        assert!(looks_binary(&"QUJDRA1234+/".repeat(4)));
+        assert!(looks_binary(&format!("{}==", "0123456789abcdef".repeat(3))));
+        assert!(!looks_binary("data:text/plain,hello"));
+        assert!(!looks_binary(&"A sentence that keeps going. ".repeat(10)));
+        assert!(!looks_binary("Short1"));
+        assert!(!looks_binary(&"abcdefgh".repeat(8)), "没有数字的长单词不视为编码块");
+    }
+}
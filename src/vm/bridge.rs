@@ -1,7 +1,22 @@
 //! VM桥接层：连接Slint UI与AppState数据模型
 //!
 //! 注意：此模块的具体实现在main.rs中，因为依赖于Slint生成的类型
-//! 这里只提供公共常量
+//! 这里只提供公共常量、过滤条件解析与不依赖UI的操作守卫
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+pub use crate::model::search_query::parse_filter_list;
+use crate::model::search_query::SearchQuery;
+use crate::vm::progress::ProgressTiming;
 
 // === 常量定义（消除魔法值） ===
 pub const STATUS_READY: &str = "就绪";
@@ -10,4 +25,408 @@ pub const STATUS_LOADED: &str = "文件加载完成";
 pub const STATUS_COPIED: &str = "已复制到剪贴板";
 pub const STATUS_WRITE_BACK_SUCCESS: &str = "回写成功";
 pub const STATUS_ERROR_PREFIX: &str = "错误: ";
+pub const STATUS_BUSY_PREFIX: &str = "当前有任务进行中: ";
+pub const STATUS_CANCELLED: &str = "已取消";
+
+/// 加载后首次提交给树视图的最大节点数（超出部分以截断标记代替）
+pub const TREE_DISPLAY_LIMIT: usize = 20_000;
+/// 加载时影子树的节点数软上限，超出部分以截断标记代替（可“仍然完整加载”）
+pub const TREE_MAX_NODES: usize = 2_000_000;
+/// 首次渲染时从该深度起自动折叠放不下的已展开子树
+pub const TREE_AUTO_COLLAPSE_DEPTH: u32 = 1;
+/// 生成中间产物2时，超过该字符数的二进制编码值（base64、data URI）不送译
+pub const STAGE2_MAX_VALUE_CHARS: usize = 4096;
+/// 输入过滤条件后停顿多久（毫秒）再统计命中数
+pub const MATCH_COUNT_DEBOUNCE_MS: u64 = 150;
+/// 开始耗时工作前让出事件循环的时长（毫秒，约一帧），保证进度条先渲染出来
+pub const FRAME_YIELD_MS: u64 = 16;
+/// “预览提取”构建的中间产物2条目数
+pub const STAGE2_PREVIEW_LIMIT: usize = 50;
+/// “复制给LLM”最多导出的键值对数，避免超出模型上下文窗口
+pub const LLM_MAX_PAIRS: usize = 500;
+
+/// 过滤条件的解析结果描述（多个条件以“或”连接），用于状态栏回显
+pub fn describe_filter_list(filters: &[String]) -> String {
+    filters
+        .iter()
+        .map(|f| SearchQuery::parse(f).describe())
+        .collect::<Vec<_>>()
+        .join(" 或 ")
+}
+
+/// 长耗时操作的运行状态
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum BusyState {
+    /// 空闲
+    #[default]
+    Idle,
+    /// 正在执行的操作（`token` 用于区分取消后重新开始的同名操作）
+    Running { operation: String, token: u64 },
+}
+
+/// 操作的取消标志，可传给后台线程；取消后任务不得再写入文件或状态，结果直接丢弃
+#[derive(Debug, Clone, Default)]
+pub struct CancelFlag(Arc<AtomicBool>);
+
+impl CancelFlag {
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn set(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// 操作守卫：同一时间只允许一个长耗时操作修改共享缓存
+#[derive(Debug, Default)]
+pub struct OperationGuard {
+    state: BusyState,
+    next_token: u64,
+    cancel_flag: CancelFlag,
+}
+
+impl OperationGuard {
+    /// 尝试开始操作，成功返回令牌；已有操作进行中时返回忙碌提示
+    pub fn try_begin(&mut self, operation: &str) -> Result<u64, String> {
+        if let BusyState::Running { operation: running, .. } = &self.state {
+            return Err(format!("{}{}", STATUS_BUSY_PREFIX, running));
+        }
+        self.next_token += 1;
+        self.state = BusyState::Running { operation: operation.to_string(), token: self.next_token };
+        self.cancel_flag = CancelFlag::default();
+        Ok(self.next_token)
+    }
+
+    /// 结束操作；令牌不匹配（操作已被取消）时不改变状态并返回 false
+    pub fn finish(&mut self, token: u64) -> bool {
+        match self.state {
+            BusyState::Running { token: current, .. } if current == token => {
+                self.state = BusyState::Idle;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// 取消当前操作并置位其取消标志，返回被取消的操作名
+    pub fn cancel(&mut self) -> Option<String> {
+        match std::mem::take(&mut self.state) {
+            BusyState::Running { operation, .. } => {
+                self.cancel_flag.set();
+                Some(operation)
+            }
+            BusyState::Idle => None,
+        }
+    }
+
+    /// 最近开始的操作的取消标志
+    pub fn cancel_flag(&self) -> CancelFlag {
+        self.cancel_flag.clone()
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.state != BusyState::Idle
+    }
+
+    pub fn state(&self) -> &BusyState {
+        &self.state
+    }
+}
+
+/// 任务完成后进度条停留在 100% 的时长（毫秒），之后自动隐藏
+pub const PROGRESS_COMPLETE_HOLD_MS: u64 = 800;
+
+/// 进度条自动隐藏调度：新任务开始或再次安排时，之前安排的隐藏失效，避免定时器隐藏掉新任务的进度条
+#[derive(Debug, Default)]
+pub struct ProgressAutoHide {
+    generation: u64,
+}
+
+impl ProgressAutoHide {
+    /// 新任务开始（进度条重新显示）时调用
+    pub fn invalidate(&mut self) {
+        self.generation += 1;
+    }
+
+    /// 任务完成时调用，返回定时器到期时用于校验的代次
+    pub fn schedule(&mut self) -> u64 {
+        self.generation += 1;
+        self.generation
+    }
+
+    /// 定时器到期时是否仍应隐藏
+    pub fn should_hide(&self, generation: u64) -> bool {
+        self.generation == generation
+    }
+}
+
+/// 时长格式化为 `MM:SS`，超过一小时为 `H:MM:SS`
+fn format_clock(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs / 3600 {
+        0 => format!("{:02}:{:02}", secs / 60, secs % 60),
+        hours => format!("{}:{:02}:{:02}", hours, secs / 60 % 60, secs % 60),
+    }
+}
+
+/// 进度条的已用/剩余时间文本，如 `("已用 01:23", "预计剩余 02:10")`
+pub fn format_progress_timing(timing: &ProgressTiming) -> (String, String) {
+    let eta = match timing.eta {
+        Some(eta) => format_clock(eta),
+        None => "估算中…".to_string(),
+    };
+    (format!("已用 {}", format_clock(timing.elapsed)), format!("预计剩余 {}", eta))
+}
+
+/// 字节数格式化为 `512 B`、`1.5 KB`、`12.0 MB` 等
+pub fn format_byte_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// 导出节点时保存对话框的默认文件名 `<键名>.json`：数组元素取下标，根节点为 `root`，去掉文件名中的非法字符
+pub fn subtree_file_name(node_name: &str) -> String {
+    let key: String = node_name
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .chars()
+        .map(|c| if matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') || c.is_control() { '_' } else { c })
+        .collect();
+    match key.trim() {
+        "" | "$" => "root.json".to_string(),
+        key => format!("{}.json", key),
+    }
+}
+
+/// 让出到事件循环：`schedule` 把继续执行的回调交给事件循环（界面中为 [`FRAME_YIELD_MS`] 后到期的 `slint::Timer`），回调运行后 future 才完成
+///
+/// 在 `spawn_local` 任务开头 await，事件循环先渲染一帧（显示进度条），再继续执行耗时工作（替代在UI线程上 sleep 等待渲染）
+pub fn yield_to_event_loop(schedule: impl FnOnce(Box<dyn FnOnce()>)) -> Completion<()> {
+    let (completer, completion) = completion();
+    schedule(Box::new(move || completer.complete(())));
+    completion
+}
+
+/// [`Completion`] 与 [`Completer`] 共享的结果槽
+#[derive(Debug)]
+struct CompletionSlot<T> {
+    value: Option<T>,
+    waker: Option<Waker>,
+}
+
+/// 由 [`Completer`] 在任意线程填入结果的一次性 future，用于在 `spawn_local` 任务中等待后台线程
+#[derive(Debug)]
+pub struct Completion<T> {
+    slot: Arc<Mutex<CompletionSlot<T>>>,
+}
+
+/// [`Completion`] 的发送端，可移入后台线程
+#[derive(Debug)]
+pub struct Completer<T> {
+    slot: Arc<Mutex<CompletionSlot<T>>>,
+}
+
+/// 创建一对发送端与 future
+pub fn completion<T>() -> (Completer<T>, Completion<T>) {
+    let slot = Arc::new(Mutex::new(CompletionSlot { value: None, waker: None }));
+    (Completer { slot: slot.clone() }, Completion { slot })
+}
+
+impl<T> Completer<T> {
+    /// 填入结果并唤醒等待的任务
+    pub fn complete(self, value: T) {
+        let waker = {
+            let mut slot = self.slot.lock().unwrap_or_else(|e| e.into_inner());
+            slot.value = Some(value);
+            slot.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Future for Completion<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut slot = self.slot.lock().unwrap_or_else(|e| e.into_inner());
+        match slot.value.take() {
+            Some(value) => Poll::Ready(value),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// 在后台线程执行 `job`，返回等待其结果的 future（UI线程的 `spawn_local` 任务 await 期间界面照常渲染）
+pub fn run_in_background<T: Send + 'static>(job: impl FnOnce() -> T + Send + 'static) -> Completion<T> {
+    let (completer, completion) = completion();
+    std::thread::spawn(move || completer.complete(job()));
+    completion
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_filter_list() {
+        assert_eq!(parse_filter_list(" name, title | desc ,,name"), vec!["name", "title", "desc"]);
+        assert_eq!(parse_filter_list("title"), vec!["title"]);
+        assert!(parse_filter_list(" , | ").is_empty());
+        assert_eq!(parse_filter_list("$.items[0,1].name"), vec!["$.items[0,1].name"]);
+        assert_eq!(parse_filter_list(r#"name -"a, b" | "x\"|y""#), vec![r#"name -"a, b""#, r#""x\"|y""#]);
+
+        let filters = parse_filter_list("name -icon, title");
+        assert_eq!(describe_filter_list(&filters), "包含「name」，排除「icon」 或 包含「title」");
+    }
+
+    #[test]
+    fn test_format_byte_size() {
+        assert_eq!(format_byte_size(0), "0 B");
+        assert_eq!(format_byte_size(1023), "1023 B");
+        assert_eq!(format_byte_size(1536), "1.5 KB");
+        assert_eq!(format_byte_size(12 * 1024 * 1024), "12.0 MB");
+    }
+
+    #[test]
+    fn test_subtree_file_name() {
+        assert_eq!(subtree_file_name("menus"), "menus.json");
+        assert_eq!(subtree_file_name("[3]"), "3.json");
+        assert_eq!(subtree_file_name("$"), "root.json");
+        assert_eq!(subtree_file_name("a/b:c"), "a_b_c.json");
+    }
+
+    #[test]
+    fn test_yield_to_event_loop_waits_for_scheduled_resume() {
+        use std::sync::atomic::AtomicUsize;
+        use std::task::Wake;
+
+        struct CountingWaker(AtomicUsize);
+        impl Wake for CountingWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let counter = Arc::new(CountingWaker(AtomicUsize::new(0)));
+        let waker = Waker::from(counter.clone());
+        let mut cx = Context::from_waker(&waker);
+        let mut resume = None;
+        let mut fut = yield_to_event_loop(|r| resume = Some(r));
+
+        // 事件循环运行回调之前一直挂起，且不自行唤醒（不会在同一轮内继续执行）
+        assert_eq!(Pin::new(&mut fut).poll(&mut cx), Poll::Pending);
+        assert_eq!(Pin::new(&mut fut).poll(&mut cx), Poll::Pending);
+        assert_eq!(counter.0.load(Ordering::SeqCst), 0);
+
+        resume.take().expect("应交给事件循环一个继续执行的回调")();
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1, "回调运行后必须唤醒任务，否则任务永远挂起");
+        assert_eq!(Pin::new(&mut fut).poll(&mut cx), Poll::Ready(()));
+    }
+
+    #[test]
+    fn test_run_in_background_wakes_waiting_task() {
+        use std::sync::atomic::AtomicUsize;
+        use std::task::Wake;
+
+        struct CountingWaker(AtomicUsize);
+        impl Wake for CountingWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        let counter = Arc::new(CountingWaker(AtomicUsize::new(0)));
+        let waker = Waker::from(counter.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let (completer, mut fut) = completion();
+        assert_eq!(Pin::new(&mut fut).poll(&mut cx), Poll::Pending);
+        std::thread::spawn(move || completer.complete(42)).join().unwrap();
+        assert_eq!(counter.0.load(Ordering::SeqCst), 1, "结果填入后必须唤醒等待的任务");
+        assert_eq!(Pin::new(&mut fut).poll(&mut cx), Poll::Ready(42));
+
+        let mut fut = run_in_background(|| "完成");
+        let result = loop {
+            if let Poll::Ready(value) = Pin::new(&mut fut).poll(&mut cx) {
+                break value;
+            }
+            std::thread::yield_now();
+        };
+        assert_eq!(result, "完成");
+    }
+
+    #[test]
+    fn test_progress_auto_hide_generations() {
+        let mut auto_hide = ProgressAutoHide::default();
+        let first = auto_hide.schedule();
+        assert!(auto_hide.should_hide(first));
+
+        // 到期前开始了新任务：旧定时器不得隐藏新任务的进度条
+        auto_hide.invalidate();
+        assert!(!auto_hide.should_hide(first));
+
+        // 连续完成两次：只有最后一次安排生效
+        let second = auto_hide.schedule();
+        let third = auto_hide.schedule();
+        assert!(!auto_hide.should_hide(second));
+        assert!(auto_hide.should_hide(third));
+    }
+
+    #[test]
+    fn test_format_progress_timing() {
+        let timing = ProgressTiming { elapsed: Duration::from_secs(83), eta: Some(Duration::from_millis(130_900)) };
+        assert_eq!(format_progress_timing(&timing), ("已用 01:23".to_string(), "预计剩余 02:10".to_string()));
+
+        let timing = ProgressTiming { elapsed: Duration::from_secs(3 * 3600 + 5), eta: None };
+        assert_eq!(format_progress_timing(&timing), ("已用 3:00:05".to_string(), "预计剩余 估算中…".to_string()));
+    }
+
+    #[test]
+    fn test_guard_rejects_concurrent_operation() {
+        let mut guard = OperationGuard::default();
+        assert!(!guard.is_busy());
+
+        let token = guard.try_begin("回写").unwrap();
+        assert!(guard.is_busy());
+        assert_eq!(guard.try_begin("一键获得最终产物").unwrap_err(), "当前有任务进行中: 回写");
+
+        assert!(guard.finish(token));
+        assert_eq!(guard.state(), &BusyState::Idle);
+        assert!(guard.try_begin("一键获得最终产物").is_ok());
+    }
+
+    #[test]
+    fn test_guard_cancel_ignores_stale_finish() {
+        let mut guard = OperationGuard::default();
+        let stale = guard.try_begin("回写").unwrap();
+        assert_eq!(guard.cancel(), Some("回写".to_string()));
+        assert_eq!(guard.cancel(), None);
+
+        let stale_flag = guard.cancel_flag();
+        assert!(stale_flag.is_cancelled());
 
+        let fresh = guard.try_begin("回写").unwrap();
+        assert_ne!(stale, fresh);
+        assert!(stale_flag.is_cancelled(), "旧任务仍应看到取消");
+        assert!(!guard.cancel_flag().is_cancelled(), "新任务使用新的取消标志");
+        assert!(!guard.finish(stale), "已取消操作的结束不应清除新操作");
+        assert!(guard.is_busy());
+        assert!(guard.finish(fresh));
+        assert!(!guard.is_busy());
+    }
+}
@@ -0,0 +1,182 @@
+//! 复制内容解析：根据当前选中上下文决定复制完整节点、通配符匹配结果或搜索聚合结果
+
+use crate::model::data_core::AppState;
+
+/// 搜索结果伪路径前缀（由搜索过滤设置到 selected_json_path）
+pub const SEARCH_RESULTS_PREFIX: &str = "搜索结果";
+
+/// 复制内容来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopySource {
+    /// 搜索结果的完整聚合（中间产物全文）
+    SearchResults,
+    /// 通配符路径的全部匹配（JSON 数组）
+    Wildcard,
+    /// 单个节点的完整 JSON
+    Node,
+    /// 预览区文本（可能只是当前页）
+    Preview,
+}
+
+/// 节点的复制格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CopyFormat {
+    /// 格式化 JSON
+    #[default]
+    Pretty,
+    /// 单行紧凑 JSON
+    Compact,
+    /// 叶子节点原始文本（非叶子节点回退为格式化 JSON）
+    Raw,
+}
+
+impl CopyFormat {
+    /// 界面循环切换顺序
+    pub const ALL: [CopyFormat; 3] = [CopyFormat::Pretty, CopyFormat::Compact, CopyFormat::Raw];
+}
+
+/// 待复制的内容
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CopyContent {
+    pub text: String,
+    pub source: CopySource,
+    /// 是否只复制了部分内容（例如分页预览的当前页）
+    pub truncated: bool,
+}
+
+/// 判断 JSONPath 是否包含通配符/过滤/递归下降等多匹配语法
+pub fn is_wildcard_path(path: &str) -> bool {
+    path.starts_with('$') && (path.contains('*') || path.contains("[?") || path.contains(".."))
+}
+
+/// 解析复制内容
+///
+/// `full_text` 为中间产物全文缓存，`preview_paginated` 表示预览区是否分页显示，
+/// `format` 只影响按路径提取的节点/通配符结果
+pub fn resolve_copy_content(
+    state: &AppState,
+    selected_path: &str,
+    preview_text: &str,
+    full_text: &str,
+    preview_paginated: bool,
+    format: CopyFormat,
+) -> CopyContent {
+    if selected_path.starts_with(SEARCH_RESULTS_PREFIX) && !full_text.trim().is_empty() {
+        return CopyContent {
+            text: full_text.to_string(),
+            source: CopySource::SearchResults,
+            truncated: false,
+        };
+    }
+
+    if selected_path.starts_with('$') {
+        let extracted = if is_wildcard_path(selected_path) {
+            match format {
+                CopyFormat::Compact => state
+                    .extract_all_matching(selected_path)
+                    .and_then(|hits| Ok(serde_json::to_string(&hits)?)),
+                CopyFormat::Pretty | CopyFormat::Raw => state.extract_all_matching_pretty(selected_path),
+            }
+            .map(|text| (text, CopySource::Wildcard))
+        } else {
+            match format {
+                CopyFormat::Pretty => state.extract_subtree_pretty(selected_path),
+                CopyFormat::Compact => state.extract_subtree_compact(selected_path),
+                CopyFormat::Raw => state
+                    .extract_leaf_text(selected_path)
+                    .or_else(|_| state.extract_subtree_pretty(selected_path)),
+            }
+            .map(|text| (text, CopySource::Node))
+        };
+        match extracted {
+            Ok((text, source)) => return CopyContent { text, source, truncated: false },
+            Err(e) => tracing::warn!("基于路径提取失败，将回退使用预览文本: {}", e),
+        }
+    }
+
+    CopyContent {
+        text: preview_text.to_string(),
+        source: CopySource::Preview,
+        truncated: preview_paginated,
+    }
+}
+
+/// 复制成功后的状态消息（含长度与截断提示）
+pub fn copy_status_message(content: &CopyContent) -> String {
+    let length = content.text.chars().count();
+    if content.truncated {
+        format!("已复制到剪贴板（{} 字符）⚠ 仅为预览当前页，内容可能不完整", length)
+    } else {
+        format!("已复制到剪贴板（{} 字符）", length)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn load_state(content: &str) -> AppState {
+        let mut file = tempfile::NamedTempFile::new().expect("创建临时文件失败");
+        file.write_all(content.as_bytes()).expect("写入临时文件失败");
+        let mut state = AppState::default();
+        state.load_file(file.path()).expect("加载失败");
+        state
+    }
+
+    #[test]
+    fn test_copy_search_results_uses_full_text() {
+        let state = load_state(r#"{"a": "x"}"#);
+        let content = resolve_copy_content(&state, "搜索结果: name", "第1页", "完整的中间产物", true, CopyFormat::Pretty);
+        assert_eq!(content.source, CopySource::SearchResults);
+        assert_eq!(content.text, "完整的中间产物");
+        assert!(!content.truncated);
+
+        // 尚未生成聚合结果时回退预览文本并提示截断
+        let content = resolve_copy_content(&state, "搜索结果: name", "第1页", "", true, CopyFormat::Pretty);
+        assert_eq!(content.source, CopySource::Preview);
+        assert!(content.truncated);
+        assert!(copy_status_message(&content).contains("可能不完整"));
+    }
+
+    #[test]
+    fn test_copy_wildcard_and_node_paths() {
+        let state = load_state(r#"{"items": [{"name": "甲"}, {"name": "乙"}]}"#);
+
+        let content = resolve_copy_content(&state, "$.items[*].name", "", "", false, CopyFormat::Pretty);
+        assert_eq!(content.source, CopySource::Wildcard);
+        let parsed: serde_json::Value = serde_json::from_str(&content.text).unwrap();
+        assert_eq!(parsed, serde_json::json!(["甲", "乙"]));
+
+        let content = resolve_copy_content(&state, "$.items[0]", "预览", "", true, CopyFormat::Pretty);
+        assert_eq!(content.source, CopySource::Node);
+        assert!(content.text.contains("甲") && !content.text.contains("乙"));
+        assert_eq!(copy_status_message(&content), format!("已复制到剪贴板（{} 字符）", content.text.chars().count()));
+
+        let content = resolve_copy_content(&state, "$.missing", "预览", "", false, CopyFormat::Raw);
+        assert_eq!(content.source, CopySource::Preview);
+        assert_eq!(content.text, "预览");
+    }
+
+    #[test]
+    fn test_copy_formats() {
+        let state = load_state(r#"{"items": [{"name": "甲\n乙"}, {"name": "丙"}]}"#);
+        let copy = |path: &str, format: CopyFormat| resolve_copy_content(&state, path, "", "", false, format).text;
+
+        assert_eq!(copy("$.items[0].name", CopyFormat::Raw), "甲\n乙");
+        assert_eq!(copy("$.items[0].name", CopyFormat::Compact), r#""甲\n乙""#);
+        assert_eq!(copy("$.items[1]", CopyFormat::Compact), r#"{"name":"丙"}"#);
+        assert_eq!(copy("$.items[*].name", CopyFormat::Compact), r#"["甲\n乙","丙"]"#);
+        // 非叶子节点的原文格式回退为格式化 JSON
+        assert_eq!(copy("$.items[1]", CopyFormat::Raw), copy("$.items[1]", CopyFormat::Pretty));
+    }
+
+    #[test]
+    fn test_is_wildcard_path() {
+        assert!(is_wildcard_path("$.items[*].name"));
+        assert!(is_wildcard_path("$..name"));
+        assert!(is_wildcard_path("$.items[?@.id > 1]"));
+        assert!(!is_wildcard_path("$.items[0].name"));
+        assert!(!is_wildcard_path("搜索结果: *"));
+    }
+}
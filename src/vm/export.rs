@@ -0,0 +1,62 @@
+//! 批量导出：解析“JSONPath => 输出文件”映射文本
+
+use std::path::{Path, PathBuf};
+
+/// 映射行中 JSONPath 与输出文件的分隔符
+pub const EXPORT_SEPARATOR: &str = "=>";
+
+/// 解析批量导出映射，每行一项 `JSONPath => 输出文件`；空行与 `#` 注释行被忽略
+///
+/// 相对输出路径基于 `base_dir`（通常为源文件所在目录）解析
+pub fn parse_export_spec(text: &str, base_dir: Option<&Path>) -> Result<Vec<(String, PathBuf)>, String> {
+    let mut exports = Vec::new();
+    for (line_no, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (json_path, output) = line
+            .split_once(EXPORT_SEPARATOR)
+            .map(|(p, o)| (p.trim(), o.trim()))
+            .ok_or_else(|| format!("第{}行缺少分隔符 \"{}\": {}", line_no + 1, EXPORT_SEPARATOR, line))?;
+        if !json_path.starts_with('$') {
+            return Err(format!("第{}行JSONPath必须以$开头: {}", line_no + 1, json_path));
+        }
+        if output.is_empty() {
+            return Err(format!("第{}行缺少输出文件", line_no + 1));
+        }
+        let output = PathBuf::from(output);
+        let output = match base_dir {
+            Some(dir) if output.is_relative() => dir.join(output),
+            _ => output,
+        };
+        exports.push((json_path.to_string(), output));
+    }
+    if exports.is_empty() {
+        return Err("没有可导出的映射".to_string());
+    }
+    Ok(exports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_export_spec() {
+        let text = "# 语言拆分\n$.en => en.json\n\n  $.zh =>  /tmp/out/zh.json \n";
+        let exports = parse_export_spec(text, Some(Path::new("/data"))).unwrap();
+        assert_eq!(
+            exports,
+            vec![
+                ("$.en".to_string(), PathBuf::from("/data/en.json")),
+                ("$.zh".to_string(), PathBuf::from("/tmp/out/zh.json")),
+            ]
+        );
+
+        assert!(parse_export_spec("$.en en.json", None).unwrap_err().contains("第1行"));
+        assert!(parse_export_spec("en => en.json", None).is_err());
+        assert!(parse_export_spec("$.en =>", None).is_err());
+        assert!(parse_export_spec("# 仅注释", None).is_err());
+    }
+}
@@ -1,2 +1,7 @@
 pub mod bridge;
-
+pub mod copy;
+pub mod export;
+pub mod progress;
+pub mod reload;
+pub mod selftest;
+pub mod writeback_log;
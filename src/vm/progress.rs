@@ -0,0 +1,288 @@
+//! 进度节流：后台任务的进度回调按频率限流后再转发给界面（或命令行输出），并估算剩余时间
+
+use std::time::{Duration, Instant};
+
+/// 默认每秒最多转发的进度更新次数
+pub const DEFAULT_MAX_UPDATES_PER_SECOND: u32 = 20;
+
+/// 进度低于该值时不估算剩余时间
+pub const ETA_MIN_PROGRESS: f32 = 0.05;
+
+/// 速率的指数平滑系数（越大越跟随最新速率）
+const ETA_SMOOTHING: f64 = 0.3;
+
+/// 两次速率采样的最小间隔，避免瞬间跳变的进度产生极端速率
+const ETA_MIN_SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 已用时间与预计剩余时间
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProgressTiming {
+    pub elapsed: Duration,
+    /// None 表示仍在估算（进度过低或尚无速率）
+    pub eta: Option<Duration>,
+}
+
+/// 按进度斜率估算剩余时间：速率做指数平滑，停滞时逐渐降低、恢复后逐渐回升
+#[derive(Debug)]
+struct EtaEstimator {
+    anchor: (Instant, f32),
+    /// 平滑后的速率（进度/秒）
+    rate: Option<f64>,
+}
+
+impl EtaEstimator {
+    fn new(start: Instant) -> Self {
+        Self { anchor: (start, 0.0), rate: None }
+    }
+
+    fn observe(&mut self, now: Instant, progress: f32) {
+        let (at, from) = self.anchor;
+        let dt = now.duration_since(at);
+        if dt < ETA_MIN_SAMPLE_INTERVAL {
+            return;
+        }
+        let instant = f64::from((progress - from).max(0.0)) / dt.as_secs_f64();
+        self.rate = Some(match self.rate {
+            Some(rate) => rate + ETA_SMOOTHING * (instant - rate),
+            None => instant,
+        });
+        self.anchor = (now, progress);
+    }
+
+    fn eta(&self, progress: f32) -> Option<Duration> {
+        if progress >= 1.0 {
+            return Some(Duration::ZERO);
+        }
+        if progress < ETA_MIN_PROGRESS {
+            return None;
+        }
+        let rate = self.rate.filter(|r| *r > f64::EPSILON)?;
+        Duration::try_from_secs_f64(f64::from(1.0 - progress) / rate).ok()
+    }
+}
+
+/// 时间来源（测试中可替换为假时钟）
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+/// 系统单调时钟
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// 进度节流器：每秒最多转发 N 次，0.0 与 1.0 总是转发；
+/// 被丢弃的最后一条更新可通过 [`ThrottledProgress::flush`] 补发
+#[derive(Debug)]
+pub struct ThrottledProgress<C: Clock = SystemClock> {
+    clock: C,
+    started: Instant,
+    min_interval: Duration,
+    last_forwarded: Option<Instant>,
+    pending: Option<(f32, String)>,
+    estimator: EtaEstimator,
+}
+
+impl ThrottledProgress<SystemClock> {
+    pub fn new(max_updates_per_second: u32) -> Self {
+        Self::with_clock(max_updates_per_second, SystemClock)
+    }
+}
+
+impl Default for ThrottledProgress<SystemClock> {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_UPDATES_PER_SECOND)
+    }
+}
+
+impl<C: Clock> ThrottledProgress<C> {
+    /// `max_updates_per_second` 为 0 时不限流
+    pub fn with_clock(max_updates_per_second: u32, clock: C) -> Self {
+        let min_interval = match max_updates_per_second {
+            0 => Duration::ZERO,
+            n => Duration::from_secs(1) / n,
+        };
+        let started = clock.now();
+        Self { clock, started, min_interval, last_forwarded: None, pending: None, estimator: EtaEstimator::new(started) }
+    }
+
+    /// 记录当前进度并返回已用时间与预计剩余时间（界面每次显示进度时调用）
+    pub fn timing(&mut self, progress: f32) -> ProgressTiming {
+        let now = self.clock.now();
+        self.estimator.observe(now, progress);
+        ProgressTiming { elapsed: now.duration_since(self.started), eta: self.estimator.eta(progress) }
+    }
+
+    /// 判断本次更新是否应转发；返回 true 时记为已转发
+    pub fn should_forward(&mut self, progress: f32) -> bool {
+        let now = self.clock.now();
+        let boundary = progress <= 0.0 || progress >= 1.0;
+        let due = self.last_forwarded.is_none_or(|last| now.duration_since(last) >= self.min_interval);
+        if boundary || due {
+            self.last_forwarded = Some(now);
+            self.pending = None;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// 限流转发一条带消息的更新；被丢弃的更新保留为待补发
+    pub fn report(&mut self, progress: f32, message: &str, mut sink: impl FnMut(f32, &str, ProgressTiming)) {
+        if self.should_forward(progress) {
+            sink(progress, message, self.timing(progress));
+        } else {
+            self.pending = Some((progress, message.to_string()));
+        }
+    }
+
+    /// 补发最后一条被丢弃的更新（任务结束时调用，保证最终消息可见）
+    pub fn flush(&mut self, mut sink: impl FnMut(f32, &str, ProgressTiming)) {
+        if let Some((progress, message)) = self.pending.take() {
+            self.last_forwarded = Some(self.clock.now());
+            sink(progress, &message, self.timing(progress));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    #[derive(Clone)]
+    struct FakeClock {
+        start: Instant,
+        offset_ms: Rc<Cell<u64>>,
+    }
+
+    impl FakeClock {
+        fn new() -> Self {
+            Self { start: Instant::now(), offset_ms: Rc::new(Cell::new(0)) }
+        }
+
+        fn advance(&self, ms: u64) {
+            self.offset_ms.set(self.offset_ms.get() + ms);
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> Instant {
+            self.start + Duration::from_millis(self.offset_ms.get())
+        }
+    }
+
+    #[test]
+    fn test_throttle_rate_and_boundaries() {
+        let clock = FakeClock::new();
+        let mut throttle = ThrottledProgress::with_clock(10, clock.clone());
+        let mut forwarded = Vec::new();
+
+        throttle.report(0.0, "开始", |p, m, _| forwarded.push((p, m.to_string())));
+        // 1 秒内 100 次更新，每 10ms 一次：10 次/秒 => 约每 100ms 转发一次
+        for i in 1..100 {
+            clock.advance(10);
+            throttle.report(i as f32 / 100.0, &format!("第{}项", i), |p, m, _| forwarded.push((p, m.to_string())));
+        }
+        assert_eq!(forwarded.len(), 1 + 9);
+        assert_eq!(forwarded[1].1, "第10项");
+
+        // 间隔未到时 1.0 仍然转发
+        throttle.report(1.0, "完成", |p, m, _| forwarded.push((p, m.to_string())));
+        assert_eq!(forwarded.last().unwrap(), &(1.0, "完成".to_string()));
+
+        // 0.0 即使紧随其后也转发
+        assert!(throttle.should_forward(0.0));
+        assert!(!throttle.should_forward(0.5));
+    }
+
+    #[test]
+    fn test_flush_forwards_final_dropped_message() {
+        let clock = FakeClock::new();
+        let mut throttle = ThrottledProgress::with_clock(2, clock.clone());
+        let mut forwarded = Vec::new();
+
+        throttle.report(0.2, "a", |_, m, _| forwarded.push(m.to_string()));
+        clock.advance(100);
+        throttle.report(0.4, "b", |_, m, _| forwarded.push(m.to_string()));
+        throttle.report(0.6, "c", |_, m, _| forwarded.push(m.to_string()));
+        assert_eq!(forwarded, vec!["a"]);
+
+        throttle.flush(|_, m, _| forwarded.push(m.to_string()));
+        throttle.flush(|_, m, _| forwarded.push(m.to_string()));
+        assert_eq!(forwarded, vec!["a", "c"], "只补发最后一条且只补发一次");
+
+        // 已转发的更新不会留下待补发内容
+        clock.advance(500);
+        throttle.report(0.8, "d", |_, m, _| forwarded.push(m.to_string()));
+        throttle.flush(|_, m, _| forwarded.push(m.to_string()));
+        assert_eq!(forwarded, vec!["a", "c", "d"]);
+    }
+
+    #[test]
+    fn test_eta_smoothing_through_stall_and_resume() {
+        let clock = FakeClock::new();
+        let mut throttle = ThrottledProgress::with_clock(0, clock.clone());
+        let secs = |t: ProgressTiming| t.eta.map(|d| d.as_secs_f64());
+
+        // 每秒 1%：低于 5% 时仍在估算
+        let mut progress = 0.0;
+        for _ in 0..4 {
+            clock.advance(1000);
+            progress += 0.01;
+            assert_eq!(throttle.timing(progress).eta, None);
+        }
+        for _ in 0..16 {
+            clock.advance(1000);
+            progress += 0.01;
+        }
+        let steady = secs(throttle.timing(progress)).unwrap();
+        assert!((steady - 80.0).abs() < 1.0, "匀速时剩余约 80 秒，实际 {}", steady);
+
+        // 停滞：剩余时间逐步变长而非突变为无穷
+        let mut last = steady;
+        for _ in 0..5 {
+            clock.advance(1000);
+            let eta = secs(throttle.timing(progress)).unwrap();
+            assert!(eta > last, "停滞期间剩余时间应递增: {} -> {}", last, eta);
+            last = eta;
+        }
+        assert!(last < steady * 10.0);
+
+        // 恢复后逐步回落到真实剩余时间附近
+        for _ in 0..20 {
+            clock.advance(1000);
+            progress += 0.01;
+            let eta = secs(throttle.timing(progress)).unwrap();
+            assert!(eta <= last + 1e-6, "恢复后剩余时间不应回升: {} -> {}", last, eta);
+            last = eta;
+        }
+        let expected = f64::from(1.0 - progress) / 0.01;
+        assert!((last - expected).abs() / expected < 0.05, "期望约 {}，实际 {}", expected, last);
+
+        let done = throttle.timing(1.0);
+        assert_eq!(done.eta, Some(Duration::ZERO));
+        assert_eq!(done.elapsed, Duration::from_secs(45));
+    }
+
+    #[test]
+    fn test_eta_ignores_bursts_within_sample_interval() {
+        let clock = FakeClock::new();
+        let mut throttle = ThrottledProgress::with_clock(0, clock.clone());
+        clock.advance(10_000);
+        assert!(throttle.timing(0.1).eta.is_some());
+        // 同一时刻跳到 0.6 不产生极端速率，沿用每秒 1% 的速率
+        let eta = throttle.timing(0.6).eta.unwrap().as_secs_f64();
+        assert!((eta - 40.0).abs() < 0.01, "实际 {}", eta);
+
+        // 尚无速率采样时仍在估算
+        let mut throttle = ThrottledProgress::with_clock(0, clock.clone());
+        assert_eq!(throttle.timing(0.2).eta, None);
+    }
+}
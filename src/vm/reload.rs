@@ -0,0 +1,129 @@
+//! 回写后重新加载决策：根据用户选择与当前状态决定是否重新加载、是否保留状态及是否重跑检测
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// 用户对重新加载提示的选择
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReloadChoice {
+    /// 立即重新加载
+    Now,
+    /// 稍后
+    Later,
+}
+
+/// 重新加载计划
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ReloadPlan {
+    /// 是否重新加载文件
+    pub reload: bool,
+    /// 重新加载时是否保留展开/可见状态
+    pub preserve_state: bool,
+    /// 是否标记“磁盘文件已更新”（保持横幅可见）
+    pub mark_dirty_on_disk: bool,
+    /// 重新加载后是否重跑英文字段检测
+    pub redetect_english_fields: bool,
+}
+
+/// 计算重新加载计划
+///
+/// 写入的文件已不是当前打开的文件时（用户已切换文件），不做任何处理
+pub fn plan_reload(
+    choice: ReloadChoice,
+    written_path: &Path,
+    current_source: Option<&Path>,
+    english_fields_auto_detected: bool,
+) -> ReloadPlan {
+    if current_source != Some(written_path) {
+        return ReloadPlan::default();
+    }
+    match choice {
+        ReloadChoice::Now => ReloadPlan {
+            reload: true,
+            preserve_state: true,
+            mark_dirty_on_disk: false,
+            redetect_english_fields: english_fields_auto_detected,
+        },
+        ReloadChoice::Later => ReloadPlan {
+            mark_dirty_on_disk: true,
+            ..ReloadPlan::default()
+        },
+    }
+}
+
+/// 界面回调传递的路径句柄表：界面只持有整数 id，路径始终以 `PathBuf` 保存，
+/// 不经过有损的字符串转换（非 UTF-8 路径、Windows 未配对代理项均可原样往返）
+#[derive(Debug, Default)]
+pub struct PathRegistry {
+    next_id: i32,
+    paths: HashMap<i32, PathBuf>,
+}
+
+impl PathRegistry {
+    /// 登记路径并返回 id（从 1 开始，0 表示无）；已登记的路径返回原 id
+    pub fn register(&mut self, path: &Path) -> i32 {
+        if let Some((&id, _)) = self.paths.iter().find(|(_, p)| p.as_path() == path) {
+            return id;
+        }
+        self.next_id += 1;
+        self.paths.insert(self.next_id, path.to_path_buf());
+        self.next_id
+    }
+
+    pub fn get(&self, id: i32) -> Option<&Path> {
+        self.paths.get(&id).map(PathBuf::as_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_reload_now_and_later() {
+        let path = Path::new("/data/a.json");
+
+        let plan = plan_reload(ReloadChoice::Now, path, Some(path), true);
+        assert!(plan.reload && plan.preserve_state && plan.redetect_english_fields);
+        assert!(!plan.mark_dirty_on_disk);
+
+        let plan = plan_reload(ReloadChoice::Now, path, Some(path), false);
+        assert!(plan.reload && !plan.redetect_english_fields, "未自动检测过时不应重跑检测");
+
+        let plan = plan_reload(ReloadChoice::Later, path, Some(path), true);
+        assert!(!plan.reload && plan.mark_dirty_on_disk);
+    }
+
+    #[test]
+    fn test_plan_reload_ignores_other_file() {
+        let written = Path::new("/data/a.json");
+        assert_eq!(plan_reload(ReloadChoice::Now, written, Some(Path::new("/data/b.json")), true), ReloadPlan::default());
+        assert_eq!(plan_reload(ReloadChoice::Later, written, None, true), ReloadPlan::default());
+    }
+
+    #[test]
+    fn test_path_registry_round_trip() {
+        let mut registry = PathRegistry::default();
+        let a = registry.register(Path::new("/data/a.json"));
+        assert_eq!(a, 1);
+        assert_eq!(registry.register(Path::new("/data/a.json")), a, "同一路径复用 id");
+        assert_eq!(registry.get(a), Some(Path::new("/data/a.json")));
+        assert_eq!(registry.get(0), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_path_registry_keeps_non_utf8_paths() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+        let raw = Path::new(OsStr::from_bytes(b"/data/\xFF\xFE/a.json"));
+        let lossy = PathBuf::from(raw.to_string_lossy().to_string());
+        let mut registry = PathRegistry::default();
+        let id = registry.register(raw);
+        assert_ne!(registry.register(&lossy), id, "有损转换后的路径是另一个文件");
+        assert_eq!(registry.get(id).unwrap().as_os_str().as_bytes(), b"/data/\xFF\xFE/a.json");
+        // 以原路径制定重新加载计划，而不是其有损字符串形式
+        assert!(plan_reload(ReloadChoice::Now, registry.get(id).unwrap(), Some(raw), false).reload);
+        assert!(!plan_reload(ReloadChoice::Now, &lossy, Some(raw), false).reload);
+    }
+}
@@ -0,0 +1,279 @@
+//! 自检：在内存中用生成的文档跑通 加载 → 搜索 → 中间产物2 → 最终产物 → 伪翻译 → 回写 → 差异 全流程
+//!
+//! 用于排查用户环境问题：报告逐步计时并校验不变量，失败时指出出错的阶段，可序列化为 JSON 附在问题反馈中
+
+use std::collections::BTreeSet;
+use std::time::Instant;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::model::data_core::{AppState, Stage2Options};
+use crate::model::path::query_first;
+use crate::model::search::SearchMode;
+use crate::model::shadow_tree::{build_shadow_tree, build_shadow_tree_from_reader, field_path, ShadowTreeOptions};
+use crate::model::test_utils::{generate_test_fixture, TestFixtureShape};
+use crate::model::workflow::{build_final_product, FinalOptions, WritebackOptions, WritebackPlan};
+
+/// 自检提取的字段（每个对象都有）
+const FIELD: &str = "str_0";
+
+/// 自检文档形状（固定种子，结果可复现）
+fn doc_shape() -> TestFixtureShape {
+    TestFixtureShape { depth: 3, string_fields: 2, array_len: 60, seed: 0x5e1f_7e57, ..Default::default() }
+}
+
+/// 一个阶段的结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SelfTestStep {
+    pub stage: String,
+    pub duration_ms: u64,
+    pub passed: bool,
+    /// 通过时为统计信息，失败时为原因
+    pub detail: String,
+}
+
+/// 自检报告
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SelfTestReport {
+    pub version: String,
+    pub steps: Vec<SelfTestStep>,
+    /// 第一个失败的阶段（之后的阶段依赖其结果，不再执行）
+    pub failed_stage: Option<String>,
+}
+
+impl SelfTestReport {
+    /// 所有阶段是否都通过
+    pub fn passed(&self) -> bool {
+        self.failed_stage.is_none()
+    }
+
+    /// 序列化为格式化的 JSON
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// 渲染为预览区/终端显示的文本
+    pub fn render(&self) -> String {
+        let mut out = format!("== 自检 v{} ==\n", self.version);
+        for step in &self.steps {
+            let mark = if step.passed { "✅" } else { "❌" };
+            out.push_str(&format!("{} {:<8} {:>6}ms  {}\n", mark, step.stage, step.duration_ms, step.detail));
+        }
+        match &self.failed_stage {
+            Some(stage) => out.push_str(&format!("\n自检失败: {}\n", stage)),
+            None => out.push_str("\n自检通过\n"),
+        }
+        out
+    }
+}
+
+/// 逐阶段执行并计时，第一个失败后停止
+struct Runner {
+    steps: Vec<SelfTestStep>,
+    failed_stage: Option<String>,
+}
+
+impl Runner {
+    fn step<T>(&mut self, stage: &str, f: impl FnOnce() -> Result<(T, String), String>) -> Option<T> {
+        let start = Instant::now();
+        let result = f();
+        let duration_ms = start.elapsed().as_millis() as u64;
+        let (value, passed, detail) = match result {
+            Ok((value, detail)) => (Some(value), true, detail),
+            Err(detail) => (None, false, detail),
+        };
+        self.steps.push(SelfTestStep { stage: stage.to_string(), duration_ms, passed, detail });
+        if !passed {
+            self.failed_stage = Some(stage.to_string());
+        }
+        value
+    }
+
+    fn finish(self) -> SelfTestReport {
+        SelfTestReport { version: env!("CARGO_PKG_VERSION").to_string(), steps: self.steps, failed_stage: self.failed_stage }
+    }
+}
+
+/// 伪翻译：保留原文并加上可辨认的标记
+fn pseudo_translate(text: &str) -> String {
+    format!("[!! {} !!]", text)
+}
+
+/// 收集两份文档中取值不同的叶子路径
+fn changed_leaves(a: &Value, b: &Value, path: &str, out: &mut Vec<String>) {
+    match (a, b) {
+        (Value::Object(x), Value::Object(y)) if x.len() == y.len() => {
+            for (key, value) in x {
+                match y.get(key) {
+                    Some(other) => changed_leaves(value, other, &field_path(path, key), out),
+                    None => out.push(field_path(path, key)),
+                }
+            }
+        }
+        (Value::Array(x), Value::Array(y)) if x.len() == y.len() => {
+            for (index, (value, other)) in x.iter().zip(y).enumerate() {
+                changed_leaves(value, other, &format!("{}[{}]", path, index), out);
+            }
+        }
+        _ if a != b => out.push(path.to_string()),
+        _ => {}
+    }
+}
+
+/// 执行全流程自检
+pub fn run() -> SelfTestReport {
+    let mut runner = Runner { steps: Vec::new(), failed_stage: None };
+
+    let Some((original, text)) = runner.step("生成", || {
+        let doc = generate_test_fixture(&doc_shape());
+        let text = serde_json::to_string(&doc).map_err(|e| e.to_string())?;
+        let detail = format!("{} 字节", text.len());
+        Ok(((doc, text), detail))
+    }) else {
+        return runner.finish();
+    };
+
+    let Some(mut state) = runner.step("加载", || {
+        let (dom, tree) = build_shadow_tree_from_reader(text.as_bytes(), ShadowTreeOptions::default()).map_err(|e| e.to_string())?;
+        if dom != original {
+            return Err("解析结果与生成的文档不一致".to_string());
+        }
+        if tree.len() != build_shadow_tree(&dom).len() {
+            return Err(format!("影子树节点数 {} 与直接构建不一致", tree.len()));
+        }
+        let mut state = AppState::default();
+        state.replace_dom(dom).map_err(|e| e.to_string())?;
+        let detail = format!("{} 个节点", state.tree_flat.len());
+        Ok((state, detail))
+    }) else {
+        return runner.finish();
+    };
+
+    let expected = doc_shape().object_count();
+    let Some(matches) = runner.step("搜索", || {
+        let matches = state.search(FIELD, SearchMode::ExactKey).map_err(|e| e.to_string())?.len();
+        if matches != expected {
+            return Err(format!("命中 {} 个，应为 {} 个", matches, expected));
+        }
+        Ok((matches, format!("命中 {} 个", matches)))
+    }) else {
+        return runner.finish();
+    };
+
+    let Some(stage2) = runner.step("中间产物2", || {
+        let text = state
+            .build_intermediate_stage2_multi(&[FIELD.to_string()], Stage2Options::default(), |_, _| {})
+            .map_err(|e| e.to_string())?;
+        let stage2: Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+        let count = stage2["count"].as_u64().unwrap_or_default() as usize;
+        if count != matches {
+            return Err(format!("条目数 {} 与搜索命中数 {} 不一致", count, matches));
+        }
+        Ok((stage2, format!("{} 个条目", count)))
+    }) else {
+        return runner.finish();
+    };
+
+    let Some(final_product) = runner.step("最终产物", || {
+        let text = build_final_product(&stage2.to_string(), FinalOptions::default()).map_err(|e| e.to_string())?;
+        let final_product: Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+        let count = final_product.as_object().map(|m| m.len()).unwrap_or_default();
+        if count != matches {
+            return Err(format!("最终产物 {} 项，应为 {} 项", count, matches));
+        }
+        Ok((final_product, format!("{} 项", count)))
+    }) else {
+        return runner.finish();
+    };
+
+    let Some(translated) = runner.step("伪翻译", || {
+        let map = final_product.as_object().ok_or("最终产物不是JSON对象")?;
+        let translated: serde_json::Map<String, Value> = map
+            .iter()
+            .map(|(seq, text)| (seq.clone(), Value::String(pseudo_translate(text.as_str().unwrap_or_default()))))
+            .collect();
+        let detail = format!("{} 项", translated.len());
+        Ok((Value::Object(translated), detail))
+    }) else {
+        return runner.finish();
+    };
+
+    let Some(written) = runner.step("回写", || {
+        let mut dom = original.clone();
+        let plan = WritebackPlan::build(&translated, &stage2, &dom, WritebackOptions::default()).map_err(|e| e.to_string())?;
+        if let Some(warning) = plan.fingerprint_warning {
+            return Err(format!("文档未变化但指纹不一致: {}", warning));
+        }
+        let modified = plan.apply(&mut dom, |_, _| {});
+        if modified != matches || plan.skipped > 0 {
+            return Err(format!("回写 {} 个、跳过 {} 个，应回写 {} 个", modified, plan.skipped, matches));
+        }
+        Ok((dom, format!("回写 {} 个", modified)))
+    }) else {
+        return runner.finish();
+    };
+
+    runner.step("差异", || {
+        // 只有提取的路径发生变化，且变化恰为伪翻译
+        let mut changed = Vec::new();
+        changed_leaves(&original, &written, "$", &mut changed);
+        let changed: BTreeSet<String> = changed.into_iter().collect();
+        let items = stage2["items"].as_array().ok_or("中间产物2缺少items数组")?;
+        let extracted: BTreeSet<String> = items.iter().filter_map(|i| i["source_path"].as_str().map(str::to_string)).collect();
+        if changed != extracted {
+            let unexpected = changed.symmetric_difference(&extracted).next().cloned().unwrap_or_default();
+            return Err(format!("变化的路径与提取的路径不一致，例如 {}", unexpected));
+        }
+        for item in items {
+            let path = item["source_path"].as_str().unwrap_or_default();
+            let expected = pseudo_translate(item["name"].as_str().unwrap_or_default());
+            let actual = query_first(&written, path).ok().flatten().and_then(Value::as_str);
+            if actual != Some(expected.as_str()) {
+                return Err(format!("{} 的译文不正确", path));
+            }
+        }
+
+        // 往返：以原文回写后与原文档完全一致
+        let mut restored = written.clone();
+        let options = WritebackOptions { normalize_text: false, force_on_mismatch: true };
+        let plan = WritebackPlan::build(&final_product, &stage2, &restored, options).map_err(|e| e.to_string())?;
+        plan.apply(&mut restored, |_, _| {});
+        if restored != original {
+            return Err("以原文回写后与原文档不一致".to_string());
+        }
+        Ok(((), format!("{} 处变化，往返一致", changed.len())))
+    });
+
+    runner.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_selftest_passes() {
+        let report = run();
+        assert!(report.passed(), "{}", report.render());
+        assert_eq!(report.steps.len(), 8);
+        let json: Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["failed_stage"], Value::Null);
+        assert_eq!(json["steps"][2]["stage"], "搜索");
+    }
+
+    #[test]
+    fn test_failure_stops_and_names_stage() {
+        let mut runner = Runner { steps: Vec::new(), failed_stage: None };
+        assert_eq!(runner.step("甲", || Ok((1, String::new()))), Some(1));
+        assert_eq!(runner.step::<()>("乙", || Err("坏了".to_string())), None);
+        let report = runner.finish();
+        assert_eq!(report.failed_stage.as_deref(), Some("乙"));
+        assert!(report.render().contains("❌ 乙"));
+
+        let mut changed = Vec::new();
+        changed_leaves(&json!({"a": [1, {"b c": 2}]}), &json!({"a": [1, {"b c": 3}]}), "$", &mut changed);
+        assert_eq!(changed, vec!["$.a[1]['b c']"]);
+    }
+}
@@ -0,0 +1,93 @@
+//! 回写日志条目：本地时间戳、单调递增序号与操作耗时
+
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use chrono::{Local, NaiveTime};
+
+/// 全局单调递增的日志序号（保证排序稳定）
+static NEXT_SEQ: AtomicU64 = AtomicU64::new(1);
+/// 当前回写操作的开始时间
+static OPERATION_START: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// 一条回写日志
+#[derive(Debug, Clone, PartialEq)]
+pub struct WritebackLogEntry {
+    pub seq: u64,
+    pub time: NaiveTime,
+    /// 距当前回写操作开始的耗时（无进行中的操作时为 None）
+    pub elapsed: Option<Duration>,
+    pub message: String,
+}
+
+impl WritebackLogEntry {
+    /// 以当前本地时间创建日志条目
+    pub fn now(message: &str) -> Self {
+        Self {
+            seq: NEXT_SEQ.fetch_add(1, Ordering::Relaxed),
+            time: Local::now().time(),
+            elapsed: operation_elapsed(),
+            message: message.to_string(),
+        }
+    }
+
+    /// 格式化为 `#0007 [14:03:25.120 +3.42s] 消息`
+    pub fn format(&self) -> String {
+        let time = self.time.format("%H:%M:%S%.3f");
+        match self.elapsed {
+            Some(elapsed) => format!("#{:04} [{} +{:.2}s] {}", self.seq, time, elapsed.as_secs_f64(), self.message),
+            None => format!("#{:04} [{}] {}", self.seq, time, self.message),
+        }
+    }
+}
+
+/// 标记回写操作开始（之后的日志附带耗时）
+pub fn begin_operation() {
+    if let Ok(mut start) = OPERATION_START.lock() {
+        *start = Some(Instant::now());
+    }
+}
+
+/// 标记回写操作结束
+pub fn end_operation() {
+    if let Ok(mut start) = OPERATION_START.lock() {
+        *start = None;
+    }
+}
+
+/// 当前回写操作已进行的时间
+pub fn operation_elapsed() -> Option<Duration> {
+    OPERATION_START.lock().ok()?.map(|start| start.elapsed())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_entry_with_and_without_elapsed() {
+        let mut entry = WritebackLogEntry {
+            seq: 7,
+            time: NaiveTime::from_hms_milli_opt(23, 59, 59, 120).unwrap(),
+            elapsed: Some(Duration::from_millis(3420)),
+            message: "回写完成".to_string(),
+        };
+        assert_eq!(entry.format(), "#0007 [23:59:59.120 +3.42s] 回写完成");
+
+        entry.elapsed = None;
+        entry.seq = 12345;
+        assert_eq!(entry.format(), "#12345 [23:59:59.120] 回写完成");
+    }
+
+    #[test]
+    fn test_sequence_is_monotonic() {
+        let first = WritebackLogEntry::now("a");
+        let second = WritebackLogEntry::now("b");
+        assert!(second.seq > first.seq);
+    }
+}